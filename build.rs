@@ -2,6 +2,41 @@
 // https://github.com/nikomatsakis/lalrpop/blob/master/doc/tutorial.md
 extern crate lalrpop;
 
+use std::process::Command;
+
 fn main() {
-    lalrpop::process_root().expect("failed to process lalrpop files");
+    // Printing any `rerun-if-changed` directive disables Cargo's default of rerunning the build
+    // script whenever a file in the package changes, so the grammar files must be listed too.
+    lalrpop::Configuration::new()
+        .emit_rerun_directives(true)
+        .process_current_dir()
+        .expect("failed to process lalrpop files");
+    built::write_built_file().expect("failed to acquire build-time information");
+    emit_git_commit_hash();
+}
+
+/// Exposes the current git commit as `BSH_GIT_COMMIT_HASH`, if building from a git checkout.
+fn emit_git_commit_hash() {
+    // HEAD changes when another branch is checked out, and the branch's ref and the index when a
+    // commit is made.
+    let mut watched = vec!["HEAD".to_string(), "index".to_string()];
+    watched.extend(git(&["symbolic-ref", "-q", "HEAD"]));
+    for path in watched {
+        if let Some(path) = git(&["rev-parse", "--git-path", &path]) {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
+    if let Some(hash) = git(&["rev-parse", "--short", "HEAD"]) {
+        println!("cargo:rustc-env=BSH_GIT_COMMIT_HASH={}", hash);
+    }
+}
+
+/// Runs git with `args`, returning its trimmed output if it succeeds.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
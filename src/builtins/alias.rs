@@ -0,0 +1,185 @@
+use crate::{
+    builtins::{self, prelude::*},
+    shell::print_error,
+    util,
+};
+
+pub struct Alias;
+
+impl builtins::BuiltinCommand for Alias {
+    const NAME: &'static str = builtins::ALIAS_NAME;
+
+    const HELP: &'static str = "\
+alias: alias [-p] [name[=value] ...]
+    Define or display aliases.
+
+    Without arguments, or with -p, `alias' prints the list of aliases in the
+    reusable form `alias NAME=VALUE' on standard output.
+
+    Otherwise, an alias is defined for each NAME whose VALUE is given. A
+    trailing space in VALUE causes the next word to be checked for alias
+    substitution when the alias is expanded.
+
+    Exit Status:
+    alias returns true unless a NAME is supplied for which no alias has been
+    defined.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        if args.is_empty() || args == ["-p"] {
+            let mut aliases: Vec<(&String, &String)> = shell.aliases().iter().collect();
+            aliases.sort();
+            for (name, value) in aliases {
+                print_alias(stdout, name, value)?;
+            }
+            return Ok(());
+        }
+
+        let mut status = 0;
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, value)) if is_valid_alias_name(name) => {
+                    if let Err(e) = shell.config().check_command_name(name) {
+                        print_error(shell.shell_stderr(), format_args!("alias: {}", e));
+                        status = 1;
+                        continue;
+                    }
+                    shell
+                        .aliases_mut()
+                        .insert(name.to_string(), value.to_string());
+                }
+                Some((name, _)) => {
+                    print_error(
+                        shell.shell_stderr(),
+                        format_args!("alias: `{}': invalid alias name", name),
+                    );
+                    status = 1;
+                }
+                None => match shell.aliases().get(arg) {
+                    Some(value) => print_alias(stdout, arg, value)?,
+                    None => {
+                        print_error(
+                            shell.shell_stderr(),
+                            format_args!("alias: {}: not found", arg),
+                        );
+                        status = 1;
+                    }
+                },
+            }
+        }
+
+        if status != 0 {
+            return Err(Error::builtin_command("", status));
+        }
+        Ok(())
+    }
+}
+
+pub struct Unalias;
+
+impl builtins::BuiltinCommand for Unalias {
+    const NAME: &'static str = builtins::UNALIAS_NAME;
+
+    const HELP: &'static str = "\
+unalias: unalias [-a] name [name ...]
+    Remove each NAME from the list of defined aliases.
+
+    -a      remove all alias definitions
+
+    Return success unless a NAME is not an existing alias.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        match args.first() {
+            Some(&"-a") => {
+                shell.aliases_mut().clear();
+                return Ok(());
+            }
+            None => {
+                return Err(Error::builtin_command(
+                    format!("unalias: usage: {}", Self::usage()),
+                    2,
+                ))
+            }
+            Some(_) => {}
+        }
+
+        let mut status = 0;
+        for name in args {
+            if shell.aliases_mut().remove(name).is_none() {
+                print_error(
+                    shell.shell_stderr(),
+                    format_args!("unalias: {}: not found", name),
+                );
+                status = 1;
+            }
+        }
+
+        if status != 0 {
+            return Err(Error::builtin_command("", status));
+        }
+        Ok(())
+    }
+}
+
+fn print_alias(stdout: &mut dyn Write, name: &str, value: &str) -> Result<()> {
+    writeln!(stdout, "alias {}={}", name, util::quote_for_shell(value)).context(ErrorKind::Io)?;
+    Ok(())
+}
+
+/// Returns `true` if `name` may be used as an alias, i.e. it is a non-empty word without quotes,
+/// expansions, or metacharacters.
+fn is_valid_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "/$`=\\'\";&|()<>".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn alias_define_and_print() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        assert!(Alias::run(&mut *shell, &["ll=ls -la", "g=grep"], &mut io::sink()).is_ok());
+        assert_eq!(shell.aliases().get("ll").unwrap(), "ls -la");
+
+        let mut output = Vec::new();
+        assert!(Alias::run::<&str>(&mut *shell, &[], &mut output).is_ok());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "alias g=grep\nalias ll='ls -la'\n"
+        );
+
+        let mut output = Vec::new();
+        assert!(Alias::run(&mut *shell, &["ll", "x"], &mut output).is_err());
+        assert_eq!(String::from_utf8(output).unwrap(), "alias ll='ls -la'\n");
+
+        assert!(Alias::run(&mut *shell, &["a/b=c"], &mut io::sink()).is_err());
+    }
+
+    #[test]
+    fn unalias() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        assert!(Alias::run(&mut *shell, &["a=b", "c=d", "e=f"], &mut io::sink()).is_ok());
+        assert!(Unalias::run(&mut *shell, &["a"], &mut io::sink()).is_ok());
+        assert!(Unalias::run(&mut *shell, &["a"], &mut io::sink()).is_err());
+        assert_eq!(shell.aliases().len(), 2);
+        assert!(Unalias::run(&mut *shell, &["-a"], &mut io::sink()).is_ok());
+        assert!(shell.aliases().is_empty());
+        assert!(Unalias::run::<&str>(&mut *shell, &[], &mut io::sink()).is_err());
+    }
+}
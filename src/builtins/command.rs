@@ -0,0 +1,171 @@
+use std::env;
+use std::ffi::OsString;
+
+use crate::builtins::{
+    self,
+    prelude::*,
+    r#type::{self, CommandKind},
+    BuiltinCommand,
+};
+use crate::shell::print_error;
+use crate::util::{self, path::DEFAULT_PATH};
+
+pub struct Command;
+
+impl BuiltinCommand for Command {
+    const NAME: &'static str = builtins::COMMAND_NAME;
+
+    const HELP: &'static str = "\
+command: command [-pVv] command [arg ...]
+    Execute a simple command or display information about commands.
+
+    Runs COMMAND with ARGS suppressing shell function lookup, or display
+    information about the specified COMMANDs. Can be used to invoke commands
+    on disk when a function with the same name exists.
+
+    -p      use a default value for PATH that is guaranteed to find all of
+            the standard utilities
+    -v      print a description of COMMAND similar to the `type' builtin
+    -V      print a more verbose description of each COMMAND
+
+    Exit Status:
+    Returns exit status of COMMAND, or failure if COMMAND is not found.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (options, names) = parse_options(&args)?;
+        let path_var = if options.use_default_path {
+            OsString::from(DEFAULT_PATH)
+        } else {
+            env::var_os("PATH").unwrap_or_default()
+        };
+
+        // Commands are run by the caller, see `run_options`, so this only describes them.
+        let mut status = 0;
+        for name in names {
+            let kind = r#type::find_command(shell, name, &path_var, false).pop();
+            match (kind, options.verbose) {
+                (Some(kind), true) => r#type::describe(stdout, name, &kind)?,
+                (Some(CommandKind::Alias(value)), false) => {
+                    writeln!(stdout, "alias {}={}", name, util::quote_for_shell(&value))
+                        .context(ErrorKind::Io)?
+                }
+                (Some(CommandKind::File(path)), false) => {
+                    writeln!(stdout, "{}", path).context(ErrorKind::Io)?
+                }
+                (Some(_), false) => writeln!(stdout, "{}", name).context(ErrorKind::Io)?,
+                (None, verbose) => {
+                    if verbose {
+                        print_error(
+                            shell.shell_stderr(),
+                            format_args!("command: {}: not found", name),
+                        );
+                    }
+                    status = 1;
+                }
+            }
+        }
+
+        if status != 0 {
+            return Err(Error::builtin_command("", status));
+        }
+        Ok(())
+    }
+}
+
+/// How a simple command is looked up when it is run through `command`, e.g. `command -p ls`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunOptions {
+    /// Whether shell functions are skipped, so that a builtin or file of the same name runs.
+    pub skip_functions: bool,
+    /// Whether files are searched for in [`DEFAULT_PATH`] rather than `$PATH`.
+    pub use_default_path: bool,
+}
+
+/// If `words` runs a command through `command`, returns the number of words before the command
+/// name and how it is looked up.
+///
+/// Returns `None` if `command` only describes commands, i.e. with `-v` or `-V`, or there is no
+/// command name, so that the builtin runs instead.
+pub fn run_options<S: AsRef<str>>(words: &[S]) -> Option<(usize, RunOptions)> {
+    let (program, args) = words.split_first()?;
+    if program.as_ref() != builtins::COMMAND_NAME {
+        return None;
+    }
+
+    let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+    let (options, names) = parse_options(&args).ok()?;
+    if options.describe || names.is_empty() {
+        return None;
+    }
+    let run_options = RunOptions {
+        skip_functions: true,
+        use_default_path: options.use_default_path,
+    };
+    Some((words.len() - names.len(), run_options))
+}
+
+#[derive(Debug, Default)]
+struct CommandOptions {
+    use_default_path: bool,
+    describe: bool,
+    verbose: bool,
+}
+
+/// Parses the options of `command`, returning them and the rest of `args`.
+fn parse_options<'a>(args: &'a [&'a str]) -> Result<(CommandOptions, &'a [&'a str])> {
+    let mut options = CommandOptions::default();
+    let mut rest = args;
+    while let Some(flags) = rest
+        .first()
+        .and_then(|arg| arg.strip_prefix('-'))
+        .filter(|flags| !flags.is_empty())
+    {
+        rest = &rest[1..];
+        if flags == "-" {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'p' => options.use_default_path = true,
+                'v' => options.describe = true,
+                'V' => {
+                    options.describe = true;
+                    options.verbose = true;
+                }
+                _ => {
+                    return Err(Error::builtin_command(
+                        format!(
+                            "command: -{}: invalid option\ncommand: usage: {}",
+                            flag,
+                            Command::usage().trim_start_matches("command: ")
+                        ),
+                        2,
+                    ))
+                }
+            }
+        }
+    }
+    Ok((options, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_options() {
+        let (len, options) = run_options(&["command", "ls", "-l"]).unwrap();
+        assert_eq!(len, 1);
+        assert!(options.skip_functions && !options.use_default_path);
+
+        let (len, options) = run_options(&["command", "-p", "--", "ls"]).unwrap();
+        assert_eq!(len, 3);
+        assert!(options.use_default_path);
+
+        assert!(run_options(&["command", "-v", "ls"]).is_none());
+        assert!(run_options(&["command", "-p"]).is_none());
+        assert!(run_options(&["command", "-x", "ls"]).is_none());
+        assert!(run_options(&["ls", "command"]).is_none());
+    }
+}
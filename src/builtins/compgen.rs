@@ -0,0 +1,136 @@
+use crate::builtins::{self, complete, prelude::*};
+
+pub struct Compgen;
+
+impl builtins::BuiltinCommand for Compgen {
+    const NAME: &'static str = builtins::COMPGEN_NAME;
+
+    const HELP: &'static str = "\
+compgen: compgen [-A action] [word]
+    Display possible completions depending on the options.
+
+    Write the names generated by each ACTION that start with WORD, one per
+    line. If WORD is not given, all names are written.
+
+    Actions:
+    alias       alias names
+    builtin     names of shell builtins
+    command     names of shell builtins and executables in $PATH
+    function    names of shell functions; bsh does not support functions, so
+                there are none
+
+    Exit Status:
+    Returns success unless an invalid option is supplied or no names were
+    generated.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let mut actions = Vec::new();
+        let mut word = None;
+        let mut args = args.iter().map(AsRef::as_ref);
+        while let Some(arg) = args.next() {
+            match arg {
+                "-A" => {
+                    let action = args.next().ok_or_else(|| {
+                        Error::builtin_command("compgen: -A: option requires an argument", 2)
+                    })?;
+                    actions.push(action);
+                }
+                _ if arg.starts_with('-') || word.is_some() => {
+                    return Err(Error::builtin_command(
+                        format!("compgen: usage: {}", Self::usage()),
+                        2,
+                    ))
+                }
+                _ => word = Some(arg),
+            }
+        }
+
+        let word = word.unwrap_or("");
+        let mut names = Vec::new();
+        for action in actions {
+            match action {
+                "alias" => names.extend(
+                    shell
+                        .aliases()
+                        .keys()
+                        .filter(|name| name.starts_with(word))
+                        .cloned(),
+                ),
+                "function" => (),
+                "builtin" => names.extend(builtin_names(word)),
+                "command" => names.extend(complete::command_names(word)),
+                _ => {
+                    return Err(Error::builtin_command(
+                        format!("compgen: {}: invalid action name", action),
+                        2,
+                    ))
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+
+        if names.is_empty() {
+            return Err(Error::builtin_command("", 1));
+        }
+        for name in names {
+            writeln!(stdout, "{}", name).context(ErrorKind::Io)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the names of the builtins starting with `prefix`.
+fn builtin_names(prefix: &str) -> Vec<String> {
+    builtins::all_builtins()
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    fn compgen(args: &[&str]) -> Result<String> {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let mut output = Vec::new();
+        Compgen::run(&mut *shell, args, &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_compgen_builtin() {
+        assert_eq!(
+            compgen(&["-A", "builtin", "comp"]).unwrap(),
+            "compgen\ncomplete\ncompopt\n"
+        );
+        assert!(compgen(&["-A", "builtin"]).unwrap().contains("\nhelp\n"));
+        assert!(compgen(&["-A", "builtin", "nonexistent"]).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_compgen_command() {
+        let output = compgen(&["-A", "command", "-A", "builtin", "s"]).unwrap();
+        let names: Vec<&str> = output.lines().collect();
+        assert!(names.contains(&"sh"));
+        assert!(names.iter().all(|name| name.starts_with('s')));
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_compgen_unsupported_actions() {
+        assert!(compgen(&["-A", "function"]).is_err());
+        assert!(compgen(&["-A", "alias", "-A", "builtin", "help"]).is_ok());
+        match compgen(&["-A", "bogus"]).unwrap_err().kind() {
+            ErrorKind::BuiltinCommand { code, .. } => assert_eq!(*code, 2),
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+        assert!(compgen(&["-A"]).is_err());
+        assert!(compgen(&["-x"]).is_err());
+    }
+}
@@ -0,0 +1,532 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+
+use regex::Regex;
+
+use crate::builtins::{self, prelude::*};
+use crate::core::{conditional, glob};
+use crate::util;
+
+/// The characters `IFS` defaults to when it is unset.
+const DEFAULT_IFS: &str = " \t\n";
+
+/// The completion function used for commands without a completion spec, unless changed with
+/// `complete -D`.
+pub const DEFAULT_COMPLETION_FUNCTION: &str = "__bsh_default_complete";
+
+/// Generates the candidates for `word`, which is the command name if `is_command` is `true`.
+/// Returns `None` to complete filenames instead.
+type CompletionFunction = fn(word: &str, is_command: bool) -> Option<Vec<String>>;
+
+/// The functions that may be given to `complete -F`, as bsh does not support shell functions.
+const COMPLETION_FUNCTIONS: &[(&str, CompletionFunction)] =
+    &[(DEFAULT_COMPLETION_FUNCTION, default_complete)];
+
+thread_local! {
+    /// Completion specifications defined with `complete`.
+    static COMPLETION_REGISTRY: RefCell<CompletionRegistry> = RefCell::new(Default::default());
+}
+
+/// Completion specifications by command name, and the completion used for other commands.
+#[derive(Debug)]
+struct CompletionRegistry {
+    specs: BTreeMap<String, CompletionSpec>,
+    /// The completion function for commands without a spec, from `complete -D`.
+    default_completion: Option<String>,
+}
+
+impl Default for CompletionRegistry {
+    fn default() -> Self {
+        Self {
+            specs: BTreeMap::new(),
+            default_completion: Some(DEFAULT_COMPLETION_FUNCTION.to_string()),
+        }
+    }
+}
+
+/// How to generate the candidates for completing the arguments of a command.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompletionSpec {
+    /// Words split by `IFS`, from `-W`.
+    wordlist: Option<String>,
+    /// Glob pattern matching files, from `-G`.
+    glob: Option<String>,
+    /// Completion function, from `-F`.
+    function: Option<String>,
+    /// Glob pattern removing candidates, from `-X`. A leading `!` keeps only matching candidates.
+    exclude: Option<String>,
+}
+
+impl CompletionSpec {
+    /// Returns the candidates starting with `word`, splitting the wordlist on the characters in
+    /// `ifs`.
+    pub fn candidates(&self, word: &str, ifs: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = Vec::new();
+        if let Some(ref wordlist) = self.wordlist {
+            let words = wordlist
+                .split(|c| ifs.contains(c))
+                .filter(|word| !word.is_empty());
+            candidates.extend(words.map(str::to_string));
+        }
+        if let Some(ref glob) = self.glob {
+            candidates.extend(glob::glob_expand(glob));
+        }
+        if let Some(function) = self.function.as_deref().and_then(completion_function) {
+            candidates.extend(function(word, false).unwrap_or_default());
+        }
+
+        candidates.retain(|candidate| candidate.starts_with(word));
+        if let Some(ref exclude) = self.exclude {
+            let (pattern, keep_matches) = match exclude.strip_prefix('!') {
+                Some(pattern) => (pattern, true),
+                None => (exclude.as_str(), false),
+            };
+            if let Some(regex) = glob_regex(pattern) {
+                candidates.retain(|candidate| regex.is_match(candidate) == keep_matches);
+            }
+        }
+        candidates
+    }
+
+    /// Formats the spec as a `complete` command that defines it for `name`.
+    fn display(&self, name: &str) -> String {
+        let mut command = String::from("complete");
+        let options = [
+            ("-W", &self.wordlist),
+            ("-G", &self.glob),
+            ("-F", &self.function),
+            ("-X", &self.exclude),
+        ];
+        for (flag, value) in options.iter() {
+            if let Some(value) = value {
+                command.push_str(&format!(" {} {}", flag, util::quote_for_shell(value)));
+            }
+        }
+        command.push(' ');
+        command.push_str(name);
+        command
+    }
+}
+
+/// Returns the completion spec defined for `command`, if any.
+pub fn completion_spec(command: &str) -> Option<CompletionSpec> {
+    COMPLETION_REGISTRY.with(|registry| registry.borrow().specs.get(command).cloned())
+}
+
+/// Returns the candidates for completing `word` in a command line starting with `command`, or
+/// `None` to complete filenames instead. `word` is the command name if `is_command` is `true`.
+///
+/// The arguments of commands with a completion spec are completed from the spec, and other words
+/// by the default completion function.
+pub fn complete_word(command: Option<&str>, word: &str, is_command: bool) -> Option<Vec<String>> {
+    if !is_command {
+        if let Some(spec) = command.and_then(completion_spec) {
+            return Some(spec.candidates(word, &completion_ifs()));
+        }
+    }
+
+    let default_completion =
+        COMPLETION_REGISTRY.with(|registry| registry.borrow().default_completion.clone());
+    default_completion
+        .as_deref()
+        .and_then(completion_function)
+        .and_then(|function| function(word, is_command))
+}
+
+/// Returns the characters the wordlists of completion specs are split on.
+pub fn completion_ifs() -> String {
+    env::var("IFS").unwrap_or_else(|_| DEFAULT_IFS.to_string())
+}
+
+fn completion_function(name: &str) -> Option<CompletionFunction> {
+    COMPLETION_FUNCTIONS
+        .iter()
+        .find(|(function_name, _)| *function_name == name)
+        .map(|(_, function)| *function)
+}
+
+/// Completes builtins and executables in `$PATH` for the command name, unless it looks like a
+/// path, and filenames otherwise.
+fn default_complete(word: &str, is_command: bool) -> Option<Vec<String>> {
+    let is_path = ["/", "./", "../", "~/"]
+        .iter()
+        .any(|prefix| word.starts_with(prefix));
+    if !is_command || is_path {
+        return None;
+    }
+
+    Some(command_names(word))
+}
+
+/// Returns the names of the builtins and executables in `$PATH` starting with `prefix`, sorted
+/// and without duplicates.
+pub(crate) fn command_names(prefix: &str) -> Vec<String> {
+    let mut names: Vec<String> = builtins::all_builtins()
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    if let Some(path_var) = env::var_os("PATH") {
+        names.extend(util::path::executables_in_path(prefix, &path_var));
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Converts the glob `pattern` to a regex matching whole words.
+fn glob_regex(pattern: &str) -> Option<Regex> {
+    Regex::new(&format!("^{}$", conditional::glob_to_regex(pattern))).ok()
+}
+
+pub struct Complete;
+
+impl builtins::BuiltinCommand for Complete {
+    const NAME: &'static str = builtins::COMPLETE_NAME;
+
+    const HELP: &'static str = "\
+complete: complete [-prD] [-W wordlist] [-G globpat] [-F function] [-X filterpat] [name ...]
+    Specify how arguments are to be completed.
+
+    For each NAME, specify how arguments are to be completed. If no options
+    or NAMEs are given, existing completion specifications are printed in a
+    way that allows them to be reused as input.
+
+    -p          print existing completion specifications
+    -r          remove a completion specification for each NAME, or all
+                specifications if no NAMEs are given
+    -D          apply -F, -p, or -r to the default completion, which is used
+                for commands without a completion specification
+    -W WORDLIST complete from the words in WORDLIST, split using $IFS
+    -G GLOBPAT  complete from the files matching GLOBPAT
+    -F FUNCTION complete from the candidates generated by FUNCTION
+    -X FILTERPAT
+                remove the candidates matching FILTERPAT; if FILTERPAT starts
+                with `!', remove the candidates that do not match it
+
+    Completion Functions:
+    __bsh_default_complete
+                complete commands for the first word, unless it looks like a
+                path, and filenames otherwise; this is the default completion
+
+    Exit Status:
+    Returns success unless an invalid option is supplied or NAME does not
+    have a completion specification defined.";
+
+    fn run<T: AsRef<str>>(
+        _shell: &mut dyn Shell,
+        args: &[T],
+        stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let mut print = false;
+        let mut remove = false;
+        let mut default = false;
+        let mut spec = CompletionSpec::default();
+        let mut names = Vec::new();
+        let mut args = args.iter().map(AsRef::as_ref);
+        while let Some(arg) = args.next() {
+            let value = match arg {
+                "-p" => {
+                    print = true;
+                    continue;
+                }
+                "-r" => {
+                    remove = true;
+                    continue;
+                }
+                "-D" => {
+                    default = true;
+                    continue;
+                }
+                "-W" => &mut spec.wordlist,
+                "-G" => &mut spec.glob,
+                "-F" => &mut spec.function,
+                "-X" => &mut spec.exclude,
+                _ if arg.starts_with('-') => return Err(usage_error()),
+                _ => {
+                    names.push(arg.to_string());
+                    continue;
+                }
+            };
+            let option_value = args.next().ok_or_else(|| {
+                Error::builtin_command(format!("complete: {}: option requires an argument", arg), 2)
+            })?;
+            *value = Some(option_value.to_string());
+        }
+
+        if let Some(ref function) = spec.function {
+            if completion_function(function).is_none() {
+                return Err(Error::builtin_command(
+                    format!("complete: {}: no such completion function", function),
+                    1,
+                ));
+            }
+        }
+
+        COMPLETION_REGISTRY.with(|registry| {
+            let registry = &mut *registry.borrow_mut();
+            if default {
+                return update_default_completion(registry, print, remove, spec, stdout);
+            }
+
+            let specs = &mut registry.specs;
+            if remove {
+                if names.is_empty() {
+                    specs.clear();
+                }
+                names.retain(|name| specs.remove(name).is_none());
+                return report_missing(names.iter());
+            }
+
+            if print || spec == CompletionSpec::default() {
+                if names.is_empty() {
+                    if let Some(ref function) = registry.default_completion {
+                        writeln!(stdout, "{}", display_default(function)).context(ErrorKind::Io)?;
+                    }
+                    for (name, spec) in specs.iter() {
+                        writeln!(stdout, "{}", spec.display(name)).context(ErrorKind::Io)?;
+                    }
+                    return Ok(());
+                }
+                for name in &names {
+                    if let Some(spec) = specs.get(name) {
+                        writeln!(stdout, "{}", spec.display(name)).context(ErrorKind::Io)?;
+                    }
+                }
+                return report_missing(names.iter().filter(|name| !specs.contains_key(*name)));
+            }
+
+            for name in names {
+                specs.insert(name, spec.clone());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Runs `complete -D`, which only supports `-F`, `-p`, and `-r`.
+fn update_default_completion(
+    registry: &mut CompletionRegistry,
+    print: bool,
+    remove: bool,
+    spec: CompletionSpec,
+    stdout: &mut dyn Write,
+) -> Result<()> {
+    let function = spec.function.clone();
+    if spec
+        != (CompletionSpec {
+            function,
+            ..Default::default()
+        })
+    {
+        return Err(usage_error());
+    }
+
+    if remove {
+        registry.default_completion = None;
+    } else if let Some(function) = spec.function {
+        registry.default_completion = Some(function);
+    } else if let Some(ref function) = registry.default_completion {
+        writeln!(stdout, "{}", display_default(function)).context(ErrorKind::Io)?;
+    } else if print {
+        return Err(Error::builtin_command(
+            "complete: no default completion specification",
+            1,
+        ));
+    }
+    Ok(())
+}
+
+/// Formats the default completion as a `complete` command that defines it.
+fn display_default(function: &str) -> String {
+    format!("complete -F {} -D", util::quote_for_shell(function))
+}
+
+fn usage_error() -> Error {
+    Error::builtin_command(
+        format!(
+            "complete: usage: {}",
+            <Complete as builtins::BuiltinCommand>::usage()
+        ),
+        2,
+    )
+}
+
+/// Returns an error naming each of `names` that has no completion specification.
+fn report_missing<'a, I>(names: I) -> Result<()>
+where
+    I: Iterator<Item = &'a String>,
+{
+    let msg = names
+        .map(|name| format!("complete: {}: no completion specification", name))
+        .collect::<Vec<String>>()
+        .join("\n");
+    if msg.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::builtin_command(msg, 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs, io};
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    fn complete(args: &[&str]) -> Result<String> {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let mut output = Vec::new();
+        Complete::run(&mut *shell, args, &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    fn wordlist(words: &str) -> CompletionSpec {
+        CompletionSpec {
+            wordlist: Some(words.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_wordlist_split_by_ifs() {
+        assert_eq!(
+            wordlist("start stop\tstatus\nrestart").candidates("st", DEFAULT_IFS),
+            ["start", "stop", "status"]
+        );
+        assert_eq!(
+            wordlist("start:stop status:restart").candidates("", ":"),
+            ["start", "stop status", "restart"]
+        );
+        assert_eq!(
+            wordlist("start::stop").candidates("", ":"),
+            ["start", "stop"]
+        );
+    }
+
+    #[test]
+    fn test_completion_ifs() {
+        let key = "IFS";
+        let ifs = env::var_os(key);
+
+        env::set_var(key, ":");
+        assert_eq!(completion_ifs(), ":");
+        assert_eq!(
+            wordlist("a b:c").candidates("", &completion_ifs()),
+            ["a b", "c"]
+        );
+        env::remove_var(key);
+        assert_eq!(completion_ifs(), DEFAULT_IFS);
+
+        if let Some(ifs) = ifs {
+            env::set_var(key, ifs);
+        }
+    }
+
+    #[test]
+    fn test_exclude_pattern() {
+        let spec = CompletionSpec {
+            exclude: Some("*.o".to_string()),
+            ..wordlist("main.c main.o util.c")
+        };
+        assert_eq!(spec.candidates("", DEFAULT_IFS), ["main.c", "util.c"]);
+
+        let spec = CompletionSpec {
+            exclude: Some("!*.o".to_string()),
+            ..spec
+        };
+        assert_eq!(spec.candidates("", DEFAULT_IFS), ["main.o"]);
+    }
+
+    #[test]
+    fn test_glob_pattern() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for name in &["a.txt", "b.txt", "c.rs", ".hidden.txt"] {
+            fs::write(temp_dir.path().join(name), "").unwrap();
+        }
+        let dir = temp_dir.path().display();
+        let spec = CompletionSpec {
+            glob: Some(format!("{}/*.txt", dir)),
+            ..Default::default()
+        };
+        assert_eq!(
+            spec.candidates("", DEFAULT_IFS),
+            [format!("{}/a.txt", dir), format!("{}/b.txt", dir)]
+        );
+        assert_eq!(
+            spec.candidates(&format!("{}/b", dir), DEFAULT_IFS),
+            [format!("{}/b.txt", dir)]
+        );
+    }
+
+    #[test]
+    fn test_complete_builtin() {
+        complete(&["-W", "start stop", "-X", "st*p", "svc"]).unwrap();
+        assert_eq!(
+            completion_spec("svc").unwrap().candidates("", DEFAULT_IFS),
+            ["start"]
+        );
+        assert_eq!(
+            complete(&["-p", "svc"]).unwrap(),
+            "complete -W 'start stop' -X 'st*p' svc\n"
+        );
+
+        complete(&["-r", "svc"]).unwrap();
+        assert_eq!(completion_spec("svc"), None);
+        assert!(complete(&["-r", "svc"]).is_err());
+        assert!(complete(&["-p", "svc"]).is_err());
+        assert!(complete(&["-W"]).is_err());
+        assert!(complete(&["-q", "svc"]).is_err());
+
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        assert!(Complete::run::<&str>(&mut *shell, &[], &mut io::sink()).is_ok());
+    }
+
+    #[test]
+    fn test_default_completion() {
+        let candidates = complete_word(None, "comp", true).unwrap();
+        assert!(candidates.contains(&"complete".to_string()));
+        assert!(candidates.contains(&"compopt".to_string()));
+        assert!(candidates
+            .iter()
+            .all(|candidate| candidate.starts_with("comp")));
+
+        // Paths and arguments fall back to filename completion
+        assert_eq!(complete_word(None, "./comp", true), None);
+        assert_eq!(complete_word(None, "~/", true), None);
+        assert_eq!(complete_word(Some("echo"), "comp", false), None);
+
+        complete(&["-W", "start stop", "svc"]).unwrap();
+        assert_eq!(
+            complete_word(Some("svc"), "st", false),
+            Some(vec!["start".to_string(), "stop".to_string()])
+        );
+        assert!(complete_word(Some("svc"), "sv", true).is_some());
+    }
+
+    #[test]
+    fn test_complete_default_builtin() {
+        let default_spec = format!("complete -F {} -D\n", DEFAULT_COMPLETION_FUNCTION);
+        assert_eq!(complete(&["-p", "-D"]).unwrap(), default_spec);
+        assert_eq!(complete(&[]).unwrap(), default_spec);
+
+        complete(&["-r", "-D"]).unwrap();
+        assert_eq!(complete_word(None, "comp", true), None);
+        assert!(complete(&["-p", "-D"]).is_err());
+        assert_eq!(complete(&[]).unwrap(), "");
+
+        complete(&["-D", "-F", DEFAULT_COMPLETION_FUNCTION]).unwrap();
+        assert!(complete_word(None, "comp", true).is_some());
+        assert!(complete(&["-D", "-F", "_nonexistent"]).is_err());
+        assert!(complete(&["-D", "-W", "foo"]).is_err());
+
+        complete(&["-F", DEFAULT_COMPLETION_FUNCTION, "sudo"]).unwrap();
+        assert_eq!(
+            complete(&["-p", "sudo"]).unwrap(),
+            "complete -F __bsh_default_complete sudo\n"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::cell::Cell;
+
+use crate::builtins::{self, prelude::*};
+
+thread_local! {
+    /// Options for the completion in progress, or `None` if no completion is in progress.
+    static COMPLETION_OPTIONS: Cell<Option<CompletionOptions>> = Cell::new(None);
+}
+
+/// Options controlling how the candidates of a completion are displayed and inserted.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CompletionOptions {
+    /// Do not add a space after a completed word.
+    pub nospace: bool,
+    /// Treat candidates as filenames, e.g. do not add a space after a directory.
+    pub filenames: bool,
+    /// Do not sort candidates alphabetically.
+    pub nosort: bool,
+}
+
+impl CompletionOptions {
+    fn set(&mut self, name: &str, enabled: bool) -> Result<()> {
+        match name {
+            "nospace" => self.nospace = enabled,
+            "filenames" => self.filenames = enabled,
+            "nosort" => self.nosort = enabled,
+            _ => {
+                return Err(Error::builtin_command(
+                    format!("compopt: {}: invalid option name", name),
+                    2,
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f` as a completion starting with `options`, returning the options as modified by any
+/// `compopt` commands run by `f`.
+pub fn with_completion_options<T, F>(options: CompletionOptions, f: F) -> (T, CompletionOptions)
+where
+    F: FnOnce() -> T,
+{
+    let previous = COMPLETION_OPTIONS.with(|cell| cell.replace(Some(options)));
+    let result = f();
+    let options = COMPLETION_OPTIONS.with(|cell| cell.replace(previous));
+    (result, options.unwrap_or_default())
+}
+
+pub struct Compopt;
+
+impl builtins::BuiltinCommand for Compopt {
+    const NAME: &'static str = builtins::COMPOPT_NAME;
+
+    const HELP: &'static str = "\
+compopt: compopt [-o|+o option] ...
+    Modify completion options.
+
+    Modify the options of the completion currently being generated, or display
+    them if no OPTIONs are given.
+
+    -o      enable OPTION
+    +o      disable OPTION
+
+    Options:
+    nospace     do not add a space after a completed word
+    filenames   treat completions as filenames
+    nosort      do not sort completions alphabetically
+
+    Exit Status:
+    Returns 1 if not called while generating completions.";
+
+    fn run<T: AsRef<str>>(
+        _shell: &mut dyn Shell,
+        args: &[T],
+        stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let mut options = COMPLETION_OPTIONS.with(Cell::get).ok_or_else(|| {
+            Error::builtin_command("compopt: not currently executing completion function", 1)
+        })?;
+
+        if args.is_empty() {
+            let flag = |enabled| if enabled { "-o" } else { "+o" };
+            writeln!(
+                stdout,
+                "compopt {} nospace {} filenames {} nosort",
+                flag(options.nospace),
+                flag(options.filenames),
+                flag(options.nosort)
+            )
+            .context(ErrorKind::Io)?;
+            return Ok(());
+        }
+
+        let mut args = args.iter().map(AsRef::as_ref);
+        while let Some(flag) = args.next() {
+            let enabled = match flag {
+                "-o" => true,
+                "+o" => false,
+                _ => {
+                    return Err(Error::builtin_command(
+                        format!("compopt: usage: {}", Self::usage()),
+                        2,
+                    ))
+                }
+            };
+            let name = args.next().ok_or_else(|| {
+                Error::builtin_command(format!("compopt: {}: option requires an argument", flag), 2)
+            })?;
+            options.set(name, enabled)?;
+        }
+
+        COMPLETION_OPTIONS.with(|cell| cell.set(Some(options)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    fn compopt(args: &[&str]) -> (Result<()>, CompletionOptions) {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        with_completion_options(CompletionOptions::default(), || {
+            Compopt::run(&mut *shell, args, &mut io::sink())
+        })
+    }
+
+    #[test]
+    fn test_compopt_outside_completion() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let result = Compopt::run(&mut *shell, &["-o", "nospace"], &mut io::sink());
+        match result.unwrap_err().kind() {
+            ErrorKind::BuiltinCommand { code, .. } => assert_eq!(*code, 1),
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_compopt_options() {
+        let (result, options) = compopt(&["-o", "nospace"]);
+        assert!(result.is_ok());
+        assert!(options.nospace);
+
+        let (result, options) = compopt(&["-o", "filenames"]);
+        assert!(result.is_ok());
+        assert!(options.filenames);
+
+        let (result, options) = compopt(&["-o", "nosort", "-o", "nospace", "+o", "nospace"]);
+        assert!(result.is_ok());
+        assert_eq!(
+            options,
+            CompletionOptions {
+                nosort: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_compopt_invalid_options() {
+        assert!(compopt(&["-o", "bogus"]).0.is_err());
+        assert!(compopt(&["-o"]).0.is_err());
+        assert!(compopt(&["nospace"]).0.is_err());
+    }
+
+    #[test]
+    fn test_compopt_display() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let options = CompletionOptions {
+            filenames: true,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let (result, _) = with_completion_options(options, || {
+            Compopt::run::<&str>(&mut *shell, &[], &mut output)
+        });
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "compopt +o nospace -o filenames +o nosort\n"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use std::io;
+use std::process;
+
+use crate::builtins::{self, history, prelude::*};
+use crate::shell::{ShellOption, DEBUG_TRAP};
+
+pub struct Debug;
+
+impl builtins::BuiltinCommand for Debug {
+    const NAME: &'static str = builtins::DEBUG_NAME;
+
+    const HELP: &'static str = "\
+debug: debug start | stop | set [<option> <value>] | trap [command] | jobs | history
+    Inspect and modify the shell at runtime.
+
+    start       enable command tracing
+    stop        disable command tracing
+    set         set OPTION (failglob, posix or xtrace) to VALUE (on or off); without
+                OPTION, print each option's value
+    trap        run COMMAND before each command, which is available in
+                $BASH_COMMAND; without COMMAND, remove the trap
+    jobs        print the job table to stderr
+    history     print the command history to stderr";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        match args.as_slice() {
+            ["start"] => shell.set_option(ShellOption::Xtrace, true),
+            ["stop"] => shell.set_option(ShellOption::Xtrace, false),
+            ["set"] => {
+                for option in ShellOption::ALL {
+                    let value = if shell.get_option(*option) {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    writeln!(stdout, "{}\t{}", option.name(), value).context(ErrorKind::Io)?;
+                }
+            }
+            ["set", option, value] => set_option(shell, option, value)?,
+            ["trap"] => shell.set_trap(DEBUG_TRAP, None),
+            ["trap", handler @ ..] => shell.set_trap(DEBUG_TRAP, Some(handler.join(" "))),
+            ["jobs"] => {
+                let jobs: Vec<String> = shell.get_jobs().iter().map(|job| job.display()).collect();
+                print_with_pid(&jobs.join("\n"))?;
+            }
+            ["history"] => {
+                let editor = shell.editor();
+                print_with_pid(&history::history_display(
+                    editor,
+                    editor.get_history_count(),
+                ))?;
+            }
+            _ => {
+                return Err(Error::builtin_command(
+                    format!("debug: usage: {}", Self::usage()),
+                    2,
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn set_option(shell: &mut dyn Shell, option: &str, value: &str) -> Result<()> {
+    let enabled = match value {
+        "on" => true,
+        "off" => false,
+        _ => {
+            let msg = format!("debug: {}: value must be on or off", value);
+            return Err(Error::builtin_command(msg, 1));
+        }
+    };
+
+    let option = ShellOption::from_name(option).ok_or_else(|| {
+        Error::builtin_command(format!("debug: {}: invalid option name", option), 1)
+    })?;
+    shell.set_option(option, enabled);
+    Ok(())
+}
+
+/// Writes each line of `output` to stderr, prefixed with the shell's PID.
+fn print_with_pid(output: &str) -> Result<()> {
+    let pid = process::id();
+    let stderr = io::stderr();
+    let mut handle = stderr.lock();
+    for line in output.lines() {
+        writeln!(handle, "{}: {}", pid, line).context(ErrorKind::Io)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn debug_toggles_xtrace() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        assert!(!shell.get_option(ShellOption::Xtrace));
+
+        Debug::run(&mut *shell, &["start"], &mut io::sink()).unwrap();
+        assert!(shell.get_option(ShellOption::Xtrace));
+        Debug::run(&mut *shell, &["stop"], &mut io::sink()).unwrap();
+        assert!(!shell.get_option(ShellOption::Xtrace));
+    }
+
+    #[test]
+    fn debug_set_option() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        Debug::run(&mut *shell, &["set", "posix", "on"], &mut io::sink()).unwrap();
+        assert!(shell.get_option(ShellOption::Posix));
+        let mut output = Vec::new();
+        Debug::run(&mut *shell, &["set"], &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "checkwinsize\ton\ndotglob\toff\nerrexit\toff\nexpand_aliases\ton\nextglob\toff\n\
+             failglob\toff\nglobstar\toff\nhistappend\toff\nhistverify\toff\nnocaseglob\toff\n\
+             noclobber\toff\nnounset\toff\nnullglob\toff\npipefail\toff\nposix\ton\nvi\toff\n\
+             xtrace\toff\n"
+        );
+        Debug::run(&mut *shell, &["set", "posix", "off"], &mut io::sink()).unwrap();
+        assert!(!shell.get_option(ShellOption::Posix));
+
+        assert!(Debug::run(&mut *shell, &["set", "posix", "maybe"], &mut io::sink()).is_err());
+        assert!(Debug::run(&mut *shell, &["set", "foo", "on"], &mut io::sink()).is_err());
+        assert!(Debug::run(&mut *shell, &["bogus"], &mut io::sink()).is_err());
+    }
+}
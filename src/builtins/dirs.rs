@@ -1,7 +1,10 @@
 use std::env;
-use std::path::Path;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
 
 use crate::builtins::{self, prelude::*};
+use crate::shell::print_error;
 
 pub struct Cd;
 
@@ -12,7 +15,8 @@ impl builtins::BuiltinCommand for Cd {
 cd: cd [dir]
     Change the current directory to DIR. The variable $HOME is the default dir.
     If DIR is '-', then the current directory will be the variable $OLDPWD,
-    which is the last working directory.";
+    which is the last working directory. $PWD is set to the new directory,
+    following symbolic links.";
 
     fn run<T: AsRef<str>>(
         _shell: &mut dyn Shell,
@@ -40,8 +44,380 @@ cd: cd [dir]
             Some(val) => Path::new(val).to_path_buf(),
         };
 
-        env::set_var("OLDPWD", env::current_dir().context(ErrorKind::Io)?);
-        env::set_current_dir(dir).context(ErrorKind::Io)?;
+        change_dir(&dir).context(ErrorKind::Io)?;
         Ok(())
     }
 }
+
+pub struct Pwd;
+
+impl builtins::BuiltinCommand for Pwd {
+    const NAME: &'static str = builtins::PWD_NAME;
+
+    const HELP: &'static str = "\
+pwd: pwd [-LP]
+    Print the name of the current working directory.
+
+    -L      print the value of $PWD if it names the current working
+            directory (the default)
+    -P      print the physical directory, without any symbolic links";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let mut physical = false;
+        for arg in args {
+            match arg.as_ref() {
+                "-L" => physical = false,
+                "-P" => physical = true,
+                arg => {
+                    let message = format!("pwd: {}: invalid option", arg);
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 2));
+                }
+            }
+        }
+
+        let dir = if physical {
+            fs::canonicalize(env::current_dir().context(ErrorKind::Io)?).context(ErrorKind::Io)?
+        } else {
+            logical_current_dir().context(ErrorKind::Io)?
+        };
+        writeln!(stdout, "{}", dir.display()).context(ErrorKind::Io)?;
+        Ok(())
+    }
+}
+
+pub struct Pushd;
+
+impl builtins::BuiltinCommand for Pushd {
+    const NAME: &'static str = builtins::PUSHD_NAME;
+
+    const HELP: &'static str = "\
+pushd: pushd [dir | +N | -N]
+    Add directories to stack.
+
+    Adds a directory to the top of the directory stack, or rotates
+    the stack, making the new top of the stack the current working
+    directory. With no arguments, exchanges the top two directories.
+
+    +N      Rotates the stack so that the Nth directory (counting
+            from the left of the list shown by `dirs', starting with
+            zero) is at the top.
+    -N      Rotates the stack so that the Nth directory (counting
+            from the right of the list shown by `dirs', starting with
+            zero) is at the top.
+    dir     Adds DIR to the directory stack at the top, making it the
+            new current working directory.
+
+    The `dirs' builtin displays the directory stack.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let mut stack = full_stack(shell)?;
+        match args.as_slice() {
+            [] if stack.len() < 2 => return Err(stack_error(shell, "pushd: no other directory")),
+            [] => stack.swap(0, 1),
+            [arg] if is_stack_index(arg) => match stack_index(arg, stack.len()) {
+                Some(index) => stack.rotate_left(index),
+                None => return Err(index_out_of_range(shell, Self::NAME, arg)),
+            },
+            [dir] => stack.insert(0, PathBuf::from(dir)),
+            _ => return Err(stack_error(shell, "pushd: too many arguments")),
+        }
+
+        if let Err(e) = change_dir(&stack[0]) {
+            return Err(stack_error(
+                shell,
+                &format!("pushd: {}: {}", stack[0].display(), e),
+            ));
+        }
+        stack[0] = logical_current_dir().context(ErrorKind::Io)?;
+        print_stack(stdout, &stack, &DirsOptions::default())?;
+        *shell.dir_stack_mut() = stack.split_off(1);
+        Ok(())
+    }
+}
+
+pub struct Popd;
+
+impl builtins::BuiltinCommand for Popd {
+    const NAME: &'static str = builtins::POPD_NAME;
+
+    const HELP: &'static str = "\
+popd: popd [+N | -N]
+    Remove directories from stack.
+
+    Removes entries from the directory stack. With no arguments, removes
+    the top directory from the stack, and changes to the new top directory.
+
+    +N      Removes the Nth entry counting from the left of the list
+            shown by `dirs', starting with zero. For example: `popd +0'
+            removes the first directory, `popd +1' the second.
+    -N      Removes the Nth entry counting from the right of the list
+            shown by `dirs', starting with zero. For example: `popd -0'
+            removes the last directory, `popd -1' the next to last.
+
+    The `dirs' builtin displays the directory stack.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let mut stack = full_stack(shell)?;
+        let index = match args.as_slice() {
+            [] => 0,
+            [arg] if is_stack_index(arg) => match stack_index(arg, stack.len()) {
+                Some(index) => index,
+                None => return Err(index_out_of_range(shell, Self::NAME, arg)),
+            },
+            [arg] => {
+                let message = format!("popd: {}: invalid argument", arg);
+                print_error(shell.shell_stderr(), &message);
+                return Err(Error::builtin_command(message, 2));
+            }
+            _ => return Err(stack_error(shell, "popd: too many arguments")),
+        };
+        if stack.len() < 2 {
+            return Err(stack_error(shell, "popd: directory stack empty"));
+        }
+
+        stack.remove(index);
+        if index == 0 {
+            if let Err(e) = change_dir(&stack[0]) {
+                return Err(stack_error(
+                    shell,
+                    &format!("popd: {}: {}", stack[0].display(), e),
+                ));
+            }
+        }
+        print_stack(stdout, &stack, &DirsOptions::default())?;
+        *shell.dir_stack_mut() = stack.split_off(1);
+        Ok(())
+    }
+}
+
+pub struct Dirs;
+
+impl builtins::BuiltinCommand for Dirs {
+    const NAME: &'static str = builtins::DIRS_NAME;
+
+    const HELP: &'static str = "\
+dirs: dirs [-clpv] [+N] [-N]
+    Display directory stack.
+
+    Display the list of currently remembered directories. Directories
+    find their way onto the list with the `pushd' command; you can get
+    back up through the list with the `popd' command.
+
+    -c      clear the directory stack by deleting all of the elements
+    -l      do not print tilde-prefixed versions of directories relative
+            to your home directory
+    -p      print the directory stack with one entry per line
+    -v      print the directory stack with one entry per line prefixed
+            with its position in the stack
+
+    +N      Displays the Nth entry counting from the left of the list
+            shown by dirs when invoked without options, starting with
+            zero.
+    -N      Displays the Nth entry counting from the right of the list
+            shown by dirs when invoked without options, starting with
+            zero.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let mut options = DirsOptions::default();
+        let mut index = None;
+        for arg in args {
+            let arg = arg.as_ref();
+            if is_stack_index(arg) {
+                index = Some(arg);
+                continue;
+            }
+            let flags = match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() => flags,
+                _ => {
+                    let message = format!("dirs: {}: invalid argument", arg);
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 2));
+                }
+            };
+            for flag in flags.chars() {
+                match flag {
+                    'c' => options.clear = true,
+                    'l' => options.long = true,
+                    'p' => options.per_line = true,
+                    'v' => options.numbered = true,
+                    _ => {
+                        let message = format!(
+                            "dirs: -{}: invalid option\ndirs: usage: {}",
+                            flag,
+                            Self::usage().trim_start_matches("dirs: ")
+                        );
+                        print_error(shell.shell_stderr(), &message);
+                        return Err(Error::builtin_command(message, 2));
+                    }
+                }
+            }
+        }
+
+        if options.clear {
+            shell.dir_stack_mut().clear();
+            return Ok(());
+        }
+        let stack = full_stack(shell)?;
+        match index {
+            Some(arg) => match stack_index(arg, stack.len()) {
+                Some(index) => {
+                    writeln!(stdout, "{}", display_dir(&stack[index], options.long))
+                        .context(ErrorKind::Io)?;
+                    Ok(())
+                }
+                None => Err(index_out_of_range(shell, Self::NAME, arg)),
+            },
+            None => print_stack(stdout, &stack, &options),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct DirsOptions {
+    clear: bool,
+    long: bool,
+    per_line: bool,
+    numbered: bool,
+}
+
+/// Returns the current directory followed by the directories saved with `pushd`, as shown by
+/// `dirs`.
+fn full_stack(shell: &dyn Shell) -> Result<Vec<PathBuf>> {
+    let mut stack = vec![logical_current_dir().context(ErrorKind::Io)?];
+    stack.extend_from_slice(shell.dir_stack());
+    Ok(stack)
+}
+
+/// Prints `stack` on one line, or one directory per line, e.g. for `dirs -v`.
+fn print_stack(stdout: &mut dyn Write, stack: &[PathBuf], options: &DirsOptions) -> Result<()> {
+    let dirs = stack.iter().map(|dir| display_dir(dir, options.long));
+    if options.numbered {
+        for (index, dir) in dirs.enumerate() {
+            writeln!(stdout, "{:2}  {}", index, dir).context(ErrorKind::Io)?;
+        }
+    } else if options.per_line {
+        for dir in dirs {
+            writeln!(stdout, "{}", dir).context(ErrorKind::Io)?;
+        }
+    } else {
+        writeln!(stdout, "{}", dirs.collect::<Vec<_>>().join(" ")).context(ErrorKind::Io)?;
+    }
+    Ok(())
+}
+
+/// Returns `dir` with `$HOME` replaced by `~`, unless `long` is set.
+fn display_dir(dir: &Path, long: bool) -> String {
+    let home = env::var_os("HOME").filter(|home| !home.is_empty());
+    match home.and_then(|home| dir.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if !long && rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) if !long => format!("~/{}", rest.display()),
+        _ => dir.display().to_string(),
+    }
+}
+
+/// Returns `true` if `arg` is a position in the directory stack, e.g. `+1` or `-0`.
+fn is_stack_index(arg: &str) -> bool {
+    arg.len() > 1
+        && (arg.starts_with('+') || arg.starts_with('-'))
+        && arg[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Returns the index in a stack of `len` directories of `+N`, counting from the left, or `-N`,
+/// counting from the right, or `None` if it is out of range.
+fn stack_index(arg: &str, len: usize) -> Option<usize> {
+    let n = arg[1..].parse::<usize>().ok().filter(|&n| n < len)?;
+    if arg.starts_with('+') {
+        Some(n)
+    } else {
+        Some(len - 1 - n)
+    }
+}
+
+fn index_out_of_range(shell: &mut dyn Shell, name: &str, arg: &str) -> Error {
+    stack_error(
+        shell,
+        &format!("{}: {}: directory stack index out of range", name, arg),
+    )
+}
+
+/// Prints `message` and returns it as an error.
+fn stack_error(shell: &mut dyn Shell, message: &str) -> Error {
+    print_error(shell.shell_stderr(), message);
+    Error::builtin_command(message, 1)
+}
+
+/// Changes the current directory to `dir`, setting `$PWD` and `$OLDPWD`.
+fn change_dir(dir: &Path) -> io::Result<()> {
+    let old_dir = logical_current_dir()?;
+    let logical_dir = normalize(&old_dir.join(dir));
+    // Like bash, fall back to the physical path, e.g. if `..` follows a symbolic link to a
+    // directory that cannot be entered through its logical parent.
+    let new_dir = if env::set_current_dir(&logical_dir).is_ok() {
+        logical_dir
+    } else {
+        env::set_current_dir(dir)?;
+        env::current_dir()?
+    };
+    env::set_var("OLDPWD", old_dir);
+    env::set_var("PWD", new_dir);
+    Ok(())
+}
+
+/// Returns `$PWD` if it is an absolute path to the current directory without `.` or `..`, which
+/// may include symbolic links, or the current directory otherwise.
+fn logical_current_dir() -> io::Result<PathBuf> {
+    let current_dir = env::current_dir()?;
+    let pwd = match env::var_os("PWD") {
+        Some(pwd) => PathBuf::from(pwd),
+        None => return Ok(current_dir),
+    };
+    let is_normal = pwd.is_absolute()
+        && pwd
+            .components()
+            .all(|c| !matches!(c, Component::CurDir | Component::ParentDir));
+    if is_normal && fs::canonicalize(&pwd).ok() == fs::canonicalize(&current_dir).ok() {
+        Ok(pwd)
+    } else {
+        Ok(current_dir)
+    }
+}
+
+/// Removes the `.` and `..` components of `path` without following symbolic links.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("/a/./b/../c")), Path::new("/a/c"));
+        assert_eq!(normalize(Path::new("/a/b/../../..")), Path::new("/"));
+        assert_eq!(normalize(Path::new("/a/b/")), Path::new("/a/b"));
+    }
+
+    #[test]
+    fn test_stack_index() {
+        assert!(is_stack_index("+0") && is_stack_index("-12"));
+        assert!(!is_stack_index("+") && !is_stack_index("-v") && !is_stack_index("3"));
+        assert_eq!(stack_index("+0", 3), Some(0));
+        assert_eq!(stack_index("-0", 3), Some(2));
+        assert_eq!(stack_index("-2", 3), Some(0));
+        assert_eq!(stack_index("+3", 3), None);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::builtins::{self, prelude::*};
+
+pub struct Echo;
+
+impl builtins::BuiltinCommand for Echo {
+    const NAME: &'static str = builtins::ECHO_NAME;
+
+    const HELP: &'static str = "\
+echo: echo [-neE] [arg ...]
+    Write arguments to the standard output.
+
+    Display the ARGs, separated by a single space character and followed by a
+    newline, on the standard output.
+
+    -n      do not append a newline
+    -e      enable interpretation of the following backslash escapes
+    -E      explicitly suppress interpretation of backslash escapes
+
+    `echo' interprets the following backslash-escaped characters:
+      \\a    alert (bell)
+      \\b    backspace
+      \\c    suppress further output
+      \\e    escape character
+      \\f    form feed
+      \\n    new line
+      \\r    carriage return
+      \\t    horizontal tab
+      \\v    vertical tab
+      \\\\    backslash
+      \\0nnn the character whose ASCII code is NNN (octal)
+      \\xHH  the eight-bit character whose value is HH (hexadecimal)";
+
+    fn run<T: AsRef<str>>(
+        _shell: &mut dyn Shell,
+        args: &[T],
+        stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let mut newline = true;
+        let mut escapes = false;
+        // Like bash, options end at the first argument that is not a valid option, e.g. `-x`.
+        let mut args = args.iter().map(AsRef::as_ref).peekable();
+        while let Some(flags) = args
+            .peek()
+            .and_then(|arg| arg.strip_prefix('-'))
+            .filter(|flags| !flags.is_empty() && flags.chars().all(|c| "neE".contains(c)))
+        {
+            for flag in flags.chars() {
+                match flag {
+                    'n' => newline = false,
+                    'e' => escapes = true,
+                    _ => escapes = false,
+                }
+            }
+            args.next();
+        }
+
+        let mut output = Vec::new();
+        for (i, arg) in args.enumerate() {
+            if i > 0 {
+                output.push(b' ');
+            }
+            if !escapes {
+                output.extend_from_slice(arg.as_bytes());
+                continue;
+            }
+            let (bytes, stop) = interpret_escapes(arg);
+            output.extend(bytes);
+            if stop {
+                newline = false;
+                break;
+            }
+        }
+        if newline {
+            output.push(b'\n');
+        }
+
+        stdout.write_all(&output).context(ErrorKind::Io)?;
+        Ok(())
+    }
+}
+
+/// Interprets the backslash escapes of `echo -e` in `s`, and returns the bytes and whether `\c`
+/// ended the output.
+pub(crate) fn interpret_escapes(s: &str) -> (Vec<u8>, bool) {
+    let mut output = Vec::new();
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            output.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        i += 2;
+        let byte = match bytes[i - 1] {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'c' => return (output, true),
+            b'e' | b'E' => 0x1b,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            b'\\' => b'\\',
+            b'0' => parse_digits(bytes, &mut i, 8, 3),
+            b'x' if bytes.get(i).map_or(false, u8::is_ascii_hexdigit) => {
+                parse_digits(bytes, &mut i, 16, 2)
+            }
+            // Unknown escapes are left unchanged.
+            c => {
+                output.push(b'\\');
+                c
+            }
+        };
+        output.push(byte);
+    }
+    (output, false)
+}
+
+/// Parses at most `max_len` digits in `radix` starting at `bytes[*i]`, advancing `i` past them.
+fn parse_digits(bytes: &[u8], i: &mut usize, radix: u32, max_len: usize) -> u8 {
+    let mut value: u32 = 0;
+    for _ in 0..max_len {
+        match bytes.get(*i).and_then(|&b| (b as char).to_digit(radix)) {
+            Some(digit) => value = value * radix + digit,
+            None => break,
+        }
+        *i += 1;
+    }
+    value as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    fn echo(args: &[&str]) -> String {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let mut output = Vec::new();
+        Echo::run(&mut *shell, args, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_echo() {
+        assert_eq!(echo(&[]), "\n");
+        assert_eq!(echo(&["a", "b c"]), "a b c\n");
+        assert_eq!(echo(&["-n", "a"]), "a");
+        assert_eq!(echo(&["a\\tb"]), "a\\tb\n");
+        assert_eq!(echo(&["-e", "a\\tb\\n"]), "a\tb\n\n");
+        assert_eq!(echo(&["-n", "-e", "\\n"]), "\n");
+        assert_eq!(echo(&["-ne", "a", "\\\\"]), "a \\");
+        assert_eq!(echo(&["-eE", "\\n"]), "\\n\n");
+        assert_eq!(echo(&["-e", "a\\cb", "c"]), "a");
+        assert_eq!(echo(&["-x", "-n"]), "-x -n\n");
+        assert_eq!(echo(&["-", "--"]), "- --\n");
+        assert_eq!(echo(&["a", "-n"]), "a -n\n");
+    }
+
+    #[test]
+    fn test_interpret_escapes() {
+        let escape = |s| String::from_utf8(interpret_escapes(s).0).unwrap();
+        assert_eq!(escape("\\0101\\x42\\x4z"), "AB\x04z");
+        assert_eq!(escape("\\0"), "\0");
+        assert_eq!(escape("\\xz\\q\\"), "\\xz\\q\\");
+        assert_eq!(escape("\\a\\b\\e\\f\\r\\v"), "\x07\x08\x1b\x0c\r\x0b");
+    }
+}
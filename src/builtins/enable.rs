@@ -0,0 +1,136 @@
+use crate::builtins::{self, prelude::*};
+
+pub struct Enable;
+
+impl builtins::BuiltinCommand for Enable {
+    const NAME: &'static str = builtins::ENABLE_NAME;
+
+    const HELP: &'static str = "\
+enable: enable [-a] [-n] [-f filename] [name ...]
+    Enable and disable shell builtins.
+
+    Enables and disables builtin shell commands. Disabling a builtin allows
+    an external command with the same name to be run instead.
+
+    -a      print a list of builtins showing whether or not each is enabled
+    -n      disable each NAME or display a list of disabled builtins
+    -f      load builtin NAME from shared object FILENAME (not supported)
+
+    Without options, each NAME is enabled. If no NAMEs are given, the
+    enabled builtins are printed.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let mut all = false;
+        let mut disable = false;
+        let mut names = Vec::new();
+        let mut args = args.iter().map(AsRef::as_ref);
+        while let Some(arg) = args.next() {
+            match arg {
+                "-a" => all = true,
+                "-n" => disable = true,
+                "-f" => {
+                    let filename = args.next().unwrap_or_default();
+                    return Err(Error::builtin_command(
+                        format!(
+                            "enable: {}: cannot load builtins from shared objects",
+                            filename
+                        ),
+                        1,
+                    ));
+                }
+                _ if arg.starts_with('-') => {
+                    return Err(Error::builtin_command(
+                        format!("enable: usage: {}", Self::usage()),
+                        2,
+                    ))
+                }
+                _ => names.push(arg),
+            }
+        }
+
+        if names.is_empty() {
+            for name in builtins::BUILTIN_NAMES {
+                let enabled = shell.is_builtin_enabled(name);
+                if all || enabled != disable {
+                    let flag = if enabled { "" } else { "-n " };
+                    writeln!(stdout, "enable {}{}", flag, name).context(ErrorKind::Io)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let mut bad_args = Vec::new();
+        for name in names {
+            if builtins::BUILTIN_NAMES.contains(&name) {
+                shell.set_builtin_enabled(name, !disable);
+            } else {
+                bad_args.push(name);
+            }
+        }
+
+        if !bad_args.is_empty() {
+            let msg = bad_args
+                .iter()
+                .map(|arg| format!("enable: {}: not a shell builtin", arg))
+                .collect::<Vec<String>>()
+                .join("\n");
+            return Err(Error::builtin_command(msg, 1));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn test_enable_disable() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        assert!(builtins::is_builtin(&*shell, "cd"));
+
+        assert!(Enable::run(&mut *shell, &["-n", "cd", "help"], &mut io::sink()).is_ok());
+        assert!(!builtins::is_builtin(&*shell, "cd"));
+        assert!(!builtins::is_builtin(&*shell, "help"));
+
+        assert!(Enable::run(&mut *shell, &["cd"], &mut io::sink()).is_ok());
+        assert!(builtins::is_builtin(&*shell, "cd"));
+        assert!(!builtins::is_builtin(&*shell, "help"));
+    }
+
+    #[test]
+    fn test_enable_errors() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        assert!(Enable::run(&mut *shell, &["-n", "ls"], &mut io::sink()).is_err());
+        assert!(Enable::run(&mut *shell, &["-x"], &mut io::sink()).is_err());
+        assert!(Enable::run(&mut *shell, &["-f", "foo.so", "foo"], &mut io::sink()).is_err());
+    }
+
+    #[test]
+    fn test_enable_display() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        assert!(Enable::run(&mut *shell, &["-n", "cd"], &mut io::sink()).is_ok());
+
+        let mut output = Vec::new();
+        assert!(Enable::run(&mut *shell, &["-n"], &mut output).is_ok());
+        assert_eq!(String::from_utf8(output).unwrap(), "enable -n cd\n");
+
+        let mut output = Vec::new();
+        assert!(Enable::run::<&str>(&mut *shell, &[], &mut output).is_ok());
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("enable bg\n"));
+        assert!(!output.contains("cd"));
+
+        let mut output = Vec::new();
+        assert!(Enable::run(&mut *shell, &["-a"], &mut output).is_ok());
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("enable bg\n"));
+        assert!(output.contains("enable -n cd\n"));
+    }
+}
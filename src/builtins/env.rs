@@ -1,7 +1,16 @@
 use std::env;
-use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::iter;
+use std::process::{Command, Stdio};
 
-use crate::builtins::{self, prelude::*};
+use atty::{self, Stream};
+
+use crate::{
+    builtins::{self, prelude::*},
+    core::variable_expansion,
+    shell::{print_error, VarValue},
+    util,
+};
 
 pub struct Declare;
 
@@ -9,27 +18,571 @@ impl builtins::BuiltinCommand for Declare {
     const NAME: &'static str = builtins::DECLARE_NAME;
 
     const HELP: &'static str = "\
-declare: declare [name[=value] ...]
-    Declare a variable and assign it a value.";
+declare: declare [-aAfFirxp] [name[=value] ...]
+    Set variable values and attributes.
+
+    Declare variables and give them attributes. If no names are given,
+    display the attributes and values of all variables.
+
+    -f      display the definitions of functions
+    -F      display only the names of functions
+    -p      display the attributes and value of each name
+
+    -a      declare each name as an indexed array
+    -A      declare each name as an associative array
+    -i      make each name an integer; assigned values are evaluated as
+            arithmetic expressions
+    -r      make each name read-only
+    -x      export each name
+
+    Using `+' instead of `-' turns off the integer and export attributes.
+
+    Exit Status:
+    Returns success unless an invalid option is supplied, a variable is
+    read-only, or an assignment is invalid.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (options, args) = parse_declare_options(shell, &args)?;
+        if options.functions || options.function_names {
+            return print_functions(shell, args, options.function_names, stdout);
+        }
+        if options.print || (args.is_empty() && !options.has_attributes()) {
+            return print_variables(shell, args, stdout);
+        }
+
+        let mut bad_args = Vec::new();
+        let mut status = 0;
+        for arg in args {
+            let key_value: Vec<&str> = arg.splitn(2, '=').collect();
+            check_variable_assignment(shell, Self::NAME, key_value[0])?;
+            let name = match key_value[0] {
+                "" => {
+                    bad_args.push(arg);
+                    continue;
+                }
+                name => name,
+            };
+
+            if let Some(integer) = options.integer {
+                shell.set_integer(name, integer);
+            }
+            if options.array {
+                shell.declare_array(name);
+            } else if options.assoc_array {
+                shell.declare_assoc_array(name);
+            } else if let Some(value) = key_value.get(1) {
+                if shell.is_integer(name) {
+                    match shell.eval_arithmetic(value) {
+                        Ok(n) => shell.set_variable(name, &n.to_string()),
+                        Err(e) => {
+                            print_error(shell.shell_stderr(), format_args!("declare: {}", e));
+                            status = 1;
+                        }
+                    }
+                } else {
+                    shell.set_variable(name, value);
+                }
+            } else if !options.has_attributes() && shell.get_variable(name).is_none() {
+                shell.set_variable(name, "");
+            }
+            if let Some(exported) = options.exported {
+                shell.set_exported(name, exported);
+            }
+            if options.readonly {
+                shell.declare_readonly(name);
+            }
+        }
+
+        if !bad_args.is_empty() {
+            let msg = bad_args
+                .iter()
+                .map(|arg| format!("declare: {} is not a valid identifier", arg))
+                .collect::<Vec<String>>()
+                .join("\n");
+            return Err(Error::builtin_command(msg, 1));
+        }
+        if status != 0 {
+            return Err(Error::builtin_command("", status));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct DeclareOptions {
+    array: bool,
+    assoc_array: bool,
+    /// `Some(false)` for `+i`, which removes the attribute.
+    integer: Option<bool>,
+    readonly: bool,
+    /// `Some(false)` for `+x`, which removes the attribute.
+    exported: Option<bool>,
+    print: bool,
+    functions: bool,
+    function_names: bool,
+}
+
+impl DeclareOptions {
+    /// Returns `true` if the options change the attributes of variables, e.g. `-r`.
+    fn has_attributes(&self) -> bool {
+        self.array
+            || self.assoc_array
+            || self.integer.is_some()
+            || self.readonly
+            || self.exported.is_some()
+    }
+}
+
+/// Parses the options of `declare`, returning them and the arguments that follow.
+fn parse_declare_options<'a>(
+    shell: &mut dyn Shell,
+    args: &'a [&'a str],
+) -> Result<(DeclareOptions, &'a [&'a str])> {
+    let mut options = DeclareOptions::default();
+    let mut rest = args;
+    while let Some((enable, flags)) = rest.first().and_then(|arg| {
+        arg.strip_prefix('-')
+            .map(|flags| (true, flags))
+            .or_else(|| arg.strip_prefix('+').map(|flags| (false, flags)))
+            .filter(|(_, flags)| !flags.is_empty())
+    }) {
+        rest = &rest[1..];
+        if enable && flags == "-" {
+            break;
+        }
+        for flag in flags.chars() {
+            match (flag, enable) {
+                ('a', true) => options.array = true,
+                ('A', true) => options.assoc_array = true,
+                ('f', true) => options.functions = true,
+                ('F', true) => options.function_names = true,
+                ('i', _) => options.integer = Some(enable),
+                ('p', true) => options.print = true,
+                ('r', true) => options.readonly = true,
+                ('x', _) => options.exported = Some(enable),
+                _ => {
+                    let message = format!(
+                        "declare: {}{}: invalid option\ndeclare: usage: {}",
+                        if enable { '-' } else { '+' },
+                        flag,
+                        <Declare as builtins::BuiltinCommand>::usage()
+                            .trim_start_matches("declare: ")
+                    );
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 2));
+                }
+            }
+        }
+    }
+    Ok((options, rest))
+}
+
+/// Writes the variables `names`, or all variables if there are none, as `declare` commands with
+/// their attributes, e.g. `declare -rx NAME='value'`.
+fn print_variables(shell: &mut dyn Shell, names: &[&str], stdout: &mut dyn Write) -> Result<()> {
+    let names: Vec<String> = if names.is_empty() {
+        let mut names: Vec<String> = env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .filter(|name| variable_expansion::is_identifier(name))
+            .chain(shell.readonly_variables())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    } else {
+        names.iter().map(|name| name.to_string()).collect()
+    };
+
+    let mut status = 0;
+    for name in names {
+        let value = shell.get_value(&name);
+        let mut attributes = String::new();
+        match value {
+            Some(VarValue::Indexed(_)) => attributes.push('a'),
+            Some(VarValue::Assoc(_)) => attributes.push('A'),
+            _ => (),
+        }
+        if shell.is_integer(&name) {
+            attributes.push('i');
+        }
+        if shell.readonly_variables().contains(&name) {
+            attributes.push('r');
+        }
+        if shell.is_exported(&name) {
+            attributes.push('x');
+        }
+        if attributes.is_empty() {
+            attributes.push('-');
+        }
+        match value.map(|value| format_value(&value)) {
+            Some(value) => writeln!(stdout, "declare -{} {}={}", attributes, name, value)
+                .context(ErrorKind::Io)?,
+            None if attributes != "-" => {
+                writeln!(stdout, "declare -{} {}", attributes, name).context(ErrorKind::Io)?
+            }
+            None => {
+                print_error(
+                    shell.shell_stderr(),
+                    format_args!("declare: {}: not found", name),
+                );
+                status = 1;
+            }
+        }
+    }
+
+    if status != 0 {
+        return Err(Error::builtin_command("", status));
+    }
+    Ok(())
+}
+
+/// Formats `value` as it is assigned, e.g. `'a b'` or `([0]=a [1]=b)` for an array.
+///
+/// Associative arrays are ordered by key.
+fn format_value(value: &VarValue) -> String {
+    let elements: Vec<(String, &String)> = match value {
+        VarValue::Scalar(value) => return util::quote_for_shell(value),
+        VarValue::Indexed(array) => array
+            .iter()
+            .map(|(i, value)| (i.to_string(), value))
+            .collect(),
+        VarValue::Assoc(assoc_array) => {
+            let mut elements: Vec<(String, &String)> = assoc_array
+                .iter()
+                .map(|(key, value)| (key.clone(), value))
+                .collect();
+            elements.sort();
+            elements
+        }
+    };
+    let elements: Vec<String> = elements
+        .into_iter()
+        .map(|(key, value)| format!("[{}]={}", key, util::quote_for_shell(value)))
+        .collect();
+    format!("({})", elements.join(" "))
+}
+
+/// Writes the definitions of the functions `names`, or all functions if there are none. With
+/// `names_only`, only their names are written.
+fn print_functions(
+    shell: &dyn Shell,
+    names: &[&str],
+    names_only: bool,
+    stdout: &mut dyn Write,
+) -> Result<()> {
+    let all_names = shell.function_names();
+    let selected: Vec<&str> = if names.is_empty() {
+        all_names.iter().map(String::as_str).collect()
+    } else {
+        names.to_vec()
+    };
+
+    let mut found_all = true;
+    for name in selected {
+        match shell.get_function(name) {
+            Some(_) if names_only && names.is_empty() => {
+                writeln!(stdout, "declare -f {}", name).context(ErrorKind::Io)?
+            }
+            Some(_) if names_only => writeln!(stdout, "{}", name).context(ErrorKind::Io)?,
+            Some(body) => writeln!(stdout, "{} () {}", name, body).context(ErrorKind::Io)?,
+            // Like bash, functions that are not found are not reported.
+            None => found_all = false,
+        }
+    }
+
+    if !found_all {
+        return Err(Error::builtin_command("", 1));
+    }
+    Ok(())
+}
+
+pub struct Readonly;
+
+impl builtins::BuiltinCommand for Readonly {
+    const NAME: &'static str = builtins::READONLY_NAME;
+
+    const HELP: &'static str = "\
+readonly: readonly [-p] [name[=value] ...]
+    Mark variables as read-only.
+
+    Mark each name as read-only; the values of these names may not be changed
+    by subsequent assignment. If value is supplied, assign value before marking
+    it as read-only. Equivalent to `declare -r`.
+
+    -p      display a list of all read-only variables
+
+    If no arguments are given, or if -p is given, a list of all read-only
+    variables is displayed.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let mut rest = &args[..];
+        if let Some(flags) = rest
+            .first()
+            .and_then(|arg| arg.strip_prefix('-'))
+            .filter(|flags| !flags.is_empty())
+        {
+            rest = &rest[1..];
+            // Combined flags such as `-pf` are checked as a whole before anything is printed.
+            match flags.chars().find(|flag| !matches!(flag, 'p' | 'f')) {
+                _ if flags == "-" => {}
+                Some(flag) => {
+                    let message = format!(
+                        "readonly: -{}: invalid option\nreadonly: usage: {}",
+                        flag,
+                        Self::usage().trim_start_matches("readonly: ")
+                    );
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 2));
+                }
+                None if flags.contains('f') => {
+                    let message = "readonly: -f: functions cannot be made read-only";
+                    print_error(shell.shell_stderr(), message);
+                    return Err(Error::builtin_command(message, 1));
+                }
+                None => return print_readonly_variables(shell, stdout),
+            }
+        }
+
+        if rest.is_empty() {
+            return print_readonly_variables(shell, stdout);
+        }
+        let args: Vec<&str> = iter::once("-r").chain(rest.iter().copied()).collect();
+        <Declare as builtins::BuiltinCommand>::run(shell, &args, stdout)
+    }
+}
+
+/// Writes the read-only variables as `declare -r` commands.
+fn print_readonly_variables(shell: &dyn Shell, stdout: &mut dyn Write) -> Result<()> {
+    for name in shell.readonly_variables() {
+        match shell.get_variable(&name) {
+            Some(value) => writeln!(
+                stdout,
+                "declare -r {}={}",
+                name,
+                util::quote_for_shell(&value)
+            ),
+            None => writeln!(stdout, "declare -r {}", name),
+        }
+        .context(ErrorKind::Io)?;
+    }
+    Ok(())
+}
+
+pub struct Env;
+
+impl builtins::BuiltinCommand for Env {
+    const NAME: &'static str = builtins::ENV_NAME;
+
+    const HELP: &'static str = "\
+env: env [-i] [-u name] [name=value ...] [command [arg ...]]
+    Run a command in a modified environment.
+
+    Set each NAME to VALUE in the environment and run COMMAND. Without
+    COMMAND, print the resulting environment as NAME=VALUE lines. The
+    shell's own variables are not changed.
+
+    -i      start with an empty environment
+    -u      remove NAME from the environment
+
+    Exit Status:
+    126 if COMMAND is found but cannot be executed or a NAME may not be
+    changed, 127 if COMMAND cannot be found, otherwise the exit status of
+    COMMAND.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let mut vars = exported_environment(shell);
+        let mut rest = &args[..];
+        while let Some(flags) = rest.first().and_then(|arg| arg.strip_prefix('-')) {
+            rest = &rest[1..];
+            // Like env(1), a lone `-` is the same as `-i`.
+            if flags.is_empty() {
+                vars.clear();
+                continue;
+            }
+            if flags == "-" {
+                break;
+            }
+            for flag in flags.chars() {
+                match flag {
+                    'i' => vars.clear(),
+                    'u' => match rest.split_first() {
+                        Some((name, args)) => {
+                            check_env_variable(shell, name)?;
+                            vars.retain(|(key, _)| key != name);
+                            rest = args;
+                        }
+                        None => {
+                            return Err(env_usage_error(
+                                shell,
+                                "env: -u: option requires an argument",
+                            ))
+                        }
+                    },
+                    _ => {
+                        return Err(env_usage_error(
+                            shell,
+                            &format!("env: -{}: invalid option", flag),
+                        ))
+                    }
+                }
+            }
+        }
+
+        while let Some((name, value)) = rest.first().and_then(|arg| arg.split_once('=')) {
+            rest = &rest[1..];
+            check_env_variable(shell, name)?;
+            vars.retain(|(key, _)| key != name);
+            vars.push((name.into(), value.into()));
+        }
+
+        let (program, args) = match rest.split_first() {
+            Some(split) => split,
+            None => {
+                for (name, value) in vars {
+                    writeln!(
+                        stdout,
+                        "{}={}",
+                        name.to_string_lossy(),
+                        value.to_string_lossy()
+                    )
+                    .context(ErrorKind::Io)?;
+                }
+                return Ok(());
+            }
+        };
+
+        let mut command = Command::new(program);
+        command.args(args).env_clear().envs(vars);
+        if !atty::is(Stream::Stdout) {
+            command.stdout(Stdio::piped());
+        }
+        builtins::run_external_command(shell, Self::NAME, program, args, &mut command, stdout)
+    }
+}
+
+/// Returns the variables passed to the environment of external commands, in the order of the
+/// shell's environment.
+fn exported_environment(shell: &dyn Shell) -> Vec<(OsString, OsString)> {
+    env::vars_os()
+        .filter(|(name, _)| name.to_str().map_or(false, |name| shell.is_exported(name)))
+        .collect()
+}
+
+/// Verifies that `env` may set or remove the variable `name` for the command. Like assignments in
+/// the shell, sandbox mode protects some variables and `--restricted-path` protects `$PATH`.
+fn check_env_variable(shell: &mut dyn Shell, name: &str) -> Result<()> {
+    let config = shell.config();
+    let result = config.check_variable_assignment(name).and_then(|()| {
+        if name == "PATH" && config.restrict_path.is_some() {
+            Err(Error::readonly_variable(name))
+        } else {
+            Ok(())
+        }
+    });
+    result.map_err(|e| builtins::external_command_error(shell, builtins::ENV_NAME, e))
+}
+
+fn env_usage_error(shell: &mut dyn Shell, message: &str) -> Error {
+    let message = format!(
+        "{}\nenv: usage: {}",
+        message,
+        <Env as builtins::BuiltinCommand>::usage().trim_start_matches("env: ")
+    );
+    print_error(shell.shell_stderr(), &message);
+    Error::builtin_command(message, 2)
+}
+
+pub struct Printenv;
+
+impl builtins::BuiltinCommand for Printenv {
+    const NAME: &'static str = builtins::PRINTENV_NAME;
+
+    const HELP: &'static str = "\
+printenv: printenv [name ...]
+    Print the values of environment variables.
+
+    Print the value of each exported variable NAME, or NAME=VALUE lines for
+    all exported variables if no NAME is given.
+
+    Exit Status:
+    Returns success unless a NAME is not set or not exported.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        if args.is_empty() {
+            return <Env as builtins::BuiltinCommand>::run::<&str>(shell, &[], stdout);
+        }
+
+        let mut found_all = true;
+        for name in args {
+            let value = Some(name.as_ref())
+                .filter(|name| shell.is_exported(name))
+                .and_then(|name| env::var_os(name));
+            match value {
+                Some(value) => {
+                    writeln!(stdout, "{}", value.to_string_lossy()).context(ErrorKind::Io)?
+                }
+                None => found_all = false,
+            }
+        }
+
+        if !found_all {
+            return Err(Error::builtin_command("", 1));
+        }
+        Ok(())
+    }
+}
+
+pub struct Export;
+
+impl builtins::BuiltinCommand for Export {
+    const NAME: &'static str = builtins::EXPORT_NAME;
+
+    const HELP: &'static str = "\
+export: export [-n] [-p] [name[=value] ...]
+    Set export attribute for shell variables.
+
+    Marks each NAME for automatic export to the environment of subsequently
+    executed commands. If VALUE is supplied, assign VALUE before exporting.
+
+    -n      remove the export property from each NAME
+    -p      display a list of all exported variables
+
+    If no arguments are given, or if -p is given, a list of all exported
+    variables is displayed.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let (exported, args) = match args.split_first() {
+            None => return print_exported_variables(shell, stdout),
+            Some((flag, _)) if flag.as_ref() == "-p" => {
+                return print_exported_variables(shell, stdout)
+            }
+            Some((flag, rest)) if flag.as_ref() == "-n" => (false, rest),
+            _ => (true, args),
+        };
 
-    fn run<T: AsRef<str>>(
-        _shell: &mut dyn Shell,
-        args: &[T],
-        _stdout: &mut dyn Write,
-    ) -> Result<()> {
         let mut bad_args = Vec::new();
         for arg in args {
             let key_value: Vec<&str> = arg.as_ref().splitn(2, '=').collect();
-            match key_value.first() {
-                Some(&"") | None => bad_args.push(arg),
-                Some(s) => env::set_var(s, key_value.get(1).unwrap_or(&"")),
+            match key_value[0] {
+                "" => bad_args.push(arg),
+                name => {
+                    if let Some(value) = key_value.get(1) {
+                        check_variable_assignment(shell, Self::NAME, name)?;
+                        shell.set_variable(name, value);
+                    }
+                    shell.set_exported(name, exported);
+                }
             }
         }
 
         if !bad_args.is_empty() {
             let msg = bad_args
                 .iter()
-                .map(|arg| format!("declare: {} is not a valid identifier", arg.as_ref()))
+                .map(|arg| format!("export: {} is not a valid identifier", arg.as_ref()))
                 .collect::<Vec<String>>()
                 .join("\n");
             return Err(Error::builtin_command(msg, 1));
@@ -39,6 +592,32 @@ declare: declare [name[=value] ...]
     }
 }
 
+/// Fails if `name` may not be assigned or unset by the builtin `builtin`, e.g. because it is
+/// read-only, printing the reason.
+fn check_variable_assignment(shell: &mut dyn Shell, builtin: &str, name: &str) -> Result<()> {
+    shell.check_variable_assignment(name).map_err(|e| {
+        print_error(shell.shell_stderr(), format_args!("{}: {}", builtin, e));
+        e
+    })
+}
+
+/// Writes the exported variables as `declare -x` commands.
+fn print_exported_variables(shell: &dyn Shell, stdout: &mut dyn Write) -> Result<()> {
+    for name in shell.exported_variables() {
+        match shell.get_variable(&name) {
+            Some(value) => writeln!(
+                stdout,
+                "declare -x {}={}",
+                name,
+                util::quote_for_shell(&value)
+            ),
+            None => writeln!(stdout, "declare -x {}", name),
+        }
+        .context(ErrorKind::Io)?;
+    }
+    Ok(())
+}
+
 pub struct Unset;
 
 impl builtins::BuiltinCommand for Unset {
@@ -46,10 +625,11 @@ impl builtins::BuiltinCommand for Unset {
 
     const HELP: &'static str = "\
 unset: unset [name ...]
-    For each name, remove the corresponding variable.";
+    For each name, remove the corresponding variable. If name is of the form
+    array[subscript], remove that element of the array instead.";
 
     fn run<T: AsRef<str>>(
-        _shell: &mut dyn Shell,
+        shell: &mut dyn Shell,
         args: &[T],
         _stdout: &mut dyn Write,
     ) -> Result<()> {
@@ -57,8 +637,14 @@ unset: unset [name ...]
         for arg in args {
             if arg.as_ref().is_empty() || arg.as_ref().contains('=') {
                 bad_args.push(arg);
+            } else if let Some((name, subscript)) =
+                variable_expansion::split_subscript(arg.as_ref())
+            {
+                check_variable_assignment(shell, Self::NAME, name)?;
+                shell.unset_array_element(name, subscript);
             } else {
-                env::remove_var(OsStr::new(arg.as_ref()));
+                check_variable_assignment(shell, Self::NAME, arg.as_ref())?;
+                shell.unset_variable(arg.as_ref());
             }
         }
 
@@ -79,8 +665,10 @@ unset: unset [name ...]
 mod tests {
     use super::*;
 
+    use std::collections::{BTreeMap, HashMap};
     use std::env;
     use std::io;
+    use std::iter;
 
     use crate::builtins::BuiltinCommand;
     use crate::shell::{create_shell, ShellConfig};
@@ -153,6 +741,235 @@ mod tests {
         assert_eq!(env::var(&key2).unwrap(), value);
     }
 
+    #[test]
+    fn declare_array() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = generate_unique_env_key!();
+        assert!(Declare::run(&mut *shell, &["-a", &key], &mut io::sink()).is_ok());
+        assert_eq!(shell.get_array(&key), Some(&BTreeMap::new()));
+        assert!(env::var(&key).is_err());
+
+        shell.set_array_element(&key, 1, "foo");
+        assert!(Declare::run(&mut *shell, &["-a", &key], &mut io::sink()).is_ok());
+        assert_eq!(
+            shell.get_array(&key),
+            Some(&iter::once((1, "foo".to_string())).collect())
+        );
+    }
+
+    #[test]
+    fn declare_assoc_array() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = generate_unique_env_key!();
+        assert!(Declare::run(&mut *shell, &["-A", &key], &mut io::sink()).is_ok());
+        assert_eq!(shell.get_assoc_array(&key), Some(&HashMap::new()));
+
+        shell.set_assoc_element(&key, "foo", "bar");
+        shell.set_assoc_element(&key, "baz", "qux");
+        assert!(Unset::run(&mut *shell, &[format!("{}[foo]", key)], &mut io::sink()).is_ok());
+        let expected: HashMap<String, String> = [("baz".to_string(), "qux".to_string())]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(shell.get_assoc_array(&key), Some(&expected));
+    }
+
+    #[test]
+    fn readonly_variable() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = generate_unique_env_key!();
+        assert!(Readonly::run(&mut *shell, &[format!("{}=5", key)], &mut io::sink()).is_ok());
+        assert_eq!(shell.get_variable(&key).unwrap(), "5");
+
+        let result = Declare::run(&mut *shell, &[format!("{}=6", key)], &mut io::sink());
+        assert_eq!(
+            *result.unwrap_err().kind(),
+            ErrorKind::ReadonlyVariable(key.clone())
+        );
+        let result = Unset::run(&mut *shell, &[&key], &mut io::sink());
+        assert_eq!(
+            *result.unwrap_err().kind(),
+            ErrorKind::ReadonlyVariable(key.clone())
+        );
+        let result = Readonly::run(&mut *shell, &[format!("{}=7", key)], &mut io::sink());
+        assert_eq!(
+            *result.unwrap_err().kind(),
+            ErrorKind::ReadonlyVariable(key.clone())
+        );
+        assert!(shell.eval_arithmetic(&format!("{} = 8", key)).is_err());
+        assert!(Readonly::run(&mut *shell, &["-f", &key], &mut io::sink()).is_err());
+        assert!(Readonly::run(&mut *shell, &["-pf", &key], &mut io::sink()).is_err());
+        assert!(Readonly::run(&mut *shell, &["-pq"], &mut io::sink()).is_err());
+        assert!(Readonly::run(&mut *shell, &["-p"], &mut io::sink()).is_ok());
+        assert_eq!(shell.get_variable(&key).unwrap(), "5");
+        env::remove_var(&key);
+    }
+
+    #[test]
+    fn readonly_array_element() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = generate_unique_env_key!();
+        assert!(Declare::run(&mut *shell, &["-a", &key], &mut io::sink()).is_ok());
+        shell.set_array_element(&key, 0, "x");
+        assert!(Readonly::run(&mut *shell, &[&key], &mut io::sink()).is_ok());
+
+        let result = Unset::run(&mut *shell, &[format!("{}[0]", key)], &mut io::sink());
+        assert_eq!(
+            *result.unwrap_err().kind(),
+            ErrorKind::ReadonlyVariable(key.clone())
+        );
+        assert_eq!(
+            shell.get_array(&key),
+            Some(&iter::once((0, "x".to_string())).collect())
+        );
+    }
+
+    #[test]
+    fn declare_integer() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = generate_unique_env_key!();
+        assert!(Declare::run(
+            &mut *shell,
+            &["-i", &format!("{}=3+4", key)],
+            &mut io::sink()
+        )
+        .is_ok());
+        assert_eq!(shell.get_variable(&key).unwrap(), "7");
+        assert!(Declare::run(
+            &mut *shell,
+            &[format!("{}={}*2", key, key)],
+            &mut io::sink()
+        )
+        .is_ok());
+        assert_eq!(shell.get_variable(&key).unwrap(), "14");
+        assert!(Declare::run(&mut *shell, &[format!("{}=1+", key)], &mut io::sink()).is_err());
+        assert_eq!(shell.get_variable(&key).unwrap(), "14");
+
+        assert!(Declare::run(
+            &mut *shell,
+            &["+i", &format!("{}=1+1", key)],
+            &mut io::sink()
+        )
+        .is_ok());
+        assert_eq!(shell.get_variable(&key).unwrap(), "1+1");
+        env::remove_var(&key);
+    }
+
+    #[test]
+    fn declare_print() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key1 = generate_unique_env_key!();
+        let key2 = generate_unique_env_key!();
+        assert!(Declare::run(
+            &mut *shell,
+            &["-rx", &format!("{}=it's", key1)],
+            &mut io::sink()
+        )
+        .is_ok());
+        assert!(Declare::run(&mut *shell, &[format!("{}=", key2)], &mut io::sink()).is_ok());
+        shell.set_exported(&key2, false);
+
+        let mut output = Vec::new();
+        assert!(Declare::run(&mut *shell, &["-p", &key1, &key2], &mut output).is_ok());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("declare -rx {}='it'\\''s'\ndeclare -- {}=''\n", key1, key2)
+        );
+        assert!(Declare::run(&mut *shell, &["-p", "KEY_NOT_DECLARED"], &mut io::sink()).is_err());
+        assert!(Declare::run(&mut *shell, &["-f", "not_a_function"], &mut io::sink()).is_err());
+        assert!(Declare::run(&mut *shell, &["-z"], &mut io::sink()).is_err());
+        env::remove_var(&key1);
+        env::remove_var(&key2);
+    }
+
+    #[test]
+    fn printenv_exported_variables() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = generate_unique_env_key!();
+        assert!(Declare::run(&mut *shell, &[format!("{}=foo", key)], &mut io::sink()).is_ok());
+        assert!(Printenv::run(&mut *shell, &[&key], &mut io::sink()).is_err());
+
+        assert!(Export::run(&mut *shell, &[&key], &mut io::sink()).is_ok());
+        let mut output = Vec::new();
+        assert!(Printenv::run(&mut *shell, &[&key], &mut output).is_ok());
+        assert_eq!(String::from_utf8(output).unwrap(), "foo\n");
+
+        let mut output = Vec::new();
+        assert!(Env::run(&mut *shell, &["-i", "A=1", "B=2"], &mut output).is_ok());
+        assert_eq!(String::from_utf8(output).unwrap(), "A=1\nB=2\n");
+        env::remove_var(&key);
+    }
+
+    #[test]
+    fn readonly_existing_variable() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = generate_unique_env_key!();
+        assert!(Declare::run(&mut *shell, &[format!("{}=foo", key)], &mut io::sink()).is_ok());
+        assert!(Declare::run(&mut *shell, &["-r", &key], &mut io::sink()).is_ok());
+        assert_eq!(shell.get_variable(&key).unwrap(), "foo");
+        assert!(Declare::run(&mut *shell, &[&key], &mut io::sink()).is_err());
+        env::remove_var(&key);
+    }
+
+    #[test]
+    fn readonly_display() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key1 = generate_unique_env_key!();
+        let key2 = generate_unique_env_key!();
+        assert!(Readonly::run(
+            &mut *shell,
+            &[format!("{}=it's", key1), key2.clone()],
+            &mut io::sink()
+        )
+        .is_ok());
+
+        let expected = format!("declare -r {}='it'\\''s'\ndeclare -r {}\n", key1, key2);
+        for args in &[&[][..], &["-p"][..]] {
+            let mut output = Vec::new();
+            assert!(Readonly::run(&mut *shell, args, &mut output).is_ok());
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+        env::remove_var(&key1);
+    }
+
+    #[test]
+    fn export_variable() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = generate_unique_env_key!();
+        assert!(Declare::run(&mut *shell, &[format!("{}=foo", key)], &mut io::sink()).is_ok());
+        assert!(!shell.is_exported(&key));
+        assert!(Export::run(&mut *shell, &[&key], &mut io::sink()).is_ok());
+        assert!(shell.is_exported(&key));
+        assert_eq!(shell.get_variable(&key).unwrap(), "foo");
+
+        let mut output = Vec::new();
+        assert!(Export::run(&mut *shell, &["-p"], &mut output).is_ok());
+        let expected = format!("declare -x {}=foo\n", key);
+        assert!(String::from_utf8(output).unwrap().contains(&expected));
+
+        assert!(Export::run(&mut *shell, &["-n", &key], &mut io::sink()).is_ok());
+        assert!(!shell.is_exported(&key));
+        assert_eq!(shell.get_variable(&key).unwrap(), "foo");
+
+        assert!(Export::run(&mut *shell, &[format!("{}=bar", key)], &mut io::sink()).is_ok());
+        assert!(shell.is_exported(&key));
+        assert_eq!(shell.get_variable(&key).unwrap(), "bar");
+        assert!(Unset::run(&mut *shell, &[&key], &mut io::sink()).is_ok());
+        assert!(!shell.is_exported(&key));
+
+        assert!(Export::run(&mut *shell, &["=foo"], &mut io::sink()).is_err());
+    }
+
     #[test]
     fn unset_invalid_identifier() {
         let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
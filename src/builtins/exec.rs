@@ -0,0 +1,215 @@
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::process::Command;
+
+#[cfg(unix)]
+use nix::sys::signal::{self, SigHandler, Signal};
+
+use crate::builtins::{self, prelude::*, BuiltinCommand};
+use crate::shell::print_error;
+#[cfg(unix)]
+use crate::{
+    execute_command,
+    shell::{COMMAND_NOT_FOUND_EXIT_STATUS, PERMISSION_DENIED_EXIT_STATUS},
+};
+
+pub struct Exec;
+
+impl BuiltinCommand for Exec {
+    const NAME: &'static str = builtins::EXEC_NAME;
+
+    const HELP: &'static str = "\
+exec: exec [-cl] [-a name] [command [argument ...]]
+    Replace the shell with the given command.
+
+    Execute COMMAND, replacing this shell with the specified program.
+    ARGUMENTS become the arguments to COMMAND. If COMMAND is not specified,
+    any redirections take effect in the current shell.
+
+    -a name   pass NAME as the zeroth argument to COMMAND
+    -c        execute COMMAND with an empty environment
+    -l        place a dash in the zeroth argument to COMMAND
+
+    The EXIT trap runs and the history is saved before COMMAND is executed.
+
+    Exit Status:
+    Returns success unless COMMAND is not found or a redirection error occurs.";
+
+    #[cfg(unix)]
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (options, words) = parse_options(shell, &args)?;
+        // The redirects have already been applied to the shell itself, see `redirects_shell`.
+        let (program, args) = match words.split_first() {
+            Some((program, args)) => (*program, args),
+            None => return Ok(()),
+        };
+
+        let path = if program.contains('/') {
+            PathBuf::from(program)
+        } else {
+            match builtins::find_hashed_command(shell, program) {
+                Some(path) => path,
+                None => {
+                    let message = format!("exec: {}: not found", program);
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(
+                        message,
+                        COMMAND_NOT_FOUND_EXIT_STATUS,
+                    ));
+                }
+            }
+        };
+
+        builtins::check_restrict_path(shell, Self::NAME, program)?;
+
+        let argv0 = options.name.unwrap_or(program);
+        let mut command = Command::new(&path);
+        command.args(args);
+        if options.login {
+            command.arg0(format!("-{}", argv0));
+        } else {
+            command.arg0(argv0);
+        }
+        if options.clear_environment {
+            command.env_clear();
+        } else {
+            execute_command::set_exported_environment(shell, &mut command);
+        }
+        if shell.is_job_control_enabled() {
+            unsafe {
+                command.pre_exec(|| {
+                    // Like other commands, restore the signals ignored for job control.
+                    for signal in &[
+                        Signal::SIGINT,
+                        Signal::SIGQUIT,
+                        Signal::SIGTSTP,
+                        Signal::SIGTTIN,
+                        Signal::SIGTTOU,
+                        Signal::SIGCHLD,
+                    ] {
+                        signal::signal(*signal, SigHandler::SigDfl)
+                            .expect("failed to reset signal handler");
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        shell.prepare_for_exec();
+        let _ = io::stdout().flush();
+        // `exec` only returns if the command could not be executed.
+        let e = command.exec();
+        let message = format!("exec: {}: {}", program, e);
+        print_error(shell.shell_stderr(), &message);
+        Err(Error::builtin_command(
+            message,
+            PERMISSION_DENIED_EXIT_STATUS,
+        ))
+    }
+
+    #[cfg(windows)]
+    fn run<T: AsRef<str>>(
+        _shell: &mut dyn Shell,
+        _args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        Err(Error::not_supported("exec is not supported on Windows"))
+    }
+}
+
+/// Returns `true` if `program` is the `exec` builtin, whose redirects apply to the shell itself,
+/// e.g. `exec > log`, rather than only while the builtin runs.
+pub fn redirects_shell<S: AsRef<str>>(shell: &dyn Shell, program: S) -> bool {
+    program.as_ref() == builtins::EXEC_NAME && builtins::is_builtin(shell, &program)
+}
+
+#[derive(Debug, Default)]
+struct ExecOptions<'a> {
+    name: Option<&'a str>,
+    clear_environment: bool,
+    login: bool,
+}
+
+/// Parses the options of `exec`, returning them and the command that follows.
+fn parse_options<'a>(
+    shell: &mut dyn Shell,
+    args: &'a [&'a str],
+) -> Result<(ExecOptions<'a>, &'a [&'a str])> {
+    let mut options = ExecOptions::default();
+    let mut rest = args;
+    while let Some(flags) = rest
+        .first()
+        .and_then(|arg| arg.strip_prefix('-'))
+        .filter(|flags| !flags.is_empty())
+    {
+        rest = &rest[1..];
+        if flags == "-" {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'a' => match rest.split_first() {
+                    Some((name, command)) => {
+                        options.name = Some(name);
+                        rest = command;
+                    }
+                    None => {
+                        return Err(usage_error(shell, "exec: -a: option requires an argument"))
+                    }
+                },
+                'c' => options.clear_environment = true,
+                'l' => options.login = true,
+                _ => {
+                    return Err(usage_error(
+                        shell,
+                        &format!("exec: -{}: invalid option", flag),
+                    ))
+                }
+            }
+        }
+    }
+    Ok((options, rest))
+}
+
+fn usage_error(shell: &mut dyn Shell, message: &str) -> Error {
+    let message = format!(
+        "{}\nexec: usage: {}",
+        message,
+        Exec::usage().trim_start_matches("exec: ")
+    );
+    print_error(shell.shell_stderr(), &message);
+    Error::builtin_command(message, 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn test_parse_options() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let shell = &mut *shell;
+
+        let (options, words) = parse_options(shell, &["-cl", "-a", "name", "ls", "-a"]).unwrap();
+        assert_eq!(options.name, Some("name"));
+        assert!(options.clear_environment && options.login);
+        assert_eq!(words, ["ls", "-a"]);
+
+        let (_, words) = parse_options(shell, &["--", "-x"]).unwrap();
+        assert_eq!(words, ["-x"]);
+        assert!(parse_options(shell, &["-a"]).is_err());
+        assert!(parse_options(shell, &["-x", "ls"]).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std::process::ExitStatus;
 
 use crate::builtins::{self, prelude::*};
+use crate::shell::print_error;
 
 pub struct Exit;
 
@@ -17,14 +18,17 @@ exit: exit [n]
         args: &[T],
         _stdout: &mut dyn Write,
     ) -> Result<()> {
-        if shell.has_background_jobs() {
+        if shell.is_interactive() && shell.has_background_jobs() {
             return Err(Error::builtin_command("There are stopped jobs.", 1));
         }
         let status_code = args
             .get(0)
             .map(|arg| {
                 arg.as_ref().parse::<i32>().unwrap_or_else(|_| {
-                    eprintln!("bsh: exit: {}: numeric argument required", arg.as_ref());
+                    print_error(
+                        shell.shell_stderr(),
+                        format_args!("exit: {}: numeric argument required", arg.as_ref()),
+                    );
                     2
                 })
             })
@@ -32,3 +36,55 @@ exit: exit [n]
         shell.exit(status_code);
     }
 }
+
+pub struct Return;
+
+impl builtins::BuiltinCommand for Return {
+    const NAME: &'static str = builtins::RETURN_NAME;
+
+    const HELP: &'static str = "\
+return: return [n]
+    Return from a shell function with a status of N. If N is omitted,
+    the return status is that of the last command executed. Outside a
+    function, exit the shell like `exit`.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        if shell.function_depth() == 0 {
+            return Exit::run(shell, args, stdout);
+        }
+        let status_code = match args.get(0) {
+            Some(arg) => arg.as_ref().parse::<i32>().unwrap_or_else(|_| {
+                print_error(
+                    shell.shell_stderr(),
+                    format_args!("return: {}: numeric argument required", arg.as_ref()),
+                );
+                2
+            }),
+            None => shell.last_exit_status().code().unwrap_or(1),
+        };
+        Err(Error::function_return(status_code))
+    }
+}
+
+pub struct Logout;
+
+impl builtins::BuiltinCommand for Logout {
+    const NAME: &'static str = builtins::LOGOUT_NAME;
+
+    const HELP: &'static str = "\
+logout: logout
+    Exit a login shell. Returns an error if not executed in a login
+    shell.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        _args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        if !shell.is_login() {
+            print_error(shell.shell_stderr(), "not login shell: use 'exit'");
+            return Err(Error::builtin_command("not login shell", 1));
+        }
+        shell.exit(None);
+    }
+}
@@ -0,0 +1,318 @@
+use std::env;
+use std::fs;
+
+use tempfile::NamedTempFile;
+
+use crate::{
+    builtins::{self, prelude::*, BuiltinCommand},
+    shell::print_error,
+    util,
+};
+
+const DEFAULT_EDITOR: &str = "vi";
+/// The number of commands listed by `fc -l` without a range.
+const DEFAULT_LIST_LENGTH: usize = 16;
+
+pub struct Fc;
+
+impl BuiltinCommand for Fc {
+    const NAME: &'static str = builtins::FC_NAME;
+
+    const HELP: &'static str = "\
+fc: fc [-e ename] [-lnr] [first] [last] or fc -s [pat=rep] [command]
+    Display or execute commands from the history list.
+
+    fc is used to list or edit and re-execute commands from the history list.
+    FIRST and LAST can be numbers specifying the range, or FIRST can be a
+    string, which means the most recent command beginning with that
+    string. Negative numbers are offsets from the current command.
+
+    -e ENAME  select which editor to use. Default is FCEDIT, then EDITOR,
+              then vi
+    -l        list lines instead of editing
+    -n        omit line numbers when listing
+    -r        reverse the order of the lines (newest listed first)
+
+    With the `fc -s [pat=rep ...] [command]' format, COMMAND is
+    re-executed after each instance of PAT is replaced by REP.
+
+    Exit Status:
+    Returns success or status of executed command; non-zero if an error occurs.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (options, specs) = parse_options(shell, &args)?;
+        let entries = history_entries(shell);
+        if options.substitute {
+            return substitute(shell, &entries, specs, stdout);
+        }
+        if specs.len() > 2 {
+            return Err(usage_error(shell, "fc: too many arguments"));
+        }
+
+        let (first, last) = if options.list {
+            let default_first = format!("-{}", DEFAULT_LIST_LENGTH);
+            let first = specs.first().copied().unwrap_or(&default_first);
+            (
+                find_entry(&entries, first),
+                find_entry(&entries, specs.get(1).unwrap_or(&"-1")),
+            )
+        } else {
+            let first = specs.first().unwrap_or(&"-1");
+            let first_index = find_entry(&entries, first);
+            let last_index = specs
+                .get(1)
+                .map_or(first_index, |last| find_entry(&entries, last));
+            (first_index, last_index)
+        };
+        let (first, last) = match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                let message = "fc: history specification out of range";
+                print_error(shell.shell_stderr(), message);
+                return Err(Error::builtin_command(message, 1));
+            }
+        };
+
+        // Like bash, a range from a later command to an earlier one is reversed.
+        let mut range: Vec<&(usize, String)> = if first <= last {
+            entries[first..=last].iter().collect()
+        } else {
+            entries[last..=first].iter().rev().collect()
+        };
+        if options.reverse {
+            range.reverse();
+        }
+
+        if options.list {
+            for (number, line) in range {
+                if options.no_numbers {
+                    writeln!(stdout, "\t {}", line).context(ErrorKind::Io)?;
+                } else {
+                    writeln!(stdout, "{}\t {}", number, line).context(ErrorKind::Io)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let commands: Vec<&str> = range.iter().map(|(_, line)| line.as_str()).collect();
+        let edited = edit_commands(shell, options.editor, &commands)?;
+        execute_commands(shell, edited.lines(), stdout)
+    }
+}
+
+/// Returns the numbered history entries, excluding the `fc` command being run.
+fn history_entries(shell: &dyn Shell) -> Vec<(usize, String)> {
+    let mut entries: Vec<(usize, String)> = shell
+        .editor()
+        .enumerate_history_entries()
+        .map(|(i, line)| (i + 1, line.clone()))
+        .collect();
+    // Like bash, the `fc` command has already been added to the history, so it is skipped.
+    if shell.config().is_command_history_enabled() {
+        entries.pop();
+    }
+    entries
+}
+
+/// Returns the index in `entries` of the command `spec` refers to: a history number, a negative
+/// offset from the current command, or the prefix of the most recent matching command.
+///
+/// Like bash, numbers out of range refer to the first or last command.
+fn find_entry(entries: &[(usize, String)], spec: &str) -> Option<usize> {
+    let last = entries.len().checked_sub(1)?;
+    match spec.parse::<isize>() {
+        Ok(n) if n > 0 => {
+            let first_number = entries[0].0;
+            Some((n as usize).saturating_sub(first_number).min(last))
+        }
+        Ok(0) => Some(last),
+        Ok(n) => Some(entries.len().saturating_sub(n.unsigned_abs())),
+        Err(_) => entries.iter().rposition(|(_, line)| line.starts_with(spec)),
+    }
+}
+
+/// Writes `commands` to a temporary file, opens it in the editor, and returns the edited commands.
+fn edit_commands(shell: &mut dyn Shell, editor: Option<&str>, commands: &[&str]) -> Result<String> {
+    let mut file = NamedTempFile::new().context(ErrorKind::Io)?;
+    for command in commands {
+        writeln!(file, "{}", command).context(ErrorKind::Io)?;
+    }
+    file.flush().context(ErrorKind::Io)?;
+
+    let editor = editor
+        .map(String::from)
+        .or_else(|| {
+            shell
+                .get_variable("FCEDIT")
+                .filter(|editor| !editor.is_empty())
+        })
+        .or_else(|| env::var("EDITOR").ok().filter(|editor| !editor.is_empty()))
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
+    let editor_command = format!(
+        "{} {}",
+        editor,
+        util::quote_for_shell(&file.path().to_string_lossy())
+    );
+
+    // The editor is run on behalf of `fc`, so it is not added to the history.
+    let enable_command_history = shell.config().is_command_history_enabled();
+    shell.config_mut().set_command_history_enabled(false);
+    let result = shell.execute_command_string(&editor_command);
+    shell
+        .config_mut()
+        .set_command_history_enabled(enable_command_history);
+    result?;
+
+    let status = shell.last_exit_status();
+    if !status.success() {
+        return Err(Error::builtin_command("", status.code().unwrap_or(1)));
+    }
+    Ok(fs::read_to_string(file.path()).context(ErrorKind::Io)?)
+}
+
+/// Re-executes the command `spec` refers to after replacing each `pat` with `rep`, e.g.
+/// `fc -s foo=bar cc`.
+fn substitute(
+    shell: &mut dyn Shell,
+    entries: &[(usize, String)],
+    args: &[&str],
+    stdout: &mut dyn Write,
+) -> Result<()> {
+    let substitutions = args.iter().take_while(|arg| arg.contains('='));
+    let spec = match &args[substitutions.clone().count()..] {
+        [] => "-1",
+        [spec] => spec,
+        _ => return Err(usage_error(shell, "fc: too many arguments")),
+    };
+
+    let index = match find_entry(entries, spec) {
+        Some(index) => index,
+        None => {
+            let message = "fc: no command found";
+            print_error(shell.shell_stderr(), message);
+            return Err(Error::builtin_command(message, 1));
+        }
+    };
+    let command = substitutions.fold(entries[index].1.clone(), |command, substitution| {
+        let (pat, rep) = substitution.split_once('=').unwrap();
+        if pat.is_empty() {
+            command
+        } else {
+            command.replace(pat, rep)
+        }
+    });
+    execute_commands(shell, command.lines(), stdout)
+}
+
+/// Echoes and executes each of `commands`, adding them to the history. Fails with the exit
+/// status of the last command if it is non-zero.
+fn execute_commands<'a>(
+    shell: &mut dyn Shell,
+    commands: impl Iterator<Item = &'a str>,
+    stdout: &mut dyn Write,
+) -> Result<()> {
+    for command in commands.filter(|command| !command.trim().is_empty()) {
+        writeln!(stdout, "{}", command).context(ErrorKind::Io)?;
+        stdout.flush().context(ErrorKind::Io)?;
+        shell.execute_command_string(command)?;
+    }
+
+    let status = shell.last_exit_status();
+    if !status.success() {
+        return Err(Error::builtin_command("", status.code().unwrap_or(1)));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct FcOptions<'a> {
+    editor: Option<&'a str>,
+    list: bool,
+    no_numbers: bool,
+    reverse: bool,
+    substitute: bool,
+}
+
+/// Parses the options of `fc`, returning them and the arguments that follow.
+///
+/// Negative numbers, e.g. `fc -l -5`, are arguments rather than options.
+fn parse_options<'a>(
+    shell: &mut dyn Shell,
+    args: &'a [&'a str],
+) -> Result<(FcOptions<'a>, &'a [&'a str])> {
+    let mut options = FcOptions::default();
+    let mut rest = args;
+    while let Some(flags) = rest
+        .first()
+        .and_then(|arg| arg.strip_prefix('-'))
+        .filter(|flags| !flags.is_empty() && !flags.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        rest = &rest[1..];
+        if flags == "-" {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'e' => match rest.split_first() {
+                    // `fc -e -` re-executes commands without editing them, like `fc -s`.
+                    Some((&"-", args)) => {
+                        options.substitute = true;
+                        rest = args;
+                    }
+                    Some((editor, args)) => {
+                        options.editor = Some(editor);
+                        rest = args;
+                    }
+                    None => return Err(usage_error(shell, "fc: -e: option requires an argument")),
+                },
+                'l' => options.list = true,
+                'n' => options.no_numbers = true,
+                'r' => options.reverse = true,
+                's' => options.substitute = true,
+                _ => {
+                    return Err(usage_error(
+                        shell,
+                        &format!("fc: -{}: invalid option", flag),
+                    ))
+                }
+            }
+        }
+    }
+    Ok((options, rest))
+}
+
+fn usage_error(shell: &mut dyn Shell, message: &str) -> Error {
+    let message = format!(
+        "{}\nfc: usage: {}",
+        message,
+        Fc::usage().trim_start_matches("fc: ")
+    );
+    print_error(shell.shell_stderr(), &message);
+    Error::builtin_command(message, 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_entry() {
+        let entries: Vec<(usize, String)> = ["echo a", "ls", "echo b"]
+            .iter()
+            .enumerate()
+            .map(|(i, line)| (i + 3, line.to_string()))
+            .collect();
+        assert_eq!(find_entry(&entries, "3"), Some(0));
+        assert_eq!(find_entry(&entries, "5"), Some(2));
+        assert_eq!(find_entry(&entries, "1"), Some(0));
+        assert_eq!(find_entry(&entries, "100"), Some(2));
+        assert_eq!(find_entry(&entries, "-1"), Some(2));
+        assert_eq!(find_entry(&entries, "-3"), Some(0));
+        assert_eq!(find_entry(&entries, "-16"), Some(0));
+        assert_eq!(find_entry(&entries, "echo"), Some(2));
+        assert_eq!(find_entry(&entries, "l"), Some(1));
+        assert_eq!(find_entry(&entries, "cat"), None);
+        assert_eq!(find_entry(&[], "-1"), None);
+    }
+}
@@ -0,0 +1,236 @@
+use std::env;
+use std::path::PathBuf;
+
+use crate::{
+    builtins::{self, prelude::*, BuiltinCommand},
+    shell::print_error,
+    util,
+};
+
+pub struct Hash;
+
+impl BuiltinCommand for Hash {
+    const NAME: &'static str = builtins::HASH_NAME;
+
+    const HELP: &'static str = "\
+hash: hash [-r] [-p pathname] [-dt] [name ...]
+    Remember or display program locations.
+
+    Determine and remember the full pathname of each command NAME. If
+    no arguments are given, information about remembered commands is
+    displayed in the reusable form `hash -p PATHNAME NAME'.
+
+    -d      forget the remembered location of each NAME
+    -p pathname
+            use PATHNAME as the full pathname of NAME
+    -r      forget all remembered locations
+    -t      print the remembered location of each NAME, preceding
+            each location with the corresponding NAME if multiple
+            NAMEs are given
+
+    The remembered locations are forgotten when $PATH changes.
+
+    Exit Status:
+    Returns success unless NAME is not found or an invalid option is given.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (options, names) = parse_options(shell, &args)?;
+        if options.reset {
+            shell.command_cache_mut().clear();
+        }
+        if names.is_empty() {
+            if !options.reset {
+                print_cache(shell, stdout)?;
+            }
+            return Ok(());
+        }
+
+        let mut status = 0;
+        for name in names {
+            let found = if let Some(path) = options.path {
+                shell
+                    .command_cache_mut()
+                    .insert(name.to_string(), PathBuf::from(path));
+                true
+            } else if options.forget {
+                shell.command_cache_mut().remove(*name).is_some()
+            } else if options.print {
+                match shell.command_cache().get(*name) {
+                    Some(path) if names.len() > 1 => {
+                        writeln!(stdout, "{}\t{}", name, path.display()).context(ErrorKind::Io)?;
+                        true
+                    }
+                    Some(path) => {
+                        writeln!(stdout, "{}", path.display()).context(ErrorKind::Io)?;
+                        true
+                    }
+                    None => false,
+                }
+            } else {
+                // Like bash, builtins and functions are not looked up in `$PATH`.
+                builtins::is_builtin(&*shell, name)
+                    || shell.get_function(name).is_some()
+                    || name.contains('/')
+                    || hash_command(shell, name).is_some()
+            };
+            if !found {
+                print_error(
+                    shell.shell_stderr(),
+                    format_args!("hash: {}: not found", name),
+                );
+                status = 1;
+            }
+        }
+
+        if status != 0 {
+            return Err(Error::builtin_command("", status));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the path of the external command `name`, searching `$PATH` and remembering the
+/// result only if it is not remembered already or no longer executable.
+///
+/// Returns `None` if `name` is not found or contains a path separator, so that it is run as is.
+pub fn find_hashed_command(shell: &mut dyn Shell, name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        return None;
+    }
+    match shell.command_cache().get(name) {
+        Some(path) if util::path::is_executable(path) => Some(path.clone()),
+        _ => hash_command(shell, name),
+    }
+}
+
+/// Searches `$PATH` for `name` and remembers its path.
+fn hash_command(shell: &mut dyn Shell, name: &str) -> Option<PathBuf> {
+    let path_var = env::var_os("PATH").unwrap_or_default();
+    let path = util::path::search_in_path(name, &path_var);
+    match path {
+        Some(ref path) => {
+            shell
+                .command_cache_mut()
+                .insert(name.to_string(), path.clone());
+        }
+        None => {
+            shell.command_cache_mut().remove(name);
+        }
+    }
+    path
+}
+
+fn print_cache(shell: &mut dyn Shell, stdout: &mut dyn Write) -> Result<()> {
+    let mut entries: Vec<(&String, &PathBuf)> = shell.command_cache().iter().collect();
+    if entries.is_empty() {
+        writeln!(stdout, "hash: hash table empty").context(ErrorKind::Io)?;
+        return Ok(());
+    }
+    entries.sort();
+    for (name, path) in entries {
+        writeln!(
+            stdout,
+            "hash -p {} {}",
+            util::quote_for_shell(&path.to_string_lossy()),
+            util::quote_for_shell(name)
+        )
+        .context(ErrorKind::Io)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct HashOptions<'a> {
+    forget: bool,
+    path: Option<&'a str>,
+    reset: bool,
+    print: bool,
+}
+
+/// Parses the options of `hash`, returning them and the names that follow.
+fn parse_options<'a>(
+    shell: &mut dyn Shell,
+    args: &'a [&'a str],
+) -> Result<(HashOptions<'a>, &'a [&'a str])> {
+    let mut options = HashOptions::default();
+    let mut rest = args;
+    while let Some(flags) = rest
+        .first()
+        .and_then(|arg| arg.strip_prefix('-'))
+        .filter(|flags| !flags.is_empty())
+    {
+        rest = &rest[1..];
+        if flags == "-" {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'd' => options.forget = true,
+                'p' => match rest.split_first() {
+                    Some((path, names)) => {
+                        options.path = Some(path);
+                        rest = names;
+                    }
+                    None => {
+                        return Err(usage_error(shell, "hash: -p: option requires an argument"))
+                    }
+                },
+                'r' => options.reset = true,
+                't' => options.print = true,
+                _ => {
+                    return Err(usage_error(
+                        shell,
+                        &format!("hash: -{}: invalid option", flag),
+                    ))
+                }
+            }
+        }
+    }
+    if options.print && rest.is_empty() {
+        return Err(usage_error(shell, "hash: -t: option requires an argument"));
+    }
+    Ok((options, rest))
+}
+
+fn usage_error(shell: &mut dyn Shell, message: &str) -> Error {
+    let message = format!(
+        "{}\nhash: usage: {}",
+        message,
+        Hash::usage().trim_start_matches("hash: ")
+    );
+    print_error(shell.shell_stderr(), &message);
+    Error::builtin_command(message, 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+    use std::path::Path;
+
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    #[cfg(unix)]
+    fn hash_remember_and_forget() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        assert!(Hash::run(&mut *shell, &["sh"], &mut io::sink()).is_ok());
+        assert!(shell.command_cache().contains_key("sh"));
+        assert_eq!(
+            find_hashed_command(&mut *shell, "sh").as_deref(),
+            shell.command_cache().get("sh").map(Path::new)
+        );
+        assert!(Hash::run(&mut *shell, &["bsh_nonexistent_command"], &mut io::sink()).is_err());
+
+        assert!(Hash::run(&mut *shell, &["-d", "sh"], &mut io::sink()).is_ok());
+        assert!(shell.command_cache().is_empty());
+        assert!(Hash::run(&mut *shell, &["-t", "sh"], &mut io::sink()).is_err());
+
+        assert!(Hash::run(&mut *shell, &["-p", "/bin/true", "yes"], &mut io::sink()).is_ok());
+        assert!(Hash::run(&mut *shell, &["-r"], &mut io::sink()).is_ok());
+        assert!(shell.command_cache().is_empty());
+    }
+}
@@ -1,4 +1,8 @@
-use crate::builtins::{self, dirs, env, exit, history, jobs, kill, prelude::*, BuiltinCommand};
+use crate::builtins::{
+    self, alias, command, compgen, complete, compopt, debug, dirs, echo, enable, env, exec, exit,
+    fc, hash, history, jobs, kill, loops, mapfile, nice, nohup, noop, prelude::*, printf, r#let,
+    r#type, read, set, shopt, source, test, trap, umask, watch, BuiltinCommand,
+};
 
 pub struct Help;
 
@@ -22,16 +26,61 @@ help: help [command ...]
             let mut all_invalid = true;
             for arg in args {
                 let msg = match arg.as_ref() {
+                    builtins::ALIAS_NAME => Some(alias::Alias::HELP),
                     builtins::BG_NAME => Some(jobs::Bg::HELP),
+                    builtins::BRACKET_NAME => Some(test::Bracket::HELP),
+                    builtins::BREAK_NAME => Some(loops::Break::HELP),
                     builtins::CD_NAME => Some(dirs::Cd::HELP),
+                    builtins::COLON_NAME => Some(noop::Colon::HELP),
+                    builtins::COMMAND_NAME => Some(command::Command::HELP),
+                    builtins::COMPGEN_NAME => Some(compgen::Compgen::HELP),
+                    builtins::COMPLETE_NAME => Some(complete::Complete::HELP),
+                    builtins::COMPOPT_NAME => Some(compopt::Compopt::HELP),
+                    builtins::CONTINUE_NAME => Some(loops::Continue::HELP),
+                    builtins::DEBUG_NAME => Some(debug::Debug::HELP),
                     builtins::DECLARE_NAME => Some(env::Declare::HELP),
+                    builtins::DIRS_NAME => Some(dirs::Dirs::HELP),
+                    builtins::DOT_NAME => Some(source::Source::HELP),
+                    builtins::ECHO_NAME => Some(echo::Echo::HELP),
+                    builtins::ENABLE_NAME => Some(enable::Enable::HELP),
+                    builtins::ENV_NAME => Some(env::Env::HELP),
+                    builtins::EXEC_NAME => Some(exec::Exec::HELP),
                     builtins::EXIT_NAME => Some(exit::Exit::HELP),
+                    builtins::EXPORT_NAME => Some(env::Export::HELP),
+                    builtins::FALSE_NAME => Some(noop::False::HELP),
+                    builtins::FC_NAME => Some(fc::Fc::HELP),
                     builtins::FG_NAME => Some(jobs::Fg::HELP),
+                    builtins::HASH_NAME => Some(hash::Hash::HELP),
                     builtins::HELP_NAME => Some(Self::HELP),
                     builtins::HISTORY_NAME => Some(history::History::HELP),
                     builtins::JOBS_NAME => Some(jobs::Jobs::HELP),
                     builtins::KILL_NAME => Some(kill::Kill::HELP),
+                    builtins::LET_NAME => Some(r#let::Let::HELP),
+                    builtins::LOGOUT_NAME => Some(exit::Logout::HELP),
+                    builtins::MAPFILE_NAME => Some(mapfile::Mapfile::HELP),
+                    builtins::NICE_NAME => Some(nice::Nice::HELP),
+                    builtins::NOHUP_NAME => Some(nohup::Nohup::HELP),
+                    builtins::POPD_NAME => Some(dirs::Popd::HELP),
+                    builtins::PRINTENV_NAME => Some(env::Printenv::HELP),
+                    builtins::PRINTF_NAME => Some(printf::Printf::HELP),
+                    builtins::PUSHD_NAME => Some(dirs::Pushd::HELP),
+                    builtins::PWD_NAME => Some(dirs::Pwd::HELP),
+                    builtins::READ_NAME => Some(read::Read::HELP),
+                    builtins::READARRAY_NAME => Some(mapfile::Mapfile::HELP),
+                    builtins::READONLY_NAME => Some(env::Readonly::HELP),
+                    builtins::RETURN_NAME => Some(exit::Return::HELP),
+                    builtins::SET_NAME => Some(set::Set::HELP),
+                    builtins::SHOPT_NAME => Some(shopt::Shopt::HELP),
+                    builtins::SOURCE_NAME => Some(source::Source::HELP),
+                    builtins::TEST_NAME => Some(test::Test::HELP),
+                    builtins::TRAP_NAME => Some(trap::Trap::HELP),
+                    builtins::TRUE_NAME => Some(noop::True::HELP),
+                    builtins::TYPE_NAME => Some(r#type::Type::HELP),
+                    builtins::UMASK_NAME => Some(umask::Umask::HELP),
+                    builtins::UNALIAS_NAME => Some(alias::Unalias::HELP),
                     builtins::UNSET_NAME => Some(env::Unset::HELP),
+                    builtins::WAIT_NAME => Some(jobs::Wait::HELP),
+                    builtins::WATCH_NAME => Some(watch::Watch::HELP),
                     _ => None,
                 };
                 if let Some(msg) = msg {
@@ -52,15 +101,58 @@ help: help [command ...]
 }
 
 fn print_all_usage_strings(writer: &mut dyn Write) -> Result<()> {
+    writeln!(writer, "{}", alias::Alias::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", jobs::Bg::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", test::Bracket::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", loops::Break::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", dirs::Cd::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", noop::Colon::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", command::Command::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", compgen::Compgen::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", complete::Complete::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", compopt::Compopt::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", loops::Continue::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", debug::Debug::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", env::Declare::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", dirs::Dirs::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", echo::Echo::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", enable::Enable::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", env::Env::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", exec::Exec::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", exit::Exit::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", env::Export::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", noop::False::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", fc::Fc::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", jobs::Fg::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", hash::Hash::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", Help::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", history::History::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", jobs::Jobs::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", kill::Kill::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", r#let::Let::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", exit::Logout::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", mapfile::Mapfile::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", nice::Nice::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", nohup::Nohup::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", dirs::Popd::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", env::Printenv::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", printf::Printf::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", dirs::Pushd::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", dirs::Pwd::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", read::Read::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", env::Readonly::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", exit::Return::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", set::Set::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", shopt::Shopt::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", source::Source::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", test::Test::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", trap::Trap::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", noop::True::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", r#type::Type::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", umask::Umask::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", alias::Unalias::usage()).context(ErrorKind::Io)?;
     writeln!(writer, "{}", env::Unset::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", jobs::Wait::usage()).context(ErrorKind::Io)?;
+    writeln!(writer, "{}", watch::Watch::usage()).context(ErrorKind::Io)?;
     Ok(())
 }
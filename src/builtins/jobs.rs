@@ -1,21 +1,42 @@
-use std::num::ParseIntError;
-use std::result as res;
+use std::process::ExitStatus;
 
 use failure::ResultExt;
 use log::debug;
+use nix::{
+    sys::wait::{self, WaitStatus},
+    unistd::Pid,
+};
 use serde_derive::Deserialize;
 
 use crate::{
     builtins::{self, prelude::*},
-    shell::JobId,
+    core::variable_expansion,
+    shell::{print_error, JobId},
+    util::BshExitStatusExt,
 };
 
+/// The job spec for the shell's notion of the current job.
+const CURRENT_JOB_SPEC: &str = "%%";
+
 pub struct Jobs;
 
+/// The `docopt` usage of `jobs`, which `Jobs::HELP` describes in the format of the other builtins.
+const JOBS_USAGE: &str = "
+Usage: jobs [options] [<jobspec>...]
+
+Options:
+    -l      list process IDs in addition to the normal information
+    -n      list only jobs whose status changed since the last notification
+    -p      list process group IDs only
+    -r      restrict output to running jobs
+    -s      restrict output to stopped jobs
+";
+
 #[derive(Debug, Deserialize)]
 struct JobsArgs {
-    arg_jobspec: Vec<i32>,
+    arg_jobspec: Vec<String>,
     flag_l: bool,
+    flag_n: bool,
     flag_p: bool,
     flag_r: bool,
     flag_s: bool,
@@ -25,59 +46,84 @@ impl builtins::BuiltinCommand for Jobs {
     const NAME: &'static str = builtins::JOBS_NAME;
 
     const HELP: &'static str = "\
-jobs: jobs [options] [<jobspec>...]
-
-Display status of jobs.
+jobs: jobs [-lnprs] [jobspec ...]
+    Display status of jobs.
 
-Lists the active jobs. JOBSPEC restricts output to that job.
-Without options, the status of all active jobs is displayed.alloc
+    Lists the active jobs. JOBSPEC restricts output to that job.
+    Without options, the status of all active jobs is displayed.
 
-Options:
     -l      lists process IDs in addition to the normal information
-    -p      lists process IDs only
+    -n      lists only processes that have changed status since the last
+            notification
+    -p      lists process group IDs only
     -r      restrict output to running jobs
     -s      restrict output to stopped jobs
 
-Exit Status:
-Returns success unless an invalid option is given or an error occurs.";
+    Exit Status:
+    Returns success unless an invalid option is given or an error occurs.";
 
     fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
-        let args: JobsArgs = parse_args(Self::HELP, Self::NAME, args.iter().map(AsRef::as_ref))?;
+        let args: JobsArgs =
+            match parse_args(JOBS_USAGE, Self::NAME, args.iter().map(AsRef::as_ref)) {
+                Ok(args) => args,
+                Err(_) => {
+                    let message = format!(
+                        "jobs: usage: {}",
+                        Self::usage().trim_start_matches("jobs: ")
+                    );
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 2));
+                }
+            };
         debug!("{:?}", args);
 
-        for job in shell.get_jobs() {
-            let processes = job.processes();
-            if args.flag_l {
-                if let Some(first) = processes.first() {
-                    writeln!(
-                        stdout,
-                        "[{}] {:?}\t{}\t{}",
-                        job.id(),
-                        first.id(),
-                        first.status(),
-                        first.argv()
-                    )
-                    .context(ErrorKind::Io)?;
-                }
-                for process in processes.iter().skip(1) {
-                    writeln!(
-                        stdout,
-                        "\t{:?}\t{}\t{}",
-                        process.id(),
-                        process.status(),
-                        process.argv()
-                    )
-                    .context(ErrorKind::Io)?;
+        let mut job_ids = Vec::new();
+        for spec in &args.arg_jobspec {
+            match shell.job_by_spec(spec) {
+                Ok(job_id) => job_ids.push(job_id),
+                Err(e) => {
+                    let message = format!("jobs: {}", e);
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 1));
                 }
-            } else if args.flag_p {
-                for process in processes {
-                    writeln!(stdout, "{:?}", process.id()).context(ErrorKind::Io)?;
+            }
+        }
+
+        let mut notified = Vec::new();
+        for job in shell.get_jobs() {
+            if !job_ids.is_empty() && !job_ids.contains(&job.id()) {
+                continue;
+            }
+            let is_running = !job.is_stopped() && !job.is_completed();
+            if (args.flag_r && !is_running)
+                || (args.flag_s && !job.is_stopped())
+                || (args.flag_n && !job.is_status_changed())
+            {
+                continue;
+            }
+
+            if args.flag_p {
+                // Jobs without a process group, e.g. builtins, are listed by their first process.
+                let pgid = job.pgid().or_else(|| {
+                    job.processes()
+                        .iter()
+                        .find_map(|process| process.id())
+                        .map(u32::from)
+                });
+                if let Some(pgid) = pgid {
+                    writeln!(stdout, "{}", pgid).context(ErrorKind::Io)?;
                 }
+            } else if args.flag_l {
+                writeln!(stdout, "{}", job.display_long()).context(ErrorKind::Io)?;
             } else {
                 writeln!(stdout, "{}", job.display()).context(ErrorKind::Io)?;
             }
+            notified.push(job.id());
         }
 
+        for job_id in notified {
+            shell.set_job_notified(job_id);
+        }
         Ok(())
     }
 }
@@ -98,19 +144,17 @@ fg: fg [job_spec]
     Exit Status:
     Status of command placed in foreground or failure if an error occurs.";
 
-    fn run<T: AsRef<str>>(
-        shell: &mut dyn Shell,
-        args: &[T],
-        _stdout: &mut dyn Write,
-    ) -> Result<()> {
-        let job_id = args
-            .first()
-            .map(|s| s.as_ref().parse::<u32>())
-            .map_or(Ok(None), |v| v.map(Some));
-        match job_id {
-            Ok(job_id) => shell.put_job_in_foreground(job_id.map(JobId))?,
-            Err(e) => return Err(Error::builtin_command(format!("fg: {}", e), 1)),
-        };
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        if !shell.is_job_control_enabled() {
+            return Err(Error::builtin_command("fg: no job control", 1));
+        }
+
+        let spec = args.first().map_or(CURRENT_JOB_SPEC, AsRef::as_ref);
+        let job_id = shell
+            .job_by_spec(spec)
+            .map_err(|e| Error::builtin_command(format!("fg: {}", e), 1))?;
+        print_job(shell, job_id, stdout)?;
+        shell.put_job_in_foreground(Some(job_id))?;
         Ok(())
     }
 }
@@ -132,28 +176,132 @@ bg: bg [<jobspec>...]
     Returns success unless job control is not enabled or an error occurs.";
 
     fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
-        if args.is_empty() {
-            if let Err(e) = shell.put_job_in_background(None) {
+        if !shell.is_job_control_enabled() {
+            return Err(Error::builtin_command("bg: no job control", 1));
+        }
+
+        let specs: Vec<&str> = if args.is_empty() {
+            vec![CURRENT_JOB_SPEC]
+        } else {
+            args.iter().map(AsRef::as_ref).collect()
+        };
+
+        for spec in specs {
+            let result = shell.job_by_spec(spec).and_then(|job_id| {
+                print_job(shell, job_id, stdout)?;
+                shell.put_job_in_background(Some(job_id))
+            });
+            if let Err(e) = result {
                 writeln!(stdout, "{}", e).context(ErrorKind::Io)?;
             }
-        } else {
-            let job_ids: Vec<res::Result<JobId, ParseIntError>> = args
-                .iter()
-                .map(|s| s.as_ref().parse::<u32>().map(JobId))
-                .collect();
-
-            for job_id in &job_ids {
-                match *job_id {
-                    Ok(ref job_id) => {
-                        if let Err(e) = shell.put_job_in_background(Some(*job_id)) {
-                            writeln!(stdout, "{}", e).context(ErrorKind::Io)?;
-                        }
-                    }
-                    Err(ref e) => writeln!(stdout, "{}", e).context(ErrorKind::Io)?,
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Wait;
+
+impl builtins::BuiltinCommand for Wait {
+    const NAME: &'static str = builtins::WAIT_NAME;
+
+    const HELP: &'static str = "\
+wait: wait [-n] [id ...]
+    Wait for job completion and return exit status.
+
+    Waits for each process identified by an ID, which may be a process ID or
+    a job specification, and reports its termination status. If ID is not
+    given, waits for all currently active child processes, and the return
+    status is zero. If ID is a job specification, waits for all processes
+    in that job's pipeline.
+
+    If the -n option is supplied, waits for the next job to terminate and
+    returns its exit status.
+
+    Exit Status:
+    Returns the status of the last ID; fails if ID is invalid or an invalid
+    option is given.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let exit_status = match args.as_slice() {
+            [] => {
+                while shell.wait_for_next_job()?.is_some() {}
+                ExitStatus::from_success()
+            }
+            ["-n"] => shell
+                .wait_for_next_job()?
+                .unwrap_or_else(|| ExitStatus::from_status(NOT_A_CHILD_EXIT_STATUS)),
+            ids => {
+                let mut exit_status = ExitStatus::from_success();
+                for id in ids {
+                    exit_status = wait_for_id(shell, id)?;
                 }
+                exit_status
             }
+        };
+
+        match variable_expansion::exit_code(exit_status) {
+            0 => Ok(()),
+            code => Err(Error::builtin_command("", code)),
         }
+    }
+}
 
-        Ok(())
+/// The exit status of `wait` if the process or job is not a child of the shell.
+const NOT_A_CHILD_EXIT_STATUS: i32 = 127;
+
+/// Waits for the job or process `id`, e.g. `%1` or `1234`, and returns its exit status.
+fn wait_for_id(shell: &mut dyn Shell, id: &str) -> Result<ExitStatus> {
+    if id.starts_with('%') {
+        return match shell.job_by_spec(id) {
+            Ok(job_id) => Ok(shell
+                .wait_for_job(job_id)?
+                .unwrap_or_else(ExitStatus::from_failure)),
+            Err(e) => {
+                print_error(shell.shell_stderr(), format_args!("wait: {}", e));
+                Ok(ExitStatus::from_status(NOT_A_CHILD_EXIT_STATUS))
+            }
+        };
+    }
+
+    let pid = match id.parse::<u32>() {
+        Ok(pid) => pid,
+        Err(_) => {
+            print_error(
+                shell.shell_stderr(),
+                format_args!("wait: `{}': not a pid or valid job spec", id),
+            );
+            return Ok(ExitStatus::from_status(2));
+        }
+    };
+    if let Some(exit_status) = shell.wait_for_process(pid)? {
+        return Ok(exit_status);
+    }
+
+    // The process is not part of a background job, so it is waited for directly.
+    match wait::waitpid(Pid::from_raw(pid as libc::pid_t), None) {
+        Ok(WaitStatus::Exited(_, code)) => Ok(ExitStatus::from_status(code)),
+        Ok(WaitStatus::Signaled(_, signal, _)) => Ok(ExitStatus::from_status(128 + signal as i32)),
+        Ok(_) => Ok(ExitStatus::from_success()),
+        Err(_) => {
+            print_error(
+                shell.shell_stderr(),
+                format_args!("wait: pid {} is not a child of this shell", pid),
+            );
+            Ok(ExitStatus::from_status(NOT_A_CHILD_EXIT_STATUS))
+        }
+    }
+}
+
+/// Prints the job's id and command, e.g. `[1] sleep 10`.
+fn print_job(shell: &dyn Shell, job_id: JobId, stdout: &mut dyn Write) -> Result<()> {
+    if let Some(job) = shell.get_jobs().iter().find(|job| job.id() == job_id) {
+        writeln!(stdout, "[{}] {}", job_id, job.input()).context(ErrorKind::Io)?;
     }
+    Ok(())
 }
@@ -1,5 +1,10 @@
-use std::ffi::OsStr;
-use std::process::Command;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::Pid,
+};
 
 use crate::builtins::{self, prelude::*};
 
@@ -9,10 +14,16 @@ impl builtins::BuiltinCommand for Kill {
     const NAME: &'static str = builtins::KILL_NAME;
 
     const HELP: &'static str = "\
-kill: kill pid | %jobspec
+kill: kill [-sigspec] pid | %jobspec ... or kill -l
     Send a signal to a job.
 
-    Send SIGTERM to the processes identified by JOBSPEC.
+    Send the processes identified by PID or JOBSPEC the signal named by
+    SIGSPEC. If SIGSPEC is not present, then SIGTERM is assumed.
+
+    -l      list the signal names and numbers
+
+    SIGSPEC may be a signal name, with or without the SIG prefix, or a
+    signal number, e.g. -SIGKILL, -KILL, or -9.
 
     Kill is a shell builtin for two reasons: it allows job IDs
     to be used instead of process IDs.
@@ -21,41 +32,92 @@ kill: kill pid | %jobspec
     Returns success unless an invalid option is given or an error occurs.";
 
     fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
-        if args.is_empty() {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (signal, targets) = match args.as_slice() {
+            [] | ["-l"] => return list_signals(stdout),
+            [sigspec, targets @ ..] if sigspec.starts_with('-') => {
+                let signal = parse_signal(&sigspec[1..]).ok_or_else(|| {
+                    Error::builtin_command(
+                        format!("kill: {}: invalid signal specification", &sigspec[1..]),
+                        1,
+                    )
+                })?;
+                (signal, targets)
+            }
+            targets => (Signal::SIGTERM, targets),
+        };
+
+        if targets.is_empty() {
             return Err(Error::builtin_command(Self::usage(), 2));
         }
 
-        let arg = args.first().unwrap();
-        if arg.as_ref().starts_with('%') {
-            match arg.as_ref()[1..].parse::<u32>() {
-                Ok(n) => match shell.kill_background_job(n) {
-                    Ok(Some(job)) => {
-                        writeln!(stdout, "[{}]+\tTerminated: 15\t{}", n, job.input())
-                            .context(ErrorKind::Io)?;
-                        Ok(())
-                    }
-                    Ok(None) => Err(Error::builtin_command(
-                        format!("kill: {}: no such job", arg.as_ref()),
-                        1,
-                    )),
-                    Err(e) => Err(e),
-                },
-                Err(_) => Err(Error::builtin_command(
-                    format!(
-                        "kill: {}: arguments must be \
-                         job IDs",
-                        arg.as_ref()
-                    ),
-                    1,
-                )),
-            }
-        } else {
-            let output = Command::new("kill")
-                .args(args.iter().map(AsRef::as_ref).map(OsStr::new))
-                .output()
-                .context(ErrorKind::Io)?;
-            write!(stdout, "{}", String::from_utf8_lossy(&output.stdout)).context(ErrorKind::Io)?;
-            Ok(())
+        // Signal every target, even if an earlier one fails
+        let errors: Vec<String> = targets
+            .iter()
+            .filter_map(|target| kill_target(shell, target, signal).err())
+            .map(|e| format!("kill: {}", e))
+            .collect();
+        if !errors.is_empty() {
+            return Err(Error::builtin_command(errors.join("\n"), 1));
         }
+
+        Ok(())
+    }
+}
+
+/// Parses a signal name, with or without the `SIG` prefix, or a signal number.
+pub(super) fn parse_signal(sigspec: &str) -> Option<Signal> {
+    if let Ok(n) = sigspec.parse::<i32>() {
+        return Signal::try_from(n).ok();
+    }
+
+    let name = sigspec.to_uppercase();
+    if name.starts_with("SIG") {
+        Signal::from_str(&name).ok()
+    } else {
+        Signal::from_str(&format!("SIG{}", name)).ok()
+    }
+}
+
+fn kill_target(shell: &mut dyn Shell, target: &str, signal: Signal) -> Result<()> {
+    if target.starts_with('%') {
+        let job_id = shell.job_by_spec(target)?;
+        match shell.kill_background_job(job_id, signal)? {
+            Some(_) => Ok(()),
+            None => Err(Error::no_such_job(target)),
+        }
+    } else {
+        let pid = target.parse::<libc::pid_t>().map_err(|_| {
+            Error::builtin_command(
+                format!("{}: arguments must be process or job IDs", target),
+                1,
+            )
+        })?;
+        signal::kill(Pid::from_raw(pid), signal).context(ErrorKind::Nix)?;
+        Ok(())
+    }
+}
+
+pub(super) fn list_signals(stdout: &mut dyn Write) -> Result<()> {
+    for signal in Signal::iterator() {
+        writeln!(stdout, "{}) {}", signal as i32, signal).context(ErrorKind::Io)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("SIGTERM"), Some(Signal::SIGTERM));
+        assert_eq!(parse_signal("TERM"), Some(Signal::SIGTERM));
+        assert_eq!(parse_signal("kill"), Some(Signal::SIGKILL));
+        assert_eq!(parse_signal("15"), Some(Signal::SIGTERM));
+        assert_eq!(parse_signal("9"), Some(Signal::SIGKILL));
+        assert_eq!(parse_signal("FOO"), None);
+        assert_eq!(parse_signal("0"), None);
+        assert_eq!(parse_signal("100"), None);
     }
 }
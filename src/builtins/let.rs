@@ -0,0 +1,85 @@
+use crate::{
+    builtins::{self, prelude::*},
+    shell::print_error,
+};
+
+pub struct Let;
+
+impl builtins::BuiltinCommand for Let {
+    const NAME: &'static str = builtins::LET_NAME;
+
+    const HELP: &'static str = "\
+let: let arg [arg ...]
+    Evaluate each ARG as an arithmetic expression. Variables may be assigned
+    with =, +=, -=, *=, /=, %=, ++ and --.
+
+    Exit Status:
+    If the last ARG evaluates to 0, let returns 1; otherwise let returns 0.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        if args.is_empty() {
+            let message = "let: expression expected";
+            print_error(shell.shell_stderr(), message);
+            return Err(Error::builtin_command(message, 1));
+        }
+
+        let mut value = 0;
+        for arg in args {
+            value = match shell.eval_arithmetic(arg.as_ref()) {
+                Ok(value) => value,
+                Err(e) => {
+                    let message = format!("let: {}: {}", arg.as_ref(), e);
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 1));
+                }
+            };
+        }
+
+        if value == 0 {
+            return Err(Error::builtin_command("", 1));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn let_exit_status() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        assert!(Let::run(&mut *shell, &["5 > 3"], &mut io::sink()).is_ok());
+        assert!(Let::run(&mut *shell, &["5 < 3"], &mut io::sink()).is_err());
+        assert!(Let::run(&mut *shell, &["0", "1"], &mut io::sink()).is_ok());
+        assert!(Let::run(&mut *shell, &["1", "0"], &mut io::sink()).is_err());
+        assert!(Let::run(&mut *shell, &["1 / 0"], &mut io::sink()).is_err());
+        assert!(Let::run::<&str>(&mut *shell, &[], &mut io::sink()).is_err());
+    }
+
+    #[test]
+    fn let_assignment() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        let key = "BSH_TEST_LET_ASSIGNMENT";
+        assert!(Let::run(
+            &mut *shell,
+            &[format!("{} = 5 + 3", key), format!("{}++", key)],
+            &mut io::sink(),
+        )
+        .is_ok());
+        assert_eq!(shell.get_variable(key).unwrap(), "9");
+        shell.unset_variable(key);
+    }
+}
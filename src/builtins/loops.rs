@@ -0,0 +1,130 @@
+use crate::builtins::{self, prelude::*};
+use crate::errors::LoopControl;
+use crate::shell::print_error;
+
+pub struct Break;
+
+impl builtins::BuiltinCommand for Break {
+    const NAME: &'static str = builtins::BREAK_NAME;
+
+    const HELP: &'static str = "\
+break: break [n]
+    Exit for, while, or until loops.
+
+    Exit a FOR, WHILE or UNTIL loop. If N is specified, break N enclosing
+    loops.
+
+    Exit Status:
+    The exit status is 0 unless N is not greater than or equal to 1.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        match loop_count(shell, Self::NAME, args)? {
+            Some(n) => Err(Error::loop_control(LoopControl::Break(n))),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct Continue;
+
+impl builtins::BuiltinCommand for Continue {
+    const NAME: &'static str = builtins::CONTINUE_NAME;
+
+    const HELP: &'static str = "\
+continue: continue [n]
+    Resume for, while, or until loops.
+
+    Resumes the next iteration of the enclosing FOR, WHILE or UNTIL loop.
+    If N is specified, resumes the Nth enclosing loop.
+
+    Exit Status:
+    The exit status is 0 unless N is not greater than or equal to 1.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        match loop_count(shell, Self::NAME, args)? {
+            Some(n) => Err(Error::loop_control(LoopControl::Continue(n))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the number of loops `break` or `continue` applies to, at most the number of enclosing
+/// loops, or `None` if they are not run in a loop.
+fn loop_count<T: AsRef<str>>(
+    shell: &mut dyn Shell,
+    name: &str,
+    args: &[T],
+) -> Result<Option<usize>> {
+    let n = match args.get(0) {
+        Some(arg) => match arg.as_ref().parse::<usize>() {
+            Ok(n) if n >= 1 => n,
+            _ => {
+                let message = format!("{}: {}: loop count out of range", name, arg.as_ref());
+                print_error(shell.shell_stderr(), &message);
+                return Err(Error::builtin_command(message, 1));
+            }
+        },
+        None => 1,
+    };
+
+    // Like bash, this is only a warning.
+    if shell.loop_depth() == 0 {
+        print_error(
+            shell.shell_stderr(),
+            format_args!(
+                "{}: only meaningful in a `for', `while', or `until' loop",
+                name
+            ),
+        );
+        return Ok(None);
+    }
+    Ok(Some(n.min(shell.loop_depth())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::errors::ErrorKind;
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn test_loop_count() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        shell.set_shell_stderr(Box::new(io::sink()));
+        let kind = |result: Result<()>| result.unwrap_err().kind().clone();
+
+        assert!(Break::run::<&str>(&mut *shell, &[], &mut io::sink()).is_ok());
+
+        shell.set_loop_depth(2);
+        assert_eq!(
+            kind(Break::run::<&str>(&mut *shell, &[], &mut io::sink())),
+            ErrorKind::LoopControl(LoopControl::Break(1))
+        );
+        assert_eq!(
+            kind(Continue::run(&mut *shell, &["2"], &mut io::sink())),
+            ErrorKind::LoopControl(LoopControl::Continue(2))
+        );
+        assert_eq!(
+            kind(Break::run(&mut *shell, &["5"], &mut io::sink())),
+            ErrorKind::LoopControl(LoopControl::Break(2))
+        );
+        for arg in &["0", "-1", "x"] {
+            assert!(matches!(
+                kind(Continue::run(&mut *shell, &[arg], &mut io::sink())),
+                ErrorKind::BuiltinCommand { code: 1, .. }
+            ));
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::{collections::BTreeMap, os::unix::io::RawFd};
+
+use crate::{
+    builtins::{self, prelude::*, read::ByteReader, BuiltinCommand},
+    shell::print_error,
+    util,
+};
+
+/// The array the lines are stored in if no name is given.
+const DEFAULT_ARRAY_NAME: &str = "MAPFILE";
+/// The number of lines read between calls of the `-C` callback if `-c` is not given.
+const DEFAULT_CALLBACK_QUANTUM: usize = 5000;
+
+pub struct Mapfile;
+
+impl BuiltinCommand for Mapfile {
+    const NAME: &'static str = builtins::MAPFILE_NAME;
+
+    const HELP: &'static str = "\
+mapfile: mapfile [-d delim] [-n count] [-s count] [-t] [-u fd] [-C callback] [-c quantum] [array]
+    Read lines from the standard input into an indexed array variable.
+
+    Read lines from the standard input, or from file descriptor FD if -u is
+    given, into the indexed array ARRAY, or MAPFILE if ARRAY is not given.
+    The array is cleared before lines are assigned to it.
+
+    -d delim     use DELIM to terminate lines, instead of newline
+    -n count     copy at most COUNT lines; if COUNT is 0, all lines are copied
+    -s count     discard the first COUNT lines read
+    -t           remove a trailing DELIM from each line read
+    -u fd        read lines from file descriptor FD instead of the standard input
+    -C callback  evaluate CALLBACK each time QUANTUM lines are read
+    -c quantum   specify the number of lines read between each call to CALLBACK
+
+    CALLBACK is evaluated with the index of the next array element and the
+    line to be assigned to it as additional arguments.
+
+    Builtins run in the shell process, so unlike bash, the array is still set
+    after `command | mapfile array`. readarray is a synonym for mapfile.
+
+    Exit Status:
+    Returns success unless an invalid option is given or ARRAY is read-only.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (options, names) = parse_options(shell, &args)?;
+        let name = match names {
+            [] => DEFAULT_ARRAY_NAME,
+            [name] => name,
+            _ => return Err(usage_error(shell, "mapfile: too many arguments")),
+        };
+        if let Err(e) = shell.check_variable_assignment(name) {
+            print_error(shell.shell_stderr(), format_args!("mapfile: {}", e));
+            return Err(e);
+        }
+
+        shell.unset_variable(name);
+        shell.set_array(name, BTreeMap::new());
+        let mut reader = ByteReader::new(options.fd);
+        let mut index = 0;
+        let mut skipped = 0;
+        while options.max_lines.map_or(true, |max| index < max) {
+            let line = match read_record(&mut reader, options.delimiter) {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    let message = format!("mapfile: {}: invalid file descriptor", options.fd);
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 1));
+                }
+            };
+            if skipped < options.skip {
+                skipped += 1;
+                continue;
+            }
+
+            let line = if options.trim {
+                line.strip_suffix(options.delimiter as char)
+                    .unwrap_or(&line)
+                    .to_string()
+            } else {
+                line
+            };
+            if let Some(callback) = options.callback {
+                if (index + 1) % options.quantum == 0 {
+                    run_callback(shell, callback, index, &line)?;
+                }
+            }
+            shell.set_array_element(name, index, &line);
+            index += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Reads bytes up to and including `delimiter`, or until end of file. Returns `None` if there
+/// are no bytes left.
+fn read_record(reader: &mut ByteReader, delimiter: u8) -> Result<Option<String>> {
+    let mut bytes = Vec::new();
+    while let Some(byte) = reader.next()? {
+        bytes.push(byte);
+        if byte == delimiter {
+            break;
+        }
+    }
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Evaluates the `-C` callback with the index of the next element and the line assigned to it.
+fn run_callback(shell: &mut dyn Shell, callback: &str, index: usize, line: &str) -> Result<()> {
+    let command = format!("{} {} {}", callback, index, util::quote_for_shell(line));
+    // The callback is run on behalf of `mapfile`, so it is not added to the history.
+    let enable_command_history = shell.config().is_command_history_enabled();
+    shell.config_mut().set_command_history_enabled(false);
+    let result = shell.execute_command_string(&command);
+    shell
+        .config_mut()
+        .set_command_history_enabled(enable_command_history);
+    result
+}
+
+#[derive(Debug)]
+struct MapfileOptions<'a> {
+    delimiter: u8,
+    max_lines: Option<usize>,
+    skip: usize,
+    trim: bool,
+    fd: RawFd,
+    callback: Option<&'a str>,
+    quantum: usize,
+}
+
+impl Default for MapfileOptions<'_> {
+    fn default() -> Self {
+        MapfileOptions {
+            delimiter: b'\n',
+            max_lines: None,
+            skip: 0,
+            trim: false,
+            fd: libc::STDIN_FILENO,
+            callback: None,
+            quantum: DEFAULT_CALLBACK_QUANTUM,
+        }
+    }
+}
+
+/// Parses the options of `mapfile`, returning them and the arguments that follow.
+fn parse_options<'a>(
+    shell: &mut dyn Shell,
+    args: &'a [&'a str],
+) -> Result<(MapfileOptions<'a>, &'a [&'a str])> {
+    let mut options = MapfileOptions::default();
+    let mut rest = args;
+    while let Some(flags) = rest
+        .first()
+        .and_then(|arg| arg.strip_prefix('-'))
+        .filter(|flags| !flags.is_empty())
+    {
+        rest = &rest[1..];
+        if flags == "-" {
+            break;
+        }
+        for (i, flag) in flags.char_indices() {
+            match flag {
+                't' => options.trim = true,
+                'd' | 'n' | 's' | 'u' | 'C' | 'c' => {
+                    // The value is either the rest of the argument, e.g. `-n5`, or the next one.
+                    let value = match &flags[i + 1..] {
+                        "" => match rest.split_first() {
+                            Some((value, args)) => {
+                                rest = args;
+                                *value
+                            }
+                            None => {
+                                return Err(usage_error(
+                                    shell,
+                                    &format!("mapfile: -{}: option requires an argument", flag),
+                                ))
+                            }
+                        },
+                        value => value,
+                    };
+                    parse_value(shell, &mut options, flag, value)?;
+                    break;
+                }
+                _ => {
+                    return Err(usage_error(
+                        shell,
+                        &format!("mapfile: -{}: invalid option", flag),
+                    ))
+                }
+            }
+        }
+    }
+    Ok((options, rest))
+}
+
+fn parse_value<'a>(
+    shell: &mut dyn Shell,
+    options: &mut MapfileOptions<'a>,
+    flag: char,
+    value: &'a str,
+) -> Result<()> {
+    let invalid = |shell: &mut dyn Shell, description: &str| {
+        let message = format!("mapfile: {}: {}", value, description);
+        print_error(shell.shell_stderr(), &message);
+        Error::builtin_command(message, 1)
+    };
+    match flag {
+        // Like bash, an empty delimiter reads until a NUL byte.
+        'd' => options.delimiter = value.bytes().next().unwrap_or(0),
+        'n' => {
+            let count: usize = value
+                .parse()
+                .map_err(|_| invalid(shell, "invalid line count"))?;
+            options.max_lines = Some(count).filter(|count| *count > 0);
+        }
+        's' => {
+            options.skip = value
+                .parse()
+                .map_err(|_| invalid(shell, "invalid line count"))?
+        }
+        'u' => {
+            options.fd = value
+                .parse::<RawFd>()
+                .ok()
+                .filter(|fd| *fd >= 0)
+                .ok_or_else(|| invalid(shell, "invalid file descriptor specification"))?
+        }
+        'C' => options.callback = Some(value),
+        _ => {
+            options.quantum = value
+                .parse()
+                .ok()
+                .filter(|quantum| *quantum > 0)
+                .ok_or_else(|| invalid(shell, "invalid callback quantum"))?
+        }
+    }
+    Ok(())
+}
+
+fn usage_error(shell: &mut dyn Shell, message: &str) -> Error {
+    let message = format!(
+        "{}\nmapfile: usage: {}",
+        message,
+        Mapfile::usage().trim_start_matches("mapfile: ")
+    );
+    print_error(shell.shell_stderr(), &message);
+    Error::builtin_command(message, 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+    use std::io::{self, Seek, SeekFrom};
+    use std::os::unix::io::AsRawFd;
+
+    use crate::shell::{create_shell, ShellConfig};
+
+    fn elements(shell: &dyn Shell, name: &str) -> Vec<String> {
+        shell.get_array(name).unwrap().values().cloned().collect()
+    }
+
+    #[test]
+    fn mapfile_from_fd() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let mut file: File = tempfile::tempfile().unwrap();
+        write!(file, "a\nb\nc\nd").unwrap();
+
+        let fd = file.as_raw_fd().to_string();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        assert!(Mapfile::run(&mut *shell, &["-t", "-u", &fd, "lines"], &mut io::sink()).is_ok());
+        assert_eq!(elements(&*shell, "lines"), ["a", "b", "c", "d"]);
+
+        file.seek(SeekFrom::Start(0)).unwrap();
+        assert!(Mapfile::run(&mut *shell, &["-s1", "-n", "2", "-u", &fd], &mut io::sink()).is_ok());
+        assert_eq!(elements(&*shell, "MAPFILE"), ["b\n", "c\n"]);
+
+        file.seek(SeekFrom::Start(0)).unwrap();
+        assert!(Mapfile::run(
+            &mut *shell,
+            &["-td", "c", "-u", &fd, "parts"],
+            &mut io::sink()
+        )
+        .is_ok());
+        assert_eq!(elements(&*shell, "parts"), ["a\nb\n", "\nd"]);
+
+        assert!(Mapfile::run(&mut *shell, &["-n", "x"], &mut io::sink()).is_err());
+        assert!(Mapfile::run(&mut *shell, &["-q"], &mut io::sink()).is_err());
+        assert!(Mapfile::run(&mut *shell, &["a", "b"], &mut io::sink()).is_err());
+    }
+}
@@ -3,20 +3,58 @@
 //! This module includes the implementations of common shell builtin commands.
 //! Where possible the commands conform to their standard Bash counterparts.
 
+use std::collections::HashSet;
+use std::io;
 use std::iter;
+use std::process;
 
 use docopt::Docopt;
 use failure::Fail;
 
 use self::prelude::*;
+use crate::execute_command;
+use crate::shell::{print_error, COMMAND_NOT_FOUND_EXIT_STATUS, PERMISSION_DENIED_EXIT_STATUS};
 
-use self::dirs::Cd;
-use self::env::{Declare, Unset};
-use self::exit::Exit;
+use self::alias::{Alias, Unalias};
+use self::command::Command;
+use self::compgen::Compgen;
+use self::complete::Complete;
+use self::compopt::Compopt;
+use self::debug::Debug;
+use self::dirs::{Cd, Dirs, Popd, Pushd, Pwd};
+use self::echo::Echo;
+use self::enable::Enable;
+use self::env::{Declare, Env, Export, Printenv, Readonly, Unset};
+use self::exec::Exec;
+use self::exit::{Exit, Logout, Return};
+use self::fc::Fc;
+use self::hash::Hash;
 use self::help::Help;
 use self::history::History;
-use self::jobs::{Bg, Fg, Jobs};
+use self::jobs::{Bg, Fg, Jobs, Wait};
 use self::kill::Kill;
+use self::loops::{Break, Continue};
+use self::mapfile::Mapfile;
+use self::nice::Nice;
+use self::nohup::Nohup;
+use self::noop::{Colon, False, True};
+use self::printf::Printf;
+use self::r#let::Let;
+use self::r#type::Type;
+use self::read::Read;
+use self::set::Set;
+use self::shopt::Shopt;
+use self::source::Source;
+use self::test::{Bracket, Test};
+use self::trap::Trap;
+use self::umask::Umask;
+use self::watch::Watch;
+
+pub use self::command::{run_options, RunOptions};
+pub use self::complete::complete_word;
+pub use self::compopt::{with_completion_options, CompletionOptions};
+pub use self::exec::redirects_shell;
+pub use self::hash::find_hashed_command;
 
 pub mod prelude {
     pub use std::io::Write;
@@ -30,24 +68,100 @@ pub mod prelude {
     pub use crate::util::BshExitStatusExt;
 }
 
+mod alias;
+mod command;
+mod compgen;
+mod complete;
+mod compopt;
+mod debug;
 mod dirs;
+mod echo;
+mod enable;
 mod env;
+#[allow(unsafe_code)]
+mod exec;
 mod exit;
+mod fc;
+mod hash;
 mod help;
 mod history;
 mod jobs;
 mod kill;
+mod r#let;
+mod loops;
+mod mapfile;
+#[allow(unsafe_code)]
+mod nice;
+#[allow(unsafe_code)]
+mod nohup;
+mod noop;
+mod printf;
+mod read;
+mod set;
+mod shopt;
+mod source;
+mod test;
+mod trap;
+mod r#type;
+mod umask;
+#[allow(unsafe_code)]
+mod watch;
 
+const ALIAS_NAME: &str = "alias";
 const BG_NAME: &str = "bg";
+const BRACKET_NAME: &str = "[";
+const BREAK_NAME: &str = "break";
 const CD_NAME: &str = "cd";
+const COLON_NAME: &str = ":";
+const COMMAND_NAME: &str = "command";
+const COMPGEN_NAME: &str = "compgen";
+const COMPLETE_NAME: &str = "complete";
+const COMPOPT_NAME: &str = "compopt";
+const CONTINUE_NAME: &str = "continue";
+const DEBUG_NAME: &str = "debug";
+const DIRS_NAME: &str = "dirs";
+const DOT_NAME: &str = ".";
 const DECLARE_NAME: &str = "declare";
+const ECHO_NAME: &str = "echo";
+const ENABLE_NAME: &str = "enable";
+const ENV_NAME: &str = "env";
+const EXEC_NAME: &str = "exec";
 const EXIT_NAME: &str = "exit";
+const EXPORT_NAME: &str = "export";
+const FALSE_NAME: &str = "false";
+const FC_NAME: &str = "fc";
 const FG_NAME: &str = "fg";
+const HASH_NAME: &str = "hash";
 const HELP_NAME: &str = "help";
 const HISTORY_NAME: &str = "history";
 const JOBS_NAME: &str = "jobs";
 const KILL_NAME: &str = "kill";
+const LET_NAME: &str = "let";
+const LOGOUT_NAME: &str = "logout";
+const MAPFILE_NAME: &str = "mapfile";
+const NICE_NAME: &str = "nice";
+const NOHUP_NAME: &str = "nohup";
+const POPD_NAME: &str = "popd";
+const PRINTENV_NAME: &str = "printenv";
+const PRINTF_NAME: &str = "printf";
+const PUSHD_NAME: &str = "pushd";
+const PWD_NAME: &str = "pwd";
+const READ_NAME: &str = "read";
+const READARRAY_NAME: &str = "readarray";
+const READONLY_NAME: &str = "readonly";
+const RETURN_NAME: &str = "return";
+const SET_NAME: &str = "set";
+const SHOPT_NAME: &str = "shopt";
+const SOURCE_NAME: &str = "source";
+const TEST_NAME: &str = "test";
+const TRAP_NAME: &str = "trap";
+const TRUE_NAME: &str = "true";
+const TYPE_NAME: &str = "type";
+const UMASK_NAME: &str = "umask";
+const UNALIAS_NAME: &str = "unalias";
 const UNSET_NAME: &str = "unset";
+const WAIT_NAME: &str = "wait";
+const WATCH_NAME: &str = "watch";
 
 /// Represents a Bsh builtin command such as cd or help.
 pub trait BuiltinCommand {
@@ -63,20 +177,73 @@ pub trait BuiltinCommand {
     fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()>;
 }
 
-pub fn is_builtin<T: AsRef<str>>(program: T) -> bool {
-    [
-        BG_NAME,
-        CD_NAME,
-        DECLARE_NAME,
-        EXIT_NAME,
-        FG_NAME,
-        HELP_NAME,
-        HISTORY_NAME,
-        KILL_NAME,
-        JOBS_NAME,
-        UNSET_NAME,
-    ]
-    .contains(&program.as_ref())
+/// The names of all builtins.
+const BUILTIN_NAMES: &[&str] = &[
+    ALIAS_NAME,
+    BG_NAME,
+    BRACKET_NAME,
+    BREAK_NAME,
+    CD_NAME,
+    COLON_NAME,
+    COMMAND_NAME,
+    COMPGEN_NAME,
+    COMPLETE_NAME,
+    COMPOPT_NAME,
+    CONTINUE_NAME,
+    DEBUG_NAME,
+    DECLARE_NAME,
+    DIRS_NAME,
+    DOT_NAME,
+    ECHO_NAME,
+    ENABLE_NAME,
+    ENV_NAME,
+    EXEC_NAME,
+    EXIT_NAME,
+    EXPORT_NAME,
+    FALSE_NAME,
+    FC_NAME,
+    FG_NAME,
+    HASH_NAME,
+    HELP_NAME,
+    HISTORY_NAME,
+    KILL_NAME,
+    JOBS_NAME,
+    LET_NAME,
+    LOGOUT_NAME,
+    MAPFILE_NAME,
+    NICE_NAME,
+    NOHUP_NAME,
+    POPD_NAME,
+    PRINTENV_NAME,
+    PRINTF_NAME,
+    PUSHD_NAME,
+    PWD_NAME,
+    READ_NAME,
+    READARRAY_NAME,
+    READONLY_NAME,
+    RETURN_NAME,
+    SET_NAME,
+    SHOPT_NAME,
+    SOURCE_NAME,
+    TEST_NAME,
+    TRAP_NAME,
+    TRUE_NAME,
+    TYPE_NAME,
+    UMASK_NAME,
+    UNALIAS_NAME,
+    UNSET_NAME,
+    WAIT_NAME,
+    WATCH_NAME,
+];
+
+/// Returns the names of all builtins, e.g. to initialize the set of enabled builtins.
+pub fn all_builtins() -> HashSet<String> {
+    BUILTIN_NAMES.iter().map(|name| name.to_string()).collect()
+}
+
+/// Returns `true` if `program` is a builtin that has not been disabled in `shell`.
+pub fn is_builtin<T: AsRef<str>>(shell: &dyn Shell, program: T) -> bool {
+    BUILTIN_NAMES.contains(&program.as_ref()) && shell.is_builtin_enabled(program.as_ref())
 }
 
 /// precondition: command is a builtin.
@@ -91,19 +258,64 @@ where
     S1: AsRef<str>,
     S2: AsRef<str>,
 {
-    debug_assert!(is_builtin(&program));
+    debug_assert!(is_builtin(&*shell, &program));
 
     let result = match program.as_ref() {
+        ALIAS_NAME => Alias::run(shell, args, stdout),
         BG_NAME => Bg::run(shell, args, stdout),
+        BRACKET_NAME => Bracket::run(shell, args, stdout),
+        BREAK_NAME => Break::run(shell, args, stdout),
         CD_NAME => Cd::run(shell, args, stdout),
+        COLON_NAME => Colon::run(shell, args, stdout),
+        COMMAND_NAME => Command::run(shell, args, stdout),
+        COMPGEN_NAME => Compgen::run(shell, args, stdout),
+        COMPLETE_NAME => Complete::run(shell, args, stdout),
+        COMPOPT_NAME => Compopt::run(shell, args, stdout),
+        CONTINUE_NAME => Continue::run(shell, args, stdout),
+        DEBUG_NAME => Debug::run(shell, args, stdout),
+        DIRS_NAME => Dirs::run(shell, args, stdout),
         DECLARE_NAME => Declare::run(shell, args, stdout),
+        DOT_NAME => Source::run(shell, args, stdout),
+        ECHO_NAME => Echo::run(shell, args, stdout),
+        ENABLE_NAME => Enable::run(shell, args, stdout),
+        ENV_NAME => Env::run(shell, args, stdout),
+        EXEC_NAME => Exec::run(shell, args, stdout),
         EXIT_NAME => Exit::run(shell, args, stdout),
+        EXPORT_NAME => Export::run(shell, args, stdout),
+        FALSE_NAME => False::run(shell, args, stdout),
+        FC_NAME => Fc::run(shell, args, stdout),
         FG_NAME => Fg::run(shell, args, stdout),
+        HASH_NAME => Hash::run(shell, args, stdout),
         HELP_NAME => Help::run(shell, args, stdout),
         HISTORY_NAME => History::run(shell, args, stdout),
         JOBS_NAME => Jobs::run(shell, args, stdout),
         KILL_NAME => Kill::run(shell, args, stdout),
+        LET_NAME => Let::run(shell, args, stdout),
+        LOGOUT_NAME => Logout::run(shell, args, stdout),
+        MAPFILE_NAME => Mapfile::run(shell, args, stdout),
+        NICE_NAME => Nice::run(shell, args, stdout),
+        NOHUP_NAME => Nohup::run(shell, args, stdout),
+        POPD_NAME => Popd::run(shell, args, stdout),
+        PRINTENV_NAME => Printenv::run(shell, args, stdout),
+        PRINTF_NAME => Printf::run(shell, args, stdout),
+        PUSHD_NAME => Pushd::run(shell, args, stdout),
+        PWD_NAME => Pwd::run(shell, args, stdout),
+        READ_NAME => Read::run(shell, args, stdout),
+        READARRAY_NAME => Mapfile::run(shell, args, stdout),
+        READONLY_NAME => Readonly::run(shell, args, stdout),
+        RETURN_NAME => Return::run(shell, args, stdout),
+        SET_NAME => Set::run(shell, args, stdout),
+        SHOPT_NAME => Shopt::run(shell, args, stdout),
+        SOURCE_NAME => Source::run(shell, args, stdout),
+        TEST_NAME => Test::run(shell, args, stdout),
+        TRAP_NAME => Trap::run(shell, args, stdout),
+        TRUE_NAME => True::run(shell, args, stdout),
+        TYPE_NAME => Type::run(shell, args, stdout),
+        UMASK_NAME => Umask::run(shell, args, stdout),
+        UNALIAS_NAME => Unalias::run(shell, args, stdout),
         UNSET_NAME => Unset::run(shell, args, stdout),
+        WAIT_NAME => Wait::run(shell, args, stdout),
+        WATCH_NAME => Watch::run(shell, args, stdout),
         _ => unreachable!(),
     };
 
@@ -124,6 +336,81 @@ fn get_builtin_exit_status(result: &Result<()>) -> ExitStatus {
     ExitStatus::from_status(status)
 }
 
+/// Runs `command` on behalf of the builtin `name`, copying its stdout to `stdout` if it is piped.
+///
+/// Like the shell, fails with 127 if `program` is not found and 126 if it may not be executed,
+/// e.g. because it is outside of `--restricted-path`, or `args` exceed the argument limits;
+/// otherwise fails with the command's exit status if it is non-zero. In sandbox mode, `program`
+/// is searched for in `$PATH` without the blocked directories.
+fn run_external_command<S: AsRef<str>>(
+    shell: &mut dyn Shell,
+    name: &str,
+    program: &str,
+    args: &[S],
+    command: &mut process::Command,
+    stdout: &mut dyn Write,
+) -> Result<()> {
+    check_restrict_path(shell, name, program)?;
+    let config = shell.config();
+    let result = execute_command::check_argument_limits(config, program, args).and_then(|()| {
+        if config.sandbox_mode {
+            let path_var =
+                execute_command::check_sandbox_path(program, &config.sandbox_blocked_paths)?;
+            command.env("PATH", path_var);
+        }
+        Ok(())
+    });
+    result.map_err(|e| external_command_error(shell, name, e))?;
+
+    let mut child = command.spawn().map_err(|e| {
+        let code = if e.kind() == io::ErrorKind::NotFound {
+            COMMAND_NOT_FOUND_EXIT_STATUS
+        } else {
+            PERMISSION_DENIED_EXIT_STATUS
+        };
+        Error::builtin_command(
+            format!("{}: failed to run command '{}': {}", name, program, e),
+            code,
+        )
+    })?;
+
+    if let Some(mut child_stdout) = child.stdout.take() {
+        io::copy(&mut child_stdout, stdout).context(ErrorKind::Io)?;
+    }
+
+    let status = child.wait().context(ErrorKind::Io)?;
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(Error::builtin_command("", code)),
+        // Killed by a signal
+        None => Err(Error::builtin_command("", 1)),
+    }
+}
+
+/// Verifies that `program`, run by the builtin `name`, is in one of the directories of
+/// `--restricted-path`, if set.
+///
+/// Like the shell, fails with 127 if `program` is not found and 126 if it is not allowed.
+fn check_restrict_path(shell: &mut dyn Shell, name: &str, program: &str) -> Result<()> {
+    let result = match shell.config().restrict_path {
+        Some(ref allowed_dirs) => execute_command::check_restrict_path(program, allowed_dirs),
+        None => Ok(()),
+    };
+    result.map_err(|e| external_command_error(shell, name, e))
+}
+
+/// Prints the error `e` of the builtin `name` failing to run a command, returning it with the
+/// exit status of the shell failing to run it: 127 if the command is not found, otherwise 126.
+fn external_command_error(shell: &mut dyn Shell, name: &str, e: Error) -> Error {
+    let code = match *e.kind() {
+        ErrorKind::CommandNotFound(_) => COMMAND_NOT_FOUND_EXIT_STATUS,
+        _ => PERMISSION_DENIED_EXIT_STATUS,
+    };
+    let message = format!("{}: {}", name, e);
+    print_error(shell.shell_stderr(), &message);
+    Error::builtin_command(message, code)
+}
+
 pub fn parse_args<'a, 'de: 'a, D, S, I>(usage: &str, program: S, args: I) -> Result<D>
 where
     D: serde::Deserialize<'de>,
@@ -0,0 +1,111 @@
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+
+use atty::{self, Stream};
+use failure::Fail;
+use nix::errno::Errno;
+
+use crate::{
+    builtins::{self, prelude::*},
+    execute_command,
+};
+
+/// Niceness adjustment if `-n` is not given.
+const DEFAULT_ADJUSTMENT: i32 = 10;
+
+/// Exit status if the adjustment is invalid.
+const INVALID_ADJUSTMENT_EXIT_STATUS: i32 = 125;
+
+pub struct Nice;
+
+impl builtins::BuiltinCommand for Nice {
+    const NAME: &'static str = builtins::NICE_NAME;
+
+    const HELP: &'static str = "\
+nice: nice [-n adjustment] [command [arg ...]]
+    Run a command with modified scheduling priority.
+
+    Run COMMAND with its niceness incremented by ADJUSTMENT, 10 by default.
+    With no COMMAND, print the current niceness. Lowering the niceness below
+    its current value usually requires superuser privileges.
+
+    Exit Status:
+    125 if ADJUSTMENT is invalid, 126 if COMMAND is found but cannot be
+    executed, 127 if COMMAND cannot be found, otherwise the exit status of
+    COMMAND.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (adjustment, command_args) = match args.as_slice() {
+            [] => {
+                writeln!(stdout, "{}", get_niceness()?).context(ErrorKind::Io)?;
+                return Ok(());
+            }
+            ["-n", adjustment, command_args @ ..] => (
+                adjustment.parse::<i32>().map_err(|_| {
+                    Error::builtin_command(
+                        format!("nice: {}: invalid adjustment", adjustment),
+                        INVALID_ADJUSTMENT_EXIT_STATUS,
+                    )
+                })?,
+                command_args,
+            ),
+            ["-n"] => {
+                return Err(Error::builtin_command(
+                    format!("nice: usage: {}", Self::usage()),
+                    INVALID_ADJUSTMENT_EXIT_STATUS,
+                ))
+            }
+            command_args => (DEFAULT_ADJUSTMENT, command_args),
+        };
+
+        let (program, command_args) = match command_args.split_first() {
+            Some(split) => split,
+            None => {
+                return Err(Error::builtin_command(
+                    "nice: a command must be given with an adjustment",
+                    INVALID_ADJUSTMENT_EXIT_STATUS,
+                ))
+            }
+        };
+
+        let niceness = get_niceness()?.saturating_add(adjustment);
+        let mut command = Command::new(program);
+        command.args(command_args);
+        execute_command::set_exported_environment(shell, &mut command);
+        unsafe {
+            command.pre_exec(move || {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, niceness) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        if !atty::is(Stream::Stdout) {
+            command.stdout(Stdio::piped());
+        }
+
+        builtins::run_external_command(
+            shell,
+            Self::NAME,
+            program,
+            command_args,
+            &mut command,
+            stdout,
+        )
+    }
+}
+
+/// Returns the niceness of the shell process.
+fn get_niceness() -> Result<i32> {
+    // getpriority(2) can legitimately return -1, so errno must be checked instead
+    Errno::clear();
+    let niceness = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+    if niceness == -1 && Errno::last() != Errno::UnknownErrno {
+        return Err(Errno::last().context(ErrorKind::Nix).into());
+    }
+
+    Ok(niceness)
+}
@@ -0,0 +1,98 @@
+use std::fs::{File, OpenOptions};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use atty::{self, Stream};
+use nix::sys::signal::{self, SigHandler, Signal};
+
+use crate::{
+    builtins::{self, prelude::*},
+    execute_command,
+    shell::PERMISSION_DENIED_EXIT_STATUS,
+};
+
+const NOHUP_FILE_NAME: &str = "nohup.out";
+
+pub struct Nohup;
+
+impl builtins::BuiltinCommand for Nohup {
+    const NAME: &'static str = builtins::NOHUP_NAME;
+
+    const HELP: &'static str = "\
+nohup: nohup command [arg ...]
+    Run a command immune to hangups.
+
+    Run COMMAND with SIGHUP ignored. If standard input is a terminal, it is
+    redirected from /dev/null. If standard output is a terminal, output is
+    appended to 'nohup.out', or '$HOME/nohup.out' if that cannot be opened.
+    If both standard output and standard error are terminals, standard error
+    is also appended to the file.
+
+    Exit Status:
+    126 if COMMAND is found but cannot be executed, 127 if COMMAND cannot be
+    found, otherwise the exit status of COMMAND.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let (program, args) = match args.split_first() {
+            Some((program, args)) => (program.as_ref(), args),
+            None => {
+                return Err(Error::builtin_command(
+                    format!("nohup: usage: {}", Self::usage()),
+                    PERMISSION_DENIED_EXIT_STATUS,
+                ))
+            }
+        };
+
+        let mut command = Command::new(program);
+        command.args(args.iter().map(AsRef::as_ref));
+        execute_command::set_exported_environment(shell, &mut command);
+        unsafe {
+            command.pre_exec(|| {
+                signal::signal(Signal::SIGHUP, SigHandler::SigIgn)
+                    .expect("failed to ignore SIGHUP");
+                Ok(())
+            });
+        }
+
+        if atty::is(Stream::Stdin) {
+            command.stdin(Stdio::null());
+        }
+
+        let nohup_file = if atty::is(Stream::Stdout) {
+            let (file, path) = open_nohup_file()?;
+            eprintln!("nohup: appending output to '{}'", path.display());
+            command.stdout(file.try_clone().context(ErrorKind::Io)?);
+            Some(file)
+        } else {
+            command.stdout(Stdio::piped());
+            None
+        };
+
+        if let (true, Some(file)) = (atty::is(Stream::Stderr), &nohup_file) {
+            command.stderr(file.try_clone().context(ErrorKind::Io)?);
+        }
+
+        builtins::run_external_command(shell, Self::NAME, program, args, &mut command, stdout)
+    }
+}
+
+/// Opens `nohup.out` for appending in the current directory, falling back to the home directory.
+fn open_nohup_file() -> Result<(File, PathBuf)> {
+    let mut options = OpenOptions::new();
+    options.append(true).create(true);
+
+    let path = PathBuf::from(NOHUP_FILE_NAME);
+    if let Ok(file) = options.open(&path) {
+        return Ok((file, path));
+    }
+
+    let path = dirs::home_dir().unwrap_or_default().join(NOHUP_FILE_NAME);
+    let file = options.open(&path).map_err(|e| {
+        Error::builtin_command(
+            format!("nohup: failed to open '{}': {}", path.display(), e),
+            PERMISSION_DENIED_EXIT_STATUS,
+        )
+    })?;
+    Ok((file, path))
+}
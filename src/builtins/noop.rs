@@ -0,0 +1,66 @@
+use crate::builtins::{self, prelude::*};
+
+pub struct Colon;
+
+impl builtins::BuiltinCommand for Colon {
+    const NAME: &'static str = builtins::COLON_NAME;
+
+    const HELP: &'static str = "\
+:: : [arguments]
+    Null command.
+
+    No effect; the command does nothing.
+
+    Exit Status:
+    Always succeeds.";
+
+    fn run<T: AsRef<str>>(
+        _shell: &mut dyn Shell,
+        _args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct True;
+
+impl builtins::BuiltinCommand for True {
+    const NAME: &'static str = builtins::TRUE_NAME;
+
+    const HELP: &'static str = "\
+true: true
+    Return a successful result.
+
+    Exit Status:
+    Always succeeds.";
+
+    fn run<T: AsRef<str>>(
+        _shell: &mut dyn Shell,
+        _args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct False;
+
+impl builtins::BuiltinCommand for False {
+    const NAME: &'static str = builtins::FALSE_NAME;
+
+    const HELP: &'static str = "\
+false: false
+    Return an unsuccessful result.
+
+    Exit Status:
+    Always fails.";
+
+    fn run<T: AsRef<str>>(
+        _shell: &mut dyn Shell,
+        _args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        Err(Error::builtin_command("", 1))
+    }
+}
@@ -0,0 +1,583 @@
+use std::iter;
+use std::slice;
+
+use crate::builtins::{self, echo, prelude::*};
+use crate::shell::print_error;
+use crate::util;
+
+pub struct Printf;
+
+impl builtins::BuiltinCommand for Printf {
+    const NAME: &'static str = builtins::PRINTF_NAME;
+
+    const HELP: &'static str = "\
+printf: printf [-v var] format [arguments]
+    Formats and prints ARGUMENTS under control of the FORMAT.
+
+    -v var  assign the output to shell variable VAR rather than
+            display it on the standard output
+
+    FORMAT is a character string which contains three types of objects: plain
+    characters, which are simply copied to standard output; character escape
+    sequences, which are converted and copied to the standard output; and
+    format specifications, each of which causes printing of the next successive
+    argument.
+
+    In addition to the standard format specifications described in printf(1),
+    printf interprets:
+
+      %b    expand backslash escape sequences in the corresponding argument
+      %q    quote the argument in a way that can be reused as shell input
+
+    The format is re-used as necessary to consume all of the arguments. If
+    there are fewer arguments than the format requires, extra format
+    specifications behave as if a zero value or null string, as appropriate,
+    had been supplied.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let mut args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let mut var = None;
+        if args.first() == Some(&"-v") {
+            match args.get(1) {
+                Some(name) => var = Some(name.to_string()),
+                None => {
+                    return Err(Error::builtin_command(
+                        "printf: -v: option requires an argument",
+                        2,
+                    ))
+                }
+            }
+            args.drain(..2);
+        }
+        if args.first() == Some(&"--") {
+            args.remove(0);
+        }
+        let (format, args) = match args.split_first() {
+            Some((format, args)) => (*format, args),
+            None => {
+                return Err(Error::builtin_command(
+                    format!(
+                        "printf: usage: {}",
+                        Self::usage().trim_start_matches("printf: ")
+                    ),
+                    2,
+                ))
+            }
+        };
+        if let Some(ref name) = var {
+            shell.check_variable_assignment(name)?;
+        }
+
+        let mut formatter = Formatter {
+            args: args.iter(),
+            output: Vec::new(),
+            invalid: false,
+            stderr: shell.shell_stderr(),
+        };
+        // Like bash, the format is reused until all arguments are consumed, but it is used at
+        // least once even if there are no arguments.
+        loop {
+            let remaining = formatter.args.len();
+            if formatter.format(format)
+                || formatter.args.len() == 0
+                || formatter.args.len() == remaining
+            {
+                break;
+            }
+        }
+        let Formatter {
+            output, invalid, ..
+        } = formatter;
+
+        match var {
+            Some(name) => shell.set_variable(&name, &String::from_utf8_lossy(&output)),
+            None => stdout.write_all(&output).context(ErrorKind::Io)?,
+        }
+
+        if invalid {
+            return Err(Error::builtin_command("", 1));
+        }
+        Ok(())
+    }
+}
+
+/// A format specification such as `%-5.2f`.
+#[derive(Debug, Default, PartialEq)]
+struct Spec {
+    left_align: bool,
+    plus_sign: bool,
+    space_sign: bool,
+    alternate: bool,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+struct Formatter<'a> {
+    args: slice::Iter<'a, &'a str>,
+    output: Vec<u8>,
+    /// Whether an argument was not a valid number.
+    invalid: bool,
+    stderr: &'a mut dyn Write,
+}
+
+impl<'a> Formatter<'a> {
+    /// Formats `format` once, consuming arguments as needed, and returns `true` if `\c` ended the
+    /// output.
+    fn format(&mut self, format: &str) -> bool {
+        let mut chars = format.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let end = escape_end(format, start);
+                    while chars.peek().map_or(false, |&(i, _)| i < end) {
+                        chars.next();
+                    }
+                    let escape = &format[start..end];
+                    // Unlike `echo -e`, octal escapes in the format are `\NNN`, without a `0`.
+                    if escape.len() > 1 && escape.as_bytes()[1].is_ascii_digit() {
+                        let value = u32::from_str_radix(&escape[1..], 8).unwrap();
+                        self.output.push(value as u8);
+                        continue;
+                    }
+                    let (bytes, stop) = echo::interpret_escapes(escape);
+                    self.output.extend(bytes);
+                    if stop {
+                        return true;
+                    }
+                }
+                '%' if chars.peek().map(|&(_, c)| c) == Some('%') => {
+                    chars.next();
+                    self.output.push(b'%');
+                }
+                '%' => {
+                    let mut spec = Spec::default();
+                    while let Some(&(_, flag)) = chars.peek() {
+                        match flag {
+                            '-' => spec.left_align = true,
+                            '+' => spec.plus_sign = true,
+                            ' ' => spec.space_sign = true,
+                            '#' => spec.alternate = true,
+                            '0' => spec.zero_pad = true,
+                            _ => break,
+                        }
+                        chars.next();
+                    }
+                    spec.width = self.parse_count(&mut chars).unwrap_or(0);
+                    if chars.peek().map(|&(_, c)| c) == Some('.') {
+                        chars.next();
+                        spec.precision = Some(self.parse_count(&mut chars).unwrap_or(0));
+                    }
+                    match chars.next() {
+                        Some((_, conversion)) => {
+                            if self.convert(conversion, &spec) {
+                                return true;
+                            }
+                        }
+                        // A trailing `%` is printed as is.
+                        None => self.output.extend_from_slice(format[start..].as_bytes()),
+                    }
+                }
+                c => {
+                    let mut buf = [0; 4];
+                    self.output
+                        .extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        false
+    }
+
+    /// Parses a width or precision, which is either decimal digits or `*` to take it from the
+    /// next argument.
+    fn parse_count<J>(&mut self, chars: &mut iter::Peekable<J>) -> Option<usize>
+    where
+        J: Iterator<Item = (usize, char)>,
+    {
+        if chars.peek().map(|&(_, c)| c) == Some('*') {
+            chars.next();
+            let count = self.next_integer();
+            return Some(count.max(0) as usize);
+        }
+
+        let mut count = None;
+        while let Some(digit) = chars.peek().and_then(|&(_, c)| c.to_digit(10)) {
+            count = Some(count.unwrap_or(0) * 10 + digit as usize);
+            chars.next();
+        }
+        count
+    }
+
+    /// Formats the next argument according to `conversion` and `spec`, and returns `true` if a
+    /// `\c` in a `%b` argument ended the output.
+    fn convert(&mut self, conversion: char, spec: &Spec) -> bool {
+        let formatted = match conversion {
+            's' => {
+                let arg = self.next_arg();
+                truncate(arg, spec.precision).to_string()
+            }
+            'b' => {
+                let (bytes, stop) = echo::interpret_escapes(self.next_arg());
+                let arg = String::from_utf8_lossy(&bytes).into_owned();
+                let arg = truncate(&arg, spec.precision).to_string();
+                self.pad(&arg, spec);
+                return stop;
+            }
+            'q' => util::quote_for_shell(self.next_arg()),
+            'c' => self
+                .next_arg()
+                .chars()
+                .next()
+                .map(String::from)
+                .unwrap_or_default(),
+            'd' | 'i' => {
+                let value = self.next_integer();
+                let sign = sign(value < 0, spec);
+                let digits = integer_digits(value.unsigned_abs().to_string(), spec.precision);
+                self.pad_number(sign, &digits, spec);
+                return false;
+            }
+            'u' | 'o' | 'x' | 'X' => {
+                let value = self.next_integer() as u64;
+                let (digits, prefix) = match conversion {
+                    'u' => (value.to_string(), ""),
+                    'o' => (format!("{:o}", value), "0"),
+                    'x' => (format!("{:x}", value), "0x"),
+                    _ => (format!("{:X}", value), "0X"),
+                };
+                let mut digits = integer_digits(digits, spec.precision);
+                let prefix = if spec.alternate && value != 0 {
+                    if conversion == 'o' {
+                        if !digits.starts_with('0') {
+                            digits.insert(0, '0');
+                        }
+                        ""
+                    } else {
+                        prefix
+                    }
+                } else {
+                    ""
+                };
+                self.pad_number(prefix, &digits, spec);
+                return false;
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let value = self.next_float();
+                let sign = sign(value.is_sign_negative() && value != 0.0, spec);
+                let digits = format_float(value.abs(), conversion, spec);
+                if value.is_finite() {
+                    self.pad_number(sign, &digits, spec);
+                } else {
+                    self.pad(&format!("{}{}", sign, digits), spec);
+                }
+                return false;
+            }
+            c => {
+                print_error(
+                    self.stderr,
+                    format_args!("printf: `{}': invalid format character", c),
+                );
+                self.invalid = true;
+                return true;
+            }
+        };
+        self.pad(&formatted, spec);
+        false
+    }
+
+    fn next_arg(&mut self) -> &'a str {
+        self.args.next().copied().unwrap_or("")
+    }
+
+    /// Returns the next argument as an integer. Like bash, `'c` or `"c` is the value of the
+    /// character `c`, and invalid numbers are reported and treated as 0.
+    fn next_integer(&mut self) -> i64 {
+        let arg = self.next_arg();
+        if let Some(c) = char_constant(arg) {
+            return i64::from(c);
+        }
+
+        let trimmed = arg.trim_start();
+        let (negative, digits) = match trimmed.as_bytes().first() {
+            Some(b'-') => (true, &trimmed[1..]),
+            Some(b'+') => (false, &trimmed[1..]),
+            _ => (false, trimmed),
+        };
+        let parsed = if digits.starts_with("0x") || digits.starts_with("0X") {
+            u64::from_str_radix(&digits[2..], 16)
+        } else if digits.len() > 1 && digits.starts_with('0') {
+            u64::from_str_radix(&digits[1..], 8)
+        } else {
+            digits.parse::<u64>()
+        };
+        match parsed {
+            Ok(value) if negative => (value as i64).wrapping_neg(),
+            Ok(value) => value as i64,
+            Err(_) if arg.is_empty() => 0,
+            Err(_) => {
+                print_error(self.stderr, format_args!("printf: {}: invalid number", arg));
+                self.invalid = true;
+                0
+            }
+        }
+    }
+
+    /// Returns the next argument as a floating point number, like [`Self::next_integer`].
+    fn next_float(&mut self) -> f64 {
+        let arg = self.next_arg();
+        if let Some(c) = char_constant(arg) {
+            return f64::from(c);
+        }
+
+        match arg.trim().parse::<f64>() {
+            Ok(value) => value,
+            Err(_) if arg.is_empty() => 0.0,
+            Err(_) => {
+                print_error(self.stderr, format_args!("printf: {}: invalid number", arg));
+                self.invalid = true;
+                0.0
+            }
+        }
+    }
+
+    /// Writes the number `prefix` followed by `digits` padded to the width of `spec`, with zeros
+    /// between the two if requested.
+    fn pad_number(&mut self, prefix: &str, digits: &str, spec: &Spec) {
+        // Like C, `0` is ignored for integers with a precision.
+        let zero_pad = spec.zero_pad
+            && !spec.left_align
+            && !(spec.precision.is_some() && digits.chars().all(|c| c.is_ascii_digit()));
+        if zero_pad {
+            let len = prefix.chars().count() + digits.chars().count();
+            let zeros = "0".repeat(spec.width.saturating_sub(len));
+            self.output.extend_from_slice(prefix.as_bytes());
+            self.output.extend_from_slice(zeros.as_bytes());
+            self.output.extend_from_slice(digits.as_bytes());
+        } else {
+            self.pad(&format!("{}{}", prefix, digits), spec);
+        }
+    }
+
+    /// Writes `s` padded with spaces to the width of `spec`.
+    fn pad(&mut self, s: &str, spec: &Spec) {
+        let padding = " ".repeat(spec.width.saturating_sub(s.chars().count()));
+        if spec.left_align {
+            self.output.extend_from_slice(s.as_bytes());
+            self.output.extend_from_slice(padding.as_bytes());
+        } else {
+            self.output.extend_from_slice(padding.as_bytes());
+            self.output.extend_from_slice(s.as_bytes());
+        }
+    }
+}
+
+/// Returns the end of the backslash escape starting at `format[start]`.
+fn escape_end(format: &str, start: usize) -> usize {
+    let bytes = format.as_bytes();
+    let mut end = start + 1;
+    let (max_digits, is_digit): (usize, fn(&u8) -> bool) = match bytes.get(end) {
+        Some(b'0'..=b'7') => (3, |b| (b'0'..=b'7').contains(b)),
+        Some(b'x') => {
+            end += 1;
+            (2, u8::is_ascii_hexdigit)
+        }
+        Some(_) => return start + 1 + format[end..].chars().next().unwrap().len_utf8(),
+        None => return end,
+    };
+    let digits_start = end;
+    while end - digits_start < max_digits && bytes.get(end).map_or(false, is_digit) {
+        end += 1;
+    }
+    end
+}
+
+/// Returns the value of the character after a leading quote in `arg`, e.g. `'A` is 65.
+fn char_constant(arg: &str) -> Option<u32> {
+    if arg.starts_with('\'') || arg.starts_with('"') {
+        Some(arg[1..].chars().next().map_or(0, u32::from))
+    } else {
+        None
+    }
+}
+
+/// Returns the sign to print before a number.
+fn sign(negative: bool, spec: &Spec) -> &'static str {
+    if negative {
+        "-"
+    } else if spec.plus_sign {
+        "+"
+    } else if spec.space_sign {
+        " "
+    } else {
+        ""
+    }
+}
+
+/// Returns at most `precision` characters of `s`.
+fn truncate(s: &str, precision: Option<usize>) -> &str {
+    match precision.and_then(|precision| s.char_indices().nth(precision)) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+
+/// Pads `digits` with leading zeros to at least `precision` digits.
+fn integer_digits(digits: String, precision: Option<usize>) -> String {
+    match precision {
+        // Like C, a zero precision prints nothing for 0.
+        Some(0) if digits == "0" => String::new(),
+        Some(precision) if digits.len() < precision => {
+            format!("{}{}", "0".repeat(precision - digits.len()), digits)
+        }
+        _ => digits,
+    }
+}
+
+/// Formats the non-negative `value` like C's `printf` with `conversion`, one of `fFeEgG`.
+fn format_float(value: f64, conversion: char, spec: &Spec) -> String {
+    let formatted = if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        "inf".to_string()
+    } else {
+        let precision = spec.precision.unwrap_or(6);
+        match conversion.to_ascii_lowercase() {
+            'f' => {
+                let mut formatted = format!("{:.*}", precision, value);
+                if spec.alternate && precision == 0 {
+                    formatted.push('.');
+                }
+                formatted
+            }
+            'e' => format_exponential(value, precision, spec.alternate),
+            _ => {
+                // The precision is the number of significant digits, and the style depends on
+                // the exponent, see printf(3).
+                let precision = precision.max(1);
+                let exponential = format_exponential(value, precision - 1, false);
+                let exponent: i32 = exponential[exponential.find('e').unwrap() + 1..]
+                    .parse()
+                    .unwrap();
+                let mut formatted = if exponent < -4 || exponent >= precision as i32 {
+                    exponential
+                } else {
+                    format!("{:.*}", (precision as i32 - 1 - exponent) as usize, value)
+                };
+                if !spec.alternate {
+                    formatted = strip_trailing_zeros(&formatted);
+                }
+                formatted
+            }
+        }
+    };
+
+    if conversion.is_ascii_uppercase() {
+        formatted.to_ascii_uppercase()
+    } else {
+        formatted
+    }
+}
+
+/// Formats `value` like C's `%e`, e.g. `1.500000e+02`.
+fn format_exponential(value: f64, precision: usize, alternate: bool) -> String {
+    let formatted = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = formatted.split_at(formatted.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let point = if alternate && precision == 0 { "." } else { "" };
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}{}e{}{:02}", mantissa, point, sign, exponent.abs())
+}
+
+/// Removes trailing zeros from the fraction of `s`, and the decimal point if nothing is left.
+fn strip_trailing_zeros(s: &str) -> String {
+    let (number, exponent) = s.split_at(s.find('e').unwrap_or_else(|| s.len()));
+    if !number.contains('.') {
+        return s.to_string();
+    }
+    let number = number.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", number, exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    fn printf(args: &[&str]) -> String {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let mut output = Vec::new();
+        let _ = Printf::run(&mut *shell, args, &mut output);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_printf_integers() {
+        assert_eq!(printf(&["%d\\n", "42"]), "42\n");
+        assert_eq!(printf(&["%05d", "7"]), "00007");
+        assert_eq!(printf(&["%-4i|", "-7"]), "-7  |");
+        assert_eq!(printf(&["%+d %+d", "5", "-5"]), "+5 -5");
+        assert_eq!(printf(&["%.3d %05d", "7", "-7"]), "007 -0007");
+        assert_eq!(
+            printf(&["%u %o %x %X", "10", "8", "255", "255"]),
+            "10 10 ff FF"
+        );
+        assert_eq!(printf(&["%#o %#x", "8", "255"]), "010 0xff");
+        assert_eq!(printf(&["%d %d %d", "0x10", "010", "'A"]), "16 8 65");
+        assert_eq!(printf(&["%*d|%-*d|", "3", "1", "3", "2"]), "  1|2  |");
+        assert_eq!(printf(&["%d", "abc"]), "0");
+    }
+
+    #[test]
+    fn test_printf_floats() {
+        assert_eq!(printf(&["%.2f", "3.14159"]), "3.14");
+        assert_eq!(printf(&["%f", "1.5"]), "1.500000");
+        assert_eq!(printf(&["%08.3f", "-3.14159"]), "-003.142");
+        assert_eq!(printf(&["%e", "150"]), "1.500000e+02");
+        assert_eq!(printf(&["%.2E", "0.00123"]), "1.23E-03");
+        assert_eq!(
+            printf(&["%g %g %g", "100000", "1000000", "0.0001"]),
+            "100000 1e+06 0.0001"
+        );
+        assert_eq!(printf(&["%g %G", "3.14159", "0.00001"]), "3.14159 1E-05");
+        assert_eq!(printf(&["%#g", "1"]), "1.00000");
+    }
+
+    #[test]
+    fn test_printf_strings() {
+        assert_eq!(printf(&["%s-%s\\n", "a", "b"]), "a-b\n");
+        assert_eq!(
+            printf(&["[%5s][%-5s][%.2s]", "ab", "ab", "abc"]),
+            "[   ab][ab   ][ab]"
+        );
+        assert_eq!(printf(&["%s %q", "a b", "a b"]), "a b 'a b'");
+        assert_eq!(printf(&["%b|%s", "a\\tb", "a\\tb"]), "a\tb|a\\tb");
+        assert_eq!(printf(&["%b%s", "a\\cb", "c"]), "a");
+        assert_eq!(printf(&["%c%c", "abc", "d"]), "ad");
+        assert_eq!(printf(&["100%% %s"]), "100% ");
+        assert_eq!(printf(&["\\101\\x42\\103\\\\"]), "ABC\\");
+    }
+
+    #[test]
+    fn test_printf_reuses_format() {
+        assert_eq!(printf(&["%s\\n", "a", "b", "c"]), "a\nb\nc\n");
+        assert_eq!(printf(&["%s=%s ", "a", "1", "b"]), "a=1 b= ");
+        assert_eq!(printf(&["x\\n", "a", "b"]), "x\n");
+    }
+
+    #[test]
+    fn test_printf_variable() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let key = "BSH_TEST_PRINTF_VARIABLE";
+        let mut output = Vec::new();
+        assert!(Printf::run(&mut *shell, &["-v", key, "%.2f", "3.14159"], &mut output).is_ok());
+        assert!(output.is_empty());
+        assert_eq!(shell.get_variable(key).unwrap(), "3.14");
+        shell.unset_variable(key);
+
+        assert!(Printf::run(&mut *shell, &["-v"], &mut output).is_err());
+        assert!(Printf::run::<&str>(&mut *shell, &[], &mut output).is_err());
+        assert!(Printf::run(&mut *shell, &["%d", "x"], &mut output).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::poll::{self, PollFd, PollFlags};
+use nix::sys::termios::{self, LocalFlags, SetArg};
+use nix::unistd;
+
+use crate::builtins::{self, prelude::*};
+
+/// The variable the line is stored in, in addition to any names given.
+const REPLY_VARIABLE: &str = "REPLY";
+
+/// The characters `IFS` defaults to when it is unset.
+const DEFAULT_IFS: &str = " \t\n";
+
+pub struct Read;
+
+impl builtins::BuiltinCommand for Read {
+    const NAME: &'static str = builtins::READ_NAME;
+
+    const HELP: &'static str = "\
+read: read [-rs] [-d delim] [-n nchars] [-t timeout] [name ...]
+    Read a line from the standard input and split it into fields.
+
+    The line is split into fields on the characters in $IFS, and the first
+    field is assigned to the first NAME, the second field to the second NAME,
+    and so on, with any leftover fields assigned to the last NAME. The whole
+    line is assigned to the variable REPLY.
+
+    -d delim    continue until the first character of DELIM is read, rather
+                than newline
+    -n nchars   return after reading NCHARS characters rather than waiting
+                for a newline
+    -r          do not allow backslashes to escape any characters
+    -s          do not echo input coming from a terminal
+    -t timeout  time out and return failure if a complete line of input is
+                not read within TIMEOUT seconds
+
+    Exit Status:
+    The return code is zero, unless end-of-file is encountered or read times
+    out.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let mut options = ReadOptions::default();
+        let mut args = args.iter().map(AsRef::as_ref).peekable();
+        while let Some(flags) = args
+            .peek()
+            .and_then(|arg| arg.strip_prefix('-'))
+            .filter(|flags| !flags.is_empty())
+        {
+            args.next();
+            if flags == "-" {
+                break;
+            }
+            for (i, flag) in flags.char_indices() {
+                match flag {
+                    'r' => options.raw = true,
+                    's' => options.silent = true,
+                    'd' | 'n' | 't' => {
+                        // The value is either the rest of the argument, e.g. `-d,`, or the next one.
+                        let value = match &flags[i + 1..] {
+                            "" => args.next().ok_or_else(|| {
+                                Error::builtin_command(
+                                    format!("read: -{}: option requires an argument", flag),
+                                    2,
+                                )
+                            })?,
+                            value => value,
+                        };
+                        options.parse_value(flag, value)?;
+                        break;
+                    }
+                    _ => {
+                        return Err(Error::builtin_command(
+                            format!(
+                                "read: -{}: invalid option\nread: usage: {}",
+                                flag,
+                                Self::usage().trim_start_matches("read: ")
+                            ),
+                            2,
+                        ))
+                    }
+                }
+            }
+        }
+        let names: Vec<&str> = args.collect();
+        for name in &names {
+            shell.check_variable_assignment(name)?;
+        }
+
+        let (line, complete) = read_line(&options)?;
+        let ifs = shell
+            .get_variable("IFS")
+            .unwrap_or_else(|| DEFAULT_IFS.to_string());
+        let reply: String = line.iter().map(|&(c, _)| c).collect();
+        shell.set_variable(REPLY_VARIABLE, &reply);
+        for (name, value) in names.iter().zip(split_fields(&line, &ifs, names.len())) {
+            shell.set_variable(name, &value);
+        }
+
+        if !complete {
+            return Err(Error::builtin_command("", 1));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ReadOptions {
+    delimiter: u8,
+    max_chars: Option<usize>,
+    timeout: Option<Duration>,
+    raw: bool,
+    silent: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            delimiter: b'\n',
+            max_chars: None,
+            timeout: None,
+            raw: false,
+            silent: false,
+        }
+    }
+}
+
+impl ReadOptions {
+    fn parse_value(&mut self, flag: char, value: &str) -> Result<()> {
+        match flag {
+            // Like bash, an empty delimiter reads until a NUL byte.
+            'd' => self.delimiter = value.bytes().next().unwrap_or(0),
+            'n' => {
+                let max_chars = value.parse().map_err(|_| {
+                    Error::builtin_command(format!("read: {}: invalid number", value), 1)
+                })?;
+                self.max_chars = Some(max_chars);
+            }
+            _ => {
+                let timeout = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|t| t.is_finite() && *t >= 0.0)
+                    .ok_or_else(|| {
+                        Error::builtin_command(
+                            format!("read: {}: invalid timeout specification", value),
+                            1,
+                        )
+                    })?;
+                self.timeout = Some(Duration::from_secs_f64(timeout));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a line from the standard input, and returns its characters, each marked with whether it
+/// was escaped by a backslash, and whether the line ended before end of file or the timeout.
+fn read_line(options: &ReadOptions) -> Result<(Vec<(char, bool)>, bool)> {
+    let stdin: RawFd = libc::STDIN_FILENO;
+    // Like bash, `-s` only turns off echoing if the input is a terminal.
+    let saved_termios = if options.silent && unistd::isatty(stdin).unwrap_or(false) {
+        let saved = termios::tcgetattr(stdin).context(ErrorKind::Nix)?;
+        let mut silent = saved.clone();
+        silent.local_flags.remove(LocalFlags::ECHO);
+        termios::tcsetattr(stdin, SetArg::TCSADRAIN, &silent).context(ErrorKind::Nix)?;
+        Some(saved)
+    } else {
+        None
+    };
+
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut reader = ByteReader {
+        fd: stdin,
+        deadline,
+    };
+    let result = read_chars(&mut reader, options);
+
+    if let Some(saved) = saved_termios {
+        termios::tcsetattr(stdin, SetArg::TCSADRAIN, &saved).context(ErrorKind::Nix)?;
+    }
+    result
+}
+
+/// Reads characters from `reader` until the delimiter, see [`read_line`].
+fn read_chars(reader: &mut ByteReader, options: &ReadOptions) -> Result<(Vec<(char, bool)>, bool)> {
+    let mut line = Vec::new();
+    let mut bytes = Vec::new();
+    let mut escaped = false;
+    while options.max_chars.map_or(true, |max| line.len() < max) {
+        let byte = match reader.next()? {
+            Some(byte) => byte,
+            None => return Ok((line, false)),
+        };
+        let is_escaped = escaped;
+        escaped = false;
+        if is_escaped {
+            // A backslash before a newline continues the line.
+            if byte == b'\n' {
+                continue;
+            }
+        } else if byte == b'\\' && !options.raw {
+            escaped = true;
+            continue;
+        } else if byte == options.delimiter {
+            break;
+        }
+
+        // Reads the rest of a multi-byte character, so that `-n` counts characters.
+        bytes.clear();
+        bytes.push(byte);
+        while bytes.len() < utf8_len(byte) {
+            match reader.next()? {
+                Some(byte) => bytes.push(byte),
+                None => break,
+            }
+        }
+        line.extend(
+            String::from_utf8_lossy(&bytes)
+                .chars()
+                .map(|c| (c, is_escaped)),
+        );
+    }
+    Ok((line, true))
+}
+
+/// Returns the length of the UTF-8 character starting with `byte`.
+fn utf8_len(byte: u8) -> usize {
+    match byte.leading_ones() {
+        2 => 2,
+        3 => 3,
+        4 => 4,
+        _ => 1,
+    }
+}
+
+/// Splits `line` into `count` fields on the unescaped characters in `ifs`, with the rest of the
+/// line in the last field.
+///
+/// Like field splitting, whitespace in `ifs` around fields is ignored, and other characters in
+/// `ifs` each end a field.
+fn split_fields(line: &[(char, bool)], ifs: &str, count: usize) -> Vec<String> {
+    let is_separator = |&(c, escaped): &(char, bool)| !escaped && ifs.contains(c);
+    let to_string = |s: &[(char, bool)]| s.iter().map(|&(c, _)| c).collect::<String>();
+
+    let mut rest = skip_ifs_whitespace(line, ifs);
+    let mut fields = Vec::new();
+    for i in 0..count {
+        if i + 1 == count {
+            let trailing = rest
+                .iter()
+                .rev()
+                .take_while(|x| is_ifs_whitespace(x, ifs))
+                .count();
+            fields.push(to_string(&rest[..rest.len() - trailing]));
+            break;
+        }
+
+        let end = rest
+            .iter()
+            .position(is_separator)
+            .unwrap_or_else(|| rest.len());
+        fields.push(to_string(&rest[..end]));
+        rest = skip_ifs_whitespace(&rest[end..], ifs);
+        if rest
+            .first()
+            .map_or(false, |x| is_separator(x) && !is_ifs_whitespace(x, ifs))
+        {
+            rest = skip_ifs_whitespace(&rest[1..], ifs);
+        }
+    }
+    fields
+}
+
+fn is_ifs_whitespace(&(c, escaped): &(char, bool), ifs: &str) -> bool {
+    !escaped && ifs.contains(c) && DEFAULT_IFS.contains(c)
+}
+
+fn skip_ifs_whitespace<'a>(s: &'a [(char, bool)], ifs: &str) -> &'a [(char, bool)] {
+    let len = s.iter().take_while(|x| is_ifs_whitespace(x, ifs)).count();
+    &s[len..]
+}
+
+/// Reads the standard input one byte at a time, so that the rest of the input is left for the
+/// commands after `read`.
+pub(super) struct ByteReader {
+    fd: RawFd,
+    deadline: Option<Instant>,
+}
+
+impl ByteReader {
+    /// Constructs a reader of the file descriptor `fd` without a deadline.
+    pub(super) fn new(fd: RawFd) -> Self {
+        ByteReader { fd, deadline: None }
+    }
+
+    /// Returns the next byte, or `None` at end of file or if the deadline has passed.
+    pub(super) fn next(&mut self) -> Result<Option<u8>> {
+        if let Some(deadline) = self.deadline {
+            loop {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let mut fds = [PollFd::new(self.fd, PollFlags::POLLIN)];
+                match poll::poll(&mut fds, timeout.as_millis() as libc::c_int) {
+                    Err(Errno::EINTR) => continue,
+                    result => {
+                        if result.context(ErrorKind::Nix)? == 0 {
+                            return Ok(None);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut byte = [0; 1];
+        loop {
+            match unistd::read(self.fd, &mut byte) {
+                Err(Errno::EINTR) => continue,
+                result => {
+                    return Ok(match result.context(ErrorKind::Nix)? {
+                        0 => None,
+                        _ => Some(byte[0]),
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(line: &str, ifs: &str, count: usize) -> Vec<String> {
+        let mut chars: Vec<(char, bool)> = Vec::new();
+        let mut escaped = false;
+        for c in line.chars() {
+            if c == '\\' && !escaped {
+                escaped = true;
+                continue;
+            }
+            chars.push((c, escaped));
+            escaped = false;
+        }
+        split_fields(&chars, ifs, count)
+    }
+
+    #[test]
+    fn test_split_fields() {
+        assert_eq!(split("  foo  bar  ", DEFAULT_IFS, 2), ["foo", "bar"]);
+        assert_eq!(split("foo bar baz  ", DEFAULT_IFS, 2), ["foo", "bar baz"]);
+        assert_eq!(split("foo", DEFAULT_IFS, 3), ["foo", "", ""]);
+        assert_eq!(split("a\\ b c", DEFAULT_IFS, 2), ["a b", "c"]);
+        assert_eq!(split("a:b::c", ":", 4), ["a", "b", "", "c"]);
+        assert_eq!(split("a , b", " ,", 2), ["a", "b"]);
+        assert_eq!(split(" a b ", "", 2), [" a b ", ""]);
+        assert!(split("a", DEFAULT_IFS, 0).is_empty());
+    }
+
+    #[test]
+    fn test_utf8_len() {
+        assert_eq!(utf8_len(b'a'), 1);
+        assert_eq!("é".bytes().map(utf8_len).next(), Some(2));
+        assert_eq!("€".bytes().map(utf8_len).next(), Some(3));
+    }
+}
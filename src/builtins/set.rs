@@ -0,0 +1,236 @@
+use std::env;
+
+use crate::{
+    builtins::{self, prelude::*, BuiltinCommand},
+    core::variable_expansion,
+    shell::{print_error, ShellOption},
+    util,
+};
+
+/// The options `set -o` lists, in order; the others are set with `shopt` or `debug`.
+pub(crate) const SET_OPTIONS: &[ShellOption] = &[
+    ShellOption::Errexit,
+    ShellOption::Noclobber,
+    ShellOption::Nounset,
+    ShellOption::Pipefail,
+    ShellOption::Posix,
+    ShellOption::Vi,
+    ShellOption::Xtrace,
+];
+/// The name of the default line editing mode, which is enabled whenever `vi` is disabled.
+const EMACS_OPTION_NAME: &str = "emacs";
+
+pub struct Set;
+
+impl BuiltinCommand for Set {
+    const NAME: &'static str = builtins::SET_NAME;
+
+    const HELP: &'static str = "\
+set: set [-euxC] [-o option-name] [--] [arg ...]
+    Set or unset values of shell options and positional parameters.
+
+    Change the value of shell attributes and positional parameters, or
+    display the names and values of shell variables.
+
+    -e  Exit immediately if a command exits with a non-zero status.
+    -u  Treat unset variables as an error when substituting.
+    -x  Print commands and their arguments as they are executed,
+        prefixed with $PS4.
+    -C  Do not allow existing regular files to be overwritten by
+        redirection of output.
+    -o option-name
+        Set the option corresponding to option-name:
+            emacs        use an emacs-style line editing interface
+            errexit      same as -e
+            noclobber    same as -C
+            nounset      same as -u
+            pipefail     the return value of a pipeline is the status of
+                         the last command to exit with a non-zero status,
+                         or zero if no command exited with a non-zero status
+            posix        reject bsh extensions to keep scripts portable
+            vi           use a vi-style line editing interface
+            xtrace       same as -x
+        Without option-name, the current settings are printed.
+    --  Assign any remaining arguments to the positional parameters.
+        If there are no remaining arguments, the positional parameters
+        are unset.
+    -   Assign any remaining arguments to the positional parameters.
+
+    Using + rather than - causes these flags to be turned off. If no
+    arguments are given, the names and values of all shell variables
+    are printed.
+
+    Exit Status:
+    Returns success unless an invalid option is given.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        if args.is_empty() {
+            return print_variables(shell, stdout);
+        }
+
+        let mut rest = &args[..];
+        let mut reset_params = false;
+        while let Some((&arg, args)) = rest.split_first() {
+            let (enable, flags) = match arg.strip_prefix('-') {
+                Some(flags) => (true, flags),
+                None => match arg.strip_prefix('+') {
+                    Some(flags) if !flags.is_empty() => (false, flags),
+                    _ => break,
+                },
+            };
+            rest = args;
+            // Like bash, `set -` also turns off `-x`.
+            if flags.is_empty() {
+                shell.set_option(ShellOption::Xtrace, false);
+                reset_params = true;
+                break;
+            }
+            if flags == "-" {
+                reset_params = true;
+                break;
+            }
+            for flag in flags.chars() {
+                if flag == 'o' {
+                    match rest.split_first() {
+                        Some((name, args)) => {
+                            set_named_option(shell, name, enable)?;
+                            rest = args;
+                        }
+                        None => print_options(shell, enable, stdout)?,
+                    }
+                    continue;
+                }
+                match ShellOption::from_flag(flag) {
+                    Some(option) => shell.set_option(option, enable),
+                    None => {
+                        let message = format!(
+                            "set: {}{}: invalid option\nset: usage: {}",
+                            if enable { '-' } else { '+' },
+                            flag,
+                            Self::usage().trim_start_matches("set: ")
+                        );
+                        print_error(shell.shell_stderr(), &message);
+                        return Err(Error::builtin_command(message, 2));
+                    }
+                }
+            }
+        }
+
+        if reset_params || !rest.is_empty() {
+            shell.set_positional_params(rest.iter().map(|arg| arg.to_string()).collect());
+        }
+        Ok(())
+    }
+}
+
+/// Enables or disables the option `name`, e.g. for `set -o pipefail`.
+fn set_named_option(shell: &mut dyn Shell, name: &str, enable: bool) -> Result<()> {
+    if name == EMACS_OPTION_NAME {
+        shell.set_option(ShellOption::Vi, !enable);
+        return Ok(());
+    }
+    match ShellOption::from_name(name).filter(|option| SET_OPTIONS.contains(option)) {
+        Some(option) => {
+            shell.set_option(option, enable);
+            Ok(())
+        }
+        None => {
+            let message = format!("set: {}: invalid option name", name);
+            print_error(shell.shell_stderr(), &message);
+            Err(Error::builtin_command(message, 1))
+        }
+    }
+}
+
+/// Prints the options `set -o` accepts and whether they are enabled, or with `set +o`, the
+/// commands that restore them.
+fn print_options(shell: &dyn Shell, human_readable: bool, stdout: &mut dyn Write) -> Result<()> {
+    let mut options: Vec<(&str, bool)> = SET_OPTIONS
+        .iter()
+        .map(|option| (option.name(), shell.get_option(*option)))
+        .collect();
+    options.push((EMACS_OPTION_NAME, !shell.get_option(ShellOption::Vi)));
+    options.sort_unstable();
+
+    for (name, enabled) in options {
+        if human_readable {
+            let state = if enabled { "on" } else { "off" };
+            writeln!(stdout, "{:<15}\t{}", name, state)
+        } else {
+            let flag = if enabled { '-' } else { '+' };
+            writeln!(stdout, "set {}o {}", flag, name)
+        }
+        .context(ErrorKind::Io)?;
+    }
+    Ok(())
+}
+
+/// Prints the shell variables as assignments that may be reused as input, sorted by name.
+fn print_variables(shell: &dyn Shell, stdout: &mut dyn Write) -> Result<()> {
+    let mut names: Vec<String> = env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .filter(|name| variable_expansion::is_identifier(name))
+        .collect();
+    names.sort();
+    for name in names {
+        if let Some(value) = shell.get_variable(&name) {
+            writeln!(stdout, "{}={}", name, util::quote_for_shell(&value))
+                .context(ErrorKind::Io)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn set_options_and_params() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        assert!(Set::run(
+            &mut *shell,
+            &["-eu", "-o", "pipefail", "a", "b"],
+            &mut io::sink()
+        )
+        .is_ok());
+        assert!(shell.get_option(ShellOption::Errexit));
+        assert!(shell.get_option(ShellOption::Nounset));
+        assert!(shell.get_option(ShellOption::Pipefail));
+        assert_eq!(shell.positional_params(), ["a", "b"]);
+
+        assert!(Set::run(&mut *shell, &["+e", "+o", "pipefail"], &mut io::sink()).is_ok());
+        assert!(!shell.get_option(ShellOption::Errexit));
+        assert!(!shell.get_option(ShellOption::Pipefail));
+        assert_eq!(shell.positional_params(), ["a", "b"]);
+
+        assert!(Set::run(&mut *shell, &["--"], &mut io::sink()).is_ok());
+        assert!(shell.positional_params().is_empty());
+        assert!(Set::run(&mut *shell, &["--", "-x"], &mut io::sink()).is_ok());
+        assert_eq!(shell.positional_params(), ["-x"]);
+        assert!(!shell.get_option(ShellOption::Xtrace));
+
+        assert!(Set::run(&mut *shell, &["-q"], &mut io::sink()).is_err());
+        assert!(Set::run(&mut *shell, &["-o", "failglob"], &mut io::sink()).is_err());
+    }
+
+    #[test]
+    fn set_print_options() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        assert!(Set::run(&mut *shell, &["-C", "-o", "vi"], &mut io::sink()).is_ok());
+
+        let mut output = Vec::new();
+        assert!(Set::run(&mut *shell, &["+o"], &mut output).is_ok());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "set +o emacs\nset +o errexit\nset -o noclobber\nset +o nounset\n\
+             set +o pipefail\nset +o posix\nset -o vi\nset +o xtrace\n"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    builtins::{self, prelude::*, set, BuiltinCommand},
+    shell::{print_error, ShellOption},
+};
+
+/// The options `shopt` manages without `-o`, in order.
+const SHOPT_OPTIONS: &[ShellOption] = &[
+    ShellOption::Checkwinsize,
+    ShellOption::Dotglob,
+    ShellOption::ExpandAliases,
+    ShellOption::Extglob,
+    ShellOption::Failglob,
+    ShellOption::Globstar,
+    ShellOption::Histappend,
+    ShellOption::Histverify,
+    ShellOption::Nocaseglob,
+    ShellOption::Nullglob,
+];
+
+pub struct Shopt;
+
+impl BuiltinCommand for Shopt {
+    const NAME: &'static str = builtins::SHOPT_NAME;
+
+    const HELP: &'static str = "\
+shopt: shopt [-pqsu] [-o] [optname ...]
+    Set and unset shell options.
+
+    Change the setting of each shell option OPTNAME. Without any option
+    arguments, list each supplied OPTNAME, or all shell options if no
+    OPTNAMEs are given, with an indication of whether or not each is set.
+
+    -o      restrict OPTNAMEs to those defined for use with `set -o'
+    -p      print each shell option with an indication of its status
+    -q      suppress output
+    -s      enable (set) each OPTNAME
+    -u      disable (unset) each OPTNAME
+
+    The options are checkwinsize, dotglob, expand_aliases, extglob,
+    failglob, globstar, histappend, histverify, nocaseglob, and nullglob.
+    checkwinsize, extglob, globstar, and histverify are accepted for
+    compatibility with bash but have no effect.
+
+    Exit Status:
+    Returns success if OPTNAME is enabled; fails if an invalid option is
+    given or OPTNAME is disabled.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (options, names) = parse_options(shell, &args)?;
+        let known_options = if options.set_options {
+            set::SET_OPTIONS
+        } else {
+            SHOPT_OPTIONS
+        };
+
+        if options.enable && options.disable {
+            let message = "shopt: cannot set and unset shell options simultaneously";
+            print_error(shell.shell_stderr(), message);
+            return Err(Error::builtin_command(message, 1));
+        }
+
+        let mut selected = Vec::new();
+        let mut status = 0;
+        for name in names {
+            match ShellOption::from_name(name).filter(|option| known_options.contains(option)) {
+                Some(option) => selected.push(option),
+                None => {
+                    print_error(
+                        shell.shell_stderr(),
+                        format_args!("shopt: {}: invalid shell option name", name),
+                    );
+                    status = 1;
+                }
+            }
+        }
+
+        if names.is_empty() {
+            // `shopt -s` and `shopt -u` without names list the options in that state.
+            selected = known_options
+                .iter()
+                .copied()
+                .filter(|option| {
+                    let enabled = shell.get_option(*option);
+                    (!options.enable || enabled) && (!options.disable || !enabled)
+                })
+                .collect();
+        } else if options.enable || options.disable {
+            for option in &selected {
+                shell.set_option(*option, options.enable);
+            }
+            if status != 0 {
+                return Err(Error::builtin_command("", status));
+            }
+            return Ok(());
+        }
+
+        for option in selected {
+            let enabled = shell.get_option(option);
+            if !enabled && !names.is_empty() {
+                status = 1;
+            }
+            if !options.quiet {
+                print_option(option, enabled, &options, stdout)?;
+            }
+        }
+
+        if status != 0 {
+            return Err(Error::builtin_command("", status));
+        }
+        Ok(())
+    }
+}
+
+/// Prints whether `option` is enabled, or with `-p`, the command that restores it.
+fn print_option(
+    option: ShellOption,
+    enabled: bool,
+    options: &ShoptOptions,
+    stdout: &mut dyn Write,
+) -> Result<()> {
+    match (options.reusable, options.set_options) {
+        (true, false) => {
+            let flag = if enabled { 's' } else { 'u' };
+            writeln!(stdout, "shopt -{} {}", flag, option.name())
+        }
+        (true, true) => {
+            let flag = if enabled { '-' } else { '+' };
+            writeln!(stdout, "set {}o {}", flag, option.name())
+        }
+        (false, _) => {
+            let state = if enabled { "on" } else { "off" };
+            writeln!(stdout, "{:<15}\t{}", option.name(), state)
+        }
+    }
+    .context(ErrorKind::Io)?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct ShoptOptions {
+    set_options: bool,
+    reusable: bool,
+    quiet: bool,
+    enable: bool,
+    disable: bool,
+}
+
+/// Parses the options of `shopt`, returning them and the option names that follow.
+fn parse_options<'a>(
+    shell: &mut dyn Shell,
+    args: &'a [&'a str],
+) -> Result<(ShoptOptions, &'a [&'a str])> {
+    let mut options = ShoptOptions::default();
+    let mut rest = args;
+    while let Some(flags) = rest
+        .first()
+        .and_then(|arg| arg.strip_prefix('-'))
+        .filter(|flags| !flags.is_empty())
+    {
+        rest = &rest[1..];
+        if flags == "-" {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'o' => options.set_options = true,
+                'p' => options.reusable = true,
+                'q' => options.quiet = true,
+                's' => options.enable = true,
+                'u' => options.disable = true,
+                _ => {
+                    let message = format!(
+                        "shopt: -{}: invalid option\nshopt: usage: {}",
+                        flag,
+                        Shopt::usage().trim_start_matches("shopt: ")
+                    );
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 2));
+                }
+            }
+        }
+    }
+    Ok((options, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn shopt_set_and_query() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        assert!(Shopt::run(&mut *shell, &["-q", "nullglob"], &mut io::sink()).is_err());
+        assert!(Shopt::run(&mut *shell, &["-s", "nullglob", "dotglob"], &mut io::sink()).is_ok());
+        assert!(shell.get_option(ShellOption::Nullglob));
+        assert!(shell.get_option(ShellOption::Dotglob));
+        assert!(Shopt::run(&mut *shell, &["-q", "nullglob"], &mut io::sink()).is_ok());
+
+        let mut output = Vec::new();
+        // Like bash, the status is a failure if any of the options is disabled.
+        assert!(Shopt::run(&mut *shell, &["-p", "nullglob", "nocaseglob"], &mut output).is_err());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "shopt -s nullglob\nshopt -u nocaseglob\n"
+        );
+
+        assert!(Shopt::run(&mut *shell, &["-u", "nullglob"], &mut io::sink()).is_ok());
+        assert!(!shell.get_option(ShellOption::Nullglob));
+        assert!(Shopt::run(&mut *shell, &["-os", "pipefail"], &mut io::sink()).is_ok());
+        assert!(shell.get_option(ShellOption::Pipefail));
+
+        assert!(Shopt::run(&mut *shell, &["-s", "pipefail"], &mut io::sink()).is_err());
+        assert!(Shopt::run(&mut *shell, &["-s", "bogus"], &mut io::sink()).is_err());
+        assert!(Shopt::run(&mut *shell, &["-su", "dotglob"], &mut io::sink()).is_err());
+    }
+}
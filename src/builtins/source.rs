@@ -0,0 +1,135 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::builtins::{self, prelude::*};
+use crate::shell::print_error;
+
+pub struct Source;
+
+impl builtins::BuiltinCommand for Source {
+    const NAME: &'static str = builtins::SOURCE_NAME;
+
+    const HELP: &'static str = "\
+source: source filename [arguments]
+    Execute commands from a file in the current shell.
+
+    Read and execute commands from FILENAME in the current shell. If FILENAME
+    does not contain a slash, the entries in $PATH are searched for it before
+    the current directory. While FILENAME runs, its directory is searched
+    first for commands. If any ARGUMENTS are supplied, they become the
+    positional parameters when FILENAME is executed. `.' is a synonym for
+    `source'.
+
+    Exit Status:
+    Returns the status of the last command executed in FILENAME; fails if
+    FILENAME cannot be read.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let (file_name, args) = match args.split_first() {
+            Some((file_name, args)) => (file_name.as_ref(), args),
+            None => {
+                let message = "source: filename argument required";
+                print_error(shell.shell_stderr(), message);
+                return Err(Error::builtin_command(message, 2));
+            }
+        };
+
+        let path = find_script(file_name);
+        if !path.is_file() {
+            let message = format!("source: {}: No such file or directory", file_name);
+            print_error(shell.shell_stderr(), &message);
+            return Err(Error::builtin_command(message, 1));
+        }
+
+        let params = shell.positional_params().to_vec();
+        if !args.is_empty() {
+            shell.set_positional_params(args.iter().map(|arg| arg.as_ref().to_string()).collect());
+        }
+        let path_prefix = prepend_to_path(path.parent().unwrap_or_else(|| Path::new(".")));
+
+        shell.set_last_exit_status(ExitStatus::from_success());
+        let result = shell.execute_commands_from_file(&path);
+
+        remove_path_prefix(&path_prefix);
+        if !args.is_empty() {
+            shell.set_positional_params(params);
+        }
+        result?;
+
+        match shell.last_exit_status().code() {
+            Some(0) => Ok(()),
+            code => Err(Error::builtin_command("", code.unwrap_or(1))),
+        }
+    }
+}
+
+/// Returns the path of the script `file_name`, which is searched for in `$PATH` if it does not
+/// contain a slash, like bash.
+fn find_script(file_name: &str) -> PathBuf {
+    let path = Path::new(file_name);
+    if path.components().count() == 1 {
+        if let Some(found) = env::var_os("PATH").and_then(|path_var| {
+            env::split_paths(&path_var)
+                .map(|dir| dir.join(path))
+                .find(|candidate| candidate.is_file())
+        }) {
+            return found;
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Adds `dir` to the front of `$PATH`, and returns the prefix added.
+fn prepend_to_path(dir: &Path) -> OsString {
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut prefix = dir.as_os_str().to_os_string();
+    match env::var_os("PATH") {
+        Some(path_var) if !path_var.is_empty() => {
+            prefix.push(":");
+            let mut new_path_var = prefix.clone();
+            new_path_var.push(path_var);
+            env::set_var("PATH", new_path_var);
+        }
+        _ => env::set_var("PATH", &prefix),
+    }
+    prefix
+}
+
+/// Removes `prefix`, added by [`prepend_to_path`], from `$PATH`, unless the script replaced it.
+fn remove_path_prefix(prefix: &OsString) {
+    let path_var = env::var_os("PATH").unwrap_or_default();
+    match path_var.to_str().zip(prefix.to_str()) {
+        Some((path_var, prefix)) if path_var == prefix.trim_end_matches(':') => {
+            env::remove_var("PATH")
+        }
+        Some((path_var, prefix)) => {
+            if let Some(rest) = path_var.strip_prefix(prefix) {
+                env::set_var("PATH", rest);
+            }
+        }
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_script() {
+        assert_eq!(find_script("./script.bsh"), Path::new("./script.bsh"));
+        assert_eq!(
+            find_script("bsh_nonexistent_script"),
+            Path::new("bsh_nonexistent_script")
+        );
+    }
+}
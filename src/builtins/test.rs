@@ -0,0 +1,291 @@
+use crate::builtins::{self, prelude::*};
+use crate::core::conditional;
+use crate::shell::print_error;
+
+/// The exit status if the expression is invalid.
+const USAGE_EXIT_STATUS: i32 = 2;
+
+/// The result of evaluating an expression, or a message describing why it is invalid.
+type TestResult<T> = std::result::Result<T, String>;
+
+pub struct Test;
+
+impl builtins::BuiltinCommand for Test {
+    const NAME: &'static str = builtins::TEST_NAME;
+
+    const HELP: &'static str = "\
+test: test [expr]
+    Evaluate conditional expression.
+
+    Exits with a status of 0 (true) or 1 (false) depending on the evaluation
+    of EXPR. Expressions may be unary or binary. Unary expressions are often
+    used to examine the status of a file.
+
+    File operators:
+      -e FILE        True if file exists.
+      -f FILE        True if file exists and is a regular file.
+      -d FILE        True if file is a directory.
+      -r FILE        True if file is readable by you.
+      -w FILE        True if the file is writable by you.
+      -x FILE        True if the file is executable by you.
+      -s FILE        True if file exists and is not empty.
+      -L FILE        True if file is a symbolic link.
+      -p FILE        True if file is a named pipe.
+      -S FILE        True if file is a socket.
+
+    String operators:
+      -z STRING      True if string is empty.
+      -n STRING      True if string is not empty.
+      STRING         True if string is not empty.
+      STRING1 = STRING2
+                     True if the strings are equal.
+      STRING1 != STRING2
+                     True if the strings are not equal.
+
+    Other operators:
+      ! EXPR         True if expr is false.
+      EXPR1 -a EXPR2 True if both expr1 AND expr2 are true.
+      EXPR1 -o EXPR2 True if either expr1 OR expr2 is true.
+      ( EXPR )       True if expr is true.
+
+      arg1 OP arg2   Arithmetic tests. OP is one of -eq, -ne, -lt, -le, -gt,
+                     or -ge.
+
+    Exit Status:
+    Returns success if EXPR evaluates to true; fails if EXPR evaluates to
+    false or an invalid argument is given.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        test(shell, Self::NAME, &args)
+    }
+}
+
+pub struct Bracket;
+
+impl builtins::BuiltinCommand for Bracket {
+    const NAME: &'static str = builtins::BRACKET_NAME;
+
+    const HELP: &'static str = "\
+[: [ arg... ]
+    Evaluate conditional expression.
+
+    This is a synonym for the \"test\" builtin, but the last argument must
+    be a literal `]', to match the opening `['.";
+
+    fn run<T: AsRef<str>>(
+        shell: &mut dyn Shell,
+        args: &[T],
+        _stdout: &mut dyn Write,
+    ) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        match args.split_last() {
+            Some((&"]", args)) => test(shell, Self::NAME, args),
+            _ => {
+                let message = "[: missing `]'";
+                print_error(shell.shell_stderr(), message);
+                Err(Error::builtin_command(message, USAGE_EXIT_STATUS))
+            }
+        }
+    }
+}
+
+/// Evaluates `args`, printing any error as coming from the builtin `name`.
+fn test(shell: &mut dyn Shell, name: &str, args: &[&str]) -> Result<()> {
+    match evaluate(args) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::builtin_command("", 1)),
+        Err(message) => {
+            let message = format!("{}: {}", name, message);
+            print_error(shell.shell_stderr(), &message);
+            Err(Error::builtin_command(message, USAGE_EXIT_STATUS))
+        }
+    }
+}
+
+/// Evaluates the expression `args`, or returns a message describing why it is invalid.
+///
+/// Like POSIX, expressions of up to four arguments are evaluated by their number of arguments,
+/// so that e.g. `test ! = x` compares `!` with `x`, and longer expressions are parsed with `-o`
+/// binding more loosely than `-a`, which binds more loosely than `!`.
+fn evaluate(args: &[&str]) -> TestResult<bool> {
+    match *args {
+        [] => Ok(false),
+        [arg] => Ok(!arg.is_empty()),
+        ["!", arg] => Ok(arg.is_empty()),
+        [op, operand] if conditional::is_unary_operator(op) => {
+            Ok(conditional::test_unary(op, operand))
+        }
+        [op, _] => Err(format!("{}: unary operator expected", op)),
+        [lhs, op, rhs] if is_binary_operator(op) => test_binary(lhs, op, rhs),
+        ["!", ..] if args.len() <= 4 => evaluate(&args[1..]).map(|result| !result),
+        ["(", ref inner @ .., ")"] if args.len() <= 4 => evaluate(inner),
+        _ => {
+            let mut parser = Parser { args, pos: 0 };
+            let result = parser.parse_or()?;
+            match args.get(parser.pos) {
+                Some(arg) => Err(format!("{}: unexpected argument", arg)),
+                None => Ok(result),
+            }
+        }
+    }
+}
+
+fn is_binary_operator(op: &str) -> bool {
+    [
+        "=", "==", "!=", "<", ">", "-eq", "-ne", "-lt", "-le", "-gt", "-ge", "-a", "-o",
+    ]
+    .contains(&op)
+}
+
+fn test_binary(lhs: &str, op: &str, rhs: &str) -> TestResult<bool> {
+    let result = match op {
+        "=" | "==" => lhs == rhs,
+        "!=" => lhs != rhs,
+        "<" => lhs < rhs,
+        ">" => lhs > rhs,
+        "-a" => !lhs.is_empty() && !rhs.is_empty(),
+        "-o" => !lhs.is_empty() || !rhs.is_empty(),
+        _ => {
+            let lhs = parse_integer(lhs)?;
+            let rhs = parse_integer(rhs)?;
+            match op {
+                "-eq" => lhs == rhs,
+                "-ne" => lhs != rhs,
+                "-lt" => lhs < rhs,
+                "-le" => lhs <= rhs,
+                "-gt" => lhs > rhs,
+                "-ge" => lhs >= rhs,
+                _ => unreachable!("unknown binary operator: {}", op),
+            }
+        }
+    };
+    Ok(result)
+}
+
+fn parse_integer(s: &str) -> TestResult<i64> {
+    conditional::parse_integer(s).map_err(|_| format!("{}: integer expression expected", s))
+}
+
+struct Parser<'a> {
+    args: &'a [&'a str],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next_if(&mut self, arg: &str) -> bool {
+        if self.args.get(self.pos) == Some(&arg) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_arg(&mut self) -> TestResult<&'a str> {
+        let arg = self
+            .args
+            .get(self.pos)
+            .ok_or_else(|| "argument expected".to_string())?;
+        self.pos += 1;
+        Ok(arg)
+    }
+
+    fn parse_or(&mut self) -> TestResult<bool> {
+        let mut result = self.parse_and()?;
+        while self.next_if("-o") {
+            let rhs = self.parse_and()?;
+            result = result || rhs;
+        }
+        Ok(result)
+    }
+
+    fn parse_and(&mut self) -> TestResult<bool> {
+        let mut result = self.parse_not()?;
+        while self.next_if("-a") {
+            let rhs = self.parse_not()?;
+            result = result && rhs;
+        }
+        Ok(result)
+    }
+
+    fn parse_not(&mut self) -> TestResult<bool> {
+        if self.next_if("!") {
+            return Ok(!self.parse_not()?);
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> TestResult<bool> {
+        // A binary expression takes precedence, e.g. `( = )` compares `(` with `)`.
+        if let [lhs, op, rhs, ..] = self.args[self.pos..] {
+            if is_binary_operator(op) && op != "-a" && op != "-o" {
+                self.pos += 3;
+                return test_binary(lhs, op, rhs);
+            }
+        }
+
+        if self.next_if("(") {
+            let result = self.parse_or()?;
+            if !self.next_if(")") {
+                return Err("`)' expected".to_string());
+            }
+            return Ok(result);
+        }
+
+        let arg = self.next_arg()?;
+        if conditional::is_unary_operator(arg) && self.pos < self.args.len() {
+            let operand = self.next_arg()?;
+            return Ok(conditional::test_unary(arg, operand));
+        }
+        Ok(!arg.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_by_argument_count() {
+        assert_eq!(evaluate(&[]), Ok(false));
+        assert_eq!(evaluate(&[""]), Ok(false));
+        assert_eq!(evaluate(&["-n"]), Ok(true));
+        assert_eq!(evaluate(&["!", ""]), Ok(true));
+        assert_eq!(evaluate(&["-z", ""]), Ok(true));
+        assert_eq!(evaluate(&["!", "=", "x"]), Ok(false));
+        assert_eq!(evaluate(&["3", "-gt", "2"]), Ok(true));
+        assert_eq!(evaluate(&["!", "3", "-gt", "2"]), Ok(false));
+        assert_eq!(evaluate(&["(", "a", "=", "a", ")"]), Ok(true));
+        assert!(evaluate(&["-q", "x"]).is_err());
+        assert!(evaluate(&["a", "-lt", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_compound_expressions() {
+        assert_eq!(evaluate(&["a", "-a", ""]), Ok(false));
+        assert_eq!(evaluate(&["a", "-o", ""]), Ok(true));
+        assert_eq!(
+            evaluate(&["-n", "a", "-a", "1", "-eq", "1", "-o", "-z", "a"]),
+            Ok(true)
+        );
+        assert_eq!(
+            evaluate(&["!", "(", "-n", "a", "-o", "-n", "b", ")"]),
+            Ok(false)
+        );
+        assert_eq!(evaluate(&["-z", "a", "-o", "!", "-z", "a"]), Ok(true));
+        assert!(evaluate(&["(", "-n", "a", "-a", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_file_operators() {
+        assert_eq!(evaluate(&["-d", "/"]), Ok(true));
+        assert_eq!(evaluate(&["-f", "/"]), Ok(false));
+        assert_eq!(evaluate(&["-r", "/"]), Ok(true));
+        assert_eq!(evaluate(&["-e", "/bsh_nonexistent_file"]), Ok(false));
+    }
+}
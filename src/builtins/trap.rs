@@ -0,0 +1,230 @@
+use nix::sys::signal::Signal;
+
+use crate::builtins::{self, kill, prelude::*};
+use crate::shell::{print_error, DEBUG_TRAP, ERR_TRAP, EXIT_TRAP};
+use crate::util::signals;
+
+pub struct Trap;
+
+impl builtins::BuiltinCommand for Trap {
+    const NAME: &'static str = builtins::TRAP_NAME;
+
+    const HELP: &'static str = "\
+trap: trap [-lp] [[arg] signal_spec ...]
+    Trap signals and other events.
+
+    Defines and activates handlers to be run when the shell receives signals
+    or other conditions.
+
+    ARG is a command to be read and executed when the shell receives the
+    signal(s) SIGNAL_SPEC. If ARG is absent (and a single SIGNAL_SPEC
+    is supplied) or `-', each specified signal is reset to its original
+    value. If ARG is the null string each SIGNAL_SPEC is ignored by the
+    shell and by the commands it invokes.
+
+    If a SIGNAL_SPEC is EXIT (0) ARG is executed on exit from the shell. If
+    a SIGNAL_SPEC is DEBUG, ARG is executed before every command. If a
+    SIGNAL_SPEC is ERR, ARG is executed each time a command's failure would
+    cause the shell to exit when the -e option is enabled.
+
+    If no arguments are supplied, trap prints the list of commands associated
+    with each signal.
+
+    -l      print a list of signal names and their corresponding numbers
+    -p      display the trap commands associated with each SIGNAL_SPEC
+
+    Each SIGNAL_SPEC is either a signal name or a signal number. Signal names
+    are case insensitive and the SIG prefix is optional.
+
+    Exit Status:
+    Returns success unless a SIGSPEC is invalid or an invalid option is
+    given.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        let (handler, specs) = match args.as_slice() {
+            [] => return print_traps(shell, &[], stdout),
+            ["-p", specs @ ..] => return print_traps(shell, specs, stdout),
+            ["-l"] => return kill::list_signals(stdout),
+            ["--", rest @ ..] | rest => match rest {
+                [] => return print_traps(shell, &[], stdout),
+                // A single condition is reset, e.g. `trap INT`.
+                [spec] if parse_condition(spec).is_some() => ("-", rest),
+                [handler, specs @ ..] => (*handler, specs),
+            },
+        };
+
+        if specs.is_empty() {
+            return Err(Error::builtin_command(
+                format!("trap: usage: {}", Self::usage()),
+                2,
+            ));
+        }
+
+        let mut status = 0;
+        for spec in specs {
+            let condition = match parse_condition(spec) {
+                Some(condition) => condition,
+                None => {
+                    print_error(
+                        shell.shell_stderr(),
+                        format_args!("trap: {}: invalid signal specification", spec),
+                    );
+                    status = 1;
+                    continue;
+                }
+            };
+
+            if let Condition::Signal(signal) = condition {
+                let result = match handler {
+                    "-" => signals::reset(signal),
+                    "" => signals::ignore(signal),
+                    _ => signals::catch(signal),
+                };
+                if result.is_err() {
+                    print_error(
+                        shell.shell_stderr(),
+                        format_args!("trap: {}: cannot trap signal", signal),
+                    );
+                    status = 1;
+                    continue;
+                }
+            }
+            let handler = match handler {
+                "-" => None,
+                handler => Some(handler.to_string()),
+            };
+            shell.set_trap(condition.name(), handler);
+        }
+
+        if status != 0 {
+            return Err(Error::builtin_command("", status));
+        }
+        Ok(())
+    }
+}
+
+/// A signal or shell event that a trap may be set for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Condition {
+    Exit,
+    Signal(Signal),
+    Debug,
+    Err,
+}
+
+impl Condition {
+    /// Returns the name the trap is stored under, see [`Shell::traps`].
+    fn name(self) -> &'static str {
+        match self {
+            Condition::Exit => EXIT_TRAP,
+            Condition::Signal(signal) => signal.as_str(),
+            Condition::Debug => DEBUG_TRAP,
+            Condition::Err => ERR_TRAP,
+        }
+    }
+
+    /// Returns the position of the condition when traps are listed, like bash.
+    fn order(self) -> i32 {
+        match self {
+            Condition::Exit => 0,
+            Condition::Signal(signal) => signal as i32,
+            Condition::Debug => 100,
+            Condition::Err => 101,
+        }
+    }
+}
+
+/// Parses a condition, e.g. `EXIT`, `0`, `INT`, `SIGINT`, or `2`, case insensitively.
+fn parse_condition(spec: &str) -> Option<Condition> {
+    match spec.to_uppercase().as_str() {
+        "EXIT" | "0" => Some(Condition::Exit),
+        "DEBUG" => Some(Condition::Debug),
+        "ERR" => Some(Condition::Err),
+        _ => kill::parse_signal(spec).map(Condition::Signal),
+    }
+}
+
+/// Prints the traps for `specs`, or all traps if there are none, as `trap` commands.
+fn print_traps(shell: &mut dyn Shell, specs: &[&str], stdout: &mut dyn Write) -> Result<()> {
+    let mut conditions: Vec<Condition> = if specs.is_empty() {
+        shell
+            .traps()
+            .keys()
+            .filter_map(|name| parse_condition(name))
+            .collect()
+    } else {
+        let mut conditions = Vec::new();
+        for spec in specs {
+            match parse_condition(spec) {
+                Some(condition) => conditions.push(condition),
+                None => {
+                    let message = format!("trap: {}: invalid signal specification", spec);
+                    print_error(shell.shell_stderr(), &message);
+                    return Err(Error::builtin_command(message, 1));
+                }
+            }
+        }
+        conditions
+    };
+    conditions.sort_by_key(|condition| condition.order());
+
+    for condition in conditions {
+        if let Some(handler) = shell.traps().get(condition.name()) {
+            writeln!(
+                stdout,
+                "trap -- '{}' {}",
+                handler.replace('\'', "'\\''"),
+                condition.name()
+            )
+            .context(ErrorKind::Io)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::builtins::BuiltinCommand;
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn test_parse_condition() {
+        assert_eq!(parse_condition("EXIT"), Some(Condition::Exit));
+        assert_eq!(parse_condition("exit"), Some(Condition::Exit));
+        assert_eq!(parse_condition("0"), Some(Condition::Exit));
+        assert_eq!(parse_condition("ERR"), Some(Condition::Err));
+        assert_eq!(
+            parse_condition("int"),
+            Some(Condition::Signal(Signal::SIGINT))
+        );
+        assert_eq!(
+            parse_condition("SIGTERM"),
+            Some(Condition::Signal(Signal::SIGTERM))
+        );
+        assert_eq!(parse_condition("FOO"), None);
+    }
+
+    #[test]
+    fn trap_set_print_and_reset() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+
+        assert!(Trap::run(&mut *shell, &["echo bye", "EXIT"], &mut io::sink()).is_ok());
+        assert!(Trap::run(&mut *shell, &["echo 'err'", "ERR"], &mut io::sink()).is_ok());
+        let mut output = Vec::new();
+        assert!(Trap::run::<&str>(&mut *shell, &[], &mut output).is_ok());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "trap -- 'echo bye' EXIT\ntrap -- 'echo '\\''err'\\''' ERR\n"
+        );
+
+        assert!(Trap::run(&mut *shell, &["-", "EXIT"], &mut io::sink()).is_ok());
+        assert!(Trap::run(&mut *shell, &["ERR"], &mut io::sink()).is_ok());
+        assert!(shell.traps().is_empty());
+        assert!(Trap::run(&mut *shell, &["echo", "FOO"], &mut io::sink()).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use std::env;
+use std::ffi::OsStr;
+
+use crate::builtins::{self, prelude::*};
+use crate::shell::print_error;
+use crate::util;
+
+/// The reserved words of the shell grammar.
+const KEYWORDS: &[&str] = &[
+    "!", "[[", "]]", "{", "}", "case", "do", "done", "elif", "else", "esac", "fi", "for",
+    "function", "if", "in", "select", "then", "until", "while",
+];
+
+pub struct Type;
+
+impl builtins::BuiltinCommand for Type {
+    const NAME: &'static str = builtins::TYPE_NAME;
+
+    const HELP: &'static str = "\
+type: type [-apt] name [name ...]
+    Display information about command type.
+
+    For each NAME, indicate how it would be interpreted if used as a command
+    name.
+
+    -a      display all locations containing an executable named NAME,
+            including aliases, builtins, and functions
+    -p      display only the path of the file that would be executed, or
+            nothing if NAME is not a file
+    -t      output a single word which is one of `alias', `keyword',
+            `function', `builtin', or `file', if NAME is an alias, shell
+            reserved word, shell function, shell builtin, or disk file
+
+    Exit Status:
+    Returns success if all of the NAMEs are found; fails if any are not
+    found.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let mut options = TypeOptions::default();
+        let mut args = args.iter().map(AsRef::as_ref).peekable();
+        while let Some(flags) = args
+            .peek()
+            .and_then(|arg| arg.strip_prefix('-'))
+            .filter(|flags| !flags.is_empty())
+        {
+            args.next();
+            if flags == "-" {
+                break;
+            }
+            for flag in flags.chars() {
+                match flag {
+                    'a' => options.all = true,
+                    'p' => options.path_only = true,
+                    't' => options.type_only = true,
+                    _ => {
+                        return Err(Error::builtin_command(
+                            format!(
+                                "type: -{}: invalid option\ntype: usage: {}",
+                                flag,
+                                Self::usage().trim_start_matches("type: ")
+                            ),
+                            2,
+                        ))
+                    }
+                }
+            }
+        }
+
+        let path_var = env::var_os("PATH").unwrap_or_default();
+        let mut status = 0;
+        for name in args {
+            let kinds = find_command(shell, name, &path_var, options.all);
+            if kinds.is_empty() {
+                if !options.type_only && !options.path_only {
+                    print_error(
+                        shell.shell_stderr(),
+                        format_args!("type: {}: not found", name),
+                    );
+                }
+                status = 1;
+                continue;
+            }
+            for kind in kinds {
+                options.print(stdout, name, &kind)?;
+            }
+        }
+
+        if status != 0 {
+            return Err(Error::builtin_command("", status));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct TypeOptions {
+    all: bool,
+    path_only: bool,
+    type_only: bool,
+}
+
+impl TypeOptions {
+    fn print(&self, stdout: &mut dyn Write, name: &str, kind: &CommandKind) -> Result<()> {
+        if self.type_only {
+            writeln!(stdout, "{}", kind.word()).context(ErrorKind::Io)?;
+        } else if self.path_only {
+            if let CommandKind::File(path) = kind {
+                writeln!(stdout, "{}", path).context(ErrorKind::Io)?;
+            }
+        } else {
+            describe(stdout, name, kind)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints what `name` resolves to, e.g. `echo is a shell builtin`.
+pub(super) fn describe(stdout: &mut dyn Write, name: &str, kind: &CommandKind) -> Result<()> {
+    match kind {
+        CommandKind::Alias(value) => writeln!(stdout, "{} is an alias for `{}'", name, value),
+        CommandKind::Keyword => writeln!(stdout, "{} is a shell keyword", name),
+        CommandKind::Function => writeln!(stdout, "{} is a function", name),
+        CommandKind::Builtin => writeln!(stdout, "{} is a shell builtin", name),
+        CommandKind::File(path) => writeln!(stdout, "{} is {}", name, path),
+    }
+    .context(ErrorKind::Io)?;
+    Ok(())
+}
+
+/// What a command name resolves to.
+#[derive(Debug, PartialEq)]
+pub(super) enum CommandKind {
+    Alias(String),
+    Keyword,
+    Function,
+    Builtin,
+    File(String),
+}
+
+impl CommandKind {
+    /// Returns the word printed by `type -t`.
+    fn word(&self) -> &'static str {
+        match self {
+            CommandKind::Alias(_) => "alias",
+            CommandKind::Keyword => "keyword",
+            CommandKind::Function => "function",
+            CommandKind::Builtin => "builtin",
+            CommandKind::File(_) => "file",
+        }
+    }
+}
+
+/// Returns what `name` resolves to, in the order the shell looks it up, or everything it may
+/// resolve to if `all` is `true`. Files are searched for in `path_var`, formatted like `$PATH`.
+pub(super) fn find_command(
+    shell: &dyn Shell,
+    name: &str,
+    path_var: &OsStr,
+    all: bool,
+) -> Vec<CommandKind> {
+    let mut kinds = Vec::new();
+    if let Some(value) = shell.aliases().get(name) {
+        kinds.push(CommandKind::Alias(value.clone()));
+    }
+    if KEYWORDS.contains(&name) {
+        kinds.push(CommandKind::Keyword);
+    }
+    if shell.get_function(name).is_some() {
+        kinds.push(CommandKind::Function);
+    }
+    if builtins::is_builtin(shell, name) {
+        kinds.push(CommandKind::Builtin);
+    }
+    if !all && !kinds.is_empty() {
+        kinds.truncate(1);
+        return kinds;
+    }
+
+    let paths = if all {
+        util::path::search_all_in_path(name, path_var)
+    } else {
+        util::path::search_in_path(name, path_var)
+            .into_iter()
+            .collect()
+    };
+    kinds.extend(
+        paths
+            .into_iter()
+            .map(|path| CommandKind::File(path.to_string_lossy().into_owned())),
+    );
+    kinds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::shell::{create_shell, ShellConfig};
+
+    #[test]
+    fn test_find_command() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        shell
+            .aliases_mut()
+            .insert("echo".to_string(), "echo -n".to_string());
+
+        let path_var = OsStr::new("/bin");
+        assert_eq!(
+            find_command(&*shell, "if", path_var, false),
+            [CommandKind::Keyword]
+        );
+        assert_eq!(
+            find_command(&*shell, "echo", path_var, false),
+            [CommandKind::Alias("echo -n".to_string())]
+        );
+        let all = find_command(&*shell, "echo", path_var, true);
+        assert_eq!(all[1], CommandKind::Builtin);
+        assert!(find_command(&*shell, "bsh_nonexistent_command", path_var, true).is_empty());
+    }
+}
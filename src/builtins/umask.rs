@@ -0,0 +1,222 @@
+#[cfg(unix)]
+use nix::sys::stat::{self, Mode};
+
+use crate::{
+    builtins::{self, prelude::*, BuiltinCommand},
+    shell::print_error,
+};
+
+/// The permission bits of each class of users, in the order they are displayed.
+const CLASSES: [(char, u32); 3] = [('u', 0o700), ('g', 0o070), ('o', 0o007)];
+/// The permission bits shared by each class, shifted to the "other" class.
+const PERMISSIONS: [(char, u32); 3] = [('r', 0o4), ('w', 0o2), ('x', 0o1)];
+
+pub struct Umask;
+
+impl BuiltinCommand for Umask {
+    const NAME: &'static str = builtins::UMASK_NAME;
+
+    const HELP: &'static str = "\
+umask: umask [-p] [-S] [mode]
+    Display or set file mode mask.
+
+    Sets the user file-creation mask to MODE. If MODE is omitted, prints
+    the current value of the mask.
+
+    If MODE begins with a digit, it is interpreted as an octal number;
+    otherwise it is a symbolic mode string like that accepted by chmod(1).
+
+    -p      if MODE is omitted, output in a form that may be reused as input
+    -S      makes the output symbolic; otherwise an octal number is output
+
+    Exit Status:
+    Returns success unless MODE is invalid or an invalid option is given.";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let mut reusable = false;
+        let mut symbolic = false;
+        let mut args = args.iter().map(AsRef::as_ref).peekable();
+        while let Some(flags) = args
+            .peek()
+            .and_then(|arg| arg.strip_prefix('-'))
+            .filter(|flags| !flags.is_empty())
+        {
+            args.next();
+            if flags == "-" {
+                break;
+            }
+            for flag in flags.chars() {
+                match flag {
+                    'p' => reusable = true,
+                    'S' => symbolic = true,
+                    _ => {
+                        let message = format!(
+                            "umask: -{}: invalid option\numask: usage: {}",
+                            flag,
+                            Self::usage().trim_start_matches("umask: ")
+                        );
+                        print_error(shell.shell_stderr(), &message);
+                        return Err(Error::builtin_command(message, 2));
+                    }
+                }
+            }
+        }
+
+        let mask = get_umask()?;
+        let mode = match args.next() {
+            Some(mode) => mode,
+            None => {
+                let mask = if symbolic {
+                    format_symbolic(mask)
+                } else {
+                    format!("{:04o}", mask)
+                };
+                if reusable {
+                    let flag = if symbolic { "-S " } else { "" };
+                    writeln!(stdout, "umask {}{}", flag, mask).context(ErrorKind::Io)?;
+                } else {
+                    writeln!(stdout, "{}", mask).context(ErrorKind::Io)?;
+                }
+                return Ok(());
+            }
+        };
+
+        let new_mask = if mode.starts_with(|c: char| c.is_ascii_digit()) {
+            parse_octal(mode).ok_or_else(|| format!("umask: {}: octal number out of range", mode))
+        } else {
+            parse_symbolic(mode, mask)
+                .ok_or_else(|| format!("umask: {}: invalid symbolic mode", mode))
+        };
+        let new_mask = match new_mask {
+            Ok(new_mask) => new_mask,
+            Err(message) => {
+                print_error(shell.shell_stderr(), &message);
+                return Err(Error::builtin_command(message, 1));
+            }
+        };
+        set_umask(new_mask)?;
+        if symbolic {
+            writeln!(stdout, "{}", format_symbolic(new_mask)).context(ErrorKind::Io)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the file creation mask of the shell process.
+#[cfg(unix)]
+#[allow(trivial_numeric_casts)] // `mode_t` is narrower than `u32` on some platforms, e.g. macOS
+fn get_umask() -> Result<u32> {
+    // The mask can only be read by setting it, so the previous mask is restored immediately.
+    let mask = stat::umask(Mode::empty());
+    stat::umask(mask);
+    Ok(mask.bits() as u32)
+}
+
+#[cfg(unix)]
+#[allow(trivial_numeric_casts)]
+fn set_umask(mask: u32) -> Result<()> {
+    stat::umask(Mode::from_bits_truncate(mask as libc::mode_t));
+    Ok(())
+}
+
+#[cfg(windows)]
+fn get_umask() -> Result<u32> {
+    Err(Error::not_supported("umask is not supported on Windows"))
+}
+
+#[cfg(windows)]
+fn set_umask(_mask: u32) -> Result<()> {
+    Err(Error::not_supported("umask is not supported on Windows"))
+}
+
+/// Parses an octal mask, e.g. `022`.
+fn parse_octal(mode: &str) -> Option<u32> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mask| *mask <= 0o777)
+}
+
+/// Parses a symbolic mode, e.g. `u=rwx,g=rx,o=`, which sets the permissions allowed by `mask`
+/// rather than those removed. Returns the resulting mask.
+fn parse_symbolic(mode: &str, mask: u32) -> Option<u32> {
+    let mut allowed = !mask & 0o777;
+    for clause in mode.split(',') {
+        let op_index = clause.find(|c| matches!(c, '=' | '+' | '-'))?;
+        let (who, rest) = clause.split_at(op_index);
+        let mut classes = 0;
+        for c in who.chars() {
+            classes |= match c {
+                'a' => 0o777,
+                _ => CLASSES.iter().find(|(class, _)| *class == c)?.1,
+            };
+        }
+        if classes == 0 {
+            classes = 0o777;
+        }
+
+        let mut chars = rest.chars();
+        let op = chars.next()?;
+        let mut permissions = 0;
+        for c in chars {
+            let bits = PERMISSIONS
+                .iter()
+                .find(|(permission, _)| *permission == c)?
+                .1;
+            permissions |= bits * 0o111;
+        }
+        let permissions = permissions & classes;
+        match op {
+            '=' => allowed = (allowed & !classes) | permissions,
+            '+' => allowed |= permissions,
+            _ => allowed &= !permissions,
+        }
+    }
+    Some(!allowed & 0o777)
+}
+
+/// Formats the permissions allowed by `mask`, e.g. `u=rwx,g=rx,o=rx` for `022`.
+fn format_symbolic(mask: u32) -> String {
+    let allowed = !mask & 0o777;
+    CLASSES
+        .iter()
+        .map(|(class, class_bits)| {
+            let permissions: String = PERMISSIONS
+                .iter()
+                .filter(|(_, bits)| allowed & class_bits & (bits * 0o111) != 0)
+                .map(|(permission, _)| *permission)
+                .collect();
+            format!("{}={}", class, permissions)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_octal() {
+        assert_eq!(parse_octal("022"), Some(0o022));
+        assert_eq!(parse_octal("0"), Some(0));
+        assert_eq!(parse_octal("0777"), Some(0o777));
+        assert_eq!(parse_octal("1000"), None);
+        assert_eq!(parse_octal("089"), None);
+    }
+
+    #[test]
+    fn test_symbolic() {
+        assert_eq!(format_symbolic(0o022), "u=rwx,g=rx,o=rx");
+        assert_eq!(format_symbolic(0o077), "u=rwx,g=,o=");
+        assert_eq!(format_symbolic(0o777), "u=,g=,o=");
+
+        assert_eq!(parse_symbolic("u=rwx,g=rx,o=rx", 0o777), Some(0o022));
+        assert_eq!(parse_symbolic("go-w", 0), Some(0o022));
+        assert_eq!(parse_symbolic("a=r", 0o022), Some(0o333));
+        assert_eq!(parse_symbolic("o+x", 0o077), Some(0o076));
+        assert_eq!(parse_symbolic("=", 0o022), Some(0o777));
+        assert_eq!(parse_symbolic("u=z", 0o022), None);
+        assert_eq!(parse_symbolic("q=r", 0o022), None);
+        assert_eq!(parse_symbolic("rwx", 0o022), None);
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+use crate::{
+    builtins::{self, prelude::*},
+    execute_command::execute_command_string_capturing_output,
+};
+
+/// Seconds between runs if `-n` is not given.
+const DEFAULT_INTERVAL: f64 = 2.0;
+
+/// How often to check for Ctrl-C while waiting for the next run.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Clears the terminal and moves the cursor to the top-left corner.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Set by the SIGINT handler to stop watching.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub struct Watch;
+
+impl builtins::BuiltinCommand for Watch {
+    const NAME: &'static str = builtins::WATCH_NAME;
+
+    const HELP: &'static str = "\
+watch: watch [-n seconds] [-d] command [arg ...]
+    Execute a command periodically, showing output fullscreen.
+
+    Run COMMAND every SECONDS seconds, 2 by default, until interrupted with
+    Ctrl-C.
+
+    -n      seconds to wait between runs
+    -d      highlight lines that changed since the last run";
+
+    fn run<T: AsRef<str>>(shell: &mut dyn Shell, args: &[T], stdout: &mut dyn Write) -> Result<()> {
+        let mut args: &[T] = args;
+        let mut interval = DEFAULT_INTERVAL;
+        let mut highlight_differences = false;
+        loop {
+            match args.first().map(AsRef::as_ref) {
+                Some("-d") => {
+                    highlight_differences = true;
+                    args = &args[1..];
+                }
+                Some("-n") => {
+                    interval = args
+                        .get(1)
+                        .and_then(|n| n.as_ref().parse::<f64>().ok())
+                        .filter(|n| n.is_finite() && *n >= 0.0)
+                        .ok_or_else(|| Error::builtin_command("watch: -n: invalid interval", 2))?;
+                    args = &args[2..];
+                }
+                _ => break,
+            }
+        }
+
+        if args.is_empty() {
+            return Err(Error::builtin_command(
+                format!("watch: usage: {}", Self::usage()),
+                2,
+            ));
+        }
+
+        let command = args
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        catch_sigint(|| {
+            watch(
+                shell,
+                &command,
+                Duration::from_secs_f64(interval),
+                highlight_differences,
+                stdout,
+            )
+        })
+    }
+}
+
+/// Runs `f` with a SIGINT handler that sets [`INTERRUPTED`] instead of interrupting the shell.
+#[cfg(unix)]
+fn catch_sigint<F: FnOnce() -> Result<()>>(f: F) -> Result<()> {
+    let action = SigAction::new(
+        SigHandler::Handler(handle_sigint),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    let old_action =
+        unsafe { signal::sigaction(Signal::SIGINT, &action) }.context(ErrorKind::Nix)?;
+    let result = f();
+    unsafe { signal::sigaction(Signal::SIGINT, &old_action) }.context(ErrorKind::Nix)?;
+    result
+}
+
+#[cfg(windows)]
+fn catch_sigint<F: FnOnce() -> Result<()>>(f: F) -> Result<()> {
+    // TODO (#22): Support Windows
+    f()
+}
+
+#[cfg(unix)]
+extern "C" fn handle_sigint(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Runs `command` every `interval` until interrupted.
+fn watch(
+    shell: &mut dyn Shell,
+    command: &str,
+    interval: Duration,
+    highlight_differences: bool,
+    stdout: &mut dyn Write,
+) -> Result<()> {
+    let header = format!("Every {:.1}s: {}", interval.as_secs_f64(), command);
+    let mut previous_output: Option<String> = None;
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let (output, _) = execute_command_string_capturing_output(shell, command)?;
+
+        write!(stdout, "{}", CLEAR_SCREEN).context(ErrorKind::Io)?;
+        writeln!(stdout, "{}\t{}\n", header, local_time()).context(ErrorKind::Io)?;
+        match previous_output {
+            Some(ref previous) if highlight_differences => {
+                let mut previous_lines = previous.lines();
+                for line in output.lines() {
+                    if previous_lines.next() == Some(line) {
+                        writeln!(stdout, "{}", line)
+                    } else {
+                        // Reverse video
+                        writeln!(stdout, "\x1b[7m{}\x1b[0m", line)
+                    }
+                    .context(ErrorKind::Io)?;
+                }
+            }
+            _ => write!(stdout, "{}", output).context(ErrorKind::Io)?,
+        }
+        stdout.flush().context(ErrorKind::Io)?;
+        previous_output = Some(output);
+
+        let next_run = Instant::now() + interval;
+        while !INTERRUPTED.load(Ordering::SeqCst) && Instant::now() < next_run {
+            thread::sleep(POLL_INTERVAL.min(next_run - Instant::now()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the current local time, e.g. `2026-10-16 12:00:00`.
+#[cfg(unix)]
+fn local_time() -> String {
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return String::new();
+        }
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec
+        )
+    }
+}
+
+#[cfg(windows)]
+fn local_time() -> String {
+    // TODO (#22): Support Windows
+    String::new()
+}
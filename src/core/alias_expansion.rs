@@ -0,0 +1,252 @@
+//! Alias expansion
+//!
+//! Aliases are expanded in the text of a command before it is parsed, like bash. An unquoted word
+//! in the position of a command name is replaced by the value of the alias of the same name.
+//! Alias values are not expanded again, but if a value ends with a blank, the word after it is
+//! checked for an alias too, e.g. `alias sudo='sudo '`.
+
+use std::collections::HashMap;
+
+/// Reserved words after which a command name may follow, e.g. `if ll; then ll; fi`.
+const COMMAND_PREFIX_WORDS: &[&str] = &[
+    "!", "{", "do", "elif", "else", "if", "then", "time", "until", "while",
+];
+
+/// Characters that end a word unless quoted.
+const METACHARACTERS: &str = ";&|()<> \t\n";
+
+/// Replaces the aliases in command name positions of `input` with their values.
+pub fn expand_aliases(input: &str, aliases: &HashMap<String, String>) -> String {
+    if aliases.is_empty() {
+        return input.to_string();
+    }
+
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    let mut command_position = true;
+    let mut redirect_target = false;
+    let mut here_doc_delimiters = Vec::new();
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '\n' => {
+                output.push(c);
+                rest = &rest[1..];
+                command_position = true;
+                rest = skip_here_doc_bodies(rest, &mut here_doc_delimiters, &mut output);
+            }
+            ' ' | '\t' => {
+                output.push(c);
+                rest = &rest[1..];
+            }
+            '<' | '>' => {
+                let len = rest
+                    .find(|c: char| !"<>&|-".contains(c))
+                    .unwrap_or_else(|| rest.len());
+                let operator = &rest[..len];
+                output.push_str(operator);
+                rest = &rest[len..];
+                if operator == "<<" || operator == "<<-" {
+                    let trimmed = rest.trim_start_matches(|c| c == ' ' || c == '\t');
+                    output.push_str(&rest[..rest.len() - trimmed.len()]);
+                    let len = word_len(trimmed);
+                    output.push_str(&trimmed[..len]);
+                    let delimiter: String = trimmed[..len]
+                        .chars()
+                        .filter(|&c| !"'\"\\".contains(c))
+                        .collect();
+                    here_doc_delimiters.push((delimiter, operator == "<<-"));
+                    rest = &trimmed[len..];
+                } else {
+                    redirect_target = true;
+                }
+            }
+            ';' | '&' | '|' | '(' | ')' => {
+                let len = if rest[1..].starts_with(c) && c != '(' && c != ')' {
+                    2
+                } else {
+                    1
+                };
+                let operator = &rest[..len];
+                output.push_str(operator);
+                rest = &rest[len..];
+                // Patterns of `case` follow `;;`, and may be followed by a command name, but are
+                // not expanded.
+                command_position = operator != ")" && operator != ";;";
+            }
+            '#' => {
+                let len = rest.find('\n').unwrap_or_else(|| rest.len());
+                output.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+            _ => {
+                let len = word_len(rest);
+                let word = &rest[..len];
+                rest = &rest[len..];
+                if redirect_target {
+                    redirect_target = false;
+                    output.push_str(word);
+                    continue;
+                }
+                if !command_position {
+                    output.push_str(word);
+                    continue;
+                }
+
+                match aliases.get(word) {
+                    Some(value) => {
+                        output.push_str(value);
+                        command_position = value.ends_with(|c| c == ' ' || c == '\t');
+                    }
+                    None => {
+                        output.push_str(word);
+                        command_position = COMMAND_PREFIX_WORDS.contains(&word);
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Returns the length of the word at the start of `s`, including quoted parts, command
+/// substitutions, and parameter expansions.
+fn word_len(s: &str) -> usize {
+    let mut chars = s.char_indices().peekable();
+    // Nesting of `$(...)` and `${...}`, whose contents may include metacharacters.
+    let mut depth = 0;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' | '`' => {
+                for (_, quoted) in &mut chars {
+                    if quoted == c {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                while let Some((_, quoted)) = chars.next() {
+                    match quoted {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '$' if matches!(chars.peek(), Some((_, '(')) | Some((_, '{'))) => {
+                chars.next();
+                depth += 1;
+            }
+            '(' | '{' if depth > 0 => depth += 1,
+            ')' | '}' if depth > 0 => depth -= 1,
+            c if depth == 0 && METACHARACTERS.contains(c) => return i,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+/// Copies the bodies of the here-documents started on the previous line from `input` to
+/// `output`, so that they are not expanded, and returns the rest of `input`.
+fn skip_here_doc_bodies<'a>(
+    mut input: &'a str,
+    delimiters: &mut Vec<(String, bool)>,
+    output: &mut String,
+) -> &'a str {
+    for (delimiter, strip_tabs) in delimiters.drain(..) {
+        while !input.is_empty() {
+            let len = input.find('\n').map_or(input.len(), |i| i + 1);
+            let line = &input[..len];
+            output.push_str(line);
+            input = &input[len..];
+            let line = line.trim_end_matches('\n');
+            let line = if strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            if line == delimiter {
+                break;
+            }
+        }
+    }
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(input: &str) -> String {
+        let aliases: HashMap<String, String> = [
+            ("l", "ls -la"),
+            ("ll", "l -h"),
+            ("sudo", "sudo "),
+            ("g", "grep"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        expand_aliases(input, &aliases)
+    }
+
+    #[test]
+    fn test_expand_command_names() {
+        assert_eq!(expand("l"), "ls -la");
+        assert_eq!(expand("l foo l"), "ls -la foo l");
+        assert_eq!(
+            expand("  l;l&&l||l|l&l\nl"),
+            "  ls -la;ls -la&&ls -la||ls -la|ls -la&ls -la\nls -la"
+        );
+        assert_eq!(expand("(l) && { l; }"), "(ls -la) && { ls -la; }");
+        assert_eq!(
+            expand("if l; then l; else l; fi"),
+            "if ls -la; then ls -la; else ls -la; fi"
+        );
+        assert_eq!(
+            expand("while l; do l; done"),
+            "while ls -la; do ls -la; done"
+        );
+        assert_eq!(
+            expand("echo l; for l in l; do l; done"),
+            "echo l; for l in l; do ls -la; done"
+        );
+    }
+
+    #[test]
+    fn test_expand_not_recursive() {
+        assert_eq!(expand("ll"), "l -h");
+        assert_eq!(expand("sudo l"), "sudo  ls -la");
+        assert_eq!(expand("sudo sudo l"), "sudo  sudo  ls -la");
+        assert_eq!(expand("sudo echo l"), "sudo  echo l");
+    }
+
+    #[test]
+    fn test_quoted_words_not_expanded() {
+        assert_eq!(expand("'l'"), "'l'");
+        assert_eq!(expand("\"l\""), "\"l\"");
+        assert_eq!(expand("\\l"), "\\l");
+        assert_eq!(expand("echo 'a; l'; l"), "echo 'a; l'; ls -la");
+        assert_eq!(expand("echo \"$(l; l)\"; l"), "echo \"$(l; l)\"; ls -la");
+        assert_eq!(expand("echo ${x:-;l}; g"), "echo ${x:-;l}; grep");
+        assert_eq!(expand("echo # l; l\nl"), "echo # l; l\nls -la");
+    }
+
+    #[test]
+    fn test_redirects() {
+        assert_eq!(expand("> l l"), "> l ls -la");
+        assert_eq!(expand("echo foo >l; l <l"), "echo foo >l; ls -la <l");
+        assert_eq!(
+            expand("g x <<EOF\nl\nEOF\nl"),
+            "grep x <<EOF\nl\nEOF\nls -la"
+        );
+        assert_eq!(
+            expand("g x <<-'EOF'\n\tl\n\tEOF\nl"),
+            "grep x <<-'EOF'\n\tl\n\tEOF\nls -la"
+        );
+    }
+}
@@ -0,0 +1,505 @@
+//! Arithmetic evaluation
+//!
+//! Evaluates shell arithmetic expressions, e.g. for `let` and `(( ))`. Like bash, all arithmetic
+//! is performed on 64-bit signed integers, and variables that are unset or empty evaluate to 0.
+
+use crate::{
+    errors::{Error, Result},
+    shell::Shell,
+};
+
+/// Maximum depth when evaluating variables whose values are themselves expressions.
+const MAX_RECURSION_DEPTH: usize = 32;
+
+/// Operators, longest first so that e.g. `<<=` is not lexed as `<` `<=`.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "^=", "|=", "<<", ">>",
+    "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "=", "!", "~", "&", "^",
+    "|", "(", ")", "?", ":", ",",
+];
+
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "^=", "|=",
+];
+
+/// Evaluates `expression`, reading and assigning variables in `shell`.
+pub fn evaluate(expression: &str, shell: &mut dyn Shell) -> Result<i64> {
+    evaluate_with_depth(expression, shell, 0)
+}
+
+fn evaluate_with_depth(expression: &str, shell: &mut dyn Shell, depth: usize) -> Result<i64> {
+    if depth > MAX_RECURSION_DEPTH {
+        return Err(Error::arithmetic(format!(
+            "{}: expression recursion level exceeded",
+            expression
+        )));
+    }
+
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Ok(0);
+    }
+
+    let mut parser = Parser {
+        expression,
+        tokens: &tokens,
+        pos: 0,
+    };
+    let expr = parser.parse_comma()?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.syntax_error(token));
+    }
+
+    Evaluator { shell, depth }.eval(&expr)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Identifier(String),
+    Operator(&'static str),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or_else(|| rest.len());
+            tokens.push(Token::Number(parse_number(&rest[..len])?));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or_else(|| rest.len());
+            tokens.push(Token::Identifier(rest[..len].to_string()));
+            len
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Operator(op));
+            op.len()
+        } else {
+            return Err(Error::arithmetic(format!(
+                "{}: syntax error: invalid arithmetic operator (error token is \"{}\")",
+                expression, rest
+            )));
+        };
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Parses a decimal, octal (leading `0`), or hexadecimal (leading `0x`) number.
+fn parse_number(s: &str) -> Result<i64> {
+    let result = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16)
+    } else if s.len() > 1 && s.starts_with('0') {
+        i64::from_str_radix(&s[1..], 8)
+    } else {
+        s.parse()
+    };
+
+    result.map_err(|_| {
+        Error::arithmetic(format!(
+            "{}: value too great for base (error token is \"{}\")",
+            s, s
+        ))
+    })
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Variable(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    /// Assignment with an optional compound operator, e.g. `+` for `+=`.
+    Assign(String, Option<&'static str>, Box<Expr>),
+    /// `++x` and `--x`.
+    PreIncrement(String, i64),
+    /// `x++` and `x--`.
+    PostIncrement(String, i64),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_operator(&self) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Operator(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    /// Consumes the next token if it is one of `ops`.
+    fn next_operator(&mut self, ops: &[&str]) -> Option<&'static str> {
+        let op = self.peek_operator().filter(|op| ops.contains(op))?;
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn expect_operator(&mut self, op: &str) -> Result<()> {
+        match self.next_operator(&[op]) {
+            Some(_) => Ok(()),
+            None => Err(self.unexpected()),
+        }
+    }
+
+    fn syntax_error(&self, token: &Token) -> Error {
+        let token = match token {
+            Token::Number(n) => n.to_string(),
+            Token::Identifier(name) => name.clone(),
+            Token::Operator(op) => op.to_string(),
+        };
+        Error::arithmetic(format!(
+            "{}: syntax error in expression (error token is \"{}\")",
+            self.expression, token
+        ))
+    }
+
+    fn unexpected(&self) -> Error {
+        match self.peek() {
+            Some(token) => self.syntax_error(token),
+            None => Error::arithmetic(format!(
+                "{}: syntax error: operand expected",
+                self.expression
+            )),
+        }
+    }
+
+    fn parse_comma(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_assignment()?;
+        while let Some(op) = self.next_operator(&[","]) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_assignment()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_assignment(&mut self) -> Result<Expr> {
+        if let (Some(Token::Identifier(name)), Some(Token::Operator(op))) =
+            (self.peek(), self.tokens.get(self.pos + 1))
+        {
+            if ASSIGNMENT_OPERATORS.contains(op) {
+                self.pos += 2;
+                let value = self.parse_assignment()?;
+                let compound = match *op {
+                    "=" => None,
+                    op => Some(&op[..op.len() - 1]),
+                };
+                // Map the compound operator back to its `'static` binary operator.
+                let compound = compound.map(|c| *OPERATORS.iter().find(|op| **op == c).unwrap());
+                return Ok(Expr::Assign(name.clone(), compound, Box::new(value)));
+            }
+        }
+
+        self.parse_conditional()
+    }
+
+    fn parse_conditional(&mut self) -> Result<Expr> {
+        let condition = self.parse_binary(0)?;
+        if self.next_operator(&["?"]).is_none() {
+            return Ok(condition);
+        }
+
+        let then = self.parse_assignment()?;
+        self.expect_operator(":")?;
+        let otherwise = self.parse_conditional()?;
+        Ok(Expr::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    /// Parses left-associative binary operators, from lowest to highest precedence.
+    fn parse_binary(&mut self, level: usize) -> Result<Expr> {
+        const LEVELS: &[&[&str]] = &[
+            &["||"],
+            &["&&"],
+            &["|"],
+            &["^"],
+            &["&"],
+            &["==", "!="],
+            &["<", "<=", ">", ">="],
+            &["<<", ">>"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+
+        let ops = match LEVELS.get(level) {
+            Some(ops) => ops,
+            None => return self.parse_power(),
+        };
+
+        let mut expr = self.parse_binary(level + 1)?;
+        while let Some(op) = self.next_operator(ops) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_binary(level + 1)?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_power(&mut self) -> Result<Expr> {
+        let base = self.parse_unary()?;
+        match self.next_operator(&["**"]) {
+            Some(op) => Ok(Expr::Binary(
+                op,
+                Box::new(base),
+                Box::new(self.parse_power()?),
+            )),
+            None => Ok(base),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if let Some(op) = self.next_operator(&["++", "--"]) {
+            return match self.peek() {
+                Some(Token::Identifier(name)) => {
+                    self.pos += 1;
+                    Ok(Expr::PreIncrement(name.clone(), increment(op)))
+                }
+                _ => Err(self.unexpected()),
+            };
+        }
+
+        if let Some(op) = self.next_operator(&["+", "-", "!", "~"]) {
+            return Ok(Expr::Unary(op, Box::new(self.parse_unary()?)));
+        }
+
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                Ok(Expr::Number(*n))
+            }
+            Some(Token::Identifier(name)) => {
+                self.pos += 1;
+                match self.next_operator(&["++", "--"]) {
+                    Some(op) => Ok(Expr::PostIncrement(name.clone(), increment(op))),
+                    None => Ok(Expr::Variable(name.clone())),
+                }
+            }
+            Some(Token::Operator("(")) => {
+                self.pos += 1;
+                let expr = self.parse_comma()?;
+                self.expect_operator(")")?;
+                Ok(expr)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+fn increment(op: &str) -> i64 {
+    if op == "++" {
+        1
+    } else {
+        -1
+    }
+}
+
+struct Evaluator<'a> {
+    shell: &'a mut dyn Shell,
+    depth: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    fn eval(&mut self, expr: &Expr) -> Result<i64> {
+        match expr {
+            Expr::Number(n) => Ok(*n),
+            Expr::Variable(name) => self.get(name),
+            Expr::Unary(op, operand) => {
+                let value = self.eval(operand)?;
+                Ok(match *op {
+                    "-" => value.wrapping_neg(),
+                    "!" => (value == 0) as i64,
+                    "~" => !value,
+                    _ => value,
+                })
+            }
+            Expr::Binary("&&", lhs, rhs) => {
+                Ok((self.eval(lhs)? != 0 && self.eval(rhs)? != 0) as i64)
+            }
+            Expr::Binary("||", lhs, rhs) => {
+                Ok((self.eval(lhs)? != 0 || self.eval(rhs)? != 0) as i64)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs)?;
+                let rhs = self.eval(rhs)?;
+                apply(op, lhs, rhs)
+            }
+            Expr::Assign(name, compound, value) => {
+                let mut value = self.eval(value)?;
+                if let Some(op) = compound {
+                    value = apply(op, self.get(name)?, value)?;
+                }
+                self.set(name, value)?;
+                Ok(value)
+            }
+            Expr::PreIncrement(name, delta) => {
+                let value = self.get(name)?.wrapping_add(*delta);
+                self.set(name, value)?;
+                Ok(value)
+            }
+            Expr::PostIncrement(name, delta) => {
+                let value = self.get(name)?;
+                self.set(name, value.wrapping_add(*delta))?;
+                Ok(value)
+            }
+            Expr::Conditional(condition, then, otherwise) => {
+                if self.eval(condition)? != 0 {
+                    self.eval(then)
+                } else {
+                    self.eval(otherwise)
+                }
+            }
+        }
+    }
+
+    /// Returns the value of `name`, evaluating it if it is not a number.
+    fn get(&mut self, name: &str) -> Result<i64> {
+        let value = self.shell.get_variable(name).unwrap_or_default();
+        match value.trim().parse() {
+            Ok(n) => Ok(n),
+            Err(_) => evaluate_with_depth(&value, self.shell, self.depth + 1),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: i64) -> Result<()> {
+        self.shell.check_variable_assignment(name)?;
+        self.shell.set_variable(name, &value.to_string());
+        Ok(())
+    }
+}
+
+fn apply(op: &str, lhs: i64, rhs: i64) -> Result<i64> {
+    let value = match op {
+        "," => rhs,
+        "|" => lhs | rhs,
+        "^" => lhs ^ rhs,
+        "&" => lhs & rhs,
+        "==" => (lhs == rhs) as i64,
+        "!=" => (lhs != rhs) as i64,
+        "<" => (lhs < rhs) as i64,
+        "<=" => (lhs <= rhs) as i64,
+        ">" => (lhs > rhs) as i64,
+        ">=" => (lhs >= rhs) as i64,
+        "<<" => lhs.wrapping_shl(rhs as u32),
+        ">>" => lhs.wrapping_shr(rhs as u32),
+        "+" => lhs.wrapping_add(rhs),
+        "-" => lhs.wrapping_sub(rhs),
+        "*" => lhs.wrapping_mul(rhs),
+        "/" | "%" if rhs == 0 => return Err(Error::arithmetic("division by 0")),
+        "/" => lhs.wrapping_div(rhs),
+        "%" => lhs.wrapping_rem(rhs),
+        "**" if rhs < 0 => return Err(Error::arithmetic("exponent less than 0")),
+        "**" => lhs.wrapping_pow(rhs as u32),
+        _ => unreachable!("unknown arithmetic operator: {}", op),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::shell::{create_shell, ShellConfig};
+
+    fn eval(expression: &str) -> Result<i64> {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        evaluate(expression, &mut *shell)
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval("5 + 3").unwrap(), 8);
+        assert_eq!(eval("2 + 3 * 4").unwrap(), 14);
+        assert_eq!(eval("(2 + 3) * 4").unwrap(), 20);
+        assert_eq!(eval("2 ** 3 ** 2").unwrap(), 512);
+        assert_eq!(eval("-2 ** 2").unwrap(), 4);
+        assert_eq!(eval("7 % 4 == 3 && 1 < 2").unwrap(), 1);
+        assert_eq!(eval("1 << 4 | 1").unwrap(), 17);
+        assert_eq!(eval("0 ? 1 : 2").unwrap(), 2);
+        assert_eq!(eval("!0 + ~0").unwrap(), 0);
+        assert_eq!(eval("0x1f + 010").unwrap(), 39);
+        assert_eq!(eval("").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        assert_eq!(eval("12 & 10").unwrap(), 8);
+        assert_eq!(eval("12 | 10").unwrap(), 14);
+        assert_eq!(eval("12 ^ 10").unwrap(), 6);
+        assert_eq!(eval("~5").unwrap(), -6);
+        assert_eq!(eval("1 << 10").unwrap(), 1024);
+        assert_eq!(eval("-16 >> 2").unwrap(), -4);
+        assert_eq!(eval("1 | 2 ^ 3 & 4").unwrap(), 3);
+        assert_eq!(eval("1 + 1 << 2").unwrap(), 8);
+        assert_eq!(eval("6 & 3 == 3").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_conditional_operator() {
+        assert_eq!(eval("1 ? 2 : 3").unwrap(), 2);
+        assert_eq!(eval("1 < 2 ? 10 : 20").unwrap(), 10);
+        assert_eq!(eval("0 ? 1 : 0 ? 2 : 3").unwrap(), 3);
+        assert_eq!(eval("1 ? 0 ? 4 : 5 : 6").unwrap(), 5);
+        assert_eq!(eval("(1 ? 2 : 3) * 4").unwrap(), 8);
+        assert!(eval("1 ? 2").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("1 % 0").is_err());
+        assert!(eval("2 ** -1").is_err());
+        assert!(eval("1 +").is_err());
+        assert!(eval("(1").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("1 $ 2").is_err());
+        assert!(eval("09").is_err());
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let key = "BSH_TEST_ARITHMETIC_VARIABLES";
+
+        assert_eq!(
+            evaluate(&format!("{} = 5 + 3", key), &mut *shell).unwrap(),
+            8
+        );
+        assert_eq!(shell.get_variable(key).unwrap(), "8");
+        assert_eq!(evaluate(&format!("{} += 2", key), &mut *shell).unwrap(), 10);
+        assert_eq!(evaluate(&format!("{}++", key), &mut *shell).unwrap(), 10);
+        assert_eq!(evaluate(&format!("--{}", key), &mut *shell).unwrap(), 10);
+        assert_eq!(evaluate(&format!("{} *= 3", key), &mut *shell).unwrap(), 30);
+        assert_eq!(evaluate(&format!("{} %= 7", key), &mut *shell).unwrap(), 2);
+        assert_eq!(shell.get_variable(key).unwrap(), "2");
+
+        shell.set_variable(key, "1 + 1");
+        assert_eq!(evaluate(&format!("{} * 2", key), &mut *shell).unwrap(), 4);
+
+        shell.set_variable(key, key);
+        assert!(evaluate(key, &mut *shell).is_err());
+        shell.unset_variable(key);
+
+        assert_eq!(
+            evaluate("BSH_TEST_ARITHMETIC_UNSET + 1", &mut *shell).unwrap(),
+            1
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Brace expansion
+//!
+//! Expands `{a,b,c}` alternatives and `{1..5}` or `{a..e}` sequences in a word into several
+//! words, e.g. `file{,.bak}` to `file file.bak`. Like bash, brace expansion happens before any
+//! other expansion, and parameter expansions (`${...}`), command substitutions and arithmetic
+//! expansions are left alone.
+
+/// Returns the words that `word` expands to, or just `word` if it has no brace expressions.
+pub fn brace_expand(word: &str) -> Vec<String> {
+    let (open, close, elements) = match find_brace_expression(word) {
+        Some(expression) => expression,
+        None => return vec![word.to_string()],
+    };
+    let prefix = &word[..open];
+    let suffixes = brace_expand(&word[close + 1..]);
+    elements
+        .iter()
+        .flat_map(|element| brace_expand(element))
+        .flat_map(|element| {
+            suffixes
+                .iter()
+                .map(move |suffix| format!("{}{}{}", prefix, element, suffix))
+        })
+        .collect()
+}
+
+/// Returns the position of the first brace expression's braces and its elements.
+///
+/// Braces without a comma or a valid sequence, e.g. `{}` or `{a}`, are not brace expressions.
+fn find_brace_expression(word: &str) -> Option<(usize, usize, Vec<String>)> {
+    let bytes = word.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'$' if matches!(bytes.get(i + 1), Some(b'{') | Some(b'(')) => {
+                i = skip_group(word, i + 1)?;
+            }
+            b'`' => i += word[i + 1..].find('`')? + 1,
+            b'{' => {
+                if let Some(close) = find_closing_brace(word, i) {
+                    if let Some(elements) = split_elements(&word[i + 1..close]) {
+                        return Some((i, close, elements));
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the position of the bracket closing the one at `open`, e.g. for `${...}`.
+fn skip_group(word: &str, open: usize) -> Option<usize> {
+    let (open_char, close_char) = match word.as_bytes()[open] {
+        b'{' => (b'{', b'}'),
+        _ => (b'(', b')'),
+    };
+    let mut depth = 0;
+    for (i, &b) in word.as_bytes().iter().enumerate().skip(open) {
+        if b == open_char {
+            depth += 1;
+        } else if b == close_char {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+/// Returns the position of the `}` matching the `{` at `open`, skipping nested braces.
+fn find_closing_brace(word: &str, open: usize) -> Option<usize> {
+    let bytes = word.as_bytes();
+    let mut depth = 0;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'$' if bytes.get(i + 1) == Some(&b'{') => i = skip_group(word, i + 1)?,
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Splits the contents of a brace expression into its elements, i.e. alternatives separated by
+/// top-level commas or the values of a sequence.
+fn split_elements(contents: &str) -> Option<Vec<String>> {
+    let bytes = contents.as_bytes();
+    let mut elements = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'$' if bytes.get(i + 1) == Some(&b'{') => i = skip_group(contents, i + 1)?,
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                elements.push(contents[start..i].to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if elements.is_empty() {
+        return sequence(contents);
+    }
+    elements.push(contents[start..].to_string());
+    Some(elements)
+}
+
+/// Returns the values of a `start..end` or `start..end..step` sequence of integers or letters.
+fn sequence(contents: &str) -> Option<Vec<String>> {
+    let parts: Vec<&str> = contents.split("..").collect();
+    let (start, end, step) = match parts.as_slice() {
+        [start, end] => (*start, *end, 1),
+        [start, end, step] => (*start, *end, step.parse::<i64>().ok()?),
+        _ => return None,
+    };
+    let step = step.checked_abs()?.max(1);
+
+    if let (Ok(first), Ok(last)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        // Like bash, `{01..10}` pads every value to the width of the wider endpoint.
+        let is_padded = |s: &str| {
+            let digits = s.trim_start_matches('-');
+            digits.len() > 1 && digits.starts_with('0')
+        };
+        let width = if is_padded(start) || is_padded(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+        return Some(
+            range(first, last, step)
+                .map(|n| {
+                    if n < 0 {
+                        format!("-{:0width$}", -n, width = width.saturating_sub(1))
+                    } else {
+                        format!("{:0width$}", n, width = width)
+                    }
+                })
+                .collect(),
+        );
+    }
+
+    let letter = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Some(c as i64),
+            _ => None,
+        }
+    };
+    let (first, last) = (letter(start)?, letter(end)?);
+    Some(
+        range(first, last, step)
+            .map(|c| (c as u8 as char).to_string())
+            .collect(),
+    )
+}
+
+/// Returns the values from `first` to `last` inclusive, counting down if `last` is smaller.
+fn range(first: i64, last: i64, step: i64) -> impl Iterator<Item = i64> {
+    let count = (first - last).abs() / step + 1;
+    let step = if last < first { -step } else { step };
+    (0..count).map(move |i| first + i * step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(word: &str) -> Vec<String> {
+        brace_expand(word)
+    }
+
+    #[test]
+    fn test_alternatives() {
+        assert_eq!(expand("{a,b,c}"), vec!["a", "b", "c"]);
+        assert_eq!(expand("file{,.bak}"), vec!["file", "file.bak"]);
+        assert_eq!(expand("pre{x,y}post"), vec!["prexpost", "preypost"]);
+        assert_eq!(expand("{a,b}{1,2}"), vec!["a1", "a2", "b1", "b2"]);
+        assert_eq!(expand("{,}"), vec!["", ""]);
+    }
+
+    #[test]
+    fn test_nested_braces() {
+        assert_eq!(expand("{{a,b},{c,d}}"), vec!["a", "b", "c", "d"]);
+        assert_eq!(expand("x{a,b{1,2}}"), vec!["xa", "xb1", "xb2"]);
+        assert_eq!(expand("{a,{1..3}}"), vec!["a", "1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(expand("{1..5}"), vec!["1", "2", "3", "4", "5"]);
+        assert_eq!(expand("{3..1}"), vec!["3", "2", "1"]);
+        assert_eq!(expand("{-1..1}"), vec!["-1", "0", "1"]);
+        assert_eq!(expand("{1..10..3}"), vec!["1", "4", "7", "10"]);
+        assert_eq!(expand("{10..1..-4}"), vec!["10", "6", "2"]);
+        assert_eq!(expand("{a..e..2}"), vec!["a", "c", "e"]);
+        assert_eq!(expand("{C..A}"), vec!["C", "B", "A"]);
+        assert_eq!(expand("{5..5}"), vec!["5"]);
+        assert_eq!(expand("{08..11}"), vec!["08", "09", "10", "11"]);
+        assert_eq!(expand("{1..003}"), vec!["001", "002", "003"]);
+        assert_eq!(expand("{-05..-03}"), vec!["-05", "-04", "-03"]);
+    }
+
+    #[test]
+    fn test_no_expansion() {
+        for word in &[
+            "plain",
+            "{}",
+            "{a}",
+            "{a..}",
+            "{1..b}",
+            "{aa..c}",
+            "{a,b",
+            "a,b}",
+            "${x,y}",
+            "${a}",
+            "$(echo {a,b})",
+            "`echo {a,b}`",
+            "\\{a,b}",
+        ] {
+            assert_eq!(expand(word), vec![word.to_string()], "{}", word);
+        }
+        assert_eq!(expand("${x}{a,b}"), vec!["${x}a", "${x}b"]);
+        assert_eq!(expand("{${x},b}"), vec!["${x}", "b"]);
+        assert_eq!(expand("{}{a,b}"), vec!["{}a", "{}b"]);
+    }
+
+    #[test]
+    fn test_glob_expansion_after_brace_expansion() {
+        use crate::core::glob;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        for name in &["a.rs", "b.toml", "c.txt"] {
+            std::fs::write(temp_dir.path().join(name), "").unwrap();
+        }
+        let pattern = format!("{}/{{*.rs,*.toml}}", temp_dir.path().display());
+        let paths: Vec<String> = expand(&pattern)
+            .iter()
+            .flat_map(|pattern| glob::glob_expand(pattern))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}/a.rs", temp_dir.path().display()),
+                format!("{}/b.toml", temp_dir.path().display()),
+            ]
+        );
+    }
+}
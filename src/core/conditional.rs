@@ -0,0 +1,383 @@
+//! Conditional expressions
+//!
+//! Evaluates the contents of `[[ ... ]]`. Unlike `[`, words are not split or glob expanded, and
+//! the right-hand side of `==`, `!=`, and `=~` is a pattern unless it is quoted.
+
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+
+use nix::unistd::{self, AccessFlags};
+use regex::Regex;
+
+use crate::{
+    core::variable_expansion,
+    errors::{Error, Result},
+    shell::Shell,
+};
+
+/// Evaluates `expression`, the contents of `[[ ... ]]`, expanding variables from `shell`.
+pub fn evaluate(expression: &str, shell: &dyn Shell) -> Result<bool> {
+    let tokens = tokenize(expression, shell)?;
+    let mut parser = Parser {
+        expression,
+        tokens: &tokens,
+        pos: 0,
+    };
+    let result = parser.parse_or()?;
+    if parser.pos != tokens.len() {
+        return Err(Error::syntax(expression));
+    }
+
+    Ok(result)
+}
+
+/// A word inside `[[ ... ]]`, after quote removal and variable expansion.
+#[derive(Debug, Default)]
+struct Token {
+    /// Each part of the word, and whether the part was quoted.
+    parts: Vec<(String, bool)>,
+}
+
+impl Token {
+    fn value(&self) -> String {
+        self.parts.iter().map(|(part, _)| part.as_str()).collect()
+    }
+
+    /// Returns the token as an operator, if it is an unquoted word.
+    fn operator(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [(part, false)] => Some(part),
+            _ => None,
+        }
+    }
+
+    /// Converts the token to a regex matching the whole value as a glob pattern.
+    fn glob_regex(&self) -> String {
+        let mut regex = String::from("^");
+        for (part, quoted) in &self.parts {
+            if *quoted {
+                regex.push_str(&regex::escape(part));
+            } else {
+                regex.push_str(&glob_to_regex(part));
+            }
+        }
+        regex.push('$');
+        regex
+    }
+
+    /// Converts the token to an unanchored regex, treating quoted parts literally.
+    fn regex(&self) -> String {
+        self.parts
+            .iter()
+            .map(|(part, quoted)| {
+                if *quoted {
+                    regex::escape(part)
+                } else {
+                    part.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Splits `expression` into words, removing quotes and expanding variables.
+fn tokenize(expression: &str, shell: &dyn Shell) -> Result<Vec<Token>> {
+    let expand = |s: &str| variable_expansion::expand_word(s, dirs::home_dir(), shell);
+
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Ok(tokens);
+        }
+
+        let mut token = Token::default();
+        let mut unquoted = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            if c == '\'' || c == '"' {
+                if !unquoted.is_empty() {
+                    token.parts.push((expand(&unquoted)?, false));
+                    unquoted.clear();
+                }
+                let quoted: String = chars.by_ref().take_while(|&q| q != c).collect();
+                let quoted = if c == '"' { expand(&quoted)? } else { quoted };
+                token.parts.push((quoted, true));
+            } else if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    if !unquoted.is_empty() {
+                        token.parts.push((expand(&unquoted)?, false));
+                        unquoted.clear();
+                    }
+                    token.parts.push((escaped.to_string(), true));
+                }
+            } else {
+                unquoted.push(c);
+            }
+        }
+        if !unquoted.is_empty() {
+            token.parts.push((expand(&unquoted)?, false));
+        }
+        tokens.push(token);
+    }
+}
+
+/// Converts a glob pattern (`*`, `?`, and `[...]`) to an equivalent regex.
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                let class: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let class = match class.strip_prefix('!') {
+                    Some(negated) => format!("^{}", negated),
+                    None => class,
+                };
+                regex.push('[');
+                regex.push_str(&class.replace('\\', "\\\\"));
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek_operator(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).and_then(Token::operator)
+    }
+
+    fn next_operator(&mut self, op: &str) -> bool {
+        if self.peek_operator() == Some(op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_token(&mut self) -> Result<&'a Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| Error::syntax(self.expression))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn parse_or(&mut self) -> Result<bool> {
+        let mut result = self.parse_and()?;
+        while self.next_operator("||") {
+            let rhs = self.parse_and()?;
+            result = result || rhs;
+        }
+        Ok(result)
+    }
+
+    fn parse_and(&mut self) -> Result<bool> {
+        let mut result = self.parse_not()?;
+        while self.next_operator("&&") {
+            let rhs = self.parse_not()?;
+            result = result && rhs;
+        }
+        Ok(result)
+    }
+
+    fn parse_not(&mut self) -> Result<bool> {
+        if self.next_operator("!") {
+            return Ok(!self.parse_not()?);
+        }
+
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<bool> {
+        if self.next_operator("(") {
+            let result = self.parse_or()?;
+            if !self.next_operator(")") {
+                return Err(Error::syntax(self.expression));
+            }
+            return Ok(result);
+        }
+
+        let first = self.next_token()?;
+        if let Some(op) = first.operator().filter(|op| is_unary_operator(op)) {
+            let operand = self.next_token()?.value();
+            return Ok(test_unary(op, &operand));
+        }
+
+        match self.peek_operator().filter(|op| is_binary_operator(op)) {
+            Some(op) => {
+                self.pos += 1;
+                let rhs = self.next_token()?;
+                test_binary(op, &first.value(), rhs)
+            }
+            None => Ok(!first.value().is_empty()),
+        }
+    }
+}
+
+/// Returns `true` if `op` tests a single operand, e.g. `-f`, shared with `test`.
+pub(crate) fn is_unary_operator(op: &str) -> bool {
+    [
+        "-e", "-f", "-d", "-r", "-w", "-x", "-s", "-L", "-h", "-p", "-S", "-z", "-n",
+    ]
+    .contains(&op)
+}
+
+fn is_binary_operator(op: &str) -> bool {
+    [
+        "==", "=", "!=", "=~", "<", ">", "-eq", "-ne", "-lt", "-le", "-gt", "-ge",
+    ]
+    .contains(&op)
+}
+
+/// Evaluates the unary operator `op`, see [`is_unary_operator`].
+pub(crate) fn test_unary(op: &str, operand: &str) -> bool {
+    let path = Path::new(operand);
+    match op {
+        "-e" => path.exists(),
+        "-f" => path.is_file(),
+        "-d" => path.is_dir(),
+        "-r" => is_accessible(path, AccessFlags::R_OK),
+        "-w" => is_accessible(path, AccessFlags::W_OK),
+        "-x" => is_accessible(path, AccessFlags::X_OK),
+        "-s" => path.metadata().map_or(false, |metadata| metadata.len() > 0),
+        "-L" | "-h" => path
+            .symlink_metadata()
+            .map_or(false, |metadata| metadata.file_type().is_symlink()),
+        "-p" => path
+            .metadata()
+            .map_or(false, |metadata| metadata.file_type().is_fifo()),
+        "-S" => path
+            .metadata()
+            .map_or(false, |metadata| metadata.file_type().is_socket()),
+        "-z" => operand.is_empty(),
+        "-n" => !operand.is_empty(),
+        _ => unreachable!("unknown unary operator: {}", op),
+    }
+}
+
+fn test_binary(op: &str, lhs: &str, rhs: &Token) -> Result<bool> {
+    let result = match op {
+        "==" | "=" => glob_match(lhs, rhs)?,
+        "!=" => !glob_match(lhs, rhs)?,
+        "=~" => compile(&rhs.regex())?.is_match(lhs),
+        "<" => lhs < rhs.value().as_str(),
+        ">" => lhs > rhs.value().as_str(),
+        _ => {
+            let lhs = parse_integer(lhs)?;
+            let rhs = parse_integer(&rhs.value())?;
+            match op {
+                "-eq" => lhs == rhs,
+                "-ne" => lhs != rhs,
+                "-lt" => lhs < rhs,
+                "-le" => lhs <= rhs,
+                "-gt" => lhs > rhs,
+                "-ge" => lhs >= rhs,
+                _ => unreachable!("unknown binary operator: {}", op),
+            }
+        }
+    };
+    Ok(result)
+}
+
+fn glob_match(value: &str, pattern: &Token) -> Result<bool> {
+    Ok(compile(&pattern.glob_regex())?.is_match(value))
+}
+
+fn compile(regex: &str) -> Result<Regex> {
+    Regex::new(regex).map_err(|_| Error::syntax(regex))
+}
+
+/// Returns `true` if the current user may access `path` with `mode`, like access(2).
+fn is_accessible(path: &Path, mode: AccessFlags) -> bool {
+    unistd::access(path, mode).is_ok()
+}
+
+pub(crate) fn parse_integer(s: &str) -> Result<i64> {
+    s.trim()
+        .parse()
+        .map_err(|_| Error::syntax(format!("{}: integer expression expected", s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::shell::{create_shell, ShellConfig};
+
+    fn eval(expression: &str) -> Result<bool> {
+        let shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        evaluate(expression, &*shell)
+    }
+
+    #[test]
+    fn test_string_tests() {
+        assert!(eval("-n foo").unwrap());
+        assert!(!eval("-z foo").unwrap());
+        assert!(eval("-z ''").unwrap());
+        assert!(eval("foo").unwrap());
+        assert!(!eval("''").unwrap());
+        assert!(eval("abc < abd").unwrap());
+        assert!(eval("b > a").unwrap());
+        assert!(eval("10 -gt 9").unwrap());
+    }
+
+    #[test]
+    fn test_file_tests() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().to_str().unwrap();
+        assert!(eval(&format!("-d {}", dir)).unwrap());
+        assert!(eval(&format!("-e {}", dir)).unwrap());
+        assert!(!eval(&format!("-f {}", dir)).unwrap());
+        assert!(!eval(&format!("-e {}/missing", dir)).unwrap());
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(eval("hello == h*o").unwrap());
+        assert!(eval("hello == h?ll[a-z]").unwrap());
+        assert!(!eval("hello == 'h*o'").unwrap());
+        assert!(eval("h*o == 'h*o'").unwrap());
+        assert!(eval("hello != h*x").unwrap());
+        assert!(eval("\"hello\" =~ ^hel").unwrap());
+        assert!(!eval("hello =~ ^el").unwrap());
+        assert!(!eval("hello =~ 'h.llo'").unwrap());
+    }
+
+    #[test]
+    fn test_compound_expressions() {
+        assert!(eval("-n foo && -z ''").unwrap());
+        assert!(eval("-z foo || -n foo").unwrap());
+        assert!(eval("! -z foo").unwrap());
+        assert!(eval("( -z foo || -n foo ) && a == a").unwrap());
+        assert!(!eval("-n foo && ( a == b )").unwrap());
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(eval("").is_err());
+        assert!(eval("-n").is_err());
+        assert!(eval("a ==").is_err());
+        assert!(eval("a b").is_err());
+        assert!(eval("( a").is_err());
+        assert!(eval("a -eq b").is_err());
+    }
+}
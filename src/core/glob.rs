@@ -0,0 +1,198 @@
+//! Pathname expansion
+//!
+//! Expands unquoted words containing `*`, `?`, or `[...]` to the paths they match, e.g. `*.rs`
+//! or `src/*/mod.rs`. Like bash, `*` and `?` do not match a leading `.` unless the pattern
+//! component starts with one, and matches are sorted. [`GlobOptions`] changes this, e.g. for
+//! `shopt -s dotglob`.
+
+use std::{fs, path::Path};
+
+use regex::Regex;
+
+use crate::core::conditional;
+
+/// Returns `true` if `word` contains `*`, `?`, or a `[...]` bracket expression.
+pub fn has_glob_characters(word: &str) -> bool {
+    word.contains(|c| c == '*' || c == '?')
+        || word
+            .find('[')
+            .map_or(false, |start| word[start + 1..].contains(']'))
+}
+
+/// Returns `true` if the whole of `value` matches `pattern`, e.g. for `case`. Unlike paths, `*`
+/// and `?` match `/` and a leading `.`.
+pub fn pattern_matches(pattern: &str, value: &str) -> bool {
+    Regex::new(&format!(
+        "(?s)^(?:{})$",
+        conditional::glob_to_regex(pattern)
+    ))
+    .map_or(false, |regex| regex.is_match(value))
+}
+
+/// Options that change which paths a pattern matches, see `shopt`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlobOptions {
+    /// `*` and `?` also match a leading `.`, see `dotglob`.
+    pub dot_files: bool,
+    /// Letters match regardless of case, see `nocaseglob`.
+    pub case_insensitive: bool,
+}
+
+/// Returns the sorted paths matching `pattern`, or nothing if no paths match.
+pub fn glob_expand(pattern: &str) -> Vec<String> {
+    glob_expand_with_options(pattern, GlobOptions::default())
+}
+
+/// Like [`glob_expand`], but matches paths according to `options`.
+pub fn glob_expand_with_options(pattern: &str, options: GlobOptions) -> Vec<String> {
+    let (prefix, pattern) = match pattern.strip_prefix('/') {
+        Some(pattern) => ("/", pattern),
+        None => ("", pattern),
+    };
+    let components: Vec<&str> = pattern.split('/').collect();
+    let mut paths = expand_components(prefix.to_string(), &components, options);
+    paths.sort();
+    paths
+}
+
+/// Returns the paths under `prefix` matching the remaining path `components`.
+fn expand_components(prefix: String, components: &[&str], options: GlobOptions) -> Vec<String> {
+    let (component, rest) = match components.split_first() {
+        Some(split) => split,
+        None => return vec![prefix],
+    };
+
+    if !has_glob_characters(component) {
+        let path = format!("{}{}", prefix, component);
+        return if !rest.is_empty() {
+            expand_components(format!("{}/", path), rest, options)
+        } else if fs::symlink_metadata(&path).is_ok() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let flags = if options.case_insensitive { "si" } else { "s" };
+    let regex = match Regex::new(&format!(
+        "(?{})^(?:{})$",
+        flags,
+        conditional::glob_to_regex(component)
+    )) {
+        Ok(regex) => regex,
+        Err(_) => return Vec::new(),
+    };
+    let dir = if prefix.is_empty() { "." } else { &prefix };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.') || options.dot_files || component.starts_with('.'))
+        .filter(|name| regex.is_match(name))
+        .flat_map(|name| {
+            let path = format!("{}{}", prefix, name);
+            if rest.is_empty() {
+                vec![path]
+            } else if Path::new(&path).is_dir() {
+                expand_components(format!("{}/", path), rest, options)
+            } else {
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+
+    fn create_files(dir: &Path, paths: &[&str]) {
+        for path in paths {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            File::create(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_has_glob_characters() {
+        assert!(has_glob_characters("*.rs"));
+        assert!(has_glob_characters("file?"));
+        assert!(has_glob_characters("[ab]c"));
+        assert!(!has_glob_characters("["));
+        assert!(!has_glob_characters("a]b[c"));
+        assert!(!has_glob_characters("plain"));
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("*", ""));
+        assert!(pattern_matches("*.rs", "src/.main.rs"));
+        assert!(pattern_matches("?[ab]", "xa"));
+        assert!(pattern_matches("[!ab]*", "c\nd"));
+        assert!(!pattern_matches("?[ab]", "xab"));
+        assert!(!pattern_matches("a", "ab"));
+        assert!(!pattern_matches("a.c", "abc"));
+    }
+
+    #[test]
+    fn test_glob_expand() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        create_files(
+            temp_dir.path(),
+            &[
+                "b.rs",
+                "a.rs",
+                "c.txt",
+                ".hidden.rs",
+                "src/x/mod.rs",
+                "src/y/mod.rs",
+                "src/z",
+            ],
+        );
+        let root = format!("{}/", temp_dir.path().display());
+        let glob = |pattern: &str| -> Vec<String> {
+            glob_expand(&format!("{}{}", root, pattern))
+                .into_iter()
+                .map(|path| path[root.len()..].to_string())
+                .collect()
+        };
+
+        assert_eq!(glob("*.rs"), vec!["a.rs", "b.rs"]);
+        assert_eq!(glob(".*.rs"), vec![".hidden.rs"]);
+        assert_eq!(glob("?.*"), vec!["a.rs", "b.rs", "c.txt"]);
+        assert_eq!(glob("[ac].*"), vec!["a.rs", "c.txt"]);
+        assert_eq!(glob("[!ac].*"), vec!["b.rs"]);
+        assert_eq!(glob("src/*/mod.rs"), vec!["src/x/mod.rs", "src/y/mod.rs"]);
+        assert_eq!(glob("src/*/"), vec!["src/x/", "src/y/"]);
+        assert_eq!(glob("*/x"), vec!["src/x"]);
+        assert!(glob("*.md").is_empty());
+        assert!(glob("missing/*").is_empty());
+
+        let glob_with = |pattern: &str, options: GlobOptions| -> Vec<String> {
+            glob_expand_with_options(&format!("{}{}", root, pattern), options)
+                .into_iter()
+                .map(|path| path[root.len()..].to_string())
+                .collect()
+        };
+        let dot_files = GlobOptions {
+            dot_files: true,
+            ..GlobOptions::default()
+        };
+        assert_eq!(
+            glob_with("*.rs", dot_files),
+            vec![".hidden.rs", "a.rs", "b.rs"]
+        );
+        let case_insensitive = GlobOptions {
+            case_insensitive: true,
+            ..GlobOptions::default()
+        };
+        assert_eq!(glob_with("[AC].*", case_insensitive), vec!["a.rs", "c.txt"]);
+    }
+}
@@ -1,6 +1,11 @@
-use crate::core::parser::{
-    self,
-    ast::{self, visit::Visitor},
+use std::fmt;
+
+use crate::{
+    core::parser::{
+        self,
+        ast::{self, visit::Visitor},
+    },
+    util,
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -8,6 +13,12 @@ pub enum Stdio {
     Inherit,
     FileDescriptor(i32),
     Filename(String),
+    /// A file that output is appended to, from `>>`.
+    AppendFilename(String),
+    /// The body of a here-document, from `<<DELIM`.
+    HereDoc(ast::HereDoc),
+    /// The word of a here-string, from `<<<word`.
+    HereString(String),
 }
 
 impl Default for Stdio {
@@ -18,14 +29,19 @@ impl Default for Stdio {
 
 impl From<ast::Redirect> for Stdio {
     fn from(redirect: ast::Redirect) -> Self {
-        match redirect.redirectee {
-            ast::Redirectee::FileDescriptor(fd) => Stdio::FileDescriptor(fd),
-            ast::Redirectee::Filename(filename) => Stdio::Filename(filename),
+        match (redirect.instruction, redirect.redirectee) {
+            (_, ast::Redirectee::FileDescriptor(fd)) => Stdio::FileDescriptor(fd),
+            (ast::RedirectInstruction::Append, ast::Redirectee::Filename(filename)) => {
+                Stdio::AppendFilename(filename)
+            }
+            (_, ast::Redirectee::Filename(filename)) => Stdio::Filename(filename),
+            (_, ast::Redirectee::HereDoc(here_doc)) => Stdio::HereDoc(here_doc),
+            (_, ast::Redirectee::HereString(word)) => Stdio::HereString(word),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SimpleCommand {
     pub program: String,
     pub args: Vec<String>,
@@ -34,7 +50,7 @@ pub struct SimpleCommand {
     pub stderr: Stdio,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Command {
     Simple(SimpleCommand),
     Connection {
@@ -42,6 +58,184 @@ pub enum Command {
         second: Box<Command>,
         connector: ast::Connector,
     },
+    Arithmetic(String),
+    DoubleBracket(String),
+    Subshell(Box<Command>),
+    Negated(Box<Command>),
+    Group(Box<Command>),
+    If {
+        condition: Box<Command>,
+        then_branch: Box<Command>,
+        elif_branches: Vec<(Command, Command)>,
+        else_branch: Option<Box<Command>>,
+    },
+    ForIn {
+        var: String,
+        words: Vec<String>,
+        body: Box<Command>,
+    },
+    ForArith {
+        init: String,
+        condition: String,
+        step: String,
+        body: Box<Command>,
+    },
+    While {
+        condition: Box<Command>,
+        body: Box<Command>,
+    },
+    Until {
+        condition: Box<Command>,
+        body: Box<Command>,
+    },
+    Case {
+        word: String,
+        arms: Vec<(Vec<String>, Command)>,
+    },
+    Select {
+        var: String,
+        words: Vec<String>,
+        body: Box<Command>,
+    },
+    FunctionDef {
+        name: String,
+        body: Box<Command>,
+    },
+    ArrayAssignment {
+        /// The declaration command and its options, e.g. `declare -A`, or empty if none.
+        declaration: Vec<String>,
+        assignment: ast::ArrayAssignment,
+    },
+}
+
+/// Formats the command as a single line of input that parses to an equivalent command, e.g. to
+/// print function definitions with `declare -f`.
+///
+/// Here-documents are written as here-strings.
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Simple(command) => {
+                write!(f, "{}", command.program)?;
+                for arg in &command.args {
+                    write!(f, " {}", arg)?;
+                }
+                match &command.stdin {
+                    Stdio::Inherit => (),
+                    Stdio::FileDescriptor(fd) => write!(f, " 0<&{}", fd)?,
+                    Stdio::Filename(filename) | Stdio::AppendFilename(filename) => {
+                        write!(f, " < {}", filename)?
+                    }
+                    Stdio::HereDoc(here_doc) => {
+                        let body = here_doc.body.strip_suffix('\n').unwrap_or(&here_doc.body);
+                        write!(f, " <<< {}", util::quote_for_shell(body))?
+                    }
+                    Stdio::HereString(word) => write!(f, " <<< {}", word)?,
+                }
+                write_output_redirect(f, "", &command.stdout)?;
+                write_output_redirect(f, "2", &command.stderr)
+            }
+            Command::Connection {
+                first,
+                second,
+                connector,
+            } => {
+                let connector = match connector {
+                    ast::Connector::Pipe => " |",
+                    ast::Connector::Semicolon => ";",
+                    ast::Connector::And => " &&",
+                    ast::Connector::Or => " ||",
+                };
+                write!(f, "{}{} {}", first, connector, second)
+            }
+            Command::Arithmetic(expression) => write!(f, "(( {} ))", expression),
+            Command::DoubleBracket(expression) => write!(f, "[[ {} ]]", expression),
+            Command::Subshell(command) => write!(f, "({})", command),
+            Command::Negated(command) => write!(f, "! {}", command),
+            Command::Group(command) => write!(f, "{{ {}; }}", command),
+            Command::If {
+                condition,
+                then_branch,
+                elif_branches,
+                else_branch,
+            } => {
+                write!(f, "if {}; then {};", condition, then_branch)?;
+                for (condition, branch) in elif_branches {
+                    write!(f, " elif {}; then {};", condition, branch)?;
+                }
+                if let Some(branch) = else_branch {
+                    write!(f, " else {};", branch)?;
+                }
+                write!(f, " fi")
+            }
+            Command::ForIn { var, words, body } | Command::Select { var, words, body } => {
+                let keyword = if let Command::ForIn { .. } = self {
+                    "for"
+                } else {
+                    "select"
+                };
+                write!(f, "{} {} in", keyword, var)?;
+                for word in words {
+                    write!(f, " {}", word)?;
+                }
+                write!(f, "; do {}; done", body)
+            }
+            Command::ForArith {
+                init,
+                condition,
+                step,
+                body,
+            } => write!(
+                f,
+                "for (( {}; {}; {} )); do {}; done",
+                init, condition, step, body
+            ),
+            Command::While { condition, body } => {
+                write!(f, "while {}; do {}; done", condition, body)
+            }
+            Command::Until { condition, body } => {
+                write!(f, "until {}; do {}; done", condition, body)
+            }
+            Command::Case { word, arms } => {
+                write!(f, "case {} in", word)?;
+                for (patterns, command) in arms {
+                    write!(f, " {}) {};;", patterns.join(" | "), command)?;
+                }
+                write!(f, " esac")
+            }
+            Command::FunctionDef { name, body } => write!(f, "{} () {}", name, body),
+            Command::ArrayAssignment {
+                declaration,
+                assignment,
+            } => {
+                for word in declaration {
+                    write!(f, "{} ", word)?;
+                }
+                let append = if assignment.append { "+" } else { "" };
+                write!(f, "{}{}=(", assignment.name, append)?;
+                for (i, element) in assignment.elements.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { " " };
+                    match &element.subscript {
+                        Some(subscript) => {
+                            write!(f, "{}[{}]={}", separator, subscript, element.value)?
+                        }
+                        None => write!(f, "{}{}", separator, element.value)?,
+                    }
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Writes the redirect of the output `stdio` of file descriptor `fd`, e.g. ` 2>> log`.
+fn write_output_redirect(f: &mut fmt::Formatter, fd: &str, stdio: &Stdio) -> fmt::Result {
+    match stdio {
+        Stdio::FileDescriptor(target) => write!(f, " {}>&{}", fd, target),
+        Stdio::Filename(filename) => write!(f, " {}> {}", fd, filename),
+        Stdio::AppendFilename(filename) => write!(f, " {}>> {}", fd, filename),
+        _ => Ok(()),
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -99,6 +293,134 @@ impl Visitor<Command> for Interpreter {
         }
     }
 
+    fn visit_arithmetic_command(&mut self, expression: &str) -> Command {
+        Command::Arithmetic(expression.to_string())
+    }
+
+    fn visit_double_bracket_command(&mut self, expression: &str) -> Command {
+        Command::DoubleBracket(expression.to_string())
+    }
+
+    fn visit_subshell_command(&mut self, command: &ast::Command) -> Command {
+        Command::Subshell(Box::new(self.visit_command(command)))
+    }
+
+    fn visit_negated_command(&mut self, command: &ast::Command) -> Command {
+        Command::Negated(Box::new(self.visit_command(command)))
+    }
+
+    fn visit_group_command(&mut self, command: &ast::Command) -> Command {
+        Command::Group(Box::new(self.visit_command(command)))
+    }
+
+    fn visit_if_command(
+        &mut self,
+        condition: &ast::Command,
+        then_branch: &ast::Command,
+        elif_branches: &[(ast::Command, ast::Command)],
+        else_branch: Option<&ast::Command>,
+    ) -> Command {
+        Command::If {
+            condition: Box::new(self.visit_command(condition)),
+            then_branch: Box::new(self.visit_command(then_branch)),
+            elif_branches: elif_branches
+                .iter()
+                .map(|(condition, branch)| {
+                    (self.visit_command(condition), self.visit_command(branch))
+                })
+                .collect(),
+            else_branch: else_branch.map(|branch| Box::new(self.visit_command(branch))),
+        }
+    }
+
+    fn visit_for_in_command(
+        &mut self,
+        var: &str,
+        words: &[String],
+        body: &ast::Command,
+    ) -> Command {
+        Command::ForIn {
+            var: var.to_string(),
+            words: words.to_vec(),
+            body: Box::new(self.visit_command(body)),
+        }
+    }
+
+    fn visit_for_arith_command(
+        &mut self,
+        init: &str,
+        condition: &str,
+        step: &str,
+        body: &ast::Command,
+    ) -> Command {
+        Command::ForArith {
+            init: init.to_string(),
+            condition: condition.to_string(),
+            step: step.to_string(),
+            body: Box::new(self.visit_command(body)),
+        }
+    }
+
+    fn visit_while_command(&mut self, condition: &ast::Command, body: &ast::Command) -> Command {
+        Command::While {
+            condition: Box::new(self.visit_command(condition)),
+            body: Box::new(self.visit_command(body)),
+        }
+    }
+
+    fn visit_until_command(&mut self, condition: &ast::Command, body: &ast::Command) -> Command {
+        Command::Until {
+            condition: Box::new(self.visit_command(condition)),
+            body: Box::new(self.visit_command(body)),
+        }
+    }
+
+    fn visit_case_command(&mut self, word: &str, arms: &[(Vec<String>, ast::Command)]) -> Command {
+        Command::Case {
+            word: word.to_string(),
+            arms: arms
+                .iter()
+                .map(|(patterns, command)| (patterns.clone(), self.visit_command(command)))
+                .collect(),
+        }
+    }
+
+    fn visit_select_command(
+        &mut self,
+        var: &str,
+        words: &[String],
+        body: &ast::Command,
+    ) -> Command {
+        Command::Select {
+            var: var.to_string(),
+            words: words.to_vec(),
+            body: Box::new(self.visit_command(body)),
+        }
+    }
+
+    fn visit_function_definition(
+        &mut self,
+        name: &str,
+        body: &ast::Command,
+        _keyword: bool,
+    ) -> Command {
+        Command::FunctionDef {
+            name: name.to_string(),
+            body: Box::new(self.visit_command(body)),
+        }
+    }
+
+    fn visit_array_assignment(
+        &mut self,
+        declaration: &[String],
+        assignment: &ast::ArrayAssignment,
+    ) -> Command {
+        Command::ArrayAssignment {
+            declaration: declaration.to_vec(),
+            assignment: assignment.clone(),
+        }
+    }
+
     fn visit_command(&mut self, command: &ast::Command) -> Command {
         match command {
             ast::Command::Simple {
@@ -111,6 +433,58 @@ impl Visitor<Command> for Interpreter {
                 ref second,
                 connector,
             } => self.visit_connection_command(first, second, *connector),
+            ast::Command::Arithmetic(ref expression) => self.visit_arithmetic_command(expression),
+            ast::Command::DoubleBracket(ref expression) => {
+                self.visit_double_bracket_command(expression)
+            }
+            ast::Command::Subshell(ref command) => self.visit_subshell_command(command),
+            ast::Command::Negated { ref inner } => self.visit_negated_command(inner),
+            ast::Command::Group { ref inner } => self.visit_group_command(inner),
+            ast::Command::If {
+                ref condition,
+                ref then_branch,
+                ref elif_branches,
+                ref else_branch,
+            } => self.visit_if_command(
+                condition,
+                then_branch,
+                elif_branches,
+                else_branch.as_deref(),
+            ),
+            ast::Command::ForIn {
+                ref var,
+                ref words,
+                ref body,
+            } => self.visit_for_in_command(var, words, body),
+            ast::Command::ForArith {
+                ref init,
+                ref condition,
+                ref step,
+                ref body,
+            } => self.visit_for_arith_command(init, condition, step, body),
+            ast::Command::While {
+                ref condition,
+                ref body,
+            } => self.visit_while_command(condition, body),
+            ast::Command::Until {
+                ref condition,
+                ref body,
+            } => self.visit_until_command(condition, body),
+            ast::Command::Case { ref word, ref arms } => self.visit_case_command(word, arms),
+            ast::Command::Select {
+                ref var,
+                ref words,
+                ref body,
+            } => self.visit_select_command(var, words, body),
+            ast::Command::FunctionDef {
+                ref name,
+                ref body,
+                keyword,
+            } => self.visit_function_definition(name, body, *keyword),
+            ast::Command::ArrayAssignment {
+                ref declaration,
+                ref assignment,
+            } => self.visit_array_assignment(declaration, assignment),
         }
     }
 }
@@ -142,7 +516,10 @@ fn is_stdin_redirect(redirect: &ast::Redirect) -> bool {
         return false;
     }
 
-    matches!(redirect.redirectee, ast::Redirectee::Filename(_))
+    matches!(
+        redirect.redirectee,
+        ast::Redirectee::Filename(_) | ast::Redirectee::HereDoc(_) | ast::Redirectee::HereString(_)
+    )
 }
 
 /// Gets the last stdout redirect in `redirects`
@@ -156,11 +533,10 @@ fn is_stdout_redirect(redirect: &ast::Redirect) -> bool {
         _ => return false,
     }
 
-    if redirect.instruction != ast::RedirectInstruction::Output {
-        return false;
-    }
-
-    true
+    matches!(
+        redirect.instruction,
+        ast::RedirectInstruction::Output | ast::RedirectInstruction::Append
+    )
 }
 
 /// Gets the last stderr redirect in `redirects`
@@ -174,11 +550,10 @@ fn is_stderr_redirect(redirect: &ast::Redirect) -> bool {
         _ => return false,
     }
 
-    if redirect.instruction != ast::RedirectInstruction::Output {
-        return false;
-    }
-
-    true
+    matches!(
+        redirect.instruction,
+        ast::RedirectInstruction::Output | ast::RedirectInstruction::Append
+    )
 }
 
 #[cfg(test)]
@@ -426,6 +801,132 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_append_redirects() {
+        let append_redirection = |redirector: Option<i32>, filename: &str| ast::Redirect {
+            redirector: redirector.map(ast::Redirectee::FileDescriptor),
+            instruction: ast::RedirectInstruction::Append,
+            redirectee: ast::Redirectee::Filename(filename.into()),
+        };
+        let input = "echo test >>out 2>>err".to_string();
+        assert_eq!(
+            Interpreter::parse(parser::Command {
+                input: input.clone(),
+                inner: ast::Command::Simple {
+                    words: vec!["echo".into(), "test".into()],
+                    redirects: vec![
+                        append_redirection(None, "out"),
+                        append_redirection(Some(2), "err"),
+                    ],
+                    background: false,
+                },
+            }),
+            CommandGroup {
+                input,
+                command: Command::Simple(
+                    SimpleCommandBuilder::new("echo")
+                        .arg("test")
+                        .stdout(Stdio::AppendFilename("out".into()))
+                        .stderr(Stdio::AppendFilename("err".into()))
+                        .build()
+                ),
+                background: false,
+            }
+        );
+
+        // The last stdout redirect wins, whether it appends or not
+        let input = "echo test >>out1 >out2".to_string();
+        assert_eq!(
+            Interpreter::parse(parser::Command {
+                input: input.clone(),
+                inner: ast::Command::Simple {
+                    words: vec!["echo".into(), "test".into()],
+                    redirects: vec![
+                        append_redirection(None, "out1"),
+                        output_filename_redirection("out2"),
+                    ],
+                    background: false,
+                },
+            }),
+            CommandGroup {
+                input,
+                command: Command::Simple(
+                    SimpleCommandBuilder::new("echo")
+                        .arg("test")
+                        .stdout(Stdio::Filename("out2".into()))
+                        .build()
+                ),
+                background: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_here_doc_redirect() {
+        let here_doc = ast::HereDoc {
+            body: "hello\n".into(),
+            expand: true,
+        };
+        let input = "cat <in <<EOF\nhello\nEOF".to_string();
+        assert_eq!(
+            Interpreter::parse(parser::Command {
+                input: input.clone(),
+                inner: ast::Command::Simple {
+                    words: vec!["cat".into()],
+                    redirects: vec![
+                        input_redirection("in"),
+                        ast::Redirect {
+                            redirector: None,
+                            instruction: ast::RedirectInstruction::Input,
+                            redirectee: ast::Redirectee::HereDoc(here_doc.clone()),
+                        },
+                    ],
+                    background: false,
+                },
+            }),
+            CommandGroup {
+                input,
+                command: Command::Simple(
+                    SimpleCommandBuilder::new("cat")
+                        .stdin(Stdio::HereDoc(here_doc))
+                        .build()
+                ),
+                background: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_here_string_redirect() {
+        let input = "cat <<<word <in".to_string();
+        assert_eq!(
+            Interpreter::parse(parser::Command {
+                input: input.clone(),
+                inner: ast::Command::Simple {
+                    words: vec!["cat".into()],
+                    redirects: vec![
+                        ast::Redirect {
+                            redirector: None,
+                            instruction: ast::RedirectInstruction::Input,
+                            redirectee: ast::Redirectee::HereString("word".into()),
+                        },
+                        input_redirection("in"),
+                    ],
+                    background: false,
+                },
+            }),
+            CommandGroup {
+                input,
+                command: Command::Simple(
+                    SimpleCommandBuilder::new("cat")
+                        .stdin(Stdio::Filename("in".into()))
+                        .build()
+                ),
+                background: false,
+            }
+        );
+    }
+
     #[test]
     fn test_redirect_stderr_file() {
         let input = "2>errfile >&2 echo needle".to_string();
@@ -506,4 +1007,23 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_display() {
+        for input in &[
+            "echo 'a b' \"$c\" > out 2>&1",
+            "grep -c x < in | wc -l && echo ok || echo failed",
+            "if [ -f a ]; then cat a; elif (( x > 1 )); then ! true; else { echo b; echo c; }; fi",
+            "for i in 1 2; do echo $i; done",
+            "for (( i = 0; i < 2; i++ )); do echo $i >> log; done",
+            "while [[ -n $x ]]; do (cd /; ls); done",
+            "case $x in a | b) echo ab;; *) echo other;; esac",
+            "f () { echo f; }",
+            "arr=(a [3]=\"b c\")",
+            "declare -A m+=([k]=v)",
+        ] {
+            let command = Interpreter::parse(parser::Command::parse(input).unwrap()).command;
+            assert_eq!(command.to_string(), *input);
+        }
+    }
 }
@@ -1,3 +1,10 @@
+pub mod alias_expansion;
+pub mod arithmetic;
+pub mod brace_expansion;
+pub mod conditional;
+pub mod glob;
 pub mod intermediate_representation;
+pub mod parameter_expansion;
 pub mod parser;
+pub mod posix;
 pub mod variable_expansion;
@@ -0,0 +1,352 @@
+//! Parameter expansion string operations
+//!
+//! Implements the operators of `${name%pattern}`, `${name%%pattern}`, `${name#pattern}`,
+//! `${name##pattern}`, `${name/pattern/replacement}`, `${name//pattern/replacement}` and
+//! `${name:offset:length}`. Patterns are globs (`*`, `?`, and `[...]`), and all operations work
+//! on characters rather than bytes.
+
+use regex::{NoExpand, Regex};
+
+use crate::{
+    core::conditional,
+    errors::{Error, Result},
+};
+
+/// A string operation applied to the value of a parameter, see [`parse`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StringOperation {
+    /// `%pattern` or `%%pattern`: removes the shortest or longest matching suffix.
+    RemoveSuffix {
+        /// Glob pattern.
+        pattern: String,
+        /// Whether to remove the longest match.
+        longest: bool,
+    },
+    /// `#pattern` or `##pattern`: removes the shortest or longest matching prefix.
+    RemovePrefix {
+        /// Glob pattern.
+        pattern: String,
+        /// Whether to remove the longest match.
+        longest: bool,
+    },
+    /// `/pattern/replacement` or `//pattern/replacement`: replaces the first or all matches.
+    Replace {
+        /// Glob pattern.
+        pattern: String,
+        /// Replacement text.
+        replacement: String,
+        /// Whether to replace all matches.
+        all: bool,
+    },
+    /// `:offset` or `:offset:length`: extracts a substring.
+    Substring {
+        /// Start of the substring, counted from the end if negative.
+        offset: i64,
+        /// Length of the substring, or its end counted from the end of the value if negative.
+        length: Option<i64>,
+    },
+}
+
+/// Parses the operator following a parameter name, e.g. `%.txt` in `${file%.txt}`.
+///
+/// Returns `None` if `s` is not one of the string operations. A negative offset must be separated
+/// from the colon, e.g. `${name: -2}` or `${name:(-2)}`, as `${name:-2}` is a default value.
+pub fn parse(s: &str) -> Option<StringOperation> {
+    if let Some(pattern) = s.strip_prefix("%%") {
+        Some(StringOperation::RemoveSuffix {
+            pattern: pattern.to_string(),
+            longest: true,
+        })
+    } else if let Some(pattern) = s.strip_prefix('%') {
+        Some(StringOperation::RemoveSuffix {
+            pattern: pattern.to_string(),
+            longest: false,
+        })
+    } else if let Some(pattern) = s.strip_prefix("##") {
+        Some(StringOperation::RemovePrefix {
+            pattern: pattern.to_string(),
+            longest: true,
+        })
+    } else if let Some(pattern) = s.strip_prefix('#') {
+        Some(StringOperation::RemovePrefix {
+            pattern: pattern.to_string(),
+            longest: false,
+        })
+    } else if let Some(rest) = s.strip_prefix('/') {
+        let (rest, all) = match rest.strip_prefix('/') {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        let (pattern, replacement) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i + 1..]),
+            None => (rest, ""),
+        };
+        Some(StringOperation::Replace {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            all,
+        })
+    } else if let Some(rest) = s.strip_prefix(':') {
+        let (offset, length) = match rest.find(':') {
+            Some(i) => (&rest[..i], Some(parse_integer(&rest[i + 1..])?)),
+            None => (rest, None),
+        };
+        Some(StringOperation::Substring {
+            offset: parse_integer(offset)?,
+            length,
+        })
+    } else {
+        None
+    }
+}
+
+impl StringOperation {
+    /// Returns the operation with `expand` applied to its pattern and replacement, e.g. to expand
+    /// variables in them.
+    pub fn expand_operands<F>(self, expand: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Result<String>,
+    {
+        let operation = match self {
+            StringOperation::RemoveSuffix { pattern, longest } => StringOperation::RemoveSuffix {
+                pattern: expand(&pattern)?,
+                longest,
+            },
+            StringOperation::RemovePrefix { pattern, longest } => StringOperation::RemovePrefix {
+                pattern: expand(&pattern)?,
+                longest,
+            },
+            StringOperation::Replace {
+                pattern,
+                replacement,
+                all,
+            } => StringOperation::Replace {
+                pattern: expand(&pattern)?,
+                replacement: expand(&replacement)?,
+                all,
+            },
+            substring => substring,
+        };
+        Ok(operation)
+    }
+
+    /// Applies the operation to `value`.
+    pub fn apply(&self, value: &str) -> Result<String> {
+        match *self {
+            StringOperation::RemoveSuffix {
+                ref pattern,
+                longest,
+            } => {
+                let regex = whole_match_regex(pattern)?;
+                let mut starts = char_boundaries(value);
+                // The shortest suffix starts at the last boundary.
+                if !longest {
+                    starts.reverse();
+                }
+                Ok(starts
+                    .into_iter()
+                    .find(|&i| regex.is_match(&value[i..]))
+                    .map_or(value, |i| &value[..i])
+                    .to_string())
+            }
+            StringOperation::RemovePrefix {
+                ref pattern,
+                longest,
+            } => {
+                let regex = whole_match_regex(pattern)?;
+                let mut ends = char_boundaries(value);
+                if longest {
+                    ends.reverse();
+                }
+                Ok(ends
+                    .into_iter()
+                    .find(|&i| regex.is_match(&value[..i]))
+                    .map_or(value, |i| &value[i..])
+                    .to_string())
+            }
+            StringOperation::Replace {
+                ref pattern,
+                ref replacement,
+                all,
+            } => {
+                if pattern.is_empty() {
+                    return Ok(value.to_string());
+                }
+                let regex = compile(&format!("(?s){}", conditional::glob_to_regex(pattern)))?;
+                let replaced = if all {
+                    regex.replace_all(value, NoExpand(replacement))
+                } else {
+                    regex.replace(value, NoExpand(replacement))
+                };
+                Ok(replaced.into_owned())
+            }
+            StringOperation::Substring { offset, length } => substring(value, offset, length),
+        }
+    }
+}
+
+/// Returns the characters of `value` from `offset`, like bash's `${name:offset:length}`.
+fn substring(value: &str, offset: i64, length: Option<i64>) -> Result<String> {
+    let len = value.chars().count() as i64;
+    let start = if offset < 0 { len + offset } else { offset };
+    if start < 0 || start > len {
+        return Ok(String::new());
+    }
+    let end = match length {
+        Some(length) if length < 0 => {
+            let end = len + length;
+            if end < start {
+                return Err(Error::arithmetic(format!(
+                    "{}: substring expression < 0",
+                    length
+                )));
+            }
+            end
+        }
+        Some(length) => (start + length).min(len),
+        None => len,
+    };
+    Ok(value
+        .chars()
+        .skip(start as usize)
+        .take((end - start) as usize)
+        .collect())
+}
+
+/// Returns the byte offsets of the character boundaries of `value`, including its end.
+fn char_boundaries(value: &str) -> Vec<usize> {
+    value
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(Some(value.len()))
+        .collect()
+}
+
+/// Parses an offset or length, which may be parenthesized, e.g. `(-2)`.
+fn parse_integer(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or(s);
+    s.trim().parse().ok()
+}
+
+fn whole_match_regex(pattern: &str) -> Result<Regex> {
+    compile(&format!(
+        "(?s)^(?:{})$",
+        conditional::glob_to_regex(pattern)
+    ))
+}
+
+fn compile(regex: &str) -> Result<Regex> {
+    Regex::new(regex).map_err(|_| Error::syntax(regex))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(operation: &str, value: &str) -> String {
+        parse(operation)
+            .unwrap_or_else(|| panic!("{:?} should be an operation", operation))
+            .apply(value)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("%%.*"),
+            Some(StringOperation::RemoveSuffix {
+                pattern: ".*".to_string(),
+                longest: true
+            })
+        );
+        assert_eq!(
+            parse("/a"),
+            Some(StringOperation::Replace {
+                pattern: "a".to_string(),
+                replacement: "".to_string(),
+                all: false
+            })
+        );
+        assert_eq!(
+            parse(":(-2):1"),
+            Some(StringOperation::Substring {
+                offset: -2,
+                length: Some(1)
+            })
+        );
+        assert_eq!(parse(":x"), None);
+        assert_eq!(parse("-default"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_remove_suffix() {
+        assert_eq!(apply("%.*", "archive.tar.gz"), "archive.tar");
+        assert_eq!(apply("%%.*", "archive.tar.gz"), "archive");
+        assert_eq!(apply("%.zip", "archive.tar.gz"), "archive.tar.gz");
+        assert_eq!(apply("%*", "abc"), "abc");
+        assert_eq!(apply("%%*", "abc"), "");
+        assert_eq!(apply("%", "abc"), "abc");
+        assert_eq!(apply("%.*", ""), "");
+        assert_eq!(apply("%é?", "caféé"), "caf");
+        assert_eq!(apply("%[0-9]", "file1"), "file");
+    }
+
+    #[test]
+    fn test_remove_prefix() {
+        assert_eq!(apply("#*/", "/usr/local/bin"), "usr/local/bin");
+        assert_eq!(apply("##*/", "/usr/local/bin"), "bin");
+        assert_eq!(apply("#x", "/usr/local/bin"), "/usr/local/bin");
+        assert_eq!(apply("#*", "abc"), "abc");
+        assert_eq!(apply("##*", "abc"), "");
+        assert_eq!(apply("#", "abc"), "abc");
+        assert_eq!(apply("##*", ""), "");
+        assert_eq!(apply("#?", "日本語"), "本語");
+        assert_eq!(apply("##*本", "日本語"), "語");
+    }
+
+    #[test]
+    fn test_replace() {
+        assert_eq!(apply("/o/0", "foo boo"), "f0o boo");
+        assert_eq!(apply("//o/0", "foo boo"), "f00 b00");
+        assert_eq!(apply("//o", "foo boo"), "f b");
+        assert_eq!(apply("/b*/x", "foo boo"), "foo x");
+        assert_eq!(apply("/?/_", "日本"), "_本");
+        assert_eq!(apply("//[aeiou]/$1", "audio"), "$1$1d$1$1");
+        assert_eq!(apply("/x/y", "abc"), "abc");
+        assert_eq!(apply("//", "abc"), "abc");
+        assert_eq!(apply("//a/b", ""), "");
+    }
+
+    #[test]
+    fn test_substring() {
+        assert_eq!(apply(":0", "abcdef"), "abcdef");
+        assert_eq!(apply(":2", "abcdef"), "cdef");
+        assert_eq!(apply(":2:3", "abcdef"), "cde");
+        assert_eq!(apply(":2:100", "abcdef"), "cdef");
+        assert_eq!(apply(":6", "abcdef"), "");
+        assert_eq!(apply(":7", "abcdef"), "");
+        assert_eq!(apply(": -2", "abcdef"), "ef");
+        assert_eq!(apply(":(-4):2", "abcdef"), "cd");
+        assert_eq!(apply(": -10", "abcdef"), "");
+        assert_eq!(apply(":1:-1", "abcdef"), "bcde");
+        assert_eq!(apply(":1:0", "abcdef"), "");
+        assert_eq!(apply(":1:2", "日本語です"), "本語");
+        assert_eq!(apply(": -1", "日本語"), "語");
+        assert_eq!(apply(":0:1", ""), "");
+        assert!(parse(":4:-3").unwrap().apply("abcdef").is_err());
+    }
+
+    #[test]
+    fn test_expand_operands() {
+        let operation = parse("//$a/$b")
+            .unwrap()
+            .expand_operands(|word| Ok(word.replace("$a", "x").replace("$b", "y")))
+            .unwrap();
+        assert_eq!(operation.apply("axbx").unwrap(), "ayby");
+    }
+}
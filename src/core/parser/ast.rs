@@ -2,11 +2,24 @@
 pub enum Redirectee {
     FileDescriptor(i32),
     Filename(String),
+    HereDoc(HereDoc),
+    /// The word of a here-string, e.g. `<<<word`.
+    HereString(String),
+}
+
+/// The body of a here-document, e.g. `<<EOF`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HereDoc {
+    pub body: String,
+    /// Variables in the body are expanded unless the delimiter is quoted, e.g. `<<'EOF'`.
+    pub expand: bool,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum RedirectInstruction {
     Output,
+    /// `>>`, which appends to the redirectee instead of overwriting it.
+    Append,
     Input,
 }
 
@@ -25,6 +38,17 @@ pub enum Connector {
     Or,
 }
 
+impl Connector {
+    /// How tightly the connector binds: `|` before `&&` and `||`, which bind before `;`.
+    fn precedence(self) -> u8 {
+        match self {
+            Connector::Pipe => 2,
+            Connector::And | Connector::Or => 1,
+            Connector::Semicolon => 0,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Command {
     Simple {
@@ -37,6 +61,142 @@ pub enum Command {
         second: Box<Command>,
         connector: Connector,
     },
+    /// `(( expression ))`
+    Arithmetic(String),
+    /// `[[ expression ]]`
+    DoubleBracket(String),
+    /// `( command )`
+    Subshell(Box<Command>),
+    /// `! command`
+    Negated { inner: Box<Command> },
+    /// `{ command; }`
+    Group { inner: Box<Command> },
+    /// `if condition; then command; elif condition; then command; else command; fi`
+    If {
+        condition: Box<Command>,
+        then_branch: Box<Command>,
+        /// The condition and command of each `elif`.
+        elif_branches: Vec<(Command, Command)>,
+        else_branch: Option<Box<Command>>,
+    },
+    /// `for var in words; do command; done`
+    ForIn {
+        var: String,
+        words: Vec<String>,
+        body: Box<Command>,
+    },
+    /// `for (( init; condition; step )); do command; done`
+    ForArith {
+        init: String,
+        condition: String,
+        step: String,
+        body: Box<Command>,
+    },
+    /// `while condition; do command; done`
+    While {
+        condition: Box<Command>,
+        body: Box<Command>,
+    },
+    /// `until condition; do command; done`
+    Until {
+        condition: Box<Command>,
+        body: Box<Command>,
+    },
+    /// `case word in pattern | pattern) command;; esac`
+    Case {
+        word: String,
+        /// The patterns and command of each arm.
+        arms: Vec<(Vec<String>, Command)>,
+    },
+    /// `select var in words; do command; done`
+    Select {
+        var: String,
+        words: Vec<String>,
+        body: Box<Command>,
+    },
+    /// `name() { command; }` or `function name { command; }`
+    FunctionDef {
+        name: String,
+        body: Box<Command>,
+        /// Whether the definition starts with the `function` keyword.
+        keyword: bool,
+    },
+    /// `name=(words)`, or `declare -A name=([key]=word)` if it is the argument of a declaration
+    ArrayAssignment {
+        /// The declaration command and its options, e.g. `declare -A`, or empty if none.
+        declaration: Vec<String>,
+        assignment: ArrayAssignment,
+    },
+}
+
+/// Builtins that may take an array assignment as their last argument.
+const DECLARATION_COMMANDS: &[&str] = &["declare", "export", "readonly"];
+
+/// The assignment of a list of words to an array, e.g. `name=(a [3]=b)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrayAssignment {
+    pub name: String,
+    /// Whether the elements are appended to the array, e.g. `name+=(c)`.
+    pub append: bool,
+    pub elements: Vec<ArrayElement>,
+}
+
+/// An element of an array assignment, e.g. `a` or `[3]=b`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrayElement {
+    /// The index or key of the element, e.g. `3` for `[3]=b`.
+    pub subscript: Option<String>,
+    pub value: String,
+}
+
+/// Connects `first` to `second`, which the grammar has already parsed as the rest of the list,
+/// respecting the precedence of the connectors, e.g. `a | b; c` is `(a | b); c` and
+/// `a && b || c` is `(a && b) || c`.
+pub fn connect(first: Command, connector: Connector, second: Command) -> Command {
+    match second {
+        Command::Connection {
+            first: second_first,
+            second: second_second,
+            connector: second_connector,
+        } if connector.precedence() > second_connector.precedence()
+            || (connector.precedence() == 1 && second_connector.precedence() == 1) =>
+        {
+            Command::Connection {
+                first: Box::new(connect(first, connector, *second_first)),
+                second: second_second,
+                connector: second_connector,
+            }
+        }
+        second => Command::Connection {
+            first: Box::new(first),
+            second: Box::new(second),
+            connector,
+        },
+    }
+}
+
+/// How a word is quoted, see [`split_quotes`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quoting {
+    /// The word is expanded, split into fields, and glob expanded.
+    Unquoted,
+    /// `'word'`: the word is used literally.
+    Single,
+    /// `"word"`: variables and command substitutions are expanded, but the word is not split
+    /// into fields or glob expanded.
+    Double,
+}
+
+/// Splits a word into how it is quoted and its text without the quotes.
+///
+/// The parser keeps the quotes of a quoted word so that expansion can treat it differently.
+pub fn split_quotes(word: &str) -> (Quoting, &str) {
+    for (quote, quoting) in &[('\'', Quoting::Single), ('"', Quoting::Double)] {
+        if word.len() >= 2 && word.starts_with(*quote) && word.ends_with(*quote) {
+            return (*quoting, &word[1..word.len() - 1]);
+        }
+    }
+    (Quoting::Unquoted, word)
 }
 
 #[derive(Debug, Default)]
@@ -44,6 +204,8 @@ pub struct SimpleCommandBuilder {
     pub words: Vec<String>,
     pub redirects: Vec<Redirect>,
     pub background: bool,
+    /// The array assignments and the number of words before each.
+    pub array_assignments: Vec<(usize, ArrayAssignment)>,
 }
 
 impl SimpleCommandBuilder {
@@ -58,16 +220,38 @@ impl SimpleCommandBuilder {
         match command_part {
             SimpleCommandPart::Word(w) => self.words.push(w),
             SimpleCommandPart::Redirect(r) => self.redirects.push(r),
+            SimpleCommandPart::Redirects(rs) => self.redirects.extend(rs),
+            SimpleCommandPart::ArrayAssignment(a) => {
+                self.array_assignments.push((self.words.len(), a))
+            }
         };
 
         self
     }
 
-    pub fn build(&self) -> Command {
-        Command::Simple {
-            words: self.words.clone(),
-            redirects: self.redirects.clone(),
-            background: self.background,
+    /// Builds the command, which is an error if it has an array assignment that is not the last
+    /// argument of a declaration, e.g. `echo a=(b)`.
+    pub fn build(&self) -> Result<Command, &'static str> {
+        match self.array_assignments.as_slice() {
+            [] => Ok(Command::Simple {
+                words: self.words.clone(),
+                redirects: self.redirects.clone(),
+                background: self.background,
+            }),
+            [(position, assignment)]
+                if *position == self.words.len()
+                    && self.redirects.is_empty()
+                    && !self.background
+                    && self.words.first().map_or(false, |program| {
+                        DECLARATION_COMMANDS.contains(&program.as_str())
+                    }) =>
+            {
+                Ok(Command::ArrayAssignment {
+                    declaration: self.words.clone(),
+                    assignment: assignment.clone(),
+                })
+            }
+            _ => Err("array assignments must be the last argument of declare, export or readonly"),
         }
     }
 }
@@ -76,6 +260,10 @@ impl SimpleCommandBuilder {
 pub enum SimpleCommandPart {
     Word(String),
     Redirect(Redirect),
+    /// Redirects written as one, e.g. `&>>file` for `>>file 2>&1`.
+    Redirects(Vec<Redirect>),
+    /// An array assignment argument, e.g. `b=(c)` of `declare -a b=(c)`.
+    ArrayAssignment(ArrayAssignment),
 }
 
 pub mod visit {
@@ -96,6 +284,50 @@ pub mod visit {
             connector: Connector,
         ) -> T;
 
+        fn visit_arithmetic_command(&mut self, expression: &str) -> T;
+
+        fn visit_double_bracket_command(&mut self, expression: &str) -> T;
+
+        fn visit_subshell_command(&mut self, command: &Command) -> T;
+
+        fn visit_negated_command(&mut self, command: &Command) -> T;
+
+        fn visit_group_command(&mut self, command: &Command) -> T;
+
+        fn visit_if_command(
+            &mut self,
+            condition: &Command,
+            then_branch: &Command,
+            elif_branches: &[(Command, Command)],
+            else_branch: Option<&Command>,
+        ) -> T;
+
+        fn visit_for_in_command(&mut self, var: &str, words: &[String], body: &Command) -> T;
+
+        fn visit_for_arith_command(
+            &mut self,
+            init: &str,
+            condition: &str,
+            step: &str,
+            body: &Command,
+        ) -> T;
+
+        fn visit_while_command(&mut self, condition: &Command, body: &Command) -> T;
+
+        fn visit_until_command(&mut self, condition: &Command, body: &Command) -> T;
+
+        fn visit_case_command(&mut self, word: &str, arms: &[(Vec<String>, Command)]) -> T;
+
+        fn visit_select_command(&mut self, var: &str, words: &[String], body: &Command) -> T;
+
+        fn visit_function_definition(&mut self, name: &str, body: &Command, keyword: bool) -> T;
+
+        fn visit_array_assignment(
+            &mut self,
+            declaration: &[String],
+            assignment: &ArrayAssignment,
+        ) -> T;
+
         fn visit_command(&mut self, command: &Command) -> T;
     }
 }
@@ -159,22 +391,22 @@ mod tests {
 
     #[test]
     fn test_simple_command() {
-        assert!(CommandParser::new().parse("").is_err());
+        assert!(CommandParser::new().parse(&[], "").is_err());
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob")
+                .parse(&[], "echo bob")
                 .expect("'echo bob' should be valid"),
             simple_command(&["echo", "bob"])
         );
         assert_eq!(
             CommandParser::new()
-                .parse("ls ~/1code")
+                .parse(&[], "ls ~/1code")
                 .expect("'ls ~/1code' should be valid"),
             simple_command(&["ls", "~/1code"])
         );
         assert_eq!(
             CommandParser::new()
-                .parse("echo 5")
+                .parse(&[], "echo 5")
                 .expect("'echo 5' should be valid"),
             simple_command(&["echo", "5"])
         );
@@ -184,7 +416,7 @@ mod tests {
     fn test_input_redirection() {
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob <in")
+                .parse(&[], "echo bob <in")
                 .expect("'echo bob <in' should be valid"),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -194,7 +426,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob < in")
+                .parse(&[], "echo bob < in")
                 .expect("'echo bob < in' should be valid"),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -202,15 +434,15 @@ mod tests {
                 background: false,
             }
         );
-        assert!(CommandParser::new().parse("<").is_err());
-        assert!(CommandParser::new().parse("echo <").is_err());
+        assert!(CommandParser::new().parse(&[], "<").is_err());
+        assert!(CommandParser::new().parse(&[], "echo <").is_err());
     }
 
     #[test]
     fn test_output_redirection() {
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob >out")
+                .parse(&[], "echo bob >out")
                 .expect("'echo bob >out' should be valid"),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -220,7 +452,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob > out")
+                .parse(&[], "echo bob > out")
                 .expect("'echo bob > out' should be valid"),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -230,7 +462,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob 1>out")
+                .parse(&[], "echo bob 1>out")
                 .expect("'echo bob 1>out' should be valid"),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -240,7 +472,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob 1> out")
+                .parse(&[], "echo bob 1> out")
                 .expect("'echo bob 1>out' should be valid"),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -248,15 +480,120 @@ mod tests {
                 background: false,
             }
         );
-        assert!(CommandParser::new().parse(">").is_err());
-        assert!(CommandParser::new().parse("echo >").is_err());
+        assert!(CommandParser::new().parse(&[], ">").is_err());
+        assert!(CommandParser::new().parse(&[], "echo >").is_err());
+    }
+
+    #[test]
+    fn test_append_redirection() {
+        let append_redirection = |redirector: Option<i32>, filename: &str| Redirect {
+            redirector: redirector.map(Redirectee::FileDescriptor),
+            instruction: RedirectInstruction::Append,
+            redirectee: Redirectee::Filename(filename.into()),
+        };
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "echo bob >>out")
+                .expect("'echo bob >>out' should be valid"),
+            Command::Simple {
+                words: vec!["echo".into(), "bob".into()],
+                redirects: vec![append_redirection(None, "out")],
+                background: false,
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "echo bob 2>> err")
+                .expect("'echo bob 2>> err' should be valid"),
+            Command::Simple {
+                words: vec!["echo".into(), "bob".into()],
+                redirects: vec![append_redirection(Some(2), "err")],
+                background: false,
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "echo bob &>>out")
+                .expect("'echo bob &>>out' should be valid"),
+            Command::Simple {
+                words: vec!["echo".into(), "bob".into()],
+                redirects: vec![
+                    append_redirection(None, "out"),
+                    fd_to_fd_redirection(2, RedirectInstruction::Output, 1),
+                ],
+                background: false,
+            }
+        );
+        assert!(CommandParser::new().parse(&[], "echo >>").is_err());
+        assert!(CommandParser::new().parse(&[], "echo > >out").is_err());
+    }
+
+    #[test]
+    fn test_here_doc_redirection() {
+        let here_docs = [HereDoc {
+            body: "hello\n".into(),
+            expand: true,
+        }];
+        assert_eq!(
+            CommandParser::new()
+                .parse(&here_docs, "cat <<0 | tr a-z A-Z")
+                .expect("'cat <<0 | tr a-z A-Z' should be valid"),
+            Command::Connection {
+                first: Box::new(Command::Simple {
+                    words: vec!["cat".into()],
+                    redirects: vec![Redirect {
+                        redirector: None,
+                        instruction: RedirectInstruction::Input,
+                        redirectee: Redirectee::HereDoc(here_docs[0].clone()),
+                    }],
+                    background: false,
+                }),
+                second: Box::new(Command::Simple {
+                    words: vec!["tr".into(), "a-z".into(), "A-Z".into()],
+                    redirects: vec![],
+                    background: false,
+                }),
+                connector: Connector::Pipe,
+            }
+        );
+        assert!(CommandParser::new().parse(&here_docs, "cat <<1").is_err());
+    }
+
+    #[test]
+    fn test_here_string_redirection() {
+        let here_string = |word: &str| Redirect {
+            redirector: None,
+            instruction: RedirectInstruction::Input,
+            redirectee: Redirectee::HereString(word.into()),
+        };
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "cat <<<word")
+                .expect("'cat <<<word' should be valid"),
+            Command::Simple {
+                words: vec!["cat".into()],
+                redirects: vec![here_string("word")],
+                background: false,
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "cat <<< \"hello world\"")
+                .expect("'cat <<< \"hello world\"' should be valid"),
+            Command::Simple {
+                words: vec!["cat".into()],
+                redirects: vec![here_string("\"hello world\"")],
+                background: false,
+            }
+        );
+        assert!(CommandParser::new().parse(&[], "cat <<<").is_err());
     }
 
     #[test]
     fn test_fd_duplication() {
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob 1>&2")
+                .parse(&[], "echo bob 1>&2")
                 .expect("'echo bob 1>&2' should be valid"),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -266,7 +603,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("echo bob 2<&1")
+                .parse(&[], "echo bob 2<&1")
                 .expect("'echo bob 2>&1' should be valid"),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -280,7 +617,7 @@ mod tests {
     fn test_multiple_unique_redirection() {
         assert_eq!(
             CommandParser::new()
-                .parse(">out echo <in bob",)
+                .parse(&[], ">out echo <in bob",)
                 .expect("'>out echo <in bob' should be valid",),
             Command::Simple {
                 words: vec!["echo".into(), "bob".into()],
@@ -291,7 +628,7 @@ mod tests {
 
         assert_eq!(
             CommandParser::new()
-                .parse("2>errfile >&2 echo needle",)
+                .parse(&[], "2>errfile >&2 echo needle",)
                 .expect("'2>errfile >&2 echo needle' should be valid",),
             Command::Simple {
                 words: vec!["echo".into(), "needle".into()],
@@ -308,7 +645,7 @@ mod tests {
     fn test_multiple_same_redirects() {
         assert_eq!(
             CommandParser::new()
-                .parse("<in1 <in2")
+                .parse(&[], "<in1 <in2")
                 .expect("'<in1 <in2' should be valid"),
             Command::Simple {
                 words: vec![],
@@ -318,7 +655,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse(">out1 >out2")
+                .parse(&[], ">out1 >out2")
                 .expect("'>out1 >out2' should be valid"),
             Command::Simple {
                 words: vec![],
@@ -335,7 +672,7 @@ mod tests {
     fn test_connection_command() {
         assert_eq!(
             CommandParser::new()
-                .parse("cmd1 | cmd2")
+                .parse(&[], "cmd1 | cmd2")
                 .expect("'cmd1 | cmd2' should be valid"),
             Command::Connection {
                 first: Box::new(simple_command(&["cmd1"])),
@@ -345,7 +682,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("cmd1 ; cmd2")
+                .parse(&[], "cmd1 ; cmd2")
                 .expect("'cmd1 ; cmd2' should be valid"),
             Command::Connection {
                 first: Box::new(simple_command(&["cmd1"])),
@@ -355,7 +692,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("<in cmd1 | cmd2 >out",)
+                .parse(&[], "<in cmd1 | cmd2 >out",)
                 .expect("'<in cmd1 | cmd2 >out' should be valid",),
             Command::Connection {
                 first: Box::new(Command::Simple {
@@ -373,7 +710,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("cmd1 && cmd2")
+                .parse(&[], "cmd1 && cmd2")
                 .expect("'cmd1 && cmd2' should be valid"),
             Command::Connection {
                 first: Box::new(Command::Simple {
@@ -391,7 +728,7 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("cmd1 || cmd2")
+                .parse(&[], "cmd1 || cmd2")
                 .expect("'cmd1 || cmd2' should be valid"),
             Command::Connection {
                 first: Box::new(Command::Simple {
@@ -413,7 +750,7 @@ mod tests {
     fn test_long_connection_command() {
         assert_eq!(
             CommandParser::new()
-                .parse("cmd1 | cmd2 | cmd3",)
+                .parse(&[], "cmd1 | cmd2 | cmd3",)
                 .expect("'cmd1 | cmd2 | cmd3' should be valid",),
             Command::Connection {
                 first: Box::new(simple_command(&["cmd1"])),
@@ -427,16 +764,34 @@ mod tests {
         );
         assert_eq!(
             CommandParser::new()
-                .parse("cmd1 | cmd2 ; cmd3",)
+                .parse(&[], "cmd1 | cmd2 ; cmd3",)
                 .expect("'cmd1 | cmd2 ; cmd3' should be valid",),
             Command::Connection {
-                first: Box::new(simple_command(&["cmd1"])),
-                second: Box::new(Command::Connection {
-                    first: Box::new(simple_command(&["cmd2"])),
-                    second: Box::new(simple_command(&["cmd3"])),
-                    connector: Connector::Semicolon,
+                first: Box::new(Command::Connection {
+                    first: Box::new(simple_command(&["cmd1"])),
+                    second: Box::new(simple_command(&["cmd2"])),
+                    connector: Connector::Pipe,
                 }),
-                connector: Connector::Pipe,
+                second: Box::new(simple_command(&["cmd3"])),
+                connector: Connector::Semicolon,
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "cmd1 && cmd2 | cmd3 || cmd4",)
+                .expect("'cmd1 && cmd2 | cmd3 || cmd4' should be valid",),
+            Command::Connection {
+                first: Box::new(Command::Connection {
+                    first: Box::new(simple_command(&["cmd1"])),
+                    second: Box::new(Command::Connection {
+                        first: Box::new(simple_command(&["cmd2"])),
+                        second: Box::new(simple_command(&["cmd3"])),
+                        connector: Connector::Pipe,
+                    }),
+                    connector: Connector::And,
+                }),
+                second: Box::new(simple_command(&["cmd4"])),
+                connector: Connector::Or,
             }
         );
     }
@@ -444,7 +799,7 @@ mod tests {
     #[test]
     fn test_job_background() {
         assert_eq!(
-            CommandParser::new().parse("cmd &").unwrap(),
+            CommandParser::new().parse(&[], "cmd &").unwrap(),
             Command::Simple {
                 words: vec!["cmd".into()],
                 redirects: vec![],
@@ -452,7 +807,7 @@ mod tests {
             }
         );
         assert_eq!(
-            CommandParser::new().parse("cmd1 & | cmd2").unwrap(),
+            CommandParser::new().parse(&[], "cmd1 & | cmd2").unwrap(),
             Command::Connection {
                 first: Box::new(Command::Simple {
                     words: vec!["cmd1".into()],
@@ -464,76 +819,530 @@ mod tests {
             }
         );
 
-        assert!(CommandParser::new().parse("&").is_err());
+        assert!(CommandParser::new().parse(&[], "&").is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_command() {
+        assert_eq!(
+            CommandParser::new().parse(&[], "(( 5 > 3 ))").unwrap(),
+            Command::Arithmetic("5 > 3".into())
+        );
+        assert_eq!(
+            CommandParser::new().parse(&[], "((x=(1+2)*3))").unwrap(),
+            Command::Arithmetic("x=(1+2)*3".into())
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "(( x < 1 )) && echo yes")
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(Command::Arithmetic("x < 1".into())),
+                second: Box::new(simple_command(&["echo", "yes"])),
+                connector: Connector::And,
+            }
+        );
+    }
+
+    #[test]
+    fn test_double_bracket_command() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], r#"[[ "hello" =~ ^hel ]]"#)
+                .unwrap(),
+            Command::DoubleBracket(r#""hello" =~ ^hel"#.into())
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "[[ -n $x && $x == [a-z]* ]] || echo no")
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(Command::DoubleBracket("-n $x && $x == [a-z]*".into())),
+                second: Box::new(simple_command(&["echo", "no"])),
+                connector: Connector::Or,
+            }
+        );
+    }
+
+    #[test]
+    fn test_subshell_command() {
+        assert_eq!(
+            CommandParser::new().parse(&[], "(cd /tmp; pwd)").unwrap(),
+            Command::Subshell(Box::new(Command::Connection {
+                first: Box::new(simple_command(&["cd", "/tmp"])),
+                second: Box::new(simple_command(&["pwd"])),
+                connector: Connector::Semicolon,
+            }))
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "( echo $(date) ) | cat")
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(Command::Subshell(Box::new(simple_command(&[
+                    "echo", "$(date)"
+                ])))),
+                second: Box::new(simple_command(&["cat"])),
+                connector: Connector::Pipe,
+            }
+        );
+        assert!(CommandParser::new().parse(&[], "(echo").is_err());
+        assert!(CommandParser::new().parse(&[], "echo (foo)").is_err());
+    }
+
+    #[test]
+    fn test_negated_command() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "! false && echo yes")
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(Command::Negated {
+                    inner: Box::new(simple_command(&["false"])),
+                }),
+                second: Box::new(simple_command(&["echo", "yes"])),
+                connector: Connector::And,
+            }
+        );
+        assert_eq!(
+            CommandParser::new().parse(&[], "! ! (( x ))").unwrap(),
+            Command::Negated {
+                inner: Box::new(Command::Negated {
+                    inner: Box::new(Command::Arithmetic("x".into())),
+                }),
+            }
+        );
+        assert_eq!(
+            CommandParser::new().parse(&[], "[ ! -f x ]").unwrap(),
+            simple_command(&["[", "!", "-f", "x", "]"])
+        );
+        assert_eq!(
+            CommandParser::new().parse(&[], "echo !foo").unwrap(),
+            simple_command(&["echo", "!foo"])
+        );
+        assert!(CommandParser::new().parse(&[], "!").is_err());
+    }
+
+    #[test]
+    fn test_group_command() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "{ echo foo; echo bar; } | wc -l")
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(Command::Group {
+                    inner: Box::new(Command::Connection {
+                        first: Box::new(simple_command(&["echo", "foo"])),
+                        second: Box::new(simple_command(&["echo", "bar"])),
+                        connector: Connector::Semicolon,
+                    }),
+                }),
+                second: Box::new(simple_command(&["wc", "-l"])),
+                connector: Connector::Pipe,
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "{ true && { false; }; }")
+                .unwrap(),
+            Command::Group {
+                inner: Box::new(Command::Connection {
+                    first: Box::new(simple_command(&["true"])),
+                    second: Box::new(Command::Group {
+                        inner: Box::new(simple_command(&["false"])),
+                    }),
+                    connector: Connector::And,
+                }),
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "echo { } {a,b} {}")
+                .unwrap(),
+            simple_command(&["echo", "{", "}", "{a,b}", "{}"])
+        );
+        assert!(CommandParser::new().parse(&[], "{ echo foo }").is_err());
+        assert!(CommandParser::new().parse(&[], "{ echo foo;").is_err());
+        assert!(CommandParser::new().parse(&[], "{ }").is_err());
+    }
+
+    #[test]
+    fn test_if_command() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "if true; then echo yes; fi")
+                .unwrap(),
+            Command::If {
+                condition: Box::new(simple_command(&["true"])),
+                then_branch: Box::new(simple_command(&["echo", "yes"])),
+                elif_branches: vec![],
+                else_branch: None,
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(
+                    &[],
+                    "if a; then b; elif c && d; then e; elif f; then g; else h; i; fi | cat"
+                )
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(Command::If {
+                    condition: Box::new(simple_command(&["a"])),
+                    then_branch: Box::new(simple_command(&["b"])),
+                    elif_branches: vec![
+                        (
+                            Command::Connection {
+                                first: Box::new(simple_command(&["c"])),
+                                second: Box::new(simple_command(&["d"])),
+                                connector: Connector::And,
+                            },
+                            simple_command(&["e"]),
+                        ),
+                        (simple_command(&["f"]), simple_command(&["g"])),
+                    ],
+                    else_branch: Some(Box::new(Command::Connection {
+                        first: Box::new(simple_command(&["h"])),
+                        second: Box::new(simple_command(&["i"])),
+                        connector: Connector::Semicolon,
+                    })),
+                }),
+                second: Box::new(simple_command(&["cat"])),
+                connector: Connector::Pipe,
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "echo if then elif else fi")
+                .unwrap(),
+            simple_command(&["echo", "if", "then", "elif", "else", "fi"])
+        );
+        assert!(CommandParser::new()
+            .parse(&[], "if true; then echo; ")
+            .is_err());
+        assert!(CommandParser::new().parse(&[], "if true; fi").is_err());
+        assert!(CommandParser::new()
+            .parse(&[], "if true then echo; fi")
+            .is_err());
+    }
+
+    #[test]
+    fn test_for_command() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "for x in 1 '2 3' *.rs; do echo $x; done")
+                .unwrap(),
+            Command::ForIn {
+                var: "x".into(),
+                words: vec!["1".into(), "'2 3'".into(), "*.rs".into()],
+                body: Box::new(simple_command(&["echo", "$x"])),
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "for x in; do :; done")
+                .unwrap(),
+            Command::ForIn {
+                var: "x".into(),
+                words: vec![],
+                body: Box::new(simple_command(&[":"])),
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "for (( i = 0; i < 3; i++ )); do echo $i; break; done")
+                .unwrap(),
+            Command::ForArith {
+                init: "i = 0".into(),
+                condition: "i < 3".into(),
+                step: "i++".into(),
+                body: Box::new(Command::Connection {
+                    first: Box::new(simple_command(&["echo", "$i"])),
+                    second: Box::new(simple_command(&["break"])),
+                    connector: Connector::Semicolon,
+                }),
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "for ((;;)) do :; done")
+                .unwrap(),
+            Command::ForArith {
+                init: "".into(),
+                condition: "".into(),
+                step: "".into(),
+                body: Box::new(simple_command(&[":"])),
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "echo for in do done")
+                .unwrap(),
+            simple_command(&["echo", "for", "in", "do", "done"])
+        );
+        assert!(CommandParser::new()
+            .parse(&[], "for 1x in a; do :; done")
+            .is_err());
+        assert!(CommandParser::new()
+            .parse(&[], "for x in a; do :; ")
+            .is_err());
+        assert!(CommandParser::new()
+            .parse(&[], "for x in a do :; done")
+            .is_err());
+        assert!(CommandParser::new()
+            .parse(&[], "for (( i < 3 )); do :; done")
+            .is_err());
+    }
+
+    #[test]
+    fn test_select_command() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "select x in a 'b c'; do echo $x; break; done")
+                .unwrap(),
+            Command::Select {
+                var: "x".into(),
+                words: vec!["a".into(), "'b c'".into()],
+                body: Box::new(Command::Connection {
+                    first: Box::new(simple_command(&["echo", "$x"])),
+                    second: Box::new(simple_command(&["break"])),
+                    connector: Connector::Semicolon,
+                }),
+            }
+        );
+        assert_eq!(
+            CommandParser::new().parse(&[], "echo select").unwrap(),
+            simple_command(&["echo", "select"])
+        );
+        assert!(CommandParser::new()
+            .parse(&[], "select $x in a; do :; done")
+            .is_err());
+        assert!(CommandParser::new()
+            .parse(&[], "select x in a do :; done")
+            .is_err());
+    }
+
+    #[test]
+    fn test_while_command() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "while [ -f x ]; do echo y; done; echo z")
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(Command::While {
+                    condition: Box::new(simple_command(&["[", "-f", "x", "]"])),
+                    body: Box::new(simple_command(&["echo", "y"])),
+                }),
+                second: Box::new(simple_command(&["echo", "z"])),
+                connector: Connector::Semicolon,
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "until false; do break; done")
+                .unwrap(),
+            Command::Until {
+                condition: Box::new(simple_command(&["false"])),
+                body: Box::new(simple_command(&["break"])),
+            }
+        );
+        assert_eq!(
+            CommandParser::new().parse(&[], "echo while until").unwrap(),
+            simple_command(&["echo", "while", "until"])
+        );
+        assert!(CommandParser::new()
+            .parse(&[], "while true do echo; done")
+            .is_err());
+        assert!(CommandParser::new().parse(&[], "until true; done").is_err());
+    }
+
+    #[test]
+    fn test_case_command() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(
+                    &[],
+                    "case $x in a | 'b') echo ab; echo c;; (*) echo other;; esac"
+                )
+                .unwrap(),
+            Command::Case {
+                word: "$x".into(),
+                arms: vec![
+                    (
+                        vec!["a".into(), "'b'".into()],
+                        Command::Connection {
+                            first: Box::new(simple_command(&["echo", "ab"])),
+                            second: Box::new(simple_command(&["echo", "c"])),
+                            connector: Connector::Semicolon,
+                        }
+                    ),
+                    (vec!["*".into()], simple_command(&["echo", "other"])),
+                ],
+            }
+        );
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "case x in x) echo x; esac | cat")
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(Command::Case {
+                    word: "x".into(),
+                    arms: vec![(vec!["x".into()], simple_command(&["echo", "x"]))],
+                }),
+                second: Box::new(simple_command(&["cat"])),
+                connector: Connector::Pipe,
+            }
+        );
+        assert_eq!(
+            CommandParser::new().parse(&[], "case x in esac").unwrap(),
+            Command::Case {
+                word: "x".into(),
+                arms: vec![],
+            }
+        );
+        assert_eq!(
+            CommandParser::new().parse(&[], "echo case esac").unwrap(),
+            simple_command(&["echo", "case", "esac"])
+        );
+        assert!(CommandParser::new()
+            .parse(&[], "case x in x) echo x;; y) echo y;; z)")
+            .is_err());
+        assert!(CommandParser::new()
+            .parse(&[], "case x in x echo x;; esac")
+            .is_err());
+    }
+
+    #[test]
+    fn test_function_definition() {
+        let function = |name: &str, body, keyword| Command::FunctionDef {
+            name: name.into(),
+            body: Box::new(body),
+            keyword,
+        };
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "f() { echo $1; }; f a")
+                .unwrap(),
+            Command::Connection {
+                first: Box::new(function(
+                    "f",
+                    Command::Group {
+                        inner: Box::new(simple_command(&["echo", "$1"])),
+                    },
+                    false
+                )),
+                second: Box::new(simple_command(&["f", "a"])),
+                connector: Connector::Semicolon,
+            }
+        );
+        for input in &["function f { true; }", "function f() { true; }"] {
+            assert_eq!(
+                CommandParser::new().parse(&[], input).unwrap(),
+                function(
+                    "f",
+                    Command::Group {
+                        inner: Box::new(simple_command(&["true"])),
+                    },
+                    true
+                )
+            );
+        }
+        assert_eq!(
+            CommandParser::new().parse(&[], "f() (exit 1)").unwrap(),
+            function(
+                "f",
+                Command::Subshell(Box::new(simple_command(&["exit", "1"]))),
+                false
+            )
+        );
+        assert!(CommandParser::new().parse(&[], "f() echo").is_err());
+        assert!(CommandParser::new().parse(&[], "$f() { true; }").is_err());
+        assert!(CommandParser::new().parse(&[], "f(x) { true; }").is_err());
     }
 
     #[test]
     fn test_quotes() {
         assert_eq!(
             CommandParser::new()
-                .parse(">'out' 'echo' <in 'arg'",)
+                .parse(&[], ">'out' 'echo' <in 'arg'",)
                 .expect(r#">''out' 'echo' <in 'arg' should be valid"#,),
             Command::Simple {
-                words: vec!["echo".into(), "arg".into()],
-                redirects: vec![output_filename_redirection("out"), input_redirection("in"),],
+                words: vec!["'echo'".into(), "'arg'".into()],
+                redirects: vec![
+                    output_filename_redirection("'out'"),
+                    input_redirection("in"),
+                ],
                 background: false,
             }
         );
 
         assert_eq!(
             CommandParser::new()
-                .parse(">'out 1' echo 'arg arg arg'")
+                .parse(&[], ">'out 1' echo 'arg arg arg'")
                 .expect(r#"'>'out 1' echo 'arg arg arg'' should be valid"#),
             Command::Simple {
-                words: vec!["echo".into(), "arg arg arg".into()],
-                redirects: vec![output_filename_redirection("out 1")],
+                words: vec!["echo".into(), "'arg arg arg'".into()],
+                redirects: vec![output_filename_redirection("'out 1'")],
                 background: false,
             }
         );
 
         assert_eq!(
             CommandParser::new()
-                .parse(r#">"out" "echo" <in "arg""#)
+                .parse(&[], r#">"out" "echo" <in "arg""#)
                 .expect(r#"'>"out" "echo" <in "arg"' should ve valid"#),
             Command::Simple {
-                words: vec!["echo".into(), "arg".into()],
-                redirects: vec![output_filename_redirection("out"), input_redirection("in"),],
+                words: vec![r#""echo""#.into(), r#""arg""#.into()],
+                redirects: vec![
+                    output_filename_redirection(r#""out""#),
+                    input_redirection("in"),
+                ],
                 background: false,
             }
         );
 
-        assert!(CommandParser::new().parse("echo 'arg").is_err());
-        assert!(CommandParser::new().parse(r#"echo "arg"#).is_err());
+        assert!(CommandParser::new().parse(&[], "echo 'arg").is_err());
+        assert!(CommandParser::new().parse(&[], r#"echo "arg"#).is_err());
+    }
+
+    #[test]
+    fn test_split_quotes() {
+        assert_eq!(split_quotes("'a $b'"), (Quoting::Single, "a $b"));
+        assert_eq!(split_quotes(r#""a $b""#), (Quoting::Double, "a $b"));
+        assert_eq!(split_quotes(r#""'a'""#), (Quoting::Double, "'a'"));
+        assert_eq!(split_quotes("*.rs"), (Quoting::Unquoted, "*.rs"));
+        assert_eq!(split_quotes("'"), (Quoting::Unquoted, "'"));
+        assert_eq!(split_quotes(""), (Quoting::Unquoted, ""));
     }
 
     #[test]
     fn test_nested_quotes() {
         assert_eq!(
             CommandParser::new()
-                .parse(r#"echo '"arg"'"#)
+                .parse(&[], r#"echo '"arg"'"#)
                 .expect(r#"'echo '"arg"' should be valid"#),
-            simple_command(&["echo", r#""arg""#])
+            simple_command(&["echo", r#"'"arg"'"#])
         );
 
         assert_eq!(
             CommandParser::new()
-                .parse(r#"echo "'arg'""#)
+                .parse(&[], r#"echo "'arg'""#)
                 .expect(r#"'echo "'arg'"' should be valid"#),
-            simple_command(&["echo", "'arg'"])
+            simple_command(&["echo", r#""'arg'""#])
         );
 
         assert_eq!(
             CommandParser::new()
-                .parse(r#"echo '"arg"'"#)
+                .parse(&[], r#"echo '"arg"'"#)
                 .expect(r#"'echo '"arg"'' should be valid"#),
-            simple_command(&["echo", r#""arg""#])
+            simple_command(&["echo", r#"'"arg"'"#])
         );
 
         assert_eq!(
             CommandParser::new()
-                .parse(r#"echo "arg'""#)
+                .parse(&[], r#"echo "arg'""#)
                 .expect(r#"'echo "arg'""' should be valid"#),
-            simple_command(&["echo", r#"arg'"#])
+            simple_command(&["echo", r#""arg'""#])
         );
     }
 
@@ -541,9 +1350,33 @@ mod tests {
     fn test_quotes_allow_special_characters() {
         assert_eq!(
             CommandParser::new()
-                .parse(r#"echo '& ; echo |'"#,)
+                .parse(&[], r#"echo '& ; echo |'"#,)
                 .expect(r#"'echo '& ; echo |'' should be valid"#,),
-            simple_command(&["echo", r#"& ; echo |"#])
+            simple_command(&["echo", r#"'& ; echo |'"#])
+        );
+    }
+
+    #[test]
+    fn test_empty_quotes() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], r#"[ "" = '' ]"#)
+                .expect(r#"'[ "" = '' ]' should be valid"#),
+            simple_command(&["[", r#""""#, "=", "''", "]"])
+        );
+    }
+
+    #[test]
+    fn test_parameter_expansion_with_spaces() {
+        assert_eq!(
+            CommandParser::new()
+                .parse(&[], "echo ${name:-a default}; x")
+                .expect("'echo ${name:-a default}; x' should be valid"),
+            Command::Connection {
+                first: Box::new(simple_command(&["echo", "${name:-a default}"])),
+                second: Box::new(simple_command(&["x"])),
+                connector: Connector::Semicolon,
+            }
         );
     }
 }
@@ -0,0 +1,255 @@
+//! Here-documents, e.g. `<<EOF`
+//!
+//! The body of a here-document follows the line containing its operator, which a single-pass
+//! grammar cannot express. Instead, the bodies are removed from the input before it is parsed, and
+//! each operator is replaced with a `<<N` marker referring to the `N`th body.
+
+use super::ast::HereDoc;
+
+/// The characters that end an unquoted here-document delimiter.
+const DELIMITER_TERMINATORS: &str = "|;&<>()";
+
+/// A here-document operator, e.g. `<<-'EOF'`.
+#[derive(Debug, PartialEq)]
+struct Operator {
+    /// The byte range of the operator and its delimiter in the line.
+    start: usize,
+    end: usize,
+    /// The delimiter without quotes.
+    delimiter: String,
+    /// Set by `<<-`, which strips leading tabs from the body and the delimiter line.
+    strip_tabs: bool,
+    /// Quoting any part of the delimiter suppresses expansion in the body.
+    quoted: bool,
+}
+
+impl Operator {
+    fn is_delimiter(&self, line: &str) -> bool {
+        self.strip_line(line) == self.delimiter
+    }
+
+    fn strip_line<'a>(&self, line: &'a str) -> &'a str {
+        if self.strip_tabs {
+            line.trim_start_matches('\t')
+        } else {
+            line
+        }
+    }
+}
+
+/// Removes the bodies of the here-documents in `input`. Returns the remaining input, in which each
+/// operator is replaced with a `<<N` marker, and the bodies in order.
+pub fn extract_here_docs(input: &str) -> (String, Vec<HereDoc>) {
+    let mut lines = input.split('\n');
+    let mut command_lines = Vec::new();
+    let mut here_docs = Vec::new();
+    while let Some(line) = lines.next() {
+        let mut command_line = String::new();
+        let mut copied = 0;
+        for operator in find_operators(line) {
+            command_line.push_str(&line[copied..operator.start]);
+            command_line.push_str(&format!(" <<{} ", here_docs.len()));
+            copied = operator.end;
+
+            let mut body = String::new();
+            // Like bash, a here-document without its delimiter ends at the end of the input.
+            for body_line in lines.by_ref() {
+                if operator.is_delimiter(body_line) {
+                    break;
+                }
+                body.push_str(operator.strip_line(body_line));
+                body.push('\n');
+            }
+            here_docs.push(HereDoc {
+                body,
+                expand: !operator.quoted,
+            });
+        }
+        command_line.push_str(&line[copied..]);
+        command_lines.push(command_line);
+    }
+
+    (command_lines.join("\n"), here_docs)
+}
+
+/// Splits `script` into lines, keeping the bodies of here-documents with the line containing
+/// their operators. Returns each command with the line number it starts on.
+pub fn split_script(script: &str) -> Vec<(usize, String)> {
+    let mut lines = script.split('\n').enumerate();
+    let mut commands = Vec::new();
+    while let Some((i, line)) = lines.next() {
+        let mut command = line.to_string();
+        for operator in find_operators(line) {
+            for (_, body_line) in lines.by_ref() {
+                command.push('\n');
+                command.push_str(body_line);
+                if operator.is_delimiter(body_line) {
+                    break;
+                }
+            }
+        }
+        commands.push((i + 1, command));
+    }
+
+    commands
+}
+
+/// Finds the here-document operators in `line`, skipping quotes and arithmetic, e.g. `((1 << 2))`.
+fn find_operators(line: &str) -> Vec<Operator> {
+    let mut operators = Vec::new();
+    let mut quote = None;
+    let mut arithmetic_depth = 0;
+    let mut i = 0;
+    while let Some(c) = line[i..].chars().next() {
+        let rest = &line[i..];
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+        } else if c == '\'' || c == '"' {
+            quote = Some(c);
+        } else if rest.starts_with("((") {
+            arithmetic_depth += 1;
+            i += 2;
+            continue;
+        } else if rest.starts_with("))") && arithmetic_depth > 0 {
+            arithmetic_depth -= 1;
+            i += 2;
+            continue;
+        } else if rest.starts_with("<<<") {
+            // A here-string
+            i += 3;
+            continue;
+        } else if arithmetic_depth == 0 && rest.starts_with("<<") {
+            if let Some(operator) = parse_operator(line, i) {
+                i = operator.end;
+                operators.push(operator);
+                continue;
+            }
+        }
+        i += c.len_utf8();
+    }
+
+    operators
+}
+
+/// Parses the operator starting at `start` in `line`, which must start with `<<`.
+fn parse_operator(line: &str, start: usize) -> Option<Operator> {
+    let mut pos = start + 2;
+    let strip_tabs = line[pos..].starts_with('-');
+    if strip_tabs {
+        pos += 1;
+    }
+    pos += line[pos..].len() - line[pos..].trim_start_matches(&[' ', '\t'][..]).len();
+
+    let mut delimiter = String::new();
+    let mut quoted = false;
+    let mut quote = None;
+    for c in line[pos..].chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => delimiter.push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                quoted = true;
+            }
+            None if c.is_whitespace() || DELIMITER_TERMINATORS.contains(c) => break,
+            None => delimiter.push(c),
+        }
+        pos += c.len_utf8();
+    }
+
+    if delimiter.is_empty() && !quoted {
+        return None;
+    }
+
+    Some(Operator {
+        start,
+        end: pos,
+        delimiter,
+        strip_tabs,
+        quoted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn here_doc(body: &str, expand: bool) -> HereDoc {
+        HereDoc {
+            body: body.to_string(),
+            expand,
+        }
+    }
+
+    #[test]
+    fn test_find_operators() {
+        let operators = find_operators("cat <<EOF | grep -f - <<- 'END A'");
+        assert_eq!(
+            operators,
+            [
+                Operator {
+                    start: 4,
+                    end: 9,
+                    delimiter: "EOF".into(),
+                    strip_tabs: false,
+                    quoted: false,
+                },
+                Operator {
+                    start: 22,
+                    end: 33,
+                    delimiter: "END A".into(),
+                    strip_tabs: true,
+                    quoted: true,
+                },
+            ]
+        );
+
+        assert_eq!(find_operators("cat <<\"EOF\";")[0].delimiter, "EOF");
+        assert!(find_operators("echo '<<EOF' \"<<EOF\"").is_empty());
+        assert!(find_operators("(( x = 1 << 2 )); echo $((1<<2))").is_empty());
+        assert!(find_operators("cat <<<word").is_empty());
+        assert!(find_operators("cat <<").is_empty());
+    }
+
+    #[test]
+    fn test_extract_here_docs() {
+        assert_eq!(
+            extract_here_docs("echo a\necho b"),
+            ("echo a\necho b".into(), vec![])
+        );
+        assert_eq!(
+            extract_here_docs("cat <<EOF | tr a-z A-Z\nhello $USER\n  EOF\nEOF\necho done"),
+            (
+                "cat  <<0  | tr a-z A-Z\necho done".into(),
+                vec![here_doc("hello $USER\n  EOF\n", true)]
+            )
+        );
+        assert_eq!(
+            extract_here_docs("cat <<-'EOF' <<END\n\t\tindented\n\tEOF\nsecond\nEND"),
+            (
+                "cat  <<0   <<1 ".into(),
+                vec![here_doc("indented\n", false), here_doc("second\n", true)]
+            )
+        );
+        // A missing delimiter ends the body at the end of the input
+        assert_eq!(
+            extract_here_docs("cat <<EOF\nunterminated"),
+            ("cat  <<0 ".into(), vec![here_doc("unterminated\n", true)])
+        );
+    }
+
+    #[test]
+    fn test_split_script() {
+        assert_eq!(
+            split_script("echo a\ncat <<EOF\nbody\nEOF\necho b\n"),
+            [
+                (1, "echo a".to_string()),
+                (2, "cat <<EOF\nbody\nEOF".to_string()),
+                (5, "echo b".to_string()),
+                (6, "".to_string()),
+            ]
+        );
+    }
+}
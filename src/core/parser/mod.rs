@@ -6,7 +6,10 @@ use log::debug;
 use self::grammar::CommandParser;
 use crate::errors::{Error, Result};
 
+pub use self::here_doc::split_script;
+
 pub mod ast;
+mod here_doc;
 #[rustfmt::skip]
 lalrpop_mod!(#[allow(clippy::all, unused_qualifications)] grammar, "/core/parser/grammar.rs");
 
@@ -17,16 +20,10 @@ pub struct Command {
 }
 
 impl Command {
-    pub fn new(input: &str, inner: ast::Command) -> Self {
-        Self {
-            input: input.to_string(),
-            inner,
-        }
-    }
-
     pub fn parse(input: &str) -> Result<Self> {
+        let (command_line, here_docs) = here_doc::extract_here_docs(input);
         let result = CommandParser::new()
-            .parse(input)
+            .parse(&here_docs, &command_line)
             .map_err(|_| Error::syntax(input))
             .map(|inner| Command {
                 input: input.into(),
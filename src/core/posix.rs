@@ -0,0 +1,325 @@
+//! POSIX mode
+//!
+//! Rejects bsh extensions so that scripts written with `--posix` stay portable to other shells.
+
+use crate::{
+    core::{
+        parser::{
+            ast::{
+                split_quotes, visit::Visitor, ArrayAssignment, Command, Connector, Quoting,
+                Redirect,
+            },
+            Command as ParsedCommand,
+        },
+        variable_expansion::{self, WordPart},
+    },
+    errors::{Error, Result},
+};
+
+/// Builtins that are extensions to POSIX.
+const EXTENSION_BUILTINS: &[&str] = &["declare", "local"];
+
+/// Returns an error naming the first non-POSIX feature used by `command`.
+pub fn check_posix_compat(command: &Command) -> Result<()> {
+    PosixChecker.visit_command(command)
+}
+
+struct PosixChecker;
+
+impl Visitor<Result<()>> for PosixChecker {
+    fn visit_simple_command<S: AsRef<str>>(
+        &mut self,
+        words: &[S],
+        _redirects: &[Redirect],
+        _background: bool,
+    ) -> Result<()> {
+        if let Some(program) = words.first() {
+            let program = program.as_ref();
+            let feature = match program {
+                "alias" | "unalias" => Some("aliases".to_string()),
+                p if EXTENSION_BUILTINS.contains(&p) => Some(p.to_string()),
+                // POSIX `export` only has `-p`, e.g. `export -n` is an extension.
+                "export" => words[1..]
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .take_while(|arg| arg.starts_with('-') && *arg != "--")
+                    .find(|flag| *flag != "-p")
+                    .map(|flag| format!("export {}", flag)),
+                _ => None,
+            };
+            if let Some(feature) = feature {
+                return Err(Error::not_available_in_posix_mode(feature));
+            }
+        }
+
+        check_words(words)
+    }
+
+    fn visit_connection_command(
+        &mut self,
+        first: &Command,
+        second: &Command,
+        _connector: Connector,
+    ) -> Result<()> {
+        self.visit_command(first)?;
+        self.visit_command(second)
+    }
+
+    fn visit_arithmetic_command(&mut self, _expression: &str) -> Result<()> {
+        Err(Error::not_available_in_posix_mode("((...))"))
+    }
+
+    fn visit_double_bracket_command(&mut self, _expression: &str) -> Result<()> {
+        Err(Error::not_available_in_posix_mode("[[...]]"))
+    }
+
+    fn visit_subshell_command(&mut self, command: &Command) -> Result<()> {
+        self.visit_command(command)
+    }
+
+    fn visit_negated_command(&mut self, command: &Command) -> Result<()> {
+        self.visit_command(command)
+    }
+
+    fn visit_group_command(&mut self, command: &Command) -> Result<()> {
+        self.visit_command(command)
+    }
+
+    fn visit_if_command(
+        &mut self,
+        condition: &Command,
+        then_branch: &Command,
+        elif_branches: &[(Command, Command)],
+        else_branch: Option<&Command>,
+    ) -> Result<()> {
+        self.visit_command(condition)?;
+        self.visit_command(then_branch)?;
+        for (condition, branch) in elif_branches {
+            self.visit_command(condition)?;
+            self.visit_command(branch)?;
+        }
+        else_branch.map_or(Ok(()), |branch| self.visit_command(branch))
+    }
+
+    fn visit_for_in_command(&mut self, _var: &str, words: &[String], body: &Command) -> Result<()> {
+        check_words(words)?;
+        self.visit_command(body)
+    }
+
+    fn visit_for_arith_command(
+        &mut self,
+        _init: &str,
+        _condition: &str,
+        _step: &str,
+        _body: &Command,
+    ) -> Result<()> {
+        Err(Error::not_available_in_posix_mode("for ((...))"))
+    }
+
+    fn visit_while_command(&mut self, condition: &Command, body: &Command) -> Result<()> {
+        self.visit_command(condition)?;
+        self.visit_command(body)
+    }
+
+    fn visit_until_command(&mut self, condition: &Command, body: &Command) -> Result<()> {
+        self.visit_command(condition)?;
+        self.visit_command(body)
+    }
+
+    fn visit_case_command(&mut self, word: &str, arms: &[(Vec<String>, Command)]) -> Result<()> {
+        check_words(&[word])?;
+        for (patterns, command) in arms {
+            check_words(patterns)?;
+            self.visit_command(command)?;
+        }
+        Ok(())
+    }
+
+    fn visit_select_command(
+        &mut self,
+        _var: &str,
+        _words: &[String],
+        _body: &Command,
+    ) -> Result<()> {
+        Err(Error::not_available_in_posix_mode("select"))
+    }
+
+    fn visit_function_definition(
+        &mut self,
+        _name: &str,
+        body: &Command,
+        keyword: bool,
+    ) -> Result<()> {
+        if keyword {
+            return Err(Error::not_available_in_posix_mode("function keyword"));
+        }
+        self.visit_command(body)
+    }
+
+    fn visit_array_assignment(
+        &mut self,
+        _declaration: &[String],
+        _assignment: &ArrayAssignment,
+    ) -> Result<()> {
+        Err(Error::not_available_in_posix_mode("arrays"))
+    }
+
+    fn visit_command(&mut self, command: &Command) -> Result<()> {
+        match command {
+            Command::Simple {
+                ref words,
+                ref redirects,
+                background,
+            } => self.visit_simple_command(words, redirects, *background),
+            Command::Connection {
+                ref first,
+                ref second,
+                connector,
+            } => self.visit_connection_command(first, second, *connector),
+            Command::Arithmetic(ref expression) => self.visit_arithmetic_command(expression),
+            Command::DoubleBracket(ref expression) => self.visit_double_bracket_command(expression),
+            Command::Subshell(ref command) => self.visit_subshell_command(command),
+            Command::Negated { ref inner } => self.visit_negated_command(inner),
+            Command::Group { ref inner } => self.visit_group_command(inner),
+            Command::If {
+                ref condition,
+                ref then_branch,
+                ref elif_branches,
+                ref else_branch,
+            } => self.visit_if_command(
+                condition,
+                then_branch,
+                elif_branches,
+                else_branch.as_deref(),
+            ),
+            Command::ForIn {
+                ref var,
+                ref words,
+                ref body,
+            } => self.visit_for_in_command(var, words, body),
+            Command::ForArith {
+                ref init,
+                ref condition,
+                ref step,
+                ref body,
+            } => self.visit_for_arith_command(init, condition, step, body),
+            Command::While {
+                ref condition,
+                ref body,
+            } => self.visit_while_command(condition, body),
+            Command::Until {
+                ref condition,
+                ref body,
+            } => self.visit_until_command(condition, body),
+            Command::Case { ref word, ref arms } => self.visit_case_command(word, arms),
+            Command::Select {
+                ref var,
+                ref words,
+                ref body,
+            } => self.visit_select_command(var, words, body),
+            Command::FunctionDef {
+                ref name,
+                ref body,
+                keyword,
+            } => self.visit_function_definition(name, body, *keyword),
+            Command::ArrayAssignment {
+                ref declaration,
+                ref assignment,
+            } => self.visit_array_assignment(declaration, assignment),
+        }
+    }
+}
+
+/// Returns an error if `words` use a non-POSIX expansion, e.g. `$(...)`.
+///
+/// Words are split into substitutions the way they are expanded, so quoted text such as
+/// `'$(date)'` is allowed.
+fn check_words<S: AsRef<str>>(words: &[S]) -> Result<()> {
+    for word in words {
+        let (quoting, text) = split_quotes(word.as_ref());
+        if quoting == Quoting::Single {
+            continue;
+        }
+        let parts = variable_expansion::split_substitutions(text)
+            .unwrap_or_else(|| vec![WordPart::Text(text)]);
+        for part in parts {
+            match part {
+                WordPart::CommandSubstitution(_) => {
+                    return Err(Error::not_available_in_posix_mode("$(...)"));
+                }
+                // The command of a backquoted substitution is checked like any other.
+                WordPart::Backquoted(command) => {
+                    if let Ok(command) = ParsedCommand::parse(command) {
+                        check_posix_compat(&command.inner)?;
+                    }
+                }
+                WordPart::Text(text) if quoting == Quoting::Unquoted => {
+                    if text.contains("<(") || text.contains(">(") {
+                        return Err(Error::not_available_in_posix_mode("process substitution"));
+                    } else if is_brace_expansion(text) {
+                        return Err(Error::not_available_in_posix_mode("{...} brace expansion"));
+                    }
+                }
+                WordPart::Text(_) | WordPart::Arithmetic(_) => (),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if `word` contains a `{a,b}` or `{1..3}` brace expression.
+fn is_brace_expansion(word: &str) -> bool {
+    word.find('{')
+        .and_then(|start| {
+            word[start..]
+                .find('}')
+                .map(|end| &word[start + 1..start + end])
+        })
+        .map_or(false, |inner| inner.contains(',') || inner.contains(".."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &str) -> Result<()> {
+        check_posix_compat(&ParsedCommand::parse(input).unwrap().inner)
+    }
+
+    #[test]
+    fn test_posix_commands_allowed() {
+        assert!(check("echo foo | grep f && ls {} ; export FOO=bar").is_ok());
+        assert!(check("export -p; export -- FOO").is_ok());
+        assert!(check("echo `date`").is_ok());
+        assert!(check("echo '$(date)' \"<(x)\" '{a,b}' \"{1..3}\"").is_ok());
+        assert!(check("echo $((1 + 2)) \"$((1 + $((2))))\"").is_ok());
+        assert!(check("(cd /tmp; ls)").is_ok());
+        assert!(check("(echo foo; (( x > 1 )))").is_err());
+    }
+
+    #[test]
+    fn test_extensions_rejected() {
+        for input in &[
+            "alias ll=ls",
+            "function foo { true; }",
+            "[[ -n foo ]]",
+            "((1+2))",
+            "echo foo; (( x > 1 ))",
+            "declare -x FOO",
+            "declare FOO=bar",
+            "local FOO",
+            "echo foo && export -n FOO",
+            "export -p -f foo",
+            "echo $(date)",
+            "echo \"$((1 + 2)) $(date)\"",
+            "echo `echo $(date)`",
+            "echo {a,b}",
+            "echo {1..3}",
+            "select x in a; do break; done",
+            "arr=(a b)",
+        ] {
+            assert!(check(input).is_err(), "{} was allowed", input);
+        }
+    }
+}
@@ -1,135 +1,625 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    env,
     path::{Path, PathBuf},
+    process::{self, ExitStatus},
 };
 
-use crate::core::parser::ast::{visit::Visitor, Command, Connector, Redirect, Redirectee};
+use rand::Rng;
 
-pub fn expand_variables<I, P, K, V>(command: &Command, home_dir: Option<P>, vars: I) -> Command
+use crate::{
+    core::parameter_expansion::{self, StringOperation},
+    errors::{Error, Result},
+};
+
+/// The version of bash that bsh claims to be compatible with, see `$BASH_VERSION`.
+const BASH_VERSION: &str = "5.1.0(1)-release";
+
+/// Provides the values of variables during expansion.
+pub trait VariableLookup {
+    /// Returns the value of the scalar variable `name`.
+    fn lookup_variable(&self, name: &str) -> Option<String>;
+
+    /// Returns the elements of the indexed array `name`.
+    fn lookup_array(&self, name: &str) -> Option<&BTreeMap<usize, String>>;
+
+    /// Returns the associative array `name`.
+    fn lookup_assoc_array(&self, name: &str) -> Option<&HashMap<String, String>>;
+
+    /// Returns the last argument of the previous command, the value of `$_`.
+    fn lookup_last_argument(&self) -> Option<String>;
+
+    /// Returns the exit status of the previous command, the value of `$?`.
+    fn lookup_last_exit_status(&self) -> ExitStatus;
+
+    /// Returns the process ID of the most recent background job, the value of `$!`.
+    fn lookup_last_background_pid(&self) -> Option<u32>;
+
+    /// Returns the name of the shell or the script it runs, the value of `$0`.
+    fn lookup_shell_name(&self) -> String;
+
+    /// Returns the line number of the command being executed, the value of `$LINENO`.
+    fn lookup_line_number(&self) -> usize;
+
+    /// Returns the positional parameters, e.g. the arguments of a script, the values of `$1`,
+    /// `$2`, and so on.
+    fn lookup_positional_params(&self) -> &[String];
+
+    /// Returns the home directory of `user`, the value of `~user`.
+    fn lookup_user_home_dir(&self, user: &str) -> Option<PathBuf> {
+        user_home_dir(user)
+    }
+
+    /// Returns `true` if expanding an unset variable is an error, see `set -u`.
+    fn treats_unset_as_error(&self) -> bool {
+        false
+    }
+}
+
+/// Expands `~` and variables in `words`, including `${name[n]}`, `${name[@]}`, `${!name[@]}`,
+/// and `${#name[@]}` for arrays.
+///
+/// A word may expand to several words (e.g. `${name[@]}`) or to none at all.
+///
+/// Also expands `${name-word}`, `${name=word}`, `${name+word}` and `${name?word}`, and their
+/// variants with a colon (e.g. `${name:-word}`), which also treat an empty `name` as unset.
+/// `${name:=word}` assigns `word` to the environment variable `name`, and `${name:?word}` fails
+/// with `word` as the error message.
+pub fn expand_words<I, S, P, L>(words: I, home_dir: Option<P>, lookup: &L) -> Result<Vec<String>>
 where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
     P: AsRef<Path>,
-    I: IntoIterator<Item = (K, V)>,
-    K: AsRef<str>,
-    V: AsRef<str>,
+    L: VariableLookup + ?Sized,
 {
-    let mut variable_expander = VariableExpander::new(home_dir, vars);
-    variable_expander.visit_command(command)
+    let variable_expander = VariableExpander::new(home_dir, lookup);
+    let mut expanded = Vec::new();
+    for word in words {
+        expanded.extend(variable_expander.expand_variables_fields(word.as_ref())?);
+    }
+    Ok(expanded)
+}
+
+/// Expands `~` and variables in a single `word`, e.g. a redirect's filename.
+pub fn expand_word<P, L>(word: &str, home_dir: Option<P>, lookup: &L) -> Result<String>
+where
+    P: AsRef<Path>,
+    L: VariableLookup + ?Sized,
+{
+    VariableExpander::new(home_dir, lookup).expand_variables_word(word)
+}
+
+/// Expands variables anywhere in `text`, e.g. the body of a here-document, rather than only in
+/// whole words. A backslash quotes a following `$` or `\`.
+pub fn expand_text<L>(text: &str, lookup: &L) -> Result<String>
+where
+    L: VariableLookup + ?Sized,
+{
+    let variable_expander = VariableExpander::new(None::<&Path>, lookup);
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find(|c| c == '$' || c == '\\') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let next = rest[1..].chars().next();
+        let len = if rest.starts_with('\\') {
+            match next {
+                Some(c @ '$') | Some(c @ '\\') => {
+                    expanded.push(c);
+                    2
+                }
+                _ => {
+                    expanded.push('\\');
+                    1
+                }
+            }
+        } else {
+            let parameter_len = match next {
+                Some('{') => rest.find('}').map_or(0, |end| end + 1),
+                Some(c) if c.is_ascii_digit() || "?$!#@*".contains(c) => 2,
+                Some(c) if c.is_ascii_alphabetic() || c == '_' => rest[1..]
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .map_or(rest.len(), |end| end + 1),
+                _ => 0,
+            };
+            if parameter_len == 0 {
+                expanded.push('$');
+                1
+            } else {
+                expanded
+                    .push_str(&variable_expander.expand_variables_word(&rest[..parameter_len])?);
+                parameter_len
+            }
+        };
+        rest = &rest[len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expands a leading `~` and variables anywhere in `word`, as in a double-quoted string, e.g. the
+/// word of a here-string.
+pub fn expand_string<P, L>(word: &str, home_dir: Option<P>, lookup: &L) -> Result<String>
+where
+    P: AsRef<Path>,
+    L: VariableLookup + ?Sized,
+{
+    match home_dir {
+        Some(home_dir) if word == "~" || word.starts_with("~/") => Ok(format!(
+            "{}{}",
+            home_dir.as_ref().to_string_lossy(),
+            expand_text(&word[1..], lookup)?
+        )),
+        _ => expand_text(word, lookup),
+    }
+}
+
+/// A part of a word containing substitutions, see [`split_substitutions`].
+#[derive(Debug, PartialEq)]
+pub enum WordPart<'a> {
+    Text(&'a str),
+    /// The command of a command substitution, e.g. `date` for `$(date)`.
+    CommandSubstitution(&'a str),
+    /// The command of a backquoted command substitution, e.g. `date` for `` `date` ``.
+    Backquoted(&'a str),
+    /// The expression of an arithmetic expansion, e.g. ` n + 1 ` for `$(( n + 1 ))`.
+    Arithmetic(&'a str),
+}
+
+/// Splits `word` into text, command substitutions and arithmetic expansions. Returns `None` if
+/// `word` contains neither.
+pub fn split_substitutions(word: &str) -> Option<Vec<WordPart<'_>>> {
+    let mut parts = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < word.len() {
+        let rest = &word[i..];
+        let substitution = if let Some(parenthesized) = rest.strip_prefix("$(") {
+            find_arithmetic_expansion(parenthesized)
+                .map(|end| (WordPart::Arithmetic(&parenthesized[1..end]), end + 4))
+                .or_else(|| {
+                    find_closing_paren(parenthesized).map(|end| {
+                        (
+                            WordPart::CommandSubstitution(&parenthesized[..end]),
+                            end + 3,
+                        )
+                    })
+                })
+        } else if let Some(backquoted) = rest.strip_prefix('`') {
+            backquoted
+                .find('`')
+                .map(|end| (WordPart::Backquoted(&backquoted[..end]), end + 2))
+        } else {
+            None
+        };
+        match substitution {
+            Some((part, len)) => {
+                if text_start < i {
+                    parts.push(WordPart::Text(&word[text_start..i]));
+                }
+                parts.push(part);
+                i += len;
+                text_start = i;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+
+    if parts.is_empty() {
+        return None;
+    }
+    if text_start < word.len() {
+        parts.push(WordPart::Text(&word[text_start..]));
+    }
+    Some(parts)
+}
+
+/// Returns the index of the `)` that closes the parenthesis starting `s` if it is immediately
+/// followed by another `)`, e.g. in `(1 + 2))` following `$(`.
+fn find_arithmetic_expansion(s: &str) -> Option<usize> {
+    let end = find_closing_paren(s.strip_prefix('(')?)? + 1;
+    if s[end + 1..].starts_with(')') {
+        Some(end)
+    } else {
+        None
+    }
 }
 
-struct VariableExpander {
+/// Returns the index of the `)` that closes an already opened parenthesis in `s`.
+fn find_closing_paren(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+struct VariableExpander<'a, L: ?Sized> {
     home_dir: Option<PathBuf>,
-    vars: HashMap<String, String>,
+    lookup: &'a L,
 }
 
-impl VariableExpander {
-    fn new<P, I, K, V>(home_dir: Option<P>, vars: I) -> Self
-    where
-        P: AsRef<Path>,
-        I: IntoIterator<Item = (K, V)>,
-        K: AsRef<str>,
-        V: AsRef<str>,
-    {
+impl<'a, L: VariableLookup + ?Sized> VariableExpander<'a, L> {
+    fn new<P: AsRef<Path>>(home_dir: Option<P>, lookup: &'a L) -> Self {
         Self {
             home_dir: home_dir.map(|p| p.as_ref().to_path_buf()),
-            vars: vars
+            lookup,
+        }
+    }
+
+    /// Expands shell and environment variables in command parts.
+    fn expand_variables_word(&self, s: &str) -> Result<String> {
+        // TODO: expand tilde in any part of the word
+        let expansion = match s {
+            "~" => self
+                .home_dir
+                .as_ref()
+                .map(|p| p.to_string_lossy().into_owned()),
+            // Like bash, `~user` is left unchanged if there is no such user.
+            s if s.len() > 1 && s.starts_with('~') && !s.contains('/') => Some(
+                self.lookup
+                    .lookup_user_home_dir(&s[1..])
+                    .map_or_else(|| s.to_string(), |p| p.to_string_lossy().into_owned()),
+            ),
+            s => match strip_braces(s) {
+                Some(parameter) => self.expand_braces(parameter)?,
+                None if s.starts_with('$') => {
+                    self.check_unset(&s[1..], self.lookup_variable(&s[1..]))?
+                }
+                None => Some(s.to_string()),
+            },
+        };
+
+        Ok(expansion.unwrap_or_else(|| "".to_string()))
+    }
+
+    /// Expands a command word, which may produce multiple words, e.g. `${name[@]}`.
+    fn expand_variables_fields(&self, s: &str) -> Result<Vec<String>> {
+        if s == "$@" || s == "${@}" {
+            return Ok(self.lookup.lookup_positional_params().to_vec());
+        }
+        match strip_braces(s).and_then(split_all_elements) {
+            Some((name, keys)) => Ok(self.array_fields(name, keys)),
+            None => Ok(vec![self.expand_variables_word(s)?]),
+        }
+    }
+
+    /// Expands the contents of a `${...}` parameter expansion, including its operators.
+    fn expand_braces(&self, parameter: &str) -> Result<Option<String>> {
+        if let Some((name, operator, word)) = split_operator(parameter) {
+            return self.expand_operator(name, operator, word);
+        }
+        if let Some((name, operation)) = split_string_operation(parameter) {
+            let operation = operation.expand_operands(|word| expand_text(word, self.lookup))?;
+            let value = self.expand_parameter(name).unwrap_or_default();
+            return operation.apply(&value).map(Some);
+        }
+        self.check_unset(parameter, self.expand_parameter(parameter))
+    }
+
+    /// Fails if `value`, the expansion of `parameter`, is unset and unset variables are errors.
+    fn check_unset(&self, parameter: &str, value: Option<String>) -> Result<Option<String>> {
+        if value.is_none() && self.lookup.treats_unset_as_error() {
+            return Err(Error::parameter_null_or_unset(
+                parameter,
+                "unbound variable",
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Expands `${name<operator>word}`, e.g. `${name:-default}`, where `operator` is one of `-`,
+    /// `=`, `+` or `?`, optionally preceded by a colon.
+    ///
+    /// Without a colon, only an unset `name` is replaced. With a colon, an empty `name` is too.
+    fn expand_operator(&self, name: &str, operator: &str, word: &str) -> Result<Option<String>> {
+        let value = self.expand_parameter(name);
+        let (check_null, operator) = match operator.strip_prefix(':') {
+            Some(operator) => (true, operator),
+            None => (false, operator),
+        };
+        let is_unset = match value {
+            Some(ref value) => check_null && value.is_empty(),
+            None => true,
+        };
+        let expanded = match operator {
+            "-" if is_unset => Some(expand_string(word, self.home_dir.as_ref(), self.lookup)?),
+            "=" if is_unset => {
+                if !is_identifier(name) {
+                    return Err(Error::parameter_null_or_unset(
+                        name,
+                        "cannot assign in this way",
+                    ));
+                }
+                let word = expand_string(word, self.home_dir.as_ref(), self.lookup)?;
+                env::set_var(name, &word);
+                Some(word)
+            }
+            "+" if is_unset => None,
+            "+" => Some(expand_string(word, self.home_dir.as_ref(), self.lookup)?),
+            "?" if is_unset => {
+                let message = match word {
+                    "" if check_null => "parameter null or not set".to_string(),
+                    "" => "parameter not set".to_string(),
+                    word => expand_string(word, self.home_dir.as_ref(), self.lookup)?,
+                };
+                return Err(Error::parameter_null_or_unset(name, message));
+            }
+            _ => value,
+        };
+        Ok(expanded)
+    }
+
+    /// Expands the contents of a `${...}` parameter expansion.
+    fn expand_parameter(&self, parameter: &str) -> Option<String> {
+        if let Some(name) = parameter.strip_prefix('#') {
+            let len = match split_all_elements(name) {
+                Some((name, false)) => self.array_fields(name, false).len(),
+                _ => self
+                    .lookup_variable(name)
+                    .map_or(0, |value| value.chars().count()),
+            };
+            return Some(len.to_string());
+        }
+
+        if let Some((name, keys)) = split_all_elements(parameter) {
+            return Some(self.array_fields(name, keys).join(" "));
+        }
+
+        match split_subscript(parameter) {
+            Some((name, key)) if self.lookup.lookup_assoc_array(name).is_some() => self
+                .lookup
+                .lookup_assoc_array(name)
+                .and_then(|assoc_array| assoc_array.get(key).cloned()),
+            Some((name, index)) => {
+                let index = index.parse::<usize>().ok()?;
+                match self.lookup.lookup_array(name) {
+                    Some(array) => array.get(&index).cloned(),
+                    // Like bash, a scalar variable is treated as an array with one element.
+                    None if index == 0 => self.lookup_variable(name),
+                    None => None,
+                }
+            }
+            None => self.lookup_variable(parameter),
+        }
+    }
+
+    /// Returns the value of the scalar variable `name`, including special variables.
+    fn lookup_variable(&self, name: &str) -> Option<String> {
+        match name {
+            "_" => self.lookup.lookup_last_argument(),
+            "?" => Some(exit_code(self.lookup.lookup_last_exit_status()).to_string()),
+            "$" => Some(process::id().to_string()),
+            "!" => self
+                .lookup
+                .lookup_last_background_pid()
+                .map(|pid| pid.to_string()),
+            "0" => Some(self.lookup.lookup_shell_name()),
+            "LINENO" => Some(self.lookup.lookup_line_number().to_string()),
+            "#" => Some(self.lookup.lookup_positional_params().len().to_string()),
+            "@" => Some(self.lookup.lookup_positional_params().join(" ")),
+            // Joined with the first character of `$IFS`, or a space if it is unset.
+            "*" => {
+                let separator = self
+                    .lookup
+                    .lookup_variable("IFS")
+                    .map_or(Some(' '), |ifs| ifs.chars().next());
+                let params = self.lookup.lookup_positional_params();
+                Some(params.join(&separator.map(String::from).unwrap_or_default()))
+            }
+            name if name.bytes().all(|b| b.is_ascii_digit()) => {
+                let index = name.parse::<usize>().ok()?.checked_sub(1)?;
+                self.lookup.lookup_positional_params().get(index).cloned()
+            }
+            // Like bash, a random integer between 0 and 32767.
+            "RANDOM" => Some(rand::thread_rng().gen_range(0..32768).to_string()),
+            // Unlike the other special variables, `$SHELL` is usually inherited from the
+            // environment, so it is only set if missing.
+            "SHELL" => self.lookup.lookup_variable(name).or_else(current_exe_path),
+            name => expand_version_variable(name)
+                .or_else(|| self.lookup.lookup_variable(name))
+                // Like bash, an array expands to its first element, e.g. `$arr` to `${arr[0]}`.
+                .or_else(|| match self.lookup.lookup_assoc_array(name) {
+                    Some(assoc_array) => assoc_array.get("0").cloned(),
+                    None => self.lookup.lookup_array(name)?.get(&0).cloned(),
+                }),
+        }
+    }
+
+    /// Returns the elements of the array `name`, or its keys if `keys` is `true`.
+    ///
+    /// Associative arrays are ordered by key so that keys and values expand in the same order.
+    fn array_fields(&self, name: &str, keys: bool) -> Vec<String> {
+        if let Some(assoc_array) = self.lookup.lookup_assoc_array(name) {
+            let mut entries: Vec<(&String, &String)> = assoc_array.iter().collect();
+            entries.sort();
+            entries
                 .into_iter()
-                .map(|(k, v)| (k.as_ref().to_string(), v.as_ref().to_string()))
-                .collect(),
-        }
-    }
-
-    fn expand_variables_word(&self, s: &str) -> String {
-        expand_variables_word(s, &self.home_dir, &self.vars)
-    }
-}
-
-impl Visitor<Command> for VariableExpander {
-    fn visit_simple_command<S: AsRef<str>>(
-        &mut self,
-        words: &[S],
-        redirects: &[Redirect],
-        background: bool,
-    ) -> Command {
-        Command::Simple {
-            words: words
-                .iter()
-                .map(|w| self.expand_variables_word(w.as_ref()))
-                .collect(),
-            redirects: redirects
-                .iter()
-                .map(|r| Redirect {
-                    redirector: match r.redirector {
-                        Some(Redirectee::Filename(ref filename)) => {
-                            Some(Redirectee::Filename(self.expand_variables_word(filename)))
-                        }
-                        ref other => other.clone(),
-                    },
-                    instruction: r.instruction,
-                    redirectee: match r.redirectee {
-                        Redirectee::Filename(ref filename) => {
-                            Redirectee::Filename(self.expand_variables_word(filename))
-                        }
-                        ref other => other.clone(),
-                    },
-                })
-                .collect(),
-            background,
+                .map(|(key, value)| if keys { key.clone() } else { value.clone() })
+                .collect()
+        } else if let Some(array) = self.lookup.lookup_array(name) {
+            if keys {
+                array.keys().map(|i| i.to_string()).collect()
+            } else {
+                array.values().cloned().collect()
+            }
+        } else {
+            Vec::new()
         }
     }
+}
+
+/// Returns the home directory of `user` from the password database.
+#[cfg(unix)]
+fn user_home_dir(user: &str) -> Option<PathBuf> {
+    nix::unistd::User::from_name(user)
+        .ok()
+        .flatten()
+        .map(|user| user.dir)
+}
+
+#[cfg(windows)]
+fn user_home_dir(_user: &str) -> Option<PathBuf> {
+    // TODO (#22): Support Windows
+    None
+}
 
-    fn visit_connection_command(
-        &mut self,
-        first: &Command,
-        second: &Command,
-        connector: Connector,
-    ) -> Command {
-        Command::Connection {
-            first: Box::new(self.visit_command(first)),
-            second: Box::new(self.visit_command(second)),
-            connector,
+/// Returns the value of a variable describing the shell, e.g. `$BASH_VERSION`, for scripts that
+/// check which shell they are running in.
+///
+/// `$BASH_VERSINFO_n` is the nth component of `$BASH_VERSION`, like bash's `BASH_VERSINFO` array.
+fn expand_version_variable(name: &str) -> Option<String> {
+    match name {
+        "BASH_VERSION" => Some(BASH_VERSION.to_string()),
+        "BSH_VERSION" => Some(env!("CARGO_PKG_VERSION").to_string()),
+        "BASH" => current_exe_path(),
+        name => {
+            let index = name.strip_prefix("BASH_VERSINFO_")?.parse::<usize>().ok()?;
+            bash_versinfo().get(index).cloned()
         }
     }
+}
+
+/// Splits `$BASH_VERSION` into its major, minor, and patch versions, build, and release status,
+/// followed by the machine type.
+fn bash_versinfo() -> Vec<String> {
+    let (version, status) = BASH_VERSION.split_once('-').unwrap_or((BASH_VERSION, ""));
+    let (version, build) = version
+        .strip_suffix(')')
+        .and_then(|version| version.split_once('('))
+        .unwrap_or((version, ""));
+    version
+        .split('.')
+        .chain(vec![build, status])
+        .map(str::to_string)
+        .chain(Some(format!("{}-{}", env::consts::ARCH, env::consts::OS)))
+        .collect()
+}
+
+/// Returns the path of the running bsh binary, see `$BASH` and `$SHELL`.
+fn current_exe_path() -> Option<String> {
+    env::current_exe()
+        .ok()
+        .map(|path| path.to_string_lossy().into_owned())
+}
 
-    fn visit_command(&mut self, command: &Command) -> Command {
-        match command {
-            Command::Simple {
-                ref words,
-                ref redirects,
-                background,
-            } => self.visit_simple_command(words, redirects, *background),
-            Command::Connection {
-                ref first,
-                ref second,
-                connector,
-            } => self.visit_connection_command(first, second, *connector),
+/// Returns the value of the pseudo-variable `$PIPESTATUS`, the space-separated exit codes of the
+/// last pipeline's processes, or `$PIPESTATUS_n`, the exit code of its nth process.
+pub(crate) fn expand_pipeline_status(name: &str, pipeline_status: &[ExitStatus]) -> Option<String> {
+    if name == "PIPESTATUS" {
+        let codes: Vec<String> = pipeline_status
+            .iter()
+            .map(|status| exit_code(*status).to_string())
+            .collect();
+        return Some(codes.join(" "));
+    }
+
+    let index = name.strip_prefix("PIPESTATUS_")?.parse::<usize>().ok()?;
+    pipeline_status
+        .get(index)
+        .map(|status| exit_code(*status).to_string())
+}
+
+/// Returns the exit code of a process, which is 128 plus the signal number if it was killed by a
+/// signal.
+pub(crate) fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
         }
     }
+
+    status.code().unwrap_or(1)
 }
 
-/// Expands shell and environment variables in command parts.
-fn expand_variables_word<P>(s: &str, home_dir: &Option<P>, vars: &HashMap<String, String>) -> String
-where
-    P: AsRef<Path>,
-{
-    // TODO: expand tilde in any part of the word
-    let expansion = match s {
-        "~" => home_dir
-            .as_ref()
-            .map(|p| p.as_ref().to_string_lossy().into_owned()),
-        s if s.starts_with('$') => vars.get(&s[1..].to_string()).cloned(),
-        _ => Some(s.to_string()),
+/// Returns the contents of `s` if it is a `${...}` parameter expansion.
+fn strip_braces(s: &str) -> Option<&str> {
+    s.strip_prefix("${").and_then(|s| s.strip_suffix('}'))
+}
+
+/// Splits `name:-word` and the other operators of [`VariableExpander::expand_operator`] into the
+/// parameter, the operator and the word. `name` may be a special parameter, e.g. `1` or `@`, or
+/// an array element, e.g. `name[1]`.
+fn split_operator(s: &str) -> Option<(&str, &str, &str)> {
+    let name_len = parameter_name_len(s)?;
+    let rest = &s[name_len..];
+    let operator_len = if rest.starts_with(':') { 2 } else { 1 };
+    let operator = rest.get(..operator_len)?;
+    if operator.ends_with(|c| "-=+?".contains(c)) {
+        Some((&s[..name_len], operator, &rest[operator_len..]))
+    } else {
+        None
+    }
+}
+
+/// Splits `name%pattern` and the other operators of [`parameter_expansion`] into the parameter
+/// and the operation.
+fn split_string_operation(s: &str) -> Option<(&str, StringOperation)> {
+    let name_len = parameter_name_len(s)?;
+    parameter_expansion::parse(&s[name_len..]).map(|operation| (&s[..name_len], operation))
+}
+
+/// Returns the length of the parameter name at the start of `s`, e.g. `name`, `1`, `@` or
+/// `name[1]`.
+fn parameter_name_len(s: &str) -> Option<usize> {
+    let name_len = match s.chars().next()? {
+        c if c.is_ascii_digit() => s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()),
+        c if "?$!@*".contains(c) => 1,
+        _ => s
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(s.len()),
+    };
+    let name_len = if s[name_len..].starts_with('[') {
+        name_len + s[name_len..].find(']')? + 1
+    } else {
+        name_len
     };
+    if name_len == 0 {
+        None
+    } else {
+        Some(name_len)
+    }
+}
 
-    expansion.unwrap_or_else(|| "".to_string())
+/// Splits `name[subscript]` into its name and subscript.
+pub(crate) fn split_subscript(s: &str) -> Option<(&str, &str)> {
+    let open = s.find('[')?;
+    s[open + 1..]
+        .strip_suffix(']')
+        .map(|subscript| (&s[..open], subscript))
+}
+
+/// Parses `name[@]` or `!name[@]` into the array name and whether its keys were requested.
+fn split_all_elements(s: &str) -> Option<(&str, bool)> {
+    let (s, keys) = match s.strip_prefix('!') {
+        Some(s) => (s, true),
+        None => (s, false),
+    };
+    match split_subscript(s) {
+        Some((name, "@")) | Some((name, "*")) if is_identifier(name) => Some((name, keys)),
+        _ => None,
+    }
+}
+
+/// Returns `true` if `s` is a valid variable name.
+pub(crate) fn is_identifier(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use std::iter;
-
-    use crate::core::parser::ast::{Command, RedirectInstruction, Redirectee};
+    use crate::util::BshExitStatusExt;
 
     macro_rules! generate_unique_env_key {
         () => {
@@ -137,67 +627,570 @@ mod tests {
         };
     }
 
+    #[derive(Default)]
+    struct TestVariables {
+        vars: HashMap<String, String>,
+        arrays: HashMap<String, BTreeMap<usize, String>>,
+        assoc_arrays: HashMap<String, HashMap<String, String>>,
+        last_arg: Option<String>,
+        last_exit_code: i32,
+        last_background_pid: Option<u32>,
+        line_number: usize,
+        positional_params: Vec<String>,
+        user_home_dirs: HashMap<String, String>,
+    }
+
+    impl VariableLookup for TestVariables {
+        fn lookup_variable(&self, name: &str) -> Option<String> {
+            self.vars.get(name).cloned()
+        }
+
+        fn lookup_array(&self, name: &str) -> Option<&BTreeMap<usize, String>> {
+            self.arrays.get(name)
+        }
+
+        fn lookup_assoc_array(&self, name: &str) -> Option<&HashMap<String, String>> {
+            self.assoc_arrays.get(name)
+        }
+
+        fn lookup_last_argument(&self) -> Option<String> {
+            self.last_arg.clone()
+        }
+
+        fn lookup_last_exit_status(&self) -> ExitStatus {
+            ExitStatus::from_status(self.last_exit_code)
+        }
+
+        fn lookup_last_background_pid(&self) -> Option<u32> {
+            self.last_background_pid
+        }
+
+        fn lookup_shell_name(&self) -> String {
+            "bsh".to_string()
+        }
+
+        fn lookup_line_number(&self) -> usize {
+            self.line_number
+        }
+
+        fn lookup_positional_params(&self) -> &[String] {
+            &self.positional_params
+        }
+
+        fn lookup_user_home_dir(&self, user: &str) -> Option<PathBuf> {
+            self.user_home_dirs.get(user).map(PathBuf::from)
+        }
+    }
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn array(words: &[&str]) -> BTreeMap<usize, String> {
+        words
+            .iter()
+            .map(|word| word.to_string())
+            .enumerate()
+            .collect()
+    }
+
     #[test]
     fn test_home_dir_expansion() {
-        let command = Command::Simple {
-            words: vec!["cmd1".to_string(), "~".to_string()],
-            redirects: vec![Redirect {
-                redirector: None,
-                instruction: RedirectInstruction::Output,
-                redirectee: Redirectee::Filename("~".to_string()),
-            }],
-            background: false,
+        let expected_home_dir = "MockHomeDir".to_string();
+        let variables = TestVariables::default();
+        assert_eq!(
+            expand_words(&["cmd1", "~"], Some(&expected_home_dir), &variables).unwrap(),
+            vec!["cmd1".to_string(), expected_home_dir.clone()]
+        );
+        assert_eq!(
+            expand_word("~", Some(&expected_home_dir), &variables).unwrap(),
+            expected_home_dir
+        );
+    }
+
+    #[test]
+    fn test_user_home_dir_expansion() {
+        let mut variables = TestVariables::default();
+        variables
+            .user_home_dirs
+            .insert("alice".to_string(), "/home/alice".to_string());
+        let expand = |word: &str, variables: &TestVariables| {
+            expand_word(word, Some("MockHomeDir"), variables).unwrap()
         };
 
-        let expected_home_dir = "MockHomeDir".to_string();
+        assert_eq!(expand("~alice", &variables), "/home/alice");
+        assert_eq!(expand("~bob", &variables), "~bob");
+        assert_eq!(expand("~alice/dir", &variables), "~alice/dir");
+        assert_eq!(expand("a~alice", &variables), "a~alice");
         assert_eq!(
-            expand_variables(
-                &command,
-                Some(&expected_home_dir),
-                iter::empty::<(String, String)>()
-            ),
-            Command::Simple {
-                words: vec!["cmd1".to_string(), expected_home_dir.clone()],
-                redirects: vec![Redirect {
-                    redirector: None,
-                    instruction: RedirectInstruction::Output,
-                    redirectee: Redirectee::Filename(expected_home_dir)
-                }],
-                background: false,
-            }
+            expand_words(&["ls", "~", "~alice"], Some("MockHomeDir"), &variables).unwrap(),
+            strings(&["ls", "MockHomeDir", "/home/alice"])
         );
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_user_home_dir_lookup() {
+        let user = nix::unistd::User::from_uid(nix::unistd::getuid())
+            .unwrap()
+            .unwrap();
+        assert_eq!(user_home_dir(&user.name), Some(user.dir));
+        assert_eq!(user_home_dir("bsh-no-such-user"), None);
+    }
+
     #[test]
     fn test_env_var_expansion() {
         let key = generate_unique_env_key!();
         let value = "test".to_string();
-        let command = Command::Simple {
-            words: vec!["cmd1".to_string(), format!("${}", key)],
-            redirects: vec![Redirect {
-                redirector: None,
-                instruction: RedirectInstruction::Output,
-                redirectee: Redirectee::Filename(format!("${}", key)),
-            }],
-            background: false,
+        let mut variables = TestVariables::default();
+        variables.vars.insert(key.clone(), value.clone());
+
+        assert_eq!(
+            expand_words(
+                &["cmd1".to_string(), format!("${}", key)],
+                None::<PathBuf>,
+                &variables
+            )
+            .unwrap(),
+            vec!["cmd1".to_string(), value.clone()]
+        );
+        assert_eq!(
+            expand_word(&format!("${}", key), None::<PathBuf>, &variables).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn test_text_expansion() {
+        let mut variables = TestVariables::default();
+        variables.vars.insert("name".to_string(), "bsh".to_string());
+        variables.positional_params = strings(&["first"]);
+        variables
+            .arrays
+            .insert("arr".to_string(), array(&["a", "b"]));
+
+        assert_eq!(
+            expand_text("hello $name, ${name}!\n$missing.", &variables).unwrap(),
+            "hello bsh, bsh!\n."
+        );
+        assert_eq!(
+            expand_text("$1st ${arr[1]} ${#arr[@]}", &variables).unwrap(),
+            "firstst b 2"
+        );
+        assert_eq!(
+            expand_text("\\$name \\\\$name \\n $ $- ${name", &variables).unwrap(),
+            "$name \\bsh \\n $ $- ${name"
+        );
+    }
+
+    #[test]
+    fn test_string_expansion() {
+        let mut variables = TestVariables::default();
+        variables.vars.insert("name".to_string(), "bsh".to_string());
+        let home_dir = Some("MockHomeDir");
+
+        assert_eq!(
+            expand_string("~", home_dir, &variables).unwrap(),
+            "MockHomeDir"
+        );
+        assert_eq!(
+            expand_string("~/$name dir", home_dir, &variables).unwrap(),
+            "MockHomeDir/bsh dir"
+        );
+        assert_eq!(
+            expand_string("a ~ $name", home_dir, &variables).unwrap(),
+            "a ~ bsh"
+        );
+        assert_eq!(
+            expand_string("~", None::<PathBuf>, &variables).unwrap(),
+            "~"
+        );
+    }
+
+    #[test]
+    fn test_split_substitutions() {
+        use WordPart::*;
+
+        assert_eq!(split_substitutions("$HOME"), None);
+        assert_eq!(
+            split_substitutions("$(date)"),
+            Some(vec![CommandSubstitution("date")])
+        );
+        assert_eq!(
+            split_substitutions("a$(echo $(whoami))b`pwd`"),
+            Some(vec![
+                Text("a"),
+                CommandSubstitution("echo $(whoami)"),
+                Text("b"),
+                Backquoted("pwd"),
+            ])
+        );
+        assert_eq!(
+            split_substitutions("`echo (a) b`$"),
+            Some(vec![Backquoted("echo (a) b"), Text("$")])
+        );
+        assert_eq!(split_substitutions("$(unterminated `"), None);
+        assert_eq!(
+            split_substitutions("n$(( (n + 1) * 2 ))"),
+            Some(vec![Text("n"), Arithmetic(" (n + 1) * 2 ")])
+        );
+        assert_eq!(
+            split_substitutions("$((echo a) | cat)"),
+            Some(vec![CommandSubstitution("(echo a) | cat")])
+        );
+    }
+
+    #[test]
+    fn test_special_variable_expansion() {
+        let mut variables = TestVariables {
+            last_exit_code: 3,
+            line_number: 7,
+            ..Default::default()
+        };
+        let expand = |word: &str, variables: &TestVariables| {
+            expand_word(word, None::<PathBuf>, variables).unwrap()
         };
 
-        let vars = [(key, value.clone())];
+        assert_eq!(expand("$?", &variables), "3");
+        assert_eq!(expand("$$", &variables), process::id().to_string());
+        assert_eq!(expand("$!", &variables), "");
+        assert_eq!(expand("$0", &variables), "bsh");
+        assert_eq!(expand("${LINENO}", &variables), "7");
         assert_eq!(
-            expand_variables(
-                &command,
+            expand_text("$0:$LINENO: status $?", &variables).unwrap(),
+            "bsh:7: status 3"
+        );
+        for _ in 0..100 {
+            let random = expand("$RANDOM", &variables).parse::<u32>().unwrap();
+            assert!(random < 32768);
+        }
+
+        variables.last_background_pid = Some(1234);
+        assert_eq!(expand("$!", &variables), "1234");
+    }
+
+    #[test]
+    fn test_positional_parameter_expansion() {
+        let mut variables = TestVariables {
+            positional_params: strings(&["a", "b c", "d", "e", "f", "g", "h", "i", "j", "k"]),
+            ..Default::default()
+        };
+        let expand = |word: &str, variables: &TestVariables| {
+            expand_word(word, None::<PathBuf>, variables).unwrap()
+        };
+
+        assert_eq!(expand("$1", &variables), "a");
+        assert_eq!(expand("$2", &variables), "b c");
+        assert_eq!(expand("${10}", &variables), "k");
+        assert_eq!(expand("$11", &variables), "");
+        assert_eq!(expand("$#", &variables), "10");
+        assert_eq!(
+            expand_words(&["$@"], None::<PathBuf>, &variables).unwrap(),
+            strings(&["a", "b c", "d", "e", "f", "g", "h", "i", "j", "k"])
+        );
+        assert_eq!(expand("$*", &variables), "a b c d e f g h i j k");
+        assert_eq!(expand_text("$1$#", &variables).unwrap(), "a10");
+
+        variables.positional_params = strings(&["x", "y"]);
+        variables.vars.insert("IFS".to_string(), ",\t".to_string());
+        assert_eq!(expand("$*", &variables), "x,y");
+        variables.vars.insert("IFS".to_string(), "".to_string());
+        assert_eq!(expand("$*", &variables), "xy");
+
+        variables.positional_params.clear();
+        assert!(expand_words(&["$@"], None::<PathBuf>, &variables)
+            .unwrap()
+            .is_empty());
+        assert_eq!(expand("$#", &variables), "0");
+    }
+
+    #[test]
+    fn test_default_value_expansion() {
+        let mut variables = TestVariables::default();
+        variables
+            .vars
+            .insert("set".to_string(), "value".to_string());
+        variables.vars.insert("empty".to_string(), "".to_string());
+        variables
+            .vars
+            .insert("alt".to_string(), "other".to_string());
+        let expand = |word: &str, variables: &TestVariables| {
+            expand_word(word, Some("MockHomeDir"), variables).unwrap()
+        };
+
+        assert_eq!(expand("${set:-default}", &variables), "value");
+        assert_eq!(expand("${empty:-default}", &variables), "default");
+        assert_eq!(expand("${unset:-default}", &variables), "default");
+        assert_eq!(expand("${unset:-~/$alt}", &variables), "MockHomeDir/other");
+        assert_eq!(expand("${set-default}", &variables), "value");
+        assert_eq!(expand("${empty-default}", &variables), "");
+        assert_eq!(expand("${unset-default}", &variables), "default");
+
+        assert_eq!(expand("${set:+alt}", &variables), "alt");
+        assert_eq!(expand("${empty:+alt}", &variables), "");
+        assert_eq!(expand("${unset:+alt}", &variables), "");
+        assert_eq!(expand("${set+alt}", &variables), "alt");
+        assert_eq!(expand("${empty+alt}", &variables), "alt");
+        assert_eq!(expand("${unset+alt}", &variables), "");
+
+        variables.positional_params = strings(&["a"]);
+        assert_eq!(expand("${1:-default}", &variables), "a");
+        assert_eq!(expand("${2:-default}", &variables), "default");
+        variables.arrays.insert("arr".to_string(), array(&["a"]));
+        assert_eq!(expand("${arr[1]:-default}", &variables), "default");
+        assert_eq!(
+            expand_text("${unset:-default}, ${set:+alt}", &variables).unwrap(),
+            "default, alt"
+        );
+    }
+
+    #[test]
+    fn test_assign_default_value_expansion() {
+        let mut variables = TestVariables::default();
+        variables
+            .vars
+            .insert("set".to_string(), "value".to_string());
+        variables.vars.insert("empty".to_string(), "".to_string());
+        let expand = |word: &str, variables: &TestVariables| {
+            expand_word(word, None::<PathBuf>, variables).unwrap()
+        };
+
+        assert_eq!(expand("${set:=default}", &variables), "value");
+        assert_eq!(expand("${set=default}", &variables), "value");
+        assert_eq!(expand("${empty=default}", &variables), "");
+
+        let key = generate_unique_env_key!();
+        assert_eq!(
+            expand(&format!("${{{}:=default}}", key), &variables),
+            "default"
+        );
+        assert_eq!(env::var(&key).unwrap(), "default");
+
+        variables.vars.insert(key.clone(), "".to_string());
+        assert_eq!(expand(&format!("${{{}:=other}}", key), &variables), "other");
+        assert_eq!(env::var(&key).unwrap(), "other");
+
+        let key = generate_unique_env_key!();
+        assert_eq!(
+            expand(&format!("${{{}=default}}", key), &variables),
+            "default"
+        );
+        assert_eq!(env::var(&key).unwrap(), "default");
+
+        let error = expand_word("${1:=default}", None::<PathBuf>, &variables).unwrap_err();
+        assert_eq!(error.to_string(), "1: cannot assign in this way");
+    }
+
+    #[test]
+    fn test_required_value_expansion() {
+        let mut variables = TestVariables::default();
+        variables
+            .vars
+            .insert("set".to_string(), "value".to_string());
+        variables.vars.insert("empty".to_string(), "".to_string());
+        let expand = |word: &str| expand_word(word, None::<PathBuf>, &variables);
+
+        assert_eq!(expand("${set:?must be set}").unwrap(), "value");
+        assert_eq!(expand("${set?must be set}").unwrap(), "value");
+        assert_eq!(expand("${empty?must be set}").unwrap(), "");
+        assert_eq!(
+            expand("${empty:?must be $set}").unwrap_err().to_string(),
+            "empty: must be value"
+        );
+        assert_eq!(
+            expand("${unset:?must be set}").unwrap_err().to_string(),
+            "unset: must be set"
+        );
+        assert_eq!(
+            expand("${unset?must be set}").unwrap_err().to_string(),
+            "unset: must be set"
+        );
+        assert_eq!(
+            expand("${empty:?}").unwrap_err().to_string(),
+            "empty: parameter null or not set"
+        );
+        assert_eq!(
+            expand("${unset?}").unwrap_err().to_string(),
+            "unset: parameter not set"
+        );
+        assert!(expand_words(&["echo", "${unset:?}"], None::<PathBuf>, &variables).is_err());
+        assert!(expand_text("a ${unset:?} b", &variables).is_err());
+    }
+
+    #[test]
+    fn test_string_operation_expansion() {
+        let mut variables = TestVariables::default();
+        variables
+            .vars
+            .insert("file".to_string(), "/tmp/archive.tar.gz".to_string());
+        variables.vars.insert("ext".to_string(), ".gz".to_string());
+        variables
+            .vars
+            .insert("word".to_string(), "日本語".to_string());
+        variables.vars.insert("empty".to_string(), "".to_string());
+        variables.positional_params = strings(&["first"]);
+        let expand = |word: &str, variables: &TestVariables| {
+            expand_word(word, None::<PathBuf>, variables).unwrap()
+        };
+
+        assert_eq!(expand("${#word}", &variables), "3");
+        assert_eq!(expand("${#empty}", &variables), "0");
+        assert_eq!(expand("${file%.*}", &variables), "/tmp/archive.tar");
+        assert_eq!(expand("${file%%.*}", &variables), "/tmp/archive");
+        assert_eq!(expand("${file%$ext}", &variables), "/tmp/archive.tar");
+        assert_eq!(expand("${file#*/}", &variables), "tmp/archive.tar.gz");
+        assert_eq!(expand("${file##*/}", &variables), "archive.tar.gz");
+        assert_eq!(expand("${file/a/A}", &variables), "/tmp/Archive.tar.gz");
+        assert_eq!(expand("${file//a/A}", &variables), "/tmp/Archive.tAr.gz");
+        assert_eq!(expand("${word:1}", &variables), "本語");
+        assert_eq!(expand("${word: -1}", &variables), "語");
+        assert_eq!(expand("${word:0:2}", &variables), "日本");
+        assert_eq!(expand("${1:1:3}", &variables), "irs");
+        assert_eq!(expand("${empty%.*}", &variables), "");
+        assert_eq!(expand("${unset##*/}", &variables), "");
+        assert_eq!(
+            expand_text("${file##*/} is ${#file} long", &variables).unwrap(),
+            "archive.tar.gz is 19 long"
+        );
+        assert!(expand_word("${word:2:-2}", None::<PathBuf>, &variables).is_err());
+    }
+
+    #[test]
+    fn test_last_argument_expansion() {
+        let mut variables = TestVariables::default();
+        assert_eq!(
+            expand_word("$_", None::<PathBuf>, &variables).unwrap(),
+            "".to_string()
+        );
+
+        variables.last_arg = Some("/tmp".to_string());
+        variables
+            .vars
+            .insert("_".to_string(), "ignored".to_string());
+        assert_eq!(
+            expand_words(
+                &["echo", "$_", "${_}", "${#_}"],
                 None::<PathBuf>,
-                vars.iter().map(|&(ref key, ref value)| (key, value))
-            ),
-            Command::Simple {
-                words: vec!["cmd1".to_string(), value.clone()],
-                redirects: vec![Redirect {
-                    redirector: None,
-                    instruction: RedirectInstruction::Output,
-                    redirectee: Redirectee::Filename(value),
-                }],
-                background: false,
-            }
+                &variables
+            )
+            .unwrap(),
+            strings(&["echo", "/tmp", "/tmp", "4"])
+        );
+    }
+
+    #[test]
+    fn test_version_variable_expansion() {
+        let variables = TestVariables::default();
+        let expand =
+            |name: &str| expand_word(&format!("${}", name), None::<PathBuf>, &variables).unwrap();
+
+        assert_eq!(expand("BASH_VERSION"), BASH_VERSION);
+        assert_eq!(expand("BSH_VERSION"), env!("CARGO_PKG_VERSION"));
+        for index in 0..4 {
+            let component = expand(&format!("BASH_VERSINFO_{}", index));
+            assert!(component.parse::<u32>().is_ok(), "{:?}", component);
+        }
+        assert_eq!(expand("BASH_VERSINFO_4"), "release");
+        assert!(!expand("BASH_VERSINFO_5").is_empty());
+        assert_eq!(expand("BASH_VERSINFO_6"), "");
+
+        let exe = env::current_exe().unwrap();
+        assert_eq!(expand("BASH"), exe.to_string_lossy());
+        assert!(Path::new(&expand("SHELL")).is_absolute());
+    }
+
+    #[test]
+    fn test_shell_variable_not_overridden() {
+        let mut variables = TestVariables::default();
+        variables
+            .vars
+            .insert("SHELL".to_string(), "/bin/sh".to_string());
+        assert_eq!(
+            expand_word("$SHELL", None::<PathBuf>, &variables).unwrap(),
+            "/bin/sh"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_status_expansion() {
+        use crate::util::BshExitStatusExt;
+
+        let pipeline_status = [
+            ExitStatus::from_success(),
+            ExitStatus::from_status(1),
+            ExitStatus::from_status(127),
+        ];
+        assert_eq!(
+            expand_pipeline_status("PIPESTATUS", &pipeline_status),
+            Some("0 1 127".to_string())
+        );
+        assert_eq!(
+            expand_pipeline_status("PIPESTATUS_1", &pipeline_status),
+            Some("1".to_string())
+        );
+        assert_eq!(
+            expand_pipeline_status("PIPESTATUS_3", &pipeline_status),
+            None
+        );
+        assert_eq!(
+            expand_pipeline_status("PIPESTATUS_", &pipeline_status),
+            None
+        );
+        assert_eq!(expand_pipeline_status("PATH", &pipeline_status), None);
+        assert_eq!(
+            expand_pipeline_status("PIPESTATUS", &[]),
+            Some("".to_string())
+        );
+    }
+
+    #[test]
+    fn test_array_expansion() {
+        let mut variables = TestVariables::default();
+        variables
+            .vars
+            .insert("scalar".to_string(), "value".to_string());
+        variables
+            .arrays
+            .insert("arr".to_string(), array(&["a", "b"]));
+
+        let words = [
+            "cmd1",
+            "${arr[1]}",
+            "${arr[@]}",
+            "${#arr[@]}",
+            "${arr[5]}",
+            "${scalar[0]}",
+            "${missing[@]}",
+        ];
+        assert_eq!(
+            expand_words(&words, None::<PathBuf>, &variables).unwrap(),
+            strings(&["cmd1", "b", "a", "b", "2", "", "value"])
+        );
+        assert_eq!(
+            expand_word("${arr[@]}", None::<PathBuf>, &variables).unwrap(),
+            "a b"
+        );
+    }
+
+    #[test]
+    fn test_assoc_array_expansion() {
+        let mut map = HashMap::new();
+        map.insert("foo".to_string(), "1".to_string());
+        map.insert("bar".to_string(), "2".to_string());
+        let mut variables = TestVariables::default();
+        variables.assoc_arrays.insert("map".to_string(), map);
+
+        let words = [
+            "cmd1",
+            "${map[foo]}",
+            "${map[@]}",
+            "${!map[@]}",
+            "${#map[@]}",
+            "${map[missing]}",
+        ];
+        assert_eq!(
+            expand_words(&words, None::<PathBuf>, &variables).unwrap(),
+            strings(&["cmd1", "1", "2", "1", "bar", "foo", "2", ""])
         );
     }
 }
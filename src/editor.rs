@@ -1,23 +1,61 @@
 use std::fmt;
-use std::io;
+use std::io::{self, Write};
 use std::path::Path;
 use std::str;
 
+use atty::{self, Stream};
 use failure::{Fail, ResultExt};
 use rustyline::{
     self,
     completion::{Completer, FilenameCompleter, Pair},
+    config::Configurer,
     error::ReadlineError,
     highlight::Highlighter,
     hint::Hinter,
     history,
     validate::Validator,
-    CompletionType, Config, Helper,
+    CompletionType, Config, EditMode, Helper,
 };
 
-use crate::errors::{Error, ErrorKind, Result};
+use crate::{
+    builtins::{complete_word, with_completion_options, CompletionOptions},
+    errors::{Error, ErrorKind, Result},
+};
+
+/// Completes the arguments of commands with a completion spec defined by `complete`, and other
+/// words with the default completion, falling back to filenames.
+struct BshCompleter(FilenameCompleter);
+
+impl BshCompleter {
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &rustyline::Context<'_>,
+    ) -> ::std::result::Result<(usize, Vec<Pair>), ReadlineError> {
+        let before_cursor = &line[..pos];
+        let start = before_cursor
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| i + 1);
+        let command = before_cursor.split_whitespace().next();
+        let is_command = before_cursor[..start].trim().is_empty();
+        let candidates = match complete_word(command, &before_cursor[start..], is_command) {
+            Some(candidates) => candidates,
+            None => return self.0.complete(line, pos, ctx),
+        };
+
+        let candidates = candidates
+            .into_iter()
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
 
-struct EditorHelper(FilenameCompleter);
+struct EditorHelper(BshCompleter);
 
 impl Completer for EditorHelper {
     type Candidate = Pair;
@@ -28,7 +66,29 @@ impl Completer for EditorHelper {
         pos: usize,
         ctx: &rustyline::Context<'_>,
     ) -> ::std::result::Result<(usize, Vec<Pair>), ReadlineError> {
-        self.0.complete(line, pos, ctx)
+        let default_options = CompletionOptions {
+            filenames: true,
+            ..Default::default()
+        };
+        let (result, options) =
+            with_completion_options(default_options, || self.0.complete(line, pos, ctx));
+        let (start, mut candidates) = result?;
+        apply_completion_options(options, &mut candidates);
+        Ok((start, candidates))
+    }
+}
+
+/// Sorts `candidates` and adds a space after a unique completion, as allowed by `options`.
+fn apply_completion_options(options: CompletionOptions, candidates: &mut Vec<Pair>) {
+    if !options.nosort {
+        candidates.sort_by(|a, b| a.display.cmp(&b.display));
+    }
+
+    if let [candidate] = candidates.as_mut_slice() {
+        let is_directory = options.filenames && candidate.replacement.ends_with('/');
+        if !options.nospace && !is_directory {
+            candidate.replacement.push(' ');
+        }
     }
 }
 
@@ -65,7 +125,7 @@ impl Editor {
             .build();
 
         let mut internal = rustyline::Editor::with_config(config);
-        internal.set_helper(Some(EditorHelper(FilenameCompleter::new())));
+        internal.set_helper(Some(EditorHelper(BshCompleter(FilenameCompleter::new()))));
 
         Editor {
             internal,
@@ -87,6 +147,23 @@ impl Editor {
         }
     }
 
+    /// Reads a line from stdin without line editing, displaying `prompt` if stdin is a terminal.
+    pub fn readline_without_editing(&mut self, prompt: &str) -> Result<Option<String>> {
+        if atty::is(Stream::Stdin) {
+            print!("{}", prompt);
+            io::stdout().flush().context(ErrorKind::Io)?;
+        }
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).context(ErrorKind::Io)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+
     pub fn load_history<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
         match self.internal.load_history(path) {
             Ok(()) => Ok(()),
@@ -109,6 +186,14 @@ impl Editor {
         Ok(())
     }
 
+    /// Appends the entries added since the history was loaded or saved to the file at `path`.
+    pub fn append_history<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
+        self.internal
+            .append_history(path)
+            .context(ErrorKind::Readline)?;
+        Ok(())
+    }
+
     pub fn add_history_entry(&mut self, job: &str) {
         if self.internal.add_history_entry(job) {
             self.history_count += 1;
@@ -134,6 +219,16 @@ impl Editor {
         self.history_capacity = size;
     }
 
+    /// Switches between vi-style and emacs-style line editing, see `set -o vi`.
+    pub fn set_vi_mode(&mut self, enabled: bool) {
+        let mode = if enabled {
+            EditMode::Vi
+        } else {
+            EditMode::Emacs
+        };
+        self.internal.set_edit_mode(mode);
+    }
+
     pub fn get_history_count(&self) -> usize {
         self.history_count
     }
@@ -148,44 +243,97 @@ impl Editor {
     /// !n -> repeat command numbered n in the list of commands (starting at 1)
     /// !-n -> repeat last nth command (starting at -1)
     /// !string -> searches through history for first item that matches the string
+    /// !! -> repeat the last command
+    /// !$ -> the last word of the last command
+    /// !* -> all of the arguments of the last command
+    /// ^old^new^ -> repeat the last command, replacing the first `old` with `new`
+    ///
+    /// Events may be followed by modifiers, e.g. `!!:h:t`:
+    /// :h -> remove the trailing pathname component
+    /// :t -> remove all leading pathname components
+    /// :r -> remove a trailing suffix of the form `.xxx`
+    /// :e -> remove all but the trailing suffix
     pub fn expand_history(&self, command: &mut String) -> Result<()> {
-        if !command.starts_with('!') {
+        let expansion = if let Some(substitution) = command.strip_prefix('^') {
+            self.quick_substitution(substitution)
+                .ok_or_else(|| format!("{}: substitution failed", command))
+        } else if let Some(arg) = command.strip_prefix('!') {
+            let mut parts = arg.split(':');
+            let event = parts.next().unwrap_or_default();
+            self.expand_event(event)
+                .ok_or_else(|| format!("{}: event not found", command))
+                .and_then(|line| {
+                    parts.try_fold(line, |line, modifier| {
+                        apply_history_modifier(&line, modifier)
+                            .ok_or_else(|| format!(":{}: unrecognized history modifier", modifier))
+                    })
+                })
+        } else {
             return Ok(());
-        }
-
-        let arg = command[1..].to_string();
-        let entry = match arg.parse::<isize>() {
-            Ok(0) => None,
-            Ok(n) if n > 0 => self.get_history_entry((n - 1) as usize),
-            Ok(n) => self
-                .history_count
-                .checked_sub(n.wrapping_abs() as usize)
-                .and_then(|i| self.get_history_entry(i)),
-            Err(_) => self
-                .internal
-                .history()
-                .search(
-                    &arg,
-                    self.history_count - 1,
-                    history::SearchDirection::Reverse,
-                )
-                .and_then(|idx| self.internal.history().get(idx.idx)),
         };
 
-        match entry {
-            Some(line) => {
+        match expansion {
+            Ok(line) => {
                 command.clear();
-                command.push_str(line);
+                command.push_str(&line);
+                Ok(())
             }
-            None => {
-                return Err(Error::builtin_command(
-                    format!("{}: event not found", command),
-                    1,
-                ));
+            Err(message) => Err(Error::builtin_command(message, 1)),
+        }
+    }
+
+    /// Expands the event designator `event`, e.g. `!` for `!!`.
+    fn expand_event(&self, event: &str) -> Option<String> {
+        match event {
+            "!" => self.last_history_entry().cloned(),
+            "$" => self
+                .last_history_entry()
+                .and_then(|line| line.split_whitespace().last())
+                .map(String::from),
+            "*" => self.last_history_entry().map(|line| {
+                line.split_whitespace()
+                    .skip(1)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }),
+            _ => match event.parse::<isize>() {
+                Ok(0) => None,
+                Ok(n) if n > 0 => self.get_history_entry((n - 1) as usize),
+                Ok(n) => self
+                    .history_count
+                    .checked_sub(n.wrapping_abs() as usize)
+                    .and_then(|i| self.get_history_entry(i)),
+                Err(_) => self
+                    .internal
+                    .history()
+                    .search(
+                        event,
+                        self.internal.history().len().checked_sub(1)?,
+                        history::SearchDirection::Reverse,
+                    )
+                    .and_then(|idx| self.internal.history().get(idx.idx)),
             }
+            .cloned(),
         }
+    }
 
-        Ok(())
+    /// Replaces the first `old` with `new` in the last command, given `old^new^`.
+    fn quick_substitution(&self, substitution: &str) -> Option<String> {
+        let mut parts = substitution.splitn(3, '^');
+        let old = parts.next().filter(|old| !old.is_empty())?;
+        let new = parts.next().unwrap_or_default();
+        let line = self.last_history_entry()?;
+        if line.contains(old) {
+            Some(line.replacen(old, new, 1))
+        } else {
+            None
+        }
+    }
+
+    fn last_history_entry(&self) -> Option<&String> {
+        self.history_count
+            .checked_sub(1)
+            .and_then(|i| self.get_history_entry(i))
     }
 
     pub fn enumerate_history_entries(&self) -> EditorEnumerate<'_> {
@@ -197,6 +345,27 @@ impl Editor {
     }
 }
 
+/// Applies the history modifier `modifier`, e.g. `h` for `:h`, to `line`. Returns `None` if the
+/// modifier is not recognized.
+fn apply_history_modifier(line: &str, modifier: &str) -> Option<String> {
+    let last_component = line.rfind('/').map_or(0, |i| i + 1);
+    let suffix = line[last_component..]
+        .rfind('.')
+        .map(|i| last_component + i);
+    let modified = match modifier {
+        "h" => match line.rfind('/') {
+            Some(0) => "/",
+            Some(i) => &line[..i],
+            None => line,
+        },
+        "t" => &line[last_component..],
+        "r" => suffix.map_or(line, |i| &line[..i]),
+        "e" => suffix.map_or(line, |i| &line[i..]),
+        _ => return None,
+    };
+    Some(modified.to_string())
+}
+
 impl fmt::Display for Editor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for (i, e) in self.enumerate_history_entries() {
@@ -255,6 +424,55 @@ mod tests {
         state
     }
 
+    fn pairs(words: &[&str]) -> Vec<Pair> {
+        words
+            .iter()
+            .map(|word| Pair {
+                display: word.to_string(),
+                replacement: word.to_string(),
+            })
+            .collect()
+    }
+
+    fn replacements(candidates: &[Pair]) -> Vec<&str> {
+        candidates.iter().map(|c| c.replacement.as_str()).collect()
+    }
+
+    #[test]
+    fn completion_options() {
+        let mut candidates = pairs(&["b", "c", "a"]);
+        apply_completion_options(CompletionOptions::default(), &mut candidates);
+        assert_eq!(replacements(&candidates), ["a", "b", "c"]);
+
+        let mut candidates = pairs(&["b", "c", "a"]);
+        let options = CompletionOptions {
+            nosort: true,
+            ..Default::default()
+        };
+        apply_completion_options(options, &mut candidates);
+        assert_eq!(replacements(&candidates), ["b", "c", "a"]);
+
+        let mut candidates = pairs(&["file"]);
+        apply_completion_options(CompletionOptions::default(), &mut candidates);
+        assert_eq!(replacements(&candidates), ["file "]);
+
+        let mut candidates = pairs(&["file"]);
+        let options = CompletionOptions {
+            nospace: true,
+            ..Default::default()
+        };
+        apply_completion_options(options, &mut candidates);
+        assert_eq!(replacements(&candidates), ["file"]);
+
+        let mut candidates = pairs(&["dir/"]);
+        let options = CompletionOptions {
+            filenames: true,
+            ..Default::default()
+        };
+        apply_completion_options(options, &mut candidates);
+        assert_eq!(replacements(&candidates), ["dir/"]);
+    }
+
     #[test]
     fn init_with_capacity() {
         let capacity = 10;
@@ -354,4 +572,83 @@ mod tests {
         assert!(state.expand_history(&mut buf).is_ok());
         assert_eq!(buf, "cmd1");
     }
+
+    #[test]
+    fn expand_last_command() {
+        let state = alloc_history_state(0, 0);
+        let mut buf = String::from("!!");
+        assert!(state.expand_history(&mut buf).is_err());
+        assert_eq!(buf, "!!");
+
+        let state = alloc_history_state(10, 10);
+        let mut buf = String::from("!!");
+        assert!(state.expand_history(&mut buf).is_ok());
+        assert_eq!(buf, "cmd9");
+    }
+
+    #[test]
+    fn expand_last_command_words() {
+        let mut state = alloc_history_state(10, 10);
+
+        let mut buf = String::from("!$");
+        assert!(state.expand_history(&mut buf).is_ok());
+        assert_eq!(buf, "cmd9");
+
+        let mut buf = String::from("!*");
+        assert!(state.expand_history(&mut buf).is_ok());
+        assert_eq!(buf, "");
+
+        state.add_history_entry("cp  src   dst");
+        let mut buf = String::from("!$");
+        assert!(state.expand_history(&mut buf).is_ok());
+        assert_eq!(buf, "dst");
+
+        let mut buf = String::from("!*");
+        assert!(state.expand_history(&mut buf).is_ok());
+        assert_eq!(buf, "src dst");
+
+        let empty_state = alloc_history_state(0, 0);
+        let mut buf = String::from("!$");
+        assert!(empty_state.expand_history(&mut buf).is_err());
+    }
+
+    #[test]
+    fn expand_history_modifiers() {
+        let mut state = alloc_history_state(10, 10);
+        state.add_history_entry("/usr/src/bsh/main.rs");
+        let expand = |command: &str| {
+            let mut buf = command.to_string();
+            state.expand_history(&mut buf).map(|_| buf)
+        };
+
+        assert_eq!(expand("!!:h").unwrap(), "/usr/src/bsh");
+        assert_eq!(expand("!!:t").unwrap(), "main.rs");
+        assert_eq!(expand("!!:r").unwrap(), "/usr/src/bsh/main");
+        assert_eq!(expand("!!:e").unwrap(), ".rs");
+        assert_eq!(expand("!/usr:h:t").unwrap(), "bsh");
+        assert_eq!(expand("!!:h:h:h:h").unwrap(), "/");
+        assert_eq!(expand("!cmd9:h").unwrap(), "cmd9");
+        assert_eq!(expand("!cmd9:e").unwrap(), "cmd9");
+        assert!(expand("!!:x").is_err());
+        assert!(expand("!nonexistent:h").is_err());
+    }
+
+    #[test]
+    fn expand_quick_substitution() {
+        let mut state = alloc_history_state(10, 10);
+        state.add_history_entry("cat foo.txt foo.rs");
+        let expand = |command: &str| {
+            let mut buf = command.to_string();
+            state.expand_history(&mut buf).map(|_| buf)
+        };
+
+        assert_eq!(expand("^foo^bar^").unwrap(), "cat bar.txt foo.rs");
+        assert_eq!(expand("^cat^less").unwrap(), "less foo.txt foo.rs");
+        assert_eq!(expand("^.txt^").unwrap(), "cat foo foo.rs");
+        assert!(expand("^baz^bar^").is_err());
+        assert!(expand("^^bar").is_err());
+        assert!(alloc_history_state(0, 0)
+            .expand_history(&mut "^a^b".to_string())
+            .is_err());
+    }
 }
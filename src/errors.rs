@@ -1,6 +1,7 @@
 //! Error module. See the [failure](https://crates.io/crates/failure) crate for details.
 
 use std::fmt;
+use std::path::{Path, PathBuf};
 use std::result;
 
 use failure::{Backtrace, Context, Fail};
@@ -12,6 +13,7 @@ pub type Result<T> = result::Result<T, Error>;
 #[derive(Debug)]
 pub struct Error {
     ctx: Context<ErrorKind>,
+    source_location: Option<(PathBuf, usize)>,
 }
 
 impl Error {
@@ -20,6 +22,20 @@ impl Error {
         self.ctx.get_context()
     }
 
+    /// Returns the script file and line number the error occurred at, if known.
+    pub fn source_location(&self) -> Option<(&Path, usize)> {
+        self.source_location
+            .as_ref()
+            .map(|(file, line)| (file.as_path(), *line))
+    }
+
+    pub(crate) fn with_location(inner: Error, file: &Path, line: usize) -> Self {
+        Error {
+            source_location: Some((file.to_path_buf(), line)),
+            ..inner
+        }
+    }
+
     pub(crate) fn syntax<T: AsRef<str>>(line: T) -> Self {
         Error::from(ErrorKind::Syntax(line.as_ref().to_string()))
     }
@@ -39,11 +55,74 @@ impl Error {
         Error::from(ErrorKind::NoSuchJob(job.as_ref().to_string()))
     }
 
+    pub(crate) fn ambiguous_job_spec<T: AsRef<str>>(job: T) -> Self {
+        Error::from(ErrorKind::AmbiguousJobSpec(job.as_ref().to_string()))
+    }
+
+    pub(crate) fn permission_denied<T: AsRef<str>>(command: T) -> Self {
+        Error::from(ErrorKind::PermissionDenied(command.as_ref().to_string()))
+    }
+
+    pub(crate) fn not_available_in_posix_mode<T: AsRef<str>>(feature: T) -> Self {
+        Error::from(ErrorKind::NotAvailableInPosixMode(
+            feature.as_ref().to_string(),
+        ))
+    }
+
+    pub(crate) fn argument_list_too_long<T: AsRef<str>>(command: T) -> Self {
+        Error::from(ErrorKind::ArgumentListTooLong(command.as_ref().to_string()))
+    }
+
+    pub(crate) fn readonly_variable<T: AsRef<str>>(name: T) -> Self {
+        Error::from(ErrorKind::ReadonlyVariable(name.as_ref().to_string()))
+    }
+
+    pub(crate) fn parameter_null_or_unset<T: AsRef<str>, U: AsRef<str>>(
+        name: T,
+        message: U,
+    ) -> Self {
+        Error::from(ErrorKind::ParameterNullOrUnset {
+            name: name.as_ref().to_string(),
+            message: message.as_ref().to_string(),
+        })
+    }
+
+    pub(crate) fn no_glob_match<T: AsRef<str>>(pattern: T) -> Self {
+        Error::from(ErrorKind::NoGlobMatch(pattern.as_ref().to_string()))
+    }
+
+    pub(crate) fn no_clobber<T: AsRef<str>>(filename: T) -> Self {
+        Error::from(ErrorKind::NoClobber(filename.as_ref().to_string()))
+    }
+
+    pub(crate) fn loop_control(control: LoopControl) -> Self {
+        Error::from(ErrorKind::LoopControl(control))
+    }
+
+    pub(crate) fn function_return(code: i32) -> Self {
+        Error::from(ErrorKind::Return(code))
+    }
+
+    pub(crate) fn arithmetic<T: AsRef<str>>(message: T) -> Self {
+        Error::from(ErrorKind::Arithmetic(message.as_ref().to_string()))
+    }
+
+    pub(crate) fn configuration<T: AsRef<str>>(message: T) -> Self {
+        Error::from(ErrorKind::Configuration(message.as_ref().to_string()))
+    }
+
+    pub(crate) fn encoding<P: AsRef<Path>>(path: P) -> Self {
+        Error::from(ErrorKind::Encoding(path.as_ref().to_path_buf()))
+    }
+
+    pub(crate) fn too_many_open_files() -> Self {
+        Error::from(ErrorKind::TooManyOpenFiles)
+    }
+
     pub(crate) fn no_job_control() -> Self {
         Error::from(ErrorKind::NoJobControl)
     }
 
-    #[cfg(windows)]
     pub(crate) fn not_supported<T: AsRef<str>>(message: T) -> Self {
         Error::from(ErrorKind::NotSupported(message.as_ref().to_string()))
     }
@@ -61,6 +140,9 @@ impl Fail for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((ref file, line)) = self.source_location {
+            write!(f, "{}:{}: ", file.display(), line)?;
+        }
         self.ctx.fmt(f)
     }
 }
@@ -79,12 +161,45 @@ pub enum ErrorKind {
     },
     /// Command not found error.
     CommandNotFound(String),
+    /// Permission denied error, e.g. the command is outside the restricted path.
+    PermissionDenied(String),
+    /// The command's arguments exceed the shell's configured limits.
+    ArgumentListTooLong(String),
+    /// Attempt to assign or unset a read-only variable.
+    ReadonlyVariable(String),
+    /// Opening a file for a redirection would exceed the limit on open files.
+    TooManyOpenFiles,
+    /// Arithmetic expression error, e.g. division by zero.
+    Arithmetic(String),
+    /// No paths match a glob pattern while `failglob` is enabled.
+    NoGlobMatch(String),
+    /// A redirect would overwrite an existing file while `noclobber` is enabled.
+    NoClobber(String),
+    /// A parameter expansion such as `${name:?message}` failed because `name` is null or unset.
+    ParameterNullOrUnset {
+        /// Parameter name.
+        name: String,
+        /// Error message.
+        message: String,
+    },
+    /// `break` or `continue` unwinding the commands of the enclosing loops.
+    LoopControl(LoopControl),
+    /// `return` unwinding the commands of the enclosing function with an exit status.
+    Return(i32),
+    /// A bsh extension was used while the shell is in POSIX mode.
+    NotAvailableInPosixMode(String),
     /// No such job error.
     NoSuchJob(String),
+    /// Job spec matches more than one job error.
+    AmbiguousJobSpec(String),
     /// Job control not available error.
     NoJobControl,
     /// Operation not supported error.
     NotSupported(String),
+    /// Contradictory [`ShellConfig`](crate::ShellConfig) settings.
+    Configuration(String),
+    /// A script is not valid in the configured [`InputEncoding`](crate::InputEncoding).
+    Encoding(PathBuf),
     /// Underlying error from the Docopt crate.
     Docopt,
     /// I/O error.
@@ -95,15 +210,55 @@ pub enum ErrorKind {
     Readline,
 }
 
+/// What `break` or `continue` does to the enclosing loops, see [`ErrorKind::LoopControl`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LoopControl {
+    /// Exits this many enclosing loops.
+    Break(usize),
+    /// Exits one fewer enclosing loops than this and resumes the next iteration of the loop
+    /// enclosing them.
+    Continue(usize),
+}
+
 impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             ErrorKind::Syntax(ref line) => write!(f, "syntax error: '{}'", line),
             ErrorKind::BuiltinCommand { ref message, .. } => write!(f, "{}", message),
             ErrorKind::CommandNotFound(ref line) => write!(f, "{}: command not found", line),
+            ErrorKind::PermissionDenied(ref command) => write!(f, "{}: Permission denied", command),
+            ErrorKind::ArgumentListTooLong(ref command) => {
+                write!(f, "{}: Argument list too long", command)
+            }
+            ErrorKind::ReadonlyVariable(ref name) => write!(f, "{}: readonly variable", name),
+            ErrorKind::TooManyOpenFiles => write!(f, "too many open files"),
+            ErrorKind::Arithmetic(ref message) => write!(f, "{}", message),
+            ErrorKind::NoGlobMatch(ref pattern) => write!(f, "no match: {}", pattern),
+            ErrorKind::NoClobber(ref filename) => {
+                write!(f, "{}: cannot overwrite existing file", filename)
+            }
+            ErrorKind::ParameterNullOrUnset {
+                ref name,
+                ref message,
+            } => write!(f, "{}: {}", name, message),
+            ErrorKind::LoopControl(LoopControl::Break(_)) => write!(f, "break: not in a loop"),
+            ErrorKind::LoopControl(LoopControl::Continue(_)) => {
+                write!(f, "continue: not in a loop")
+            }
+            ErrorKind::Return(_) => write!(f, "return: not in a function"),
+            ErrorKind::NotAvailableInPosixMode(ref feature) => {
+                write!(f, "not available in POSIX mode: {}", feature)
+            }
             ErrorKind::NoSuchJob(ref job) => write!(f, "{}: no such job", job),
+            ErrorKind::AmbiguousJobSpec(ref job) => write!(f, "{}: ambiguous job spec", job),
             ErrorKind::NoJobControl => write!(f, "no job control"),
             ErrorKind::NotSupported(ref message) => write!(f, "{}", message),
+            ErrorKind::Configuration(ref message) => {
+                write!(f, "invalid configuration: {}", message)
+            }
+            ErrorKind::Encoding(ref path) => {
+                write!(f, "{}: invalid character encoding", path.display())
+            }
             ErrorKind::Docopt => write!(f, "Docopt error occurred"),
             ErrorKind::Io => write!(f, "I/O error occurred"),
             ErrorKind::Nix => write!(f, " Nix error occurred"),
@@ -120,6 +275,9 @@ impl From<ErrorKind> for Error {
 
 impl From<Context<ErrorKind>> for Error {
     fn from(ctx: Context<ErrorKind>) -> Error {
-        Error { ctx }
+        Error {
+            ctx,
+            source_location: None,
+        }
     }
 }
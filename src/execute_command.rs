@@ -1,21 +1,54 @@
-use std::ffi::OsStr;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::ffi::{OsStr, OsString};
 use std::fmt;
-use std::fs::{File, OpenOptions};
-use std::io;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
 use std::iter;
 #[cfg(unix)]
-use std::os::unix::io::{AsRawFd, RawFd};
-use std::process::{Child, ChildStdout, Command, ExitStatus, Stdio};
+use std::mem::ManuallyDrop;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, ChildStdout, Command, ExitStatus, Stdio};
+#[cfg(unix)]
+use std::thread;
 
 use failure::{Fail, ResultExt};
 
 use crate::{
-    builtins,
-    core::{intermediate_representation as ir, parser::ast},
-    errors::{Error, ErrorKind, Result},
-    shell::Shell,
+    builtins::{self, RunOptions},
+    core::{
+        brace_expansion, conditional,
+        glob::{self, GlobOptions},
+        intermediate_representation as ir,
+        parser::{
+            self,
+            ast::{self, Quoting},
+        },
+        variable_expansion,
+    },
+    errors::{Error, ErrorKind, LoopControl, Result},
+    shell::{print_error, Shell, ShellConfig, ShellOption, VarValue, ERR_TRAP},
+    util::{self, BshExitStatusExt},
 };
 
+/// Commands that may not be run in sandbox mode, as they could run code that escapes it. `nice`
+/// and `nohup` run their commands without the shell's sandbox checks.
+const SANDBOX_DISABLED_COMMANDS: &[&str] = &["exec", "source", ".", "eval", "nice", "nohup"];
+
+/// The variable `select` stores each line read in.
+const SELECT_REPLY_VARIABLE: &str = "REPLY";
+
+/// The prompt of `select` if `$PS3` is unset.
+const DEFAULT_SELECT_PROMPT: &str = "#? ";
+
+/// The variable holding the file descriptor xtrace output is written to.
+pub(crate) const XTRACE_FD_VARIABLE: &str = "BASH_XTRACEFD";
+/// The variable holding the prefix of xtrace output, see [`write_xtrace`].
+const XTRACE_PREFIX_VARIABLE: &str = "PS4";
+const DEFAULT_XTRACE_PREFIX: &str = "+ ";
+
 #[derive(Debug)]
 pub enum Stdin {
     Inherit,
@@ -34,17 +67,49 @@ enum Output {
 
 impl Stdin {
     /// simple commands prefer file redirects to piping, following bash's behavior
-    fn new(redirect: &ir::Stdio, pipe: Option<Stdin>) -> Result<Self> {
+    fn new(redirect: &ir::Stdio, pipe: Option<Stdin>, config: &ShellConfig) -> Result<Self> {
         match (redirect, pipe) {
             (ir::Stdio::FileDescriptor(0), _) => Ok(Stdin::Inherit),
             (ir::Stdio::FileDescriptor(fd), _) => Ok(Stdin::FileDescriptor(*fd)),
-            (ir::Stdio::Filename(filename), _) => Ok(Stdin::File(
-                File::open(filename).with_context(|_| ErrorKind::Io)?,
-            )),
+            (ir::Stdio::Filename(filename), _) => {
+                check_open_files(config)?;
+                Ok(Stdin::File(
+                    File::open(filename).with_context(|_| ErrorKind::Io)?,
+                ))
+            }
+            (ir::Stdio::HereDoc(here_doc), _) => {
+                check_open_files(config)?;
+                Ok(Stdin::File(here_doc_pipe(here_doc.body.clone())?))
+            }
+            (ir::Stdio::HereString(word), _) => {
+                check_open_files(config)?;
+                Ok(Stdin::File(here_doc_pipe(format!("{}\n", word))?))
+            }
             (_, Some(stdin)) => Ok(stdin),
             _ => Ok(Stdin::Inherit),
         }
     }
+
+    /// Returns a copy that reads from the same input, e.g. for each command of `{ ...; }`.
+    fn try_clone(&self) -> Result<Self> {
+        match self {
+            Stdin::Inherit => Ok(Stdin::Inherit),
+            Stdin::File(file) => Ok(Stdin::File(file.try_clone().context(ErrorKind::Io)?)),
+            Stdin::FileDescriptor(fd) => Ok(Stdin::FileDescriptor(*fd)),
+            #[cfg(unix)]
+            Stdin::Child(child) => {
+                let fd = nix::unistd::dup(child.as_raw_fd()).context(ErrorKind::Nix)?;
+                // It is safe to call from_raw_fd here because the duplicated fd is not owned by
+                // anything else.
+                Ok(Stdin::File(unsafe { File::from_raw_fd(fd) }))
+            }
+            #[cfg(windows)]
+            Stdin::Child(_) => {
+                // TODO (#22): Support Windows
+                unimplemented!()
+            }
+        }
+    }
 }
 
 impl From<File> for Stdin {
@@ -64,6 +129,20 @@ impl From<Stdin> for Stdio {
     }
 }
 
+impl Read for Stdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stdin::Inherit => io::stdin().read(buf),
+            Stdin::File(file) => file.read(buf),
+            Stdin::FileDescriptor(_) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "cannot read from a redirected file descriptor",
+            )),
+            Stdin::Child(child) => child.read(buf),
+        }
+    }
+}
+
 #[cfg(unix)]
 impl AsRawFd for Stdin {
     fn as_raw_fd(&self) -> RawFd {
@@ -76,40 +155,146 @@ impl AsRawFd for Stdin {
     }
 }
 
+/// Replaces the shell's stdin with a redirected [`Stdin`] until dropped, e.g. for builtins, which
+/// run in the shell process.
+#[cfg(unix)]
+struct StdinGuard {
+    saved_fd: Option<RawFd>,
+}
+
+#[cfg(unix)]
+impl StdinGuard {
+    fn redirect(stdin: &Stdin) -> Result<Self> {
+        if let Stdin::Inherit = stdin {
+            return Ok(StdinGuard { saved_fd: None });
+        }
+
+        let saved_fd = nix::unistd::dup(libc::STDIN_FILENO).context(ErrorKind::Nix)?;
+        if let Err(e) = nix::unistd::dup2(stdin.as_raw_fd(), libc::STDIN_FILENO) {
+            let _ = nix::unistd::close(saved_fd);
+            return Err(e.context(ErrorKind::Nix).into());
+        }
+        Ok(StdinGuard {
+            saved_fd: Some(saved_fd),
+        })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for StdinGuard {
+    fn drop(&mut self) {
+        if let Some(saved_fd) = self.saved_fd {
+            log_if_err!(
+                nix::unistd::dup2(saved_fd, libc::STDIN_FILENO),
+                "failed to restore stdin"
+            );
+            let _ = nix::unistd::close(saved_fd);
+        }
+    }
+}
+
+/// Applies the redirects of `exec` to the shell's own file descriptors, e.g. `exec 2> log`, see
+/// [`builtins::redirects_shell`].
+///
+/// Returns the stdin and stdout the builtin runs with, which are inherited unless piped.
+#[cfg(unix)]
+fn redirect_shell(stdin: Stdin, stdout: Output, stderr: Output) -> Result<(Stdin, Output)> {
+    io::stdout().flush().context(ErrorKind::Io)?;
+    let stdin = match stdin {
+        Stdin::File(_) | Stdin::FileDescriptor(_) => {
+            nix::unistd::dup2(stdin.as_raw_fd(), libc::STDIN_FILENO).context(ErrorKind::Nix)?;
+            Stdin::Inherit
+        }
+        stdin => stdin,
+    };
+    let redirect_output = |output: Output, fd: RawFd| -> Result<Output> {
+        let output_fd = match output {
+            Output::File(ref file) => file.as_raw_fd(),
+            Output::FileDescriptor(output_fd) => output_fd,
+            output => return Ok(output),
+        };
+        nix::unistd::dup2(output_fd, fd).context(ErrorKind::Nix)?;
+        Ok(Output::Inherit)
+    };
+    let stdout = redirect_output(stdout, libc::STDOUT_FILENO)?;
+    redirect_output(stderr, libc::STDERR_FILENO)?;
+    Ok((stdin, stdout))
+}
+
+#[cfg(windows)]
+struct StdinGuard;
+
+#[cfg(windows)]
+impl StdinGuard {
+    fn redirect(_stdin: &Stdin) -> Result<Self> {
+        Ok(StdinGuard)
+    }
+}
+
 impl Output {
     /// simple commands prefer file redirects to piping, following bash's behavior
-    fn new_stdout(redirect: &ir::Stdio, pipe: Option<Output>) -> Result<Self> {
+    fn new_stdout(
+        redirect: &ir::Stdio,
+        pipe: Option<Output>,
+        config: &ShellConfig,
+    ) -> Result<Self> {
         match (redirect, pipe) {
             (ir::Stdio::FileDescriptor(1), _) => Ok(Output::Inherit),
             (ir::Stdio::FileDescriptor(fd), _) => Ok(Output::FileDescriptor(*fd)),
-            (ir::Stdio::Filename(filename), _) => Ok(Output::File(
-                OpenOptions::new()
-                    .write(true)
-                    .create(true)
-                    .open(filename)
-                    .context(ErrorKind::Io)?,
-            )),
+            (ir::Stdio::Filename(filename), _) => Output::open(filename, false, config),
+            (ir::Stdio::AppendFilename(filename), _) => Output::open(filename, true, config),
             (_, Some(output)) => Ok(output),
             _ => Ok(Output::Inherit),
         }
     }
 
     /// simple commands prefer file redirects to piping, following bash's behavior
-    fn new_stderr(redirect: &ir::Stdio, pipe: Option<Output>) -> Result<Self> {
+    fn new_stderr(
+        redirect: &ir::Stdio,
+        pipe: Option<Output>,
+        config: &ShellConfig,
+    ) -> Result<Self> {
         match (redirect, pipe) {
             (ir::Stdio::FileDescriptor(2), _) => Ok(Output::Inherit),
             (ir::Stdio::FileDescriptor(fd), _) => Ok(Output::FileDescriptor(*fd)),
-            (ir::Stdio::Filename(filename), _) => Ok(Output::File(
-                OpenOptions::new()
-                    .write(true)
-                    .create(true)
-                    .open(filename)
-                    .context(ErrorKind::Io)?,
-            )),
+            (ir::Stdio::Filename(filename), _) => Output::open(filename, false, config),
+            (ir::Stdio::AppendFilename(filename), _) => Output::open(filename, true, config),
             (_, Some(output)) => Ok(output),
             _ => Ok(Output::Inherit),
         }
     }
+
+    /// Returns a copy that writes to the same output, e.g. for each command of `{ ...; }`.
+    ///
+    /// A pipe must be created before its output can be shared.
+    fn try_clone(&self) -> Result<Self> {
+        match self {
+            Output::Inherit => Ok(Output::Inherit),
+            Output::File(file) => Ok(Output::File(file.try_clone().context(ErrorKind::Io)?)),
+            Output::FileDescriptor(fd) => Ok(Output::FileDescriptor(*fd)),
+            Output::CreatePipe => unreachable!("a pipe must be created before it is shared"),
+        }
+    }
+
+    /// Opens `filename` for a redirect, appending to it if `append` is `true`.
+    ///
+    /// With `set -o noclobber`, an existing regular file is not truncated.
+    fn open(filename: &str, append: bool, config: &ShellConfig) -> Result<Self> {
+        check_open_files(config)?;
+        if !append
+            && config.get_option(ShellOption::Noclobber)
+            && fs::metadata(filename).map_or(false, |metadata| metadata.is_file())
+        {
+            return Err(Error::no_clobber(filename));
+        }
+        let file = OpenOptions::new()
+            .write(true)
+            .append(append)
+            .create(true)
+            .open(filename)
+            .context(ErrorKind::Io)?;
+        Ok(Output::File(file))
+    }
 }
 
 impl From<File> for Output {
@@ -278,17 +463,56 @@ impl Process for ExternalProcess {
     }
 
     fn kill(&mut self) -> Result<()> {
+        // The process may already have been reaped by `try_wait`, unknown to `Child`.
+        if self.status_code.is_some() {
+            return Ok(());
+        }
         self.child.kill().context(ErrorKind::Io)?;
         Ok(())
     }
 
     fn wait(&mut self) -> Result<ExitStatus> {
+        if let Some(exit_status) = self.status_code {
+            return Ok(exit_status);
+        }
         let exit_status = self.child.wait().context(ErrorKind::Io)?;
         self.status = ProcessStatus::Completed;
         self.status_code = Some(exit_status);
         Ok(exit_status)
     }
 
+    /// Unlike `Child::try_wait`, also notices the process being stopped or continued, e.g. by
+    /// `kill -STOP`, so that its job is reported as stopped.
+    #[cfg(unix)]
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        use std::os::unix::process::ExitStatusExt;
+
+        use nix::sys::wait::{self, WaitPidFlag, WaitStatus};
+
+        if self.status_code.is_some() {
+            return Ok(self.status_code);
+        }
+        let pid = nix::unistd::Pid::from_raw(self.child.id() as libc::pid_t);
+        let flags = WaitPidFlag::WNOHANG | WaitPidFlag::WUNTRACED | WaitPidFlag::WCONTINUED;
+        let exit_status = match wait::waitpid(pid, Some(flags)).context(ErrorKind::Nix)? {
+            WaitStatus::Exited(_, code) => ExitStatus::from_status(code),
+            WaitStatus::Signaled(_, signal, _) => ExitStatus::from_raw(signal as i32),
+            WaitStatus::Stopped(..) => {
+                self.status = ProcessStatus::Stopped;
+                return Ok(None);
+            }
+            WaitStatus::Continued(_) => {
+                self.status = ProcessStatus::Running;
+                return Ok(None);
+            }
+            _ => return Ok(None),
+        };
+        self.status = ProcessStatus::Completed;
+        self.status_code = Some(exit_status);
+        Ok(Some(exit_status))
+    }
+
+    #[cfg(windows)]
     fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
         if let Some(exit_status) = self.child.try_wait().context(ErrorKind::Io)? {
             self.status = ProcessStatus::Completed;
@@ -300,12 +524,196 @@ impl Process for ExternalProcess {
     }
 }
 
+/// A forked copy of the shell running `( command )`.
+#[cfg(unix)]
+struct SubshellProcess {
+    pid: nix::unistd::Pid,
+    status: ProcessStatus,
+    status_code: Option<ExitStatus>,
+    stdout: Option<Stdin>,
+}
+
+#[cfg(unix)]
+impl SubshellProcess {
+    fn new(pid: nix::unistd::Pid, stdout: Option<Stdin>) -> Self {
+        Self {
+            pid,
+            status: ProcessStatus::Running,
+            status_code: None,
+            stdout,
+        }
+    }
+
+    fn update_status(&mut self, wait_status: nix::sys::wait::WaitStatus) -> Option<ExitStatus> {
+        use std::os::unix::process::ExitStatusExt;
+
+        use nix::sys::wait::WaitStatus;
+
+        let exit_status = match wait_status {
+            WaitStatus::Exited(_, code) => ExitStatus::from_status(code),
+            // A raw wait status of just the signal number means the process was killed by it.
+            WaitStatus::Signaled(_, signal, _) => ExitStatus::from_raw(signal as i32),
+            _ => return None,
+        };
+        self.status = ProcessStatus::Completed;
+        self.status_code = Some(exit_status);
+        Some(exit_status)
+    }
+}
+
+#[cfg(unix)]
+impl Process for SubshellProcess {
+    fn id(&self) -> Option<ProcessId> {
+        Some((self.pid.as_raw() as u32).into())
+    }
+
+    fn argv(&self) -> String {
+        "( ... )".to_string()
+    }
+
+    fn status(&self) -> ProcessStatus {
+        self.status
+    }
+
+    fn status_code(&self) -> Option<ExitStatus> {
+        self.status_code
+    }
+
+    fn stdout(&mut self) -> Option<Stdin> {
+        self.stdout.take()
+    }
+
+    fn kill(&mut self) -> Result<()> {
+        use nix::sys::signal::{self, Signal};
+
+        signal::kill(self.pid, Signal::SIGKILL).context(ErrorKind::Nix)?;
+        Ok(())
+    }
+
+    fn wait(&mut self) -> Result<ExitStatus> {
+        use nix::sys::wait;
+
+        loop {
+            if let Some(exit_status) = self.status_code {
+                return Ok(exit_status);
+            }
+            let wait_status = wait::waitpid(self.pid, None).context(ErrorKind::Nix)?;
+            self.update_status(wait_status);
+        }
+    }
+
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        use nix::sys::wait::{self, WaitPidFlag};
+
+        if self.status_code.is_some() {
+            return Ok(self.status_code);
+        }
+        let wait_status =
+            wait::waitpid(self.pid, Some(WaitPidFlag::WNOHANG)).context(ErrorKind::Nix)?;
+        Ok(self.update_status(wait_status))
+    }
+}
+
+/// The last process of `! command`, whose exit status is inverted.
+struct NegatedProcess(Box<dyn Process>);
+
+impl NegatedProcess {
+    fn negate(exit_status: ExitStatus) -> ExitStatus {
+        if exit_status.success() {
+            ExitStatus::from_failure()
+        } else {
+            ExitStatus::from_success()
+        }
+    }
+}
+
+impl Process for NegatedProcess {
+    fn id(&self) -> Option<ProcessId> {
+        self.0.id()
+    }
+
+    fn argv(&self) -> String {
+        format!("! {}", self.0.argv())
+    }
+
+    fn status(&self) -> ProcessStatus {
+        self.0.status()
+    }
+
+    fn status_code(&self) -> Option<ExitStatus> {
+        self.0.status_code().map(Self::negate)
+    }
+
+    fn stdout(&mut self) -> Option<Stdin> {
+        self.0.stdout()
+    }
+
+    fn kill(&mut self) -> Result<()> {
+        self.0.kill()
+    }
+
+    fn wait(&mut self) -> Result<ExitStatus> {
+        self.0.wait().map(Self::negate)
+    }
+
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        Ok(self.0.try_wait()?.map(Self::negate))
+    }
+}
+
+/// The last process of a compound command such as `{ command; }` whose output is piped, which
+/// returns the read end of the pipe shared by all of its commands.
+struct GroupProcess {
+    process: Box<dyn Process>,
+    stdout: Option<Stdin>,
+}
+
+impl Process for GroupProcess {
+    fn id(&self) -> Option<ProcessId> {
+        self.process.id()
+    }
+
+    fn argv(&self) -> String {
+        self.process.argv()
+    }
+
+    fn status(&self) -> ProcessStatus {
+        self.process.status()
+    }
+
+    fn status_code(&self) -> Option<ExitStatus> {
+        self.process.status_code()
+    }
+
+    fn stdout(&mut self) -> Option<Stdin> {
+        self.stdout.take()
+    }
+
+    fn kill(&mut self) -> Result<()> {
+        self.process.kill()
+    }
+
+    fn wait(&mut self) -> Result<ExitStatus> {
+        self.process.wait()
+    }
+
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        self.process.try_wait()
+    }
+}
+
 impl From<u32> for ProcessId {
     fn from(value: u32) -> Self {
         ProcessId(value)
     }
 }
 
+impl From<ProcessId> for u32 {
+    fn from(value: ProcessId) -> Self {
+        value.0
+    }
+}
+
 impl fmt::Display for ProcessStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -330,27 +738,129 @@ pub fn spawn_processes(
     })
 }
 
+/// Runs `input` and returns its stdout, along with the exit status of its last process.
+pub fn execute_command_string_capturing_output(
+    shell: &mut dyn Shell,
+    input: &str,
+) -> Result<(String, ExitStatus)> {
+    let command = parser::Command::parse(input)?;
+    let command_group = ir::Interpreter::parse(command);
+    let mut process_group = spawn_processes_with_piped_stdout(shell, &command_group)?;
+
+    // Read all output before waiting, otherwise a process could block on a full pipe.
+    let mut output = String::new();
+    if let Some(mut stdout) = process_group
+        .processes
+        .last_mut()
+        .and_then(|process| process.stdout())
+    {
+        stdout.read_to_string(&mut output).context(ErrorKind::Io)?;
+    }
+
+    let mut exit_status = ExitStatus::from_success();
+    for process in &mut process_group.processes {
+        exit_status = process.wait()?;
+    }
+
+    Ok((output, exit_status))
+}
+
+/// Like [`spawn_processes`], but the stdout of the last process is piped so the caller can read
+/// it via [`Process::stdout`].
+pub fn spawn_processes_with_piped_stdout(
+    shell: &mut dyn Shell,
+    command_group: &ir::CommandGroup,
+) -> Result<ProcessGroup> {
+    let (processes, pgid) = _spawn_processes(
+        shell,
+        &command_group.command,
+        None,
+        Some(Output::CreatePipe),
+        None,
+    )?;
+    Ok(ProcessGroup {
+        id: pgid,
+        processes,
+        foreground: !command_group.background,
+    })
+}
+
+/// Copies the stdout of the last process in `process_group` to `writer`, if it was piped.
+pub fn copy_stdout(process_group: &mut ProcessGroup, writer: &mut dyn Write) -> io::Result<u64> {
+    match process_group
+        .processes
+        .last_mut()
+        .and_then(|process| process.stdout())
+    {
+        Some(mut stdout) => io::copy(&mut stdout, writer),
+        None => Ok(0),
+    }
+}
+
+/// The process group id of spawned processes, if one was created.
+type ProcessGroupId = Option<u32>;
+
 fn _spawn_processes(
     shell: &mut dyn Shell,
     command: &ir::Command,
     stdin: Option<Stdin>,
     stdout: Option<Output>,
     pgid: Option<u32>,
-) -> Result<(Vec<Box<dyn Process>>, Option<u32>)> {
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)> {
+    // Only the command that ends a list or condition can ignore its failure, see below.
+    shell.set_errexit_ignored(false);
     match command {
         ir::Command::Simple(simple_command) => {
-            let stdin = Stdin::new(&simple_command.stdin, stdin)?;
-            let stdout = Output::new_stdout(&simple_command.stdout, stdout)?;
-            let stderr = Output::new_stderr(&simple_command.stderr, None /*pipe*/)?;
-            let (result, pgid) = run_simple_command(
+            // Expand variables just before running each command so that it sees the effects
+            // of earlier commands, e.g. `let x=1; echo $x`.
+            let words = expand_command_words(
                 shell,
-                &simple_command.program,
-                &simple_command.args,
-                stdin,
-                stdout,
-                stderr,
-                pgid,
+                iter::once(&simple_command.program).chain(&simple_command.args),
             )?;
+            if let Some(last_arg) = words.last() {
+                shell.set_last_argument(last_arg);
+            }
+            let stdin_redirect = expand_stdio(shell, &simple_command.stdin)?;
+            let stdout_redirect = expand_stdio(shell, &simple_command.stdout)?;
+            let stderr_redirect = expand_stdio(shell, &simple_command.stderr)?;
+            if shell.config().sandbox_mode {
+                for redirect in &[&stdin_redirect, &stdout_redirect, &stderr_redirect] {
+                    check_sandbox_redirect(shell.config(), redirect)?;
+                }
+            }
+            let stdin = Stdin::new(&stdin_redirect, stdin, shell.config())?;
+            let stdout = Output::new_stdout(&stdout_redirect, stdout, shell.config())?;
+            let stderr = Output::new_stderr(&stderr_redirect, None /*pipe*/, shell.config())?;
+            // `command` runs the rest of the words without looking up functions.
+            let (command_words, options) = match builtins::run_options(&words) {
+                Some((len, options))
+                    if shell.get_function(&words[0]).is_none()
+                        && builtins::is_builtin(&*shell, &words[0]) =>
+                {
+                    (&words[len..], options)
+                }
+                _ => (&words[..], RunOptions::default()),
+            };
+            let (result, pgid): (Box<dyn Process>, _) = match command_words.split_first() {
+                // Like bash, functions take precedence over builtins.
+                Some((program, args)) => match shell
+                    .get_function(program)
+                    .filter(|_| !options.skip_functions)
+                    .cloned()
+                {
+                    Some(body) => {
+                        return run_function(shell, program, &body, args, stdin, stdout, pgid)
+                    }
+                    None => run_simple_command(
+                        shell, program, args, options, stdin, stdout, stderr, pgid,
+                    )?,
+                },
+                // The command expanded to nothing, e.g. `${empty[@]}`.
+                None => {
+                    let process = BuiltinProcess::new("", &words, ExitStatus::from_success(), None);
+                    (Box::new(process), pgid)
+                }
+            };
             Ok((vec![result], pgid))
         }
         ir::Command::Connection {
@@ -358,29 +868,756 @@ fn _spawn_processes(
             ref second,
             connector,
         } => run_connection_command(shell, first, second, *connector, stdin, stdout, pgid),
+        ir::Command::Arithmetic(expression) => {
+            let process = run_arithmetic_command(shell, expression);
+            Ok((vec![Box::new(process)], pgid))
+        }
+        ir::Command::DoubleBracket(expression) => {
+            let process = run_double_bracket_command(shell, expression);
+            Ok((vec![Box::new(process)], pgid))
+        }
+        ir::Command::Subshell(command) => {
+            let stdin = stdin.unwrap_or(Stdin::Inherit);
+            let stdout = stdout.unwrap_or(Output::Inherit);
+            let (process, pgid) = run_subshell_command(shell, command, stdin, stdout, pgid)?;
+            Ok((vec![process], pgid))
+        }
+        ir::Command::Negated(command) => {
+            let (mut processes, pgid) = _spawn_processes(shell, command, stdin, stdout, pgid)?;
+            // The status of a command is the status of its last process.
+            let last = processes.pop().unwrap();
+            processes.push(Box::new(NegatedProcess(last)));
+            shell.set_errexit_ignored(true);
+            Ok((processes, pgid))
+        }
+        ir::Command::Group(command) => {
+            run_compound_command(shell, stdin, stdout, pgid, |shell, stdin, stdout, pgid| {
+                spawn_group_commands(shell, command, stdin, stdout, pgid)
+            })
+        }
+        ir::Command::If {
+            condition,
+            then_branch,
+            elif_branches,
+            else_branch,
+        } => run_compound_command(shell, stdin, stdout, pgid, |shell, stdin, stdout, pgid| {
+            let branches = iter::once((&**condition, &**then_branch)).chain(
+                elif_branches
+                    .iter()
+                    .map(|(condition, branch)| (condition, branch)),
+            );
+            spawn_if_command(shell, branches, else_branch.as_deref(), stdin, stdout, pgid)
+        }),
+        ir::Command::ForIn { var, words, body } => {
+            run_compound_command(shell, stdin, stdout, pgid, |shell, stdin, stdout, _| {
+                run_for_in_command(shell, var, words, body, stdin, stdout)
+            })
+        }
+        ir::Command::ForArith {
+            init,
+            condition,
+            step,
+            body,
+        } => run_compound_command(shell, stdin, stdout, pgid, |shell, stdin, stdout, _| {
+            let mut first_iteration = true;
+            eval_loop_arithmetic(shell, init)?;
+            run_loop(shell, "for", body, stdin, stdout, |shell| {
+                if !first_iteration {
+                    eval_loop_arithmetic(shell, step)?;
+                }
+                first_iteration = false;
+                // Like bash, an empty condition is true.
+                Ok(condition.is_empty() || eval_loop_arithmetic(shell, condition)? != 0)
+            })
+        }),
+        ir::Command::While { condition, body } => {
+            run_compound_command(shell, stdin, stdout, pgid, |shell, stdin, stdout, _| {
+                run_while_command(shell, "while", condition, true, body, stdin, stdout)
+            })
+        }
+        ir::Command::Until { condition, body } => {
+            run_compound_command(shell, stdin, stdout, pgid, |shell, stdin, stdout, _| {
+                run_while_command(shell, "until", condition, false, body, stdin, stdout)
+            })
+        }
+        ir::Command::Select { var, words, body } => {
+            run_compound_command(shell, stdin, stdout, pgid, |shell, stdin, stdout, _| {
+                run_select_command(shell, var, words, body, stdin, stdout)
+            })
+        }
+        ir::Command::FunctionDef { name, body } => {
+            let status_code = match shell.define_function(name, (**body).clone()) {
+                Ok(()) => ExitStatus::from_success(),
+                Err(e) => {
+                    print_error(shell.shell_stderr(), &e);
+                    ExitStatus::from_failure()
+                }
+            };
+            let process = BuiltinProcess::new::<_, &str>(name, &[], status_code, None);
+            Ok((vec![Box::new(process)], pgid))
+        }
+        ir::Command::Case { word, arms } => {
+            run_compound_command(shell, stdin, stdout, pgid, |shell, stdin, stdout, pgid| {
+                spawn_case_command(shell, word, arms, stdin, stdout, pgid)
+            })
+        }
+        ir::Command::ArrayAssignment {
+            declaration,
+            assignment,
+        } => {
+            let process = run_array_assignment(shell, declaration, assignment)?;
+            Ok((vec![Box::new(process)], pgid))
+        }
     }
 }
 
-fn run_simple_command<S1, S2>(
-    shell: &mut dyn Shell,
-    program: S1,
-    args: &[S2],
-    stdin: Stdin,
-    stdout: Output,
-    stderr: Output,
-    pgid: Option<u32>,
-) -> Result<(Box<dyn Process>, Option<u32>)>
+/// Expands braces, `~`, variables, command substitutions and arithmetic expansions in the words
+/// of a simple command, then expands unquoted words containing glob characters to matching paths.
+///
+/// The output of an unquoted command substitution is split into words, and is not expanded
+/// further. Single-quoted words are not expanded at all.
+fn expand_command_words<'a, I>(shell: &mut dyn Shell, words: I) -> Result<Vec<String>>
 where
-    S1: AsRef<str>,
-    S2: AsRef<str>,
+    I: IntoIterator<Item = &'a String>,
 {
-    if builtins::is_builtin(&program) {
-        run_builtin_command(shell, program, args, stdout, pgid)
+    let mut expanded = Vec::new();
+    for word in words {
+        match ast::split_quotes(word) {
+            (Quoting::Single, text) => expanded.push(text.to_string()),
+            (Quoting::Double, text) => match variable_expansion::split_substitutions(text) {
+                Some(parts) => expanded.push(substitute_commands(shell, &parts, false)?.concat()),
+                // `"$@"` and `"${name[@]}"` still expand to one word per element.
+                None => expanded.extend(variable_expansion::expand_words(
+                    iter::once(text),
+                    None::<&Path>,
+                    &*shell,
+                )?),
+            },
+            (Quoting::Unquoted, word) => {
+                for word in brace_expansion::brace_expand(word) {
+                    let fields = match variable_expansion::split_substitutions(&word) {
+                        Some(parts) => substitute_commands(shell, &parts, true)?,
+                        None => variable_expansion::expand_words(
+                            iter::once(&word),
+                            dirs::home_dir(),
+                            &*shell,
+                        )?,
+                    };
+                    for field in fields {
+                        // Like bash, an unquoted word that expands to nothing is removed, e.g.
+                        // `$unset`, unlike `''` or `"$unset"`.
+                        if field.is_empty() && !word.contains(|c| c == '\'' || c == '"') {
+                            continue;
+                        }
+                        expanded.extend(expand_glob(shell, field)?);
+                    }
+                }
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+/// Expands `word` to the paths it matches if it contains glob characters.
+///
+/// If nothing matches, the word is left unchanged, removed if `nullglob` is enabled, or is an
+/// error if `failglob` is enabled.
+fn expand_glob(shell: &dyn Shell, word: String) -> Result<Vec<String>> {
+    if !glob::has_glob_characters(&word) {
+        return Ok(vec![word]);
+    }
+    let options = GlobOptions {
+        dot_files: shell.get_option(ShellOption::Dotglob),
+        case_insensitive: shell.get_option(ShellOption::Nocaseglob),
+    };
+    let paths = glob::glob_expand_with_options(&word, options);
+    if !paths.is_empty() {
+        Ok(paths)
+    } else if shell.get_option(ShellOption::Failglob) {
+        Err(Error::no_glob_match(word))
+    } else if shell.get_option(ShellOption::Nullglob) {
+        Ok(Vec::new())
     } else {
-        run_external_command(shell, program, args, stdin, stdout, stderr, pgid)
+        Ok(vec![word])
     }
 }
 
+/// Runs the command substitutions and evaluates the arithmetic expansions in a word split into
+/// `parts`, and returns the words that the word expands to.
+///
+/// The output of a command substitution is split into words if `split_fields` is `true`.
+fn substitute_commands(
+    shell: &mut dyn Shell,
+    parts: &[variable_expansion::WordPart<'_>],
+    split_fields: bool,
+) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut current: Option<String> = None;
+    for part in parts {
+        match part {
+            variable_expansion::WordPart::Text(text) => {
+                current.get_or_insert_with(String::new).push_str(text)
+            }
+            variable_expansion::WordPart::Arithmetic(expression) => {
+                let expression = expand_arithmetic_expression(shell, expression)?;
+                let value = shell.eval_arithmetic(&expression)?;
+                current
+                    .get_or_insert_with(String::new)
+                    .push_str(&value.to_string())
+            }
+            variable_expansion::WordPart::CommandSubstitution(command)
+            | variable_expansion::WordPart::Backquoted(command) => {
+                let output = run_command_substitution(shell, command)?;
+                if !split_fields {
+                    current.get_or_insert_with(String::new).push_str(&output);
+                    continue;
+                }
+                if output.starts_with(char::is_whitespace) {
+                    words.extend(current.take());
+                }
+                for (i, field) in output.split_whitespace().enumerate() {
+                    if i > 0 {
+                        words.extend(current.take());
+                    }
+                    current.get_or_insert_with(String::new).push_str(field);
+                }
+                if output.ends_with(char::is_whitespace) {
+                    words.extend(current.take());
+                }
+            }
+        }
+    }
+    words.extend(current);
+    Ok(words)
+}
+
+/// Expands the variables, command substitutions, and nested arithmetic expansions of an
+/// arithmetic expression, e.g. `$x + $((1 + 2))`, before it is evaluated.
+fn expand_arithmetic_expression(shell: &mut dyn Shell, expression: &str) -> Result<String> {
+    let expression = variable_expansion::expand_text(expression, &*shell)?;
+    match variable_expansion::split_substitutions(&expression) {
+        Some(parts) => Ok(substitute_commands(shell, &parts, false)?.concat()),
+        None => Ok(expression),
+    }
+}
+
+/// Runs `command` in a subshell and returns its output without trailing newlines.
+fn run_command_substitution(shell: &mut dyn Shell, command: &str) -> Result<String> {
+    let mut subshell = shell.clone_for_subshell()?;
+    // Like bash, `set -e` is not inherited, which would exit this shell rather than the subshell.
+    subshell.set_option(ShellOption::Errexit, false);
+    let (mut output, _) = execute_command_string_capturing_output(&mut *subshell, command)?;
+    output.truncate(output.trim_end_matches('\n').len());
+    Ok(output)
+}
+
+/// Expands a single word that is not split into fields or glob expanded, e.g. a redirect's
+/// filename.
+fn expand_redirect_word(shell: &mut dyn Shell, word: &str) -> Result<String> {
+    let (quoting, word) = ast::split_quotes(word);
+    let home_dir = match quoting {
+        Quoting::Single => return Ok(word.to_string()),
+        Quoting::Double => None,
+        Quoting::Unquoted => dirs::home_dir(),
+    };
+    match variable_expansion::split_substitutions(word) {
+        Some(parts) => Ok(substitute_commands(shell, &parts, false)?.concat()),
+        None => variable_expansion::expand_word(word, home_dir, &*shell),
+    }
+}
+
+/// Expands variables, command substitutions and arithmetic expansions in a redirect's filename,
+/// and variables in a here-document or here-string.
+fn expand_stdio(shell: &mut dyn Shell, stdio: &ir::Stdio) -> Result<ir::Stdio> {
+    let expanded = match stdio {
+        ir::Stdio::Filename(filename) => {
+            ir::Stdio::Filename(expand_redirect_word(shell, filename)?)
+        }
+        ir::Stdio::AppendFilename(filename) => {
+            ir::Stdio::AppendFilename(expand_redirect_word(shell, filename)?)
+        }
+        ir::Stdio::HereDoc(here_doc) if here_doc.expand => ir::Stdio::HereDoc(ast::HereDoc {
+            body: variable_expansion::expand_text(&here_doc.body, &*shell)?,
+            expand: false,
+        }),
+        ir::Stdio::HereString(word) => ir::Stdio::HereString(match ast::split_quotes(word) {
+            (Quoting::Single, text) => text.to_string(),
+            (Quoting::Double, text) => variable_expansion::expand_text(text, &*shell)?,
+            (Quoting::Unquoted, word) => {
+                variable_expansion::expand_string(word, dirs::home_dir(), &*shell)?
+            }
+        }),
+        other => other.clone(),
+    };
+    Ok(expanded)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_simple_command<S1, S2>(
+    shell: &mut dyn Shell,
+    program: S1,
+    args: &[S2],
+    options: RunOptions,
+    stdin: Stdin,
+    stdout: Output,
+    stderr: Output,
+    pgid: Option<u32>,
+) -> Result<(Box<dyn Process>, Option<u32>)>
+where
+    S1: AsRef<str>,
+    S2: AsRef<str>,
+{
+    if shell.get_option(ShellOption::Xtrace) {
+        let argv = iter::once(program.as_ref())
+            .chain(args.iter().map(AsRef::as_ref))
+            .collect::<Vec<_>>()
+            .join(" ");
+        write_xtrace(shell, &argv);
+    }
+
+    if shell.config().sandbox_mode && SANDBOX_DISABLED_COMMANDS.contains(&program.as_ref()) {
+        return Err(Error::permission_denied(program));
+    }
+
+    if let Some((name, subscript, value)) = parse_array_assignment(program.as_ref()) {
+        let status_code = if shell.get_assoc_array(name).is_some() {
+            shell.set_assoc_element(name, subscript, value);
+            ExitStatus::from_success()
+        } else if let Some(index) = eval_array_index(shell, name, subscript) {
+            shell.set_array_element(name, index, value);
+            ExitStatus::from_success()
+        } else {
+            ExitStatus::from_failure()
+        };
+        let process = BuiltinProcess::new(&program, args, status_code, None);
+        return Ok((Box::new(process), pgid));
+    }
+
+    #[cfg(unix)]
+    if builtins::redirects_shell(&*shell, &program) {
+        let (stdin, stdout) = redirect_shell(stdin, stdout, stderr)?;
+        return run_builtin_command(shell, program, args, stdin, stdout, Output::Inherit, pgid);
+    }
+
+    if builtins::is_builtin(&*shell, &program) {
+        run_builtin_command(shell, program, args, stdin, stdout, stderr, pgid)
+    } else if options.use_default_path {
+        let path_var = OsStr::new(util::path::DEFAULT_PATH);
+        let path = util::path::search_in_path(program.as_ref(), path_var)
+            .ok_or_else(|| Error::command_not_found(&program))?;
+        run_external_command(
+            shell,
+            program,
+            Some(&path),
+            args,
+            stdin,
+            stdout,
+            stderr,
+            pgid,
+        )
+    } else {
+        // The sandbox searches its own `$PATH`, see `check_sandbox_path`.
+        let path = if shell.config().sandbox_mode {
+            None
+        } else {
+            builtins::find_hashed_command(shell, program.as_ref())
+        };
+        run_external_command(
+            shell,
+            program,
+            path.as_deref(),
+            args,
+            stdin,
+            stdout,
+            stderr,
+            pgid,
+        )
+    }
+}
+
+/// Passes only the variables `shell` exports to the environment of `command`, see `export`.
+pub(crate) fn set_exported_environment(shell: &dyn Shell, command: &mut Command) {
+    command.env_clear();
+    command.envs(
+        env::vars_os()
+            .filter(|(name, _)| name.to_str().map_or(false, |name| shell.is_exported(name))),
+    );
+}
+
+/// Writes `argv` to the xtrace output: the file descriptor in `$BASH_XTRACEFD`, or
+/// [`ShellConfig::trace_fd`] if it is unset or not a number. Each line is prefixed with `$PS4`.
+///
+/// Falls back to stderr if the file descriptor cannot be written to, e.g. it is not open.
+#[cfg(unix)]
+fn write_xtrace(shell: &dyn Shell, argv: &str) {
+    let trace_fd = shell
+        .get_variable(XTRACE_FD_VARIABLE)
+        .and_then(|fd| fd.trim().parse::<RawFd>().ok())
+        .unwrap_or(shell.config().trace_fd);
+    let line = format!("{}{}\n", xtrace_prefix(shell), argv);
+
+    // The shell does not own the file descriptor, so it must not be closed.
+    let mut output = ManuallyDrop::new(unsafe { File::from_raw_fd(trace_fd) });
+    if output.write_all(line.as_bytes()).is_err() {
+        eprint!("{}", line);
+    }
+}
+
+#[cfg(windows)]
+fn write_xtrace(shell: &dyn Shell, argv: &str) {
+    eprintln!("{}{}", xtrace_prefix(shell), argv);
+}
+
+fn xtrace_prefix(shell: &dyn Shell) -> String {
+    shell
+        .get_variable(XTRACE_PREFIX_VARIABLE)
+        .unwrap_or_else(|| DEFAULT_XTRACE_PREFIX.to_string())
+}
+
+/// Evaluates `(( expression ))`, which succeeds if `expression` is non-zero.
+fn run_arithmetic_command(shell: &mut dyn Shell, expression: &str) -> BuiltinProcess {
+    let argv = format!("(( {} ))", expression);
+    if shell.get_option(ShellOption::Xtrace) {
+        write_xtrace(shell, &argv);
+    }
+
+    let status_code = match shell.eval_arithmetic(expression) {
+        Ok(0) => ExitStatus::from_failure(),
+        Ok(_) => ExitStatus::from_success(),
+        Err(e) => {
+            print_error(shell.shell_stderr(), format_args!("((: {}", e));
+            ExitStatus::from_failure()
+        }
+    };
+
+    BuiltinProcess::new::<_, &str>(argv, &[], status_code, None)
+}
+
+/// Evaluates `[[ expression ]]`, which succeeds if `expression` is true.
+fn run_double_bracket_command(shell: &mut dyn Shell, expression: &str) -> BuiltinProcess {
+    let argv = format!("[[ {} ]]", expression);
+    if shell.get_option(ShellOption::Xtrace) {
+        write_xtrace(shell, &argv);
+    }
+
+    let status_code = match conditional::evaluate(expression, shell) {
+        Ok(true) => ExitStatus::from_success(),
+        Ok(false) => ExitStatus::from_failure(),
+        Err(e) => {
+            print_error(shell.shell_stderr(), format_args!("[[: {}", e));
+            ExitStatus::from_status(2)
+        }
+    };
+
+    BuiltinProcess::new::<_, &str>(argv, &[], status_code, None)
+}
+
+/// Runs `( command )` in a forked copy of the shell, so changes to the shell's state, e.g.
+/// variables and the current directory, do not affect the parent shell.
+#[cfg(unix)]
+fn run_subshell_command(
+    shell: &mut dyn Shell,
+    command: &ir::Command,
+    stdin: Stdin,
+    stdout: Output,
+    pgid: Option<u32>,
+) -> Result<(Box<dyn Process>, Option<u32>)> {
+    use nix::{
+        sys::signal::{self, SigHandler, Signal},
+        unistd::{self, ForkResult, Pid},
+    };
+
+    let (stdout_read_end, stdout) = match stdout {
+        Output::CreatePipe => {
+            let (read_end_pipe, write_end_pipe) = create_pipe()?;
+            (Some(read_end_pipe), Output::File(write_end_pipe))
+        }
+        stdout => (None, stdout),
+    };
+
+    let job_control_is_enabled = shell.is_job_control_enabled();
+    match unsafe { unistd::fork() }.context(ErrorKind::Nix)? {
+        ForkResult::Parent { child } => {
+            let pgid = pgid.unwrap_or(child.as_raw() as u32);
+            if job_control_is_enabled {
+                // Also set the process group here to avoid racing with the child, see
+                // run_external_command.
+                log_if_err!(
+                    unistd::setpgid(child, Pid::from_raw(pgid as i32)),
+                    "failed to set process group of subshell"
+                );
+            }
+
+            let process = SubshellProcess::new(child, stdout_read_end.map(Stdin::File));
+            Ok((Box::new(process), Some(pgid)))
+        }
+        ForkResult::Child => {
+            // The child must never return to the caller, as it would continue running the
+            // parent's input.
+            drop(stdout_read_end);
+            if job_control_is_enabled {
+                let pid = unistd::getpid();
+                let pgid = pgid.map(|pgid| Pid::from_raw(pgid as i32)).unwrap_or(pid);
+                log_if_err!(unistd::setpgid(pid, pgid), "setpgid failed");
+                log_if_err!(
+                    unistd::tcsetpgrp(util::unix::get_terminal(), pgid),
+                    "tcsetpgrp failed"
+                );
+                for signal in [
+                    Signal::SIGINT,
+                    Signal::SIGQUIT,
+                    Signal::SIGTSTP,
+                    Signal::SIGTTIN,
+                    Signal::SIGTTOU,
+                    Signal::SIGCHLD,
+                ] {
+                    log_if_err!(
+                        unsafe { signal::signal(signal, SigHandler::SigDfl) },
+                        "failed to reset signal handler"
+                    );
+                }
+            }
+
+            let stdin = stdin.as_raw_fd();
+            if stdin != libc::STDIN_FILENO {
+                log_if_err!(
+                    unistd::dup2(stdin, libc::STDIN_FILENO),
+                    "failed to dup stdin"
+                );
+            }
+            let stdout = match stdout {
+                Output::File(ref file) => Some(file.as_raw_fd()),
+                Output::FileDescriptor(fd) => Some(fd),
+                Output::Inherit | Output::CreatePipe => None,
+            };
+            if let Some(fd) = stdout.filter(|fd| *fd != libc::STDOUT_FILENO) {
+                log_if_err!(
+                    unistd::dup2(fd, libc::STDOUT_FILENO),
+                    "failed to dup stdout"
+                );
+            }
+
+            let code = run_subshell(shell, command, pgid);
+            let _ = io::stdout().flush();
+            process::exit(code)
+        }
+    }
+}
+
+/// Runs `command` to completion in a forked subshell, returning its exit code.
+#[cfg(unix)]
+fn run_subshell(shell: &mut dyn Shell, command: &ir::Command, pgid: Option<u32>) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    use crate::shell::{COMMAND_NOT_FOUND_EXIT_STATUS, PERMISSION_DENIED_EXIT_STATUS};
+
+    let result = _spawn_processes(shell, command, None, None, pgid)
+        .and_then(|(mut processes, _)| wait_for_all_processes(shell, &mut processes));
+
+    match result {
+        Ok(exit_status) => exit_status
+            .code()
+            .unwrap_or_else(|| 128 + exit_status.signal().unwrap_or_default()),
+        Err(e) => match e.kind() {
+            ErrorKind::CommandNotFound(command) => {
+                print_error(
+                    shell.shell_stderr(),
+                    format_args!("{}: command not found", command),
+                );
+                COMMAND_NOT_FOUND_EXIT_STATUS
+            }
+            ErrorKind::PermissionDenied(_) | ErrorKind::ArgumentListTooLong(_) => {
+                print_error(shell.shell_stderr(), &e);
+                PERMISSION_DENIED_EXIT_STATUS
+            }
+            // `break` or `continue` in a loop outside the subshell only exits the subshell.
+            ErrorKind::LoopControl(_) => 0,
+            ErrorKind::Return(code) => *code,
+            _ => {
+                print_error(shell.shell_stderr(), &e);
+                1
+            }
+        },
+    }
+}
+
+/// Runs `( command )` without fork(2): the command runs in a copy of the shell and the process
+/// state it shares with this shell, i.e. the current directory and environment, is restored
+/// afterwards.
+#[cfg(windows)]
+fn run_subshell_command(
+    shell: &mut dyn Shell,
+    command: &ir::Command,
+    stdin: Stdin,
+    stdout: Output,
+    pgid: Option<u32>,
+) -> Result<(Box<dyn Process>, Option<u32>)> {
+    let cwd = env::current_dir().context(ErrorKind::Io)?;
+    let vars: Vec<(OsString, OsString)> = env::vars_os().collect();
+
+    let mut subshell = shell.clone_for_subshell()?;
+    let result = _spawn_processes(&mut *subshell, command, Some(stdin), Some(stdout), pgid)
+        .and_then(|(mut processes, _)| wait_for_all_processes(&mut *subshell, &mut processes));
+
+    log_if_err!(env::set_current_dir(&cwd), "failed to restore directory");
+    for (key, _) in env::vars_os() {
+        env::remove_var(key);
+    }
+    for (key, value) in vars {
+        env::set_var(key, value);
+    }
+
+    let process = BuiltinProcess::new::<_, &str>("( ... )", &[], result?, None);
+    Ok((Box::new(process), pgid))
+}
+
+/// Parses an array element assignment, e.g. `name[1]=value` or `name[key]=value`.
+fn parse_array_assignment(word: &str) -> Option<(&str, &str, &str)> {
+    let (lhs, value) = word.split_at(word.find("]=")? + 1);
+    let (name, subscript) = variable_expansion::split_subscript(lhs)?;
+    if !variable_expansion::is_identifier(name) {
+        return None;
+    }
+
+    Some((name, subscript, &value[1..]))
+}
+
+/// Runs an array assignment, e.g. `name=(a b)`, or one that is the last argument of a
+/// declaration, e.g. `declare -r name=(a b)`, which then sets the other attributes of the array.
+fn run_array_assignment(
+    shell: &mut dyn Shell,
+    declaration: &[String],
+    assignment: &ast::ArrayAssignment,
+) -> Result<BuiltinProcess> {
+    let argv = ir::Command::ArrayAssignment {
+        declaration: declaration.to_vec(),
+        assignment: assignment.clone(),
+    }
+    .to_string();
+    if shell.get_option(ShellOption::Xtrace) {
+        write_xtrace(shell, &argv);
+    }
+
+    let words = expand_command_words(shell, declaration)?;
+    let flags: String = words
+        .iter()
+        .skip(1)
+        .filter_map(|word| word.strip_prefix('-'))
+        .collect();
+    let mut status_code = assign_array(shell, assignment, flags.contains('A'))?;
+    // `declare` without options has no attributes to set, unlike `export` and `readonly`.
+    if let Some((program, options)) = words.split_first() {
+        if status_code.success()
+            && (!options.is_empty() || program != "declare")
+            && builtins::is_builtin(&*shell, program)
+        {
+            let args: Vec<&str> = options
+                .iter()
+                .map(String::as_str)
+                .chain(iter::once(assignment.name.as_str()))
+                .collect();
+            status_code = builtins::run(shell, program, &args, &mut io::stdout()).0;
+        }
+    }
+    Ok(BuiltinProcess::new::<_, &str>(argv, &[], status_code, None))
+}
+
+/// Assigns the expanded elements of `assignment` to its array, which is an associative array if
+/// `assoc` is `true` or it already is one.
+///
+/// Elements of the form `[subscript]=value` set that subscript, which is required for
+/// associative arrays. Later elements of an indexed array follow the last subscript.
+fn assign_array(
+    shell: &mut dyn Shell,
+    assignment: &ast::ArrayAssignment,
+    assoc: bool,
+) -> Result<ExitStatus> {
+    let name = assignment.name.as_str();
+    if let Err(e) = shell.check_variable_assignment(name) {
+        print_error(shell.shell_stderr(), &e);
+        return Ok(ExitStatus::from_failure());
+    }
+
+    let mut status_code = ExitStatus::from_success();
+    let value = if assoc || shell.get_assoc_array(name).is_some() {
+        let mut assoc_array = match shell.get_value(name) {
+            Some(VarValue::Assoc(assoc_array)) if assignment.append => assoc_array,
+            _ => HashMap::new(),
+        };
+        for element in &assignment.elements {
+            match &element.subscript {
+                Some(key) => {
+                    let key = expand_command_words(shell, iter::once(key))?.join(" ");
+                    let value = expand_command_words(shell, iter::once(&element.value))?;
+                    assoc_array.insert(key, value.join(" "));
+                }
+                None => {
+                    print_error(
+                        shell.shell_stderr(),
+                        format_args!(
+                            "{}: {}: must use subscript when assigning associative array",
+                            name, element.value
+                        ),
+                    );
+                    status_code = ExitStatus::from_failure();
+                }
+            }
+        }
+        VarValue::Assoc(assoc_array)
+    } else {
+        // Like bash, appending to a scalar variable makes it the first element.
+        let mut array = match shell.get_value(name) {
+            Some(VarValue::Indexed(array)) if assignment.append => array,
+            Some(VarValue::Scalar(value)) if assignment.append => iter::once((0, value)).collect(),
+            _ => BTreeMap::new(),
+        };
+        let mut index = array.keys().next_back().map_or(0, |last| last + 1);
+        for element in &assignment.elements {
+            let values = match &element.subscript {
+                Some(subscript) => match eval_array_index(shell, name, subscript) {
+                    Some(i) => {
+                        index = i;
+                        // `[i]=word` is one element, even if the word expands to several.
+                        let value = expand_command_words(shell, iter::once(&element.value))?;
+                        vec![value.join(" ")]
+                    }
+                    None => {
+                        status_code = ExitStatus::from_failure();
+                        continue;
+                    }
+                },
+                None => expand_command_words(shell, iter::once(&element.value))?,
+            };
+            for value in values {
+                array.insert(index, value);
+                index += 1;
+            }
+        }
+        VarValue::Indexed(array)
+    };
+    shell.set_value(name, value);
+    Ok(status_code)
+}
+
+/// Evaluates the subscript of an element of the indexed array `name` as an arithmetic
+/// expression, e.g. `i + 1` of `name[i + 1]=value`. Prints an error if it is not a valid index.
+fn eval_array_index(shell: &mut dyn Shell, name: &str, subscript: &str) -> Option<usize> {
+    let index = match subscript.trim() {
+        "" => None,
+        subscript => expand_arithmetic_expression(shell, subscript)
+            .and_then(|expression| shell.eval_arithmetic(&expression))
+            .ok()
+            .filter(|index| *index >= 0),
+    };
+    if index.is_none() {
+        print_error(
+            shell.shell_stderr(),
+            format_args!("{}[{}]: bad array subscript", name, subscript),
+        );
+    }
+    index.map(|index| index as usize)
+}
+
 fn run_connection_command(
     shell: &mut dyn Shell,
     first: &ir::Command,
@@ -389,7 +1626,7 @@ fn run_connection_command(
     stdin: Option<Stdin>,
     stdout: Option<Output>,
     pgid: Option<u32>,
-) -> Result<(Vec<Box<dyn Process>>, Option<u32>)> {
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)> {
     match connector {
         ast::Connector::Pipe => {
             let (mut first_result, pgid) =
@@ -404,80 +1641,611 @@ fn run_connection_command(
             first_result.extend(second_result);
             Ok((first_result, pgid))
         }
+        // The first command of a list has completed once the second one runs, so only the
+        // processes of the second one make up the list's status, e.g. for `$PIPESTATUS` and
+        // `set -o pipefail`.
         ast::Connector::Semicolon => {
             let (mut first_result, _) = _spawn_processes(shell, first, stdin, None, pgid)?;
-            first_result.last_mut().unwrap().wait()?;
-            let (second_result, pgid) = _spawn_processes(shell, second, None, stdout, None)?;
-            first_result.extend(second_result);
-            Ok((first_result, pgid))
+            if !wait_for_list_command(shell, &mut first_result)?.success() {
+                handle_command_failure(shell);
+            }
+            _spawn_processes(shell, second, None, stdout, None)
         }
         ast::Connector::And => {
             let (mut first_result, _) = _spawn_processes(shell, first, stdin, None, pgid)?;
-            first_result.last_mut().unwrap().wait()?;
-            let pgid = if first_result
-                .last()
-                .unwrap()
-                .status_code()
-                .unwrap()
-                .success()
-            {
-                let (second_result, pgid) = _spawn_processes(shell, second, None, stdout, None)?;
-                first_result.extend(second_result);
-                pgid
+            if wait_for_list_command(shell, &mut first_result)?.success() {
+                _spawn_processes(shell, second, None, stdout, None)
             } else {
-                None
-            };
-            Ok((first_result, pgid))
+                shell.set_errexit_ignored(true);
+                Ok((first_result, None))
+            }
         }
         ast::Connector::Or => {
             let (mut first_result, _) = _spawn_processes(shell, first, stdin, None, pgid)?;
-            first_result.last_mut().unwrap().wait()?;
-            let pgid = if !first_result
-                .last()
-                .unwrap()
-                .status_code()
-                .unwrap()
-                .success()
-            {
-                let (second_result, pgid) = _spawn_processes(shell, second, None, stdout, None)?;
-                first_result.extend(second_result);
-                pgid
+            if !wait_for_list_command(shell, &mut first_result)?.success() {
+                _spawn_processes(shell, second, None, stdout, None)
             } else {
-                None
+                Ok((first_result, None))
+            }
+        }
+    }
+}
+
+/// Runs a compound command such as `{ command; }` in the current shell, whose commands are
+/// spawned by `spawn` and all read from `stdin` and write to `stdout`.
+///
+/// Like other lists, each command is waited for before the next one is run, so a compound
+/// command whose output is piped must not write more than the pipe's buffer before its last
+/// command.
+fn run_compound_command<F>(
+    shell: &mut dyn Shell,
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+    pgid: Option<u32>,
+    spawn: F,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)>
+where
+    F: FnOnce(
+        &mut dyn Shell,
+        Option<Stdin>,
+        Option<Output>,
+        Option<u32>,
+    ) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)>,
+{
+    let (stdout_read_end, stdout) = match stdout {
+        Some(Output::CreatePipe) => {
+            let (read_end_pipe, write_end_pipe) = create_pipe()?;
+            (Some(read_end_pipe), Some(Output::File(write_end_pipe)))
+        }
+        stdout => (None, stdout),
+    };
+
+    let (mut processes, pgid) = spawn(shell, stdin, stdout, pgid)?;
+    if let Some(read_end_pipe) = stdout_read_end {
+        let last = processes.pop().unwrap();
+        processes.push(Box::new(GroupProcess {
+            process: last,
+            stdout: Some(read_end_pipe.into()),
+        }));
+    }
+    Ok((processes, pgid))
+}
+
+/// Spawns the commands of a group's list, giving each of them a copy of `stdin` and `stdout`.
+fn spawn_group_commands(
+    shell: &mut dyn Shell,
+    command: &ir::Command,
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+    pgid: Option<u32>,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)> {
+    let (first, second, connector) = match command {
+        ir::Command::Connection {
+            ref first,
+            ref second,
+            connector,
+        } if *connector != ast::Connector::Pipe => (first, second, *connector),
+        command => return _spawn_processes(shell, command, stdin, stdout, pgid),
+    };
+
+    let first_stdin = stdin.as_ref().map(Stdin::try_clone).transpose()?;
+    let first_stdout = stdout.as_ref().map(Output::try_clone).transpose()?;
+    let (mut first_result, _) =
+        spawn_group_commands(shell, first, first_stdin, first_stdout, pgid)?;
+    let status = wait_for_list_command(shell, &mut first_result)?;
+    let run_second = match connector {
+        ast::Connector::And => status.success(),
+        ast::Connector::Or => !status.success(),
+        _ => {
+            if !status.success() {
+                handle_command_failure(shell);
+            }
+            true
+        }
+    };
+    if !run_second {
+        if connector == ast::Connector::And {
+            shell.set_errexit_ignored(true);
+        }
+        return Ok((first_result, None));
+    }
+    spawn_group_commands(shell, second, stdin, stdout, None)
+}
+
+/// Spawns the branch of `if ...; fi` whose condition succeeds, or `else_branch` if none do.
+///
+/// The exit status of a condition is not stored in `$?`, and is `0` if no branch is run.
+fn spawn_if_command<'a, I>(
+    shell: &mut dyn Shell,
+    branches: I,
+    else_branch: Option<&ir::Command>,
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+    mut pgid: Option<u32>,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)>
+where
+    I: IntoIterator<Item = (&'a ir::Command, &'a ir::Command)>,
+{
+    let mut branch_to_run = else_branch;
+    for (condition, branch) in branches {
+        let condition_stdin = stdin.as_ref().map(Stdin::try_clone).transpose()?;
+        let condition_stdout = stdout.as_ref().map(Output::try_clone).transpose()?;
+        let success = run_condition(shell, |shell| {
+            let (mut condition_result, _) = spawn_group_commands(
+                shell,
+                condition,
+                condition_stdin,
+                condition_stdout,
+                pgid.take(),
+            )?;
+            Ok(wait_for_processes(shell, &mut condition_result)?.success())
+        })?;
+        if success {
+            branch_to_run = Some(branch);
+            break;
+        }
+    }
+
+    match branch_to_run {
+        Some(branch) => spawn_group_commands(shell, branch, stdin, stdout, None),
+        None => {
+            let process =
+                BuiltinProcess::new::<_, &str>("if", &[], ExitStatus::from_success(), None);
+            Ok((vec![Box::new(process)], None))
+        }
+    }
+}
+
+/// Runs the function `name` in the current shell with the positional parameters set to `args`,
+/// and then restores them.
+///
+/// If the function runs `return`, its exit status is the status given to `return`.
+fn run_function(
+    shell: &mut dyn Shell,
+    name: &str,
+    body: &ir::Command,
+    args: &[String],
+    stdin: Stdin,
+    stdout: Output,
+    pgid: Option<u32>,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)> {
+    let params = shell.positional_params().to_vec();
+    let function_depth = shell.function_depth();
+    shell.set_positional_params(args.to_vec());
+    shell.set_function_depth(function_depth + 1);
+    let result = run_compound_command(
+        shell,
+        Some(stdin),
+        Some(stdout),
+        pgid,
+        |shell, stdin, stdout, pgid| spawn_group_commands(shell, body, stdin, stdout, pgid),
+    );
+    shell.set_function_depth(function_depth);
+    shell.set_positional_params(params);
+    // Like bash, a failing function call is not ignored, even if its last command was.
+    shell.set_errexit_ignored(false);
+
+    match result {
+        Err(e) => match *e.kind() {
+            ErrorKind::Return(code) => {
+                let status_code = ExitStatus::from_status(code);
+                let process = BuiltinProcess::new(name, args, status_code, None);
+                Ok((vec![Box::new(process)], None))
+            }
+            _ => Err(e),
+        },
+        result => result,
+    }
+}
+
+/// Spawns the command of the first arm of `case word in ...; esac` with a pattern matching the
+/// expanded `word`. Quoted patterns match literally.
+///
+/// The exit status is `0` if no arm matches.
+fn spawn_case_command(
+    shell: &mut dyn Shell,
+    word: &str,
+    arms: &[(Vec<String>, ir::Command)],
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+    pgid: Option<u32>,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)> {
+    let word = expand_redirect_word(shell, word)?;
+    for (patterns, command) in arms {
+        for pattern in patterns {
+            let (quoting, _) = ast::split_quotes(pattern);
+            let expanded = expand_redirect_word(shell, pattern)?;
+            let matches = match quoting {
+                Quoting::Unquoted => glob::pattern_matches(&expanded, &word),
+                _ => expanded == word,
             };
-            Ok((first_result, pgid))
+            if matches {
+                return spawn_group_commands(shell, command, stdin, stdout, pgid);
+            }
         }
     }
+
+    let process = BuiltinProcess::new::<_, &str>("case", &[], ExitStatus::from_success(), None);
+    Ok((vec![Box::new(process)], None))
+}
+
+/// Runs `body` of `for var in words; do body; done` with `var` set to each of the expanded
+/// `words`, and then restores `var` to its value before the loop.
+fn run_for_in_command(
+    shell: &mut dyn Shell,
+    var: &str,
+    words: &[String],
+    body: &ir::Command,
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)> {
+    shell.check_variable_assignment(var)?;
+    let words = expand_command_words(shell, words)?;
+    let previous_value = shell.get_variable(var);
+
+    let mut words = words.iter();
+    let result = run_loop(shell, "for", body, stdin, stdout, |shell| {
+        Ok(match words.next() {
+            Some(word) => {
+                shell.set_variable(var, word);
+                true
+            }
+            None => false,
+        })
+    });
+
+    match previous_value {
+        Some(value) => shell.set_variable(var, &value),
+        None => shell.unset_variable(var),
+    }
+    result
+}
+
+/// Runs `body` of `select var in words; do body; done` with `var` set to the word chosen by each
+/// line read from `stdin`, and `$REPLY` set to the line, until end of file.
+///
+/// The menu of words is written to stderr before the first prompt and after each empty line. The
+/// prompt is `$PS3`. A line that is not the number of a word sets `var` to the empty string.
+/// Unlike `for`, `var` keeps the chosen word after the loop, e.g. after `break`.
+fn run_select_command(
+    shell: &mut dyn Shell,
+    var: &str,
+    words: &[String],
+    body: &ir::Command,
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)> {
+    shell.check_variable_assignment(var)?;
+    shell.check_variable_assignment(SELECT_REPLY_VARIABLE)?;
+    let words = expand_command_words(shell, words)?;
+    if words.is_empty() {
+        let process =
+            BuiltinProcess::new::<_, &str>("select", &[], ExitStatus::from_success(), None);
+        return Ok((vec![Box::new(process)], None));
+    }
+
+    let mut input = stdin.as_ref().map(Stdin::try_clone).transpose()?;
+    let mut show_menu = true;
+    run_loop(shell, "select", body, stdin, stdout, |shell| loop {
+        if show_menu {
+            let mut stderr = io::stderr();
+            for (i, word) in words.iter().enumerate() {
+                writeln!(stderr, "{}) {}", i + 1, word).context(ErrorKind::Io)?;
+            }
+        }
+        let prompt = shell
+            .get_variable("PS3")
+            .unwrap_or_else(|| DEFAULT_SELECT_PROMPT.to_string());
+        let line = match read_select_line(shell, input.as_mut(), &prompt)? {
+            Some(line) => line,
+            None => return Ok(false),
+        };
+
+        show_menu = line.trim().is_empty();
+        if show_menu {
+            continue;
+        }
+        let word = line
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| words.get(i));
+        shell.set_variable(SELECT_REPLY_VARIABLE, &line);
+        shell.set_variable(var, word.map_or("", String::as_str));
+        return Ok(true);
+    })
+}
+
+/// Displays `prompt` and reads a line of `select` input from `input`, or from the shell's stdin
+/// if `input` is inherited, or returns `None` at end of file.
+fn read_select_line(
+    shell: &mut dyn Shell,
+    input: Option<&mut Stdin>,
+    prompt: &str,
+) -> Result<Option<String>> {
+    let file: &mut dyn Read = match input {
+        Some(Stdin::File(file)) => file,
+        Some(Stdin::Child(child)) => child,
+        _ if shell.is_interactive() && shell.config().enable_line_editing => {
+            return shell.editor_mut().readline(prompt);
+        }
+        _ => {
+            write!(io::stderr(), "{}", prompt).context(ErrorKind::Io)?;
+            return shell.editor_mut().readline_without_editing("");
+        }
+    };
+
+    write!(io::stderr(), "{}", prompt).context(ErrorKind::Io)?;
+    // Reads one byte at a time so that the rest of the input is left for the loop's body.
+    let mut line = Vec::new();
+    let mut byte = [0; 1];
+    loop {
+        if file.read(&mut byte).context(ErrorKind::Io)? == 0 {
+            if line.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+        if byte[0] == b'\n' {
+            break;
+        }
+        line.push(byte[0]);
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// Runs `body` of the loop `name` while the exit status of `condition` is successful, or while it
+/// is unsuccessful if `success` is `false`, i.e. for `until`.
+///
+/// Like the conditions of `if`, the exit status of `condition` is not stored in `$?`.
+fn run_while_command(
+    shell: &mut dyn Shell,
+    name: &str,
+    condition: &ir::Command,
+    success: bool,
+    body: &ir::Command,
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)> {
+    let condition_stdin = stdin.as_ref().map(Stdin::try_clone).transpose()?;
+    let condition_stdout = stdout.as_ref().map(Output::try_clone).transpose()?;
+    run_loop(shell, name, body, stdin, stdout, |shell| {
+        run_condition(shell, |shell| {
+            let (mut processes, _) = spawn_group_commands(
+                shell,
+                condition,
+                condition_stdin.as_ref().map(Stdin::try_clone).transpose()?,
+                condition_stdout
+                    .as_ref()
+                    .map(Output::try_clone)
+                    .transpose()?,
+                None,
+            )?;
+            Ok(wait_for_processes(shell, &mut processes)?.success() == success)
+        })
+    })
+}
+
+/// Evaluates an expression of `for (( init; condition; step ))`, expanding variables first, e.g.
+/// `$n`. An empty expression evaluates to 0.
+fn eval_loop_arithmetic(shell: &mut dyn Shell, expression: &str) -> Result<i64> {
+    if expression.is_empty() {
+        return Ok(0);
+    }
+    let expression = expand_arithmetic_expression(shell, expression)?;
+    shell.eval_arithmetic(&expression)
+}
+
+/// Runs `body` of the loop `name` while `next_iteration` returns `true`, giving each iteration a
+/// copy of `stdin` and `stdout`, and handling `break` and `continue`.
+///
+/// The loop's exit status is the status of the last iteration, or 0 if `body` never ran.
+fn run_loop<F>(
+    shell: &mut dyn Shell,
+    name: &str,
+    body: &ir::Command,
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+    next_iteration: F,
+) -> Result<(Vec<Box<dyn Process>>, ProcessGroupId)>
+where
+    F: FnMut(&mut dyn Shell) -> Result<bool>,
+{
+    let loop_depth = shell.loop_depth();
+    shell.set_loop_depth(loop_depth + 1);
+    let result = run_loop_iterations(shell, body, stdin, stdout, next_iteration);
+    shell.set_loop_depth(loop_depth);
+
+    let process = BuiltinProcess::new::<_, &str>(name, &[], result?, None);
+    Ok((vec![Box::new(process)], None))
+}
+
+fn run_loop_iterations<F>(
+    shell: &mut dyn Shell,
+    body: &ir::Command,
+    stdin: Option<Stdin>,
+    stdout: Option<Output>,
+    mut next_iteration: F,
+) -> Result<ExitStatus>
+where
+    F: FnMut(&mut dyn Shell) -> Result<bool>,
+{
+    let mut exit_status = ExitStatus::from_success();
+    while next_iteration(shell)? {
+        let body_stdin = stdin.as_ref().map(Stdin::try_clone).transpose()?;
+        let body_stdout = stdout.as_ref().map(Output::try_clone).transpose()?;
+        let result = spawn_group_commands(shell, body, body_stdin, body_stdout, None)
+            .and_then(|(mut processes, _)| wait_for_list_command(shell, &mut processes));
+        let control = match result {
+            Ok(status) => {
+                exit_status = status;
+                continue;
+            }
+            Err(e) => match *e.kind() {
+                ErrorKind::LoopControl(control) => control,
+                _ => return Err(e),
+            },
+        };
+
+        exit_status = ExitStatus::from_success();
+        shell.set_last_exit_status(exit_status);
+        match control {
+            LoopControl::Break(1) => break,
+            LoopControl::Continue(1) => continue,
+            // Unwind to the enclosing loop.
+            LoopControl::Break(n) => return Err(Error::loop_control(LoopControl::Break(n - 1))),
+            LoopControl::Continue(n) => {
+                return Err(Error::loop_control(LoopControl::Continue(n - 1)))
+            }
+        }
+    }
+    Ok(exit_status)
+}
+
+/// Waits for the first command of a list, e.g. `cmd1; cmd2`, and records its exit status and
+/// those of its processes so that the next command sees them as `$?` and `$PIPESTATUS`.
+fn wait_for_list_command(
+    shell: &mut dyn Shell,
+    processes: &mut [Box<dyn Process>],
+) -> Result<ExitStatus> {
+    let statuses = processes
+        .iter_mut()
+        .map(|process| process.wait())
+        .collect::<Result<Vec<_>>>()?;
+    let status = pipefail_status(shell, &statuses)
+        .or_else(|| statuses.last().copied())
+        .unwrap_or_else(ExitStatus::from_success);
+    shell.set_last_exit_status(status);
+    shell.set_pipeline_status(statuses);
+    shell.run_signal_traps();
+    Ok(status)
+}
+
+/// Waits for the processes of a command and returns its exit status, see
+/// [`wait_for_all_processes`].
+fn wait_for_processes(shell: &dyn Shell, processes: &mut [Box<dyn Process>]) -> Result<ExitStatus> {
+    if !shell.get_option(ShellOption::Pipefail) {
+        return processes.last_mut().unwrap().wait();
+    }
+    wait_for_all_processes(shell, processes)
+}
+
+/// Waits for all `processes`, e.g. of a subshell, and returns their exit status: the status of
+/// the last process, unless [`pipefail_status`] applies.
+fn wait_for_all_processes(
+    shell: &dyn Shell,
+    processes: &mut [Box<dyn Process>],
+) -> Result<ExitStatus> {
+    let statuses = processes
+        .iter_mut()
+        .map(|process| process.wait())
+        .collect::<Result<Vec<_>>>()?;
+    Ok(pipefail_status(shell, &statuses)
+        .or_else(|| statuses.last().copied())
+        .unwrap_or_else(ExitStatus::from_success))
+}
+
+/// Returns the exit status of the last process that failed, with `set -o pipefail`, which is
+/// then the exit status of a pipeline whose processes exited with `statuses`.
+pub(crate) fn pipefail_status(shell: &dyn Shell, statuses: &[ExitStatus]) -> Option<ExitStatus> {
+    if !shell.get_option(ShellOption::Pipefail) {
+        return None;
+    }
+    statuses
+        .iter()
+        .rev()
+        .find(|status| !status.success())
+        .copied()
+}
+
+/// Evaluates the condition of `if`, `while`, or `until` with `run`, in which failures are ignored,
+/// see [`Shell::condition_depth`].
+fn run_condition<F>(shell: &mut dyn Shell, run: F) -> Result<bool>
+where
+    F: FnOnce(&mut dyn Shell) -> Result<bool>,
+{
+    let condition_depth = shell.condition_depth();
+    shell.set_condition_depth(condition_depth + 1);
+    let result = run(shell);
+    shell.set_condition_depth(condition_depth);
+    result
+}
+
+/// Handles the failure of the last command unless it is ignored, e.g. in the condition of `if`:
+/// runs the ERR trap and, with `set -e`, exits the shell.
+pub(crate) fn handle_command_failure(shell: &mut dyn Shell) {
+    let ignored = shell.condition_depth() > 0 || shell.errexit_ignored();
+    shell.set_errexit_ignored(false);
+    if ignored {
+        return;
+    }
+    shell.run_trap(ERR_TRAP);
+    if shell.get_option(ShellOption::Errexit) {
+        shell.exit(None);
+    }
 }
 
 fn run_builtin_command<S1, S2>(
     shell: &mut dyn Shell,
     program: S1,
     args: &[S2],
+    stdin: Stdin,
     stdout: Output,
+    stderr: Output,
     pgid: Option<u32>,
 ) -> Result<(Box<dyn Process>, Option<u32>)>
 where
     S1: AsRef<str>,
     S2: AsRef<str>,
 {
+    // Builtins such as `read` read the shell's stdin, so redirected input is moved there while
+    // they run.
+    let _stdin_guard = StdinGuard::redirect(&stdin)?;
     // TODO(rogardn): change Result usage in builtin to only be for rust
     // errors, e.g. builtin::execute shouldn't return a Result
-    let (status_code, output) = match stdout {
-        Output::File(mut file) => (builtins::run(shell, &program, args, &mut file).0, None),
-        Output::FileDescriptor(_fd) => unimplemented!(),
+    let ((status_code, result), output) = match stdout {
+        Output::File(mut file) => (builtins::run(shell, &program, args, &mut file), None),
+        #[cfg(unix)]
+        Output::FileDescriptor(fd) => {
+            let mut file = match stderr {
+                // Builtins do not write to stderr themselves, so its redirect is followed, e.g.
+                // for `echo foo 2>file >&2`.
+                Output::File(file) if fd == libc::STDERR_FILENO => file,
+                _ => {
+                    // Write to a duplicate so that `fd` stays open when the file is closed.
+                    let fd = nix::unistd::dup(fd).context(ErrorKind::Nix)?;
+                    // It is safe to call from_raw_fd here because the duplicated fd is not owned
+                    // by anything else.
+                    unsafe { File::from_raw_fd(fd) }
+                }
+            };
+            (builtins::run(shell, &program, args, &mut file), None)
+        }
+        #[cfg(windows)]
+        Output::FileDescriptor(_) => {
+            // TODO (#22): Support Windows
+            unimplemented!()
+        }
         Output::CreatePipe => {
             let (read_end_pipe, mut write_end_pipe) = create_pipe()?;
             (
-                builtins::run(shell, &program, args, &mut write_end_pipe).0,
+                builtins::run(shell, &program, args, &mut write_end_pipe),
                 Some(read_end_pipe.into()),
             )
         }
         Output::Inherit => (
-            builtins::run(shell, &program, args, &mut io::stdout()).0,
+            builtins::run(shell, &program, args, &mut io::stdout()),
             None,
         ),
     };
+    // `break` and `continue` unwind to the loop running them, see `run_loop`, and `return` to
+    // the function running it, see `run_function`.
+    if let Err(e) = result {
+        if let ErrorKind::LoopControl(_) | ErrorKind::Return(_) = *e.kind() {
+            return Err(e);
+        }
+    }
 
     Ok((
         Box::new(BuiltinProcess::new(&program, args, status_code, output)),
@@ -485,10 +2253,14 @@ where
     ))
 }
 
+/// Runs `program`, which is searched for in `$PATH` unless its `path` is known, e.g. from the
+/// cache of `hash`.
 #[cfg(unix)]
+#[allow(clippy::too_many_arguments)]
 fn run_external_command<S1, S2>(
     shell: &dyn Shell,
     program: S1,
+    path: Option<&Path>,
     args: &[S2],
     stdin: Stdin,
     stdout: Output,
@@ -506,10 +2278,20 @@ where
         unistd::{self, Pid},
     };
 
-    use crate::util;
+    if let Some(ref allowed_dirs) = shell.config().restrict_path {
+        check_restrict_path(&program, allowed_dirs)?;
+    }
+    check_argument_limits(shell.config(), &program, args)?;
 
-    let mut command = Command::new(OsStr::new(program.as_ref()));
+    let mut command =
+        Command::new(path.map_or_else(|| OsStr::new(program.as_ref()), Path::as_os_str));
+    command.arg0(program.as_ref());
     command.args(args.iter().map(AsRef::as_ref).map(OsStr::new));
+    set_exported_environment(shell, &mut command);
+    if shell.config().sandbox_mode {
+        let path_var = check_sandbox_path(&program, &shell.config().sandbox_blocked_paths)?;
+        command.env("PATH", path_var);
+    }
 
     // Configure stdout and stderr (e.g. pipe, redirect). Do not configure
     // stdin, as we need to do that manually in before_exec *after* we have
@@ -583,17 +2365,17 @@ where
                 unistd::close(stdin).expect("failed to close stdin");
             }
 
+            // Unlike stdin, the duplicated file descriptors are left open, e.g. `2>&1` must not
+            // close stdout.
             if let Some(fd) = stdout_fd {
                 if fd != libc::STDOUT_FILENO {
                     unistd::dup2(fd, libc::STDOUT_FILENO).expect("failed to dup stdout");
-                    unistd::close(fd).expect("failed to close stdout");
                 }
             }
 
             if let Some(fd) = stderr_fd {
                 if fd != libc::STDERR_FILENO {
                     unistd::dup2(fd, libc::STDERR_FILENO).expect("failed to dup stderr");
-                    unistd::close(fd).expect("failed to close stderr");
                 }
             }
 
@@ -643,9 +2425,11 @@ where
 }
 
 #[cfg(windows)]
+#[allow(clippy::too_many_arguments)]
 fn run_external_command<S1, S2>(
-    _shell: &Shell,
+    shell: &Shell,
     program: S1,
+    path: Option<&Path>,
     args: &[S2],
     stdin: Stdin,
     stdout: Output,
@@ -670,8 +2454,19 @@ where
         ));
     }
 
-    let mut command = Command::new(OsStr::new(program.as_ref()));
+    if let Some(ref allowed_dirs) = shell.config().restrict_path {
+        check_restrict_path(&program, allowed_dirs)?;
+    }
+    check_argument_limits(shell.config(), &program, args)?;
+
+    let mut command =
+        Command::new(path.map_or_else(|| OsStr::new(program.as_ref()), Path::as_os_str));
     command.args(args.iter().map(AsRef::as_ref).map(OsStr::new));
+    set_exported_environment(shell, &mut command);
+    if shell.config().sandbox_mode {
+        let path_var = check_sandbox_path(&program, &shell.config().sandbox_blocked_paths)?;
+        command.env("PATH", path_var);
+    }
     command.stdin(stdin);
     command.stdout(stdout);
     command.stderr(stderr);
@@ -691,12 +2486,276 @@ where
     ))
 }
 
+/// Verifies that `program` resolves to an executable in one of `allowed_dirs`.
+pub(crate) fn check_restrict_path<S: AsRef<str>>(
+    program: S,
+    allowed_dirs: &[PathBuf],
+) -> Result<()> {
+    let path_var = env::var_os("PATH").unwrap_or_default();
+    let path = match util::path::search_in_path(program.as_ref(), &path_var) {
+        Some(path) => path,
+        None => return Err(Error::command_not_found(program)),
+    };
+
+    // Resolve symlinks and `..` in the directory, e.g. `/usr/bin/../../tmp/x`, but not in the
+    // file name, as commands may be symlinks to files outside of the allowed directories.
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let is_allowed = dir.canonicalize().map_or(false, |dir| {
+        allowed_dirs.iter().any(|allowed_dir| {
+            allowed_dir
+                .canonicalize()
+                .map_or(false, |allowed_dir| dir.starts_with(allowed_dir))
+        })
+    });
+    if is_allowed {
+        Ok(())
+    } else {
+        Err(Error::permission_denied(program))
+    }
+}
+
+/// Verifies that `program` does not resolve to an executable in one of `blocked_dirs`, returning
+/// `$PATH` without them.
+pub(crate) fn check_sandbox_path<S: AsRef<str>>(
+    program: S,
+    blocked_dirs: &[PathBuf],
+) -> Result<OsString> {
+    let path_var = env::var_os("PATH").unwrap_or_default();
+    let allowed_dirs = env::split_paths(&path_var).filter(|dir| !blocked_dirs.contains(dir));
+    let allowed_path_var = env::join_paths(allowed_dirs).context(ErrorKind::Io)?;
+    match util::path::search_in_path(program.as_ref(), &allowed_path_var) {
+        Some(path) if blocked_dirs.iter().any(|dir| path.starts_with(dir)) => {
+            Err(Error::permission_denied(program))
+        }
+        Some(_) => Ok(allowed_path_var),
+        None if util::path::search_in_path(program.as_ref(), &path_var).is_some() => {
+            Err(Error::permission_denied(program))
+        }
+        None => Err(Error::command_not_found(program)),
+    }
+}
+
+/// Verifies that a file redirect refers to a file inside the configured sandbox directory.
+fn check_sandbox_redirect(config: &ShellConfig, redirect: &ir::Stdio) -> Result<()> {
+    let filename = match redirect {
+        ir::Stdio::Filename(filename) | ir::Stdio::AppendFilename(filename) => filename,
+        _ => return Ok(()),
+    };
+
+    // Resolve symlinks and `..` in the parent directory, as the file may not exist yet.
+    let path = Path::new(filename);
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let is_inside_sandbox = match (&config.sandbox_dir, parent.canonicalize()) {
+        (Some(sandbox_dir), Ok(parent)) => {
+            sandbox_dir.canonicalize().map_or(false, |sandbox_dir| {
+                parent.starts_with(sandbox_dir) && path.file_name().is_some()
+            })
+        }
+        _ => false,
+    };
+
+    if is_inside_sandbox {
+        Ok(())
+    } else {
+        Err(Error::permission_denied(filename))
+    }
+}
+
+/// Verifies that the shell may open another file for a redirection without exceeding
+/// [`ShellConfig::max_open_files`] or the process's limit on open files.
+#[cfg(unix)]
+fn check_open_files(config: &ShellConfig) -> Result<()> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    let max_open_files = if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } == 0 {
+        config.max_open_files.min(limit.rlim_cur as usize)
+    } else {
+        config.max_open_files
+    };
+
+    // The directory listing itself uses a file descriptor, which is closed again.
+    let open_files = fs::read_dir("/dev/fd")
+        .context(ErrorKind::Io)?
+        .count()
+        .saturating_sub(1);
+    if open_files >= max_open_files {
+        Err(Error::too_many_open_files())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+fn check_open_files(_config: &ShellConfig) -> Result<()> {
+    Ok(())
+}
+
+/// Verifies that `program` and `args` are within the configured limits, so that a command
+/// does not fail with `E2BIG` when it is executed.
+pub(crate) fn check_argument_limits<S1, S2>(
+    config: &ShellConfig,
+    program: S1,
+    args: &[S2],
+) -> Result<()>
+where
+    S1: AsRef<str>,
+    S2: AsRef<str>,
+{
+    let length: usize = iter::once(program.as_ref())
+        .chain(args.iter().map(AsRef::as_ref))
+        .map(|arg| arg.len() + 1)
+        .sum();
+    if args.len() + 1 > config.max_argument_count || length > config.max_argument_length {
+        return Err(Error::argument_list_too_long(program));
+    }
+
+    Ok(())
+}
+
+/// Starts `cmd` for the process substitution `<(cmd)`.
+///
+/// Returns the path of a FIFO from which the output of `cmd` can be read, which the caller
+/// substitutes as a word in the command, and the running `cmd`. The caller should wait for `cmd`
+/// after the command reading from the FIFO finishes, and then remove the FIFO's parent directory.
+#[cfg(unix)]
+pub fn create_process_substitution_input(
+    shell: &mut dyn Shell,
+    cmd: &str,
+) -> Result<(PathBuf, Child)> {
+    let (fifo_path, mut command) = prepare_process_substitution(shell, cmd)?;
+    command.stdout(Stdio::piped());
+    let mut child = spawn_process_substitution(&mut command, cmd, &fifo_path)?;
+
+    // Opening a FIFO blocks until its other end is opened, so relay the output from a thread.
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let path = fifo_path.clone();
+    thread::spawn(move || {
+        let result = OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|mut fifo| io::copy(&mut stdout, &mut fifo));
+        log_if_err!(result, "failed to write to {}", path.display());
+    });
+
+    Ok((fifo_path, child))
+}
+
+/// Starts `cmd` for the process substitution `>(cmd)`.
+///
+/// Returns the path of a FIFO to which input for `cmd` can be written, which the caller
+/// substitutes as a word in the command, and the running `cmd`. The caller should wait for `cmd`
+/// after the command writing to the FIFO finishes, and then remove the FIFO's parent directory.
+#[cfg(unix)]
+pub fn create_process_substitution_output(
+    shell: &mut dyn Shell,
+    cmd: &str,
+) -> Result<(PathBuf, Child)> {
+    let (fifo_path, mut command) = prepare_process_substitution(shell, cmd)?;
+    command.stdin(Stdio::piped());
+    let mut child = spawn_process_substitution(&mut command, cmd, &fifo_path)?;
+
+    // Opening a FIFO blocks until its other end is opened, so relay the input from a thread.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let path = fifo_path.clone();
+    thread::spawn(move || {
+        let result = File::open(&path).and_then(|mut fifo| io::copy(&mut fifo, &mut stdin));
+        log_if_err!(result, "failed to read from {}", path.display());
+    });
+
+    Ok((fifo_path, child))
+}
+
+/// Creates a FIFO in a new temporary directory and the `Command` to run `cmd`.
+#[cfg(unix)]
+fn prepare_process_substitution(shell: &mut dyn Shell, cmd: &str) -> Result<(PathBuf, Command)> {
+    use nix::{sys::stat::Mode, unistd};
+
+    let words = match ir::Interpreter::parse(parser::Command::parse(cmd)?).command {
+        ir::Command::Simple(simple_command) => expand_command_words(
+            shell,
+            iter::once(&simple_command.program).chain(&simple_command.args),
+        )?,
+        _ => {
+            return Err(Error::not_supported(
+                "process substitution only supports simple commands",
+            ))
+        }
+    };
+    let (program, args) = words
+        .split_first()
+        .ok_or_else(|| Error::command_not_found(cmd))?;
+
+    if let Some(ref allowed_dirs) = shell.config().restrict_path {
+        check_restrict_path(program, allowed_dirs)?;
+    }
+    check_argument_limits(shell.config(), program, args)?;
+
+    let mut command = Command::new(program);
+    command.args(args);
+    set_exported_environment(shell, &mut command);
+    if shell.config().sandbox_mode {
+        let path_var = check_sandbox_path(program, &shell.config().sandbox_blocked_paths)?;
+        command.env("PATH", path_var);
+    }
+
+    let fifo_path = create_private_temp_dir()?.join("fifo");
+    unistd::mkfifo(&fifo_path, Mode::S_IRUSR | Mode::S_IWUSR).context(ErrorKind::Nix)?;
+    Ok((fifo_path, command))
+}
+
+/// Creates a new directory only accessible by the current user in the system's temp directory.
+#[cfg(unix)]
+fn create_private_temp_dir() -> Result<PathBuf> {
+    use std::fs::DirBuilder;
+    use std::os::unix::fs::DirBuilderExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = env::temp_dir().join(format!(
+        "bsh-{}-{}",
+        process::id(),
+        COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .context(ErrorKind::Io)?;
+    Ok(dir)
+}
+
+/// Spawns `command`, removing the FIFO's directory if it fails.
+#[cfg(unix)]
+fn spawn_process_substitution(command: &mut Command, cmd: &str, fifo_path: &Path) -> Result<Child> {
+    command.spawn().map_err(|e| {
+        if let Some(dir) = fifo_path.parent() {
+            log_if_err!(
+                fs::remove_dir_all(dir),
+                "failed to remove {}",
+                dir.display()
+            );
+        }
+
+        if e.kind() == io::ErrorKind::NotFound {
+            Error::command_not_found(cmd)
+        } else {
+            e.context(ErrorKind::Io).into()
+        }
+    })
+}
+
 /// Wraps `unistd::pipe()` to return RAII structs instead of raw, owning file descriptors
 /// Returns (`read_end_pipe`, `write_end_pipe`)
 #[cfg(unix)]
 fn create_pipe() -> Result<(File, File)> {
-    use std::os::unix::io::FromRawFd;
-
     use nix::unistd;
 
     // IMPORTANT: immediately pass the RawFds returned by unistd::pipe()
@@ -720,3 +2779,119 @@ fn create_pipe() -> Result<(File, File)> {
     // See CreatePipe, HANDLE, and "impl FromRawHandle for File"
     unimplemented!()
 }
+
+/// Returns the read end of a pipe to which `body` is written, for a here-document or here-string.
+///
+/// The body is written from a thread, as a body larger than the pipe's buffer would otherwise
+/// block until the command reads it.
+#[cfg(unix)]
+fn here_doc_pipe(body: String) -> Result<File> {
+    use nix::fcntl::{self, FcntlArg, FdFlag};
+
+    let (read_end_pipe, mut write_end_pipe) = create_pipe()?;
+    // Children must not inherit the write end, or the command would never see end-of-file.
+    fcntl::fcntl(
+        write_end_pipe.as_raw_fd(),
+        FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC),
+    )
+    .context(ErrorKind::Nix)?;
+    thread::spawn(move || {
+        let result = write_end_pipe.write_all(body.as_bytes());
+        log_if_err!(result, "failed to write here-document");
+    });
+    Ok(read_end_pipe)
+}
+
+#[cfg(windows)]
+fn here_doc_pipe(_body: String) -> Result<File> {
+    // TODO (#22): Support Windows
+    Err(Error::not_supported(
+        "here-documents are not supported on Windows",
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use crate::shell::create_shell;
+
+    #[test]
+    fn test_run_array_assignment() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let mut assign = |input: &str| match parser::Command::parse(input).unwrap().inner {
+            ast::Command::ArrayAssignment {
+                declaration,
+                assignment,
+            } => run_array_assignment(&mut *shell, &declaration, &assignment)
+                .unwrap()
+                .status_code()
+                .unwrap()
+                .success(),
+            command => panic!("{:?} is not an array assignment", command),
+        };
+
+        assert!(assign("arr=(a [3]=\"d e\" f)"));
+        assert!(assign("arr+=(g)"));
+        assert!(!assign("bad=([-1]=a)"));
+        assert!(assign("declare -A dict=([a]=1 [b]=2)"));
+        assert!(!assign("dict+=(c)"));
+        assert!(assign("other=([k]=v)"));
+
+        let elements: Vec<(&usize, &str)> = shell
+            .get_array("arr")
+            .unwrap()
+            .iter()
+            .map(|(i, value)| (i, value.as_str()))
+            .collect();
+        assert_eq!(elements, [(&0, "a"), (&3, "d e"), (&4, "f"), (&5, "g")]);
+        assert_eq!(shell.get_assoc_array("dict").unwrap().len(), 2);
+        // Like bash, the subscript of an indexed array is an arithmetic expression.
+        assert_eq!(
+            shell.get_value("other"),
+            Some(VarValue::Indexed(
+                iter::once((0, "v".to_string())).collect()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_process_substitution_input() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let (fifo_path, mut child) =
+            create_process_substitution_input(&mut *shell, "echo hello world").unwrap();
+
+        let mut output = String::new();
+        File::open(&fifo_path)
+            .unwrap()
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, "hello world\n");
+        assert!(child.wait().unwrap().success());
+        fs::remove_dir_all(fifo_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_process_substitution_output() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output_path = temp_dir.path().join("output");
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        let (fifo_path, mut child) = create_process_substitution_output(
+            &mut *shell,
+            &format!("cp /dev/stdin {}", output_path.display()),
+        )
+        .unwrap();
+
+        fs::write(&fifo_path, "hello world\n").unwrap();
+        assert!(child.wait().unwrap().success());
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "hello world\n");
+        fs::remove_dir_all(fifo_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_process_substitution_errors() {
+        let mut shell = create_shell(ShellConfig::noninteractive()).unwrap();
+        assert!(create_process_substitution_input(&mut *shell, "echo a | cat").is_err());
+        assert!(create_process_substitution_input(&mut *shell, "bsh_nonexistent_command").is_err());
+    }
+}
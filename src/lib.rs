@@ -12,7 +12,13 @@
 )]
 
 pub use crate::errors::{Error, ErrorKind, Result};
-pub use crate::shell::{create_shell, create_simple_shell, Shell, ShellConfig};
+#[cfg(unix)]
+pub use crate::execute_command::{
+    create_process_substitution_input, create_process_substitution_output,
+};
+pub use crate::shell::{
+    create_shell, create_simple_shell, InputEncoding, Shell, ShellConfig, ShellOption, SimpleShell,
+};
 pub use crate::util::BshExitStatusExt;
 
 macro_rules! log_if_err {
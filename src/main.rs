@@ -1,5 +1,7 @@
+use std::env;
 use std::path::{Path, PathBuf};
 use std::process::{self, ExitStatus};
+use std::time::Duration;
 
 use bsh::errors::*;
 use bsh::{create_shell, BshExitStatusExt, Shell, ShellConfig};
@@ -7,6 +9,11 @@ use docopt::Docopt;
 use log::{debug, error};
 use serde_derive::Deserialize;
 
+/// Build metadata generated by `build.rs`.
+mod built_info {
+    include!(concat!(env!("OUT_DIR"), "/built.rs"));
+}
+
 const COMMAND_HISTORY_CAPACITY: usize = 10;
 const LOG_FILE_NAME: &str = ".bsh_log";
 
@@ -14,18 +21,41 @@ const USAGE: &str = "
 bsh.
 
 Usage:
-    bsh [options]
+    bsh [options] [--init-command=<cmd>]...
     bsh [options] -c <command>
-    bsh [options] <file>
+    bsh [options] <file> [<arg>...]
     bsh (-h | --help)
     bsh --version
+    bsh --version-info
 
 Options:
     -h --help       Show this screen.
     --version       Show version.
+    --version-info  Show version and build information.
     -c              If the -c option is present, then commands are read from the first non-option
                         argument command_string.
     --log=<path>    File to write log to, defaults to ~/.bsh_log
+    --posix         Reject bsh extensions to help write portable scripts.
+    --command-log=<path>  Append each command to <path> before it is executed, for an audit
+                        trail.
+    --input-encoding=<encoding>  Character encoding of <file>: utf-8 (default), latin1, or
+                        auto to detect it from a byte order mark.
+    --background-job-timeout=<seconds>  Kill background jobs that have been running for longer
+                        than <seconds>.
+    --max-open-files=<count>  Fail redirections once the shell has <count> files open.
+    --profile=<path>  Write the time each command takes to <path>, as lines of the form
+                        `time_us<TAB>line<TAB>command`.
+    -l --login      Act as a login shell, e.g. `logout` exits the shell and ~/.bsh_logout
+                        runs on exit.
+    --restricted-path=<path>  Set $PATH to <path>, a list of directories separated by `:`, and
+                        make it read-only. External commands, including those run by `env`,
+                        `exec`, `nice` and `nohup`, must be in those directories, but builtins
+                        and functions may still be run.
+    --max-output-size=<bytes>  Exit with status 1 once a script writes more than <bytes> bytes
+                        to stdout.
+    -i <cmd>, --init-command=<cmd>  Run <cmd> before the first prompt. May be given more than
+                        once.
+    --no-editing    Read lines from stdin without line editing, e.g. for piped input.
 ";
 
 /// Docopts input arguments.
@@ -33,9 +63,22 @@ Options:
 struct Args {
     arg_command: Option<String>,
     arg_file: Option<String>,
+    arg_arg: Vec<String>,
     flag_version: bool,
+    flag_version_info: bool,
     flag_c: bool,
     flag_log: Option<String>,
+    flag_posix: bool,
+    flag_login: bool,
+    flag_command_log: Option<String>,
+    flag_profile: Option<String>,
+    flag_input_encoding: Option<String>,
+    flag_max_output_size: Option<u64>,
+    flag_max_open_files: Option<usize>,
+    flag_background_job_timeout: Option<u64>,
+    flag_restricted_path: Option<String>,
+    flag_init_command: Vec<String>,
+    flag_no_editing: bool,
 }
 
 fn main() {
@@ -47,14 +90,33 @@ fn main() {
     debug!("{:?}", args);
 
     if args.flag_version {
-        println!("bsh version {}", env!("CARGO_PKG_VERSION"));
+        println!("{}", version());
+    } else if args.flag_version_info {
+        println!("{}", version_info());
     } else if args.flag_c || args.arg_file.is_some() {
         execute_from_command_string_or_file(&args);
     } else {
-        execute_from_stdin();
+        execute_from_stdin(&args);
+    }
+}
+
+fn version() -> String {
+    match option_env!("BSH_GIT_COMMIT_HASH") {
+        Some(hash) => format!("bsh version {} ({})", built_info::PKG_VERSION, hash),
+        None => format!("bsh version {}", built_info::PKG_VERSION),
     }
 }
 
+fn version_info() -> String {
+    format!(
+        "{}\nrustc: {}\ntarget: {}\nprofile: {}",
+        version(),
+        built_info::RUSTC_VERSION,
+        built_info::TARGET,
+        built_info::PROFILE,
+    )
+}
+
 fn init_logger(path: &Option<String>) {
     let log_path = path
         .clone()
@@ -84,8 +146,42 @@ fn default_log_path() -> PathBuf {
 }
 
 fn execute_from_command_string_or_file(args: &Args) -> ! {
-    let shell_config = ShellConfig::noninteractive();
-    let mut shell = create_shell(shell_config).unwrap_or_else(|e| display_error_and_exit(&e));
+    let mut shell_config = ShellConfig::noninteractive()
+        .posix_mode(args.flag_posix)
+        .login(args.flag_login);
+    if let Some(bytes) = args.flag_max_output_size {
+        shell_config = shell_config.max_output_size(bytes);
+    }
+    if let Some(count) = args.flag_max_open_files {
+        shell_config = shell_config.max_open_files(count);
+    }
+    if let Some(ref path) = args.flag_command_log {
+        shell_config = shell_config.log_commands(true).command_log_path(path);
+    }
+    if let Some(ref path) = args.flag_profile {
+        shell_config = shell_config.profiling(true).profile_output(path);
+    }
+    if let Some(ref path) = args.flag_restricted_path {
+        shell_config = restrict_path(shell_config, path);
+    }
+    if let Some(ref encoding) = args.flag_input_encoding {
+        let encoding = encoding
+            .parse()
+            .unwrap_or_else(|e| display_error_and_exit(&e));
+        shell_config = shell_config.input_encoding(encoding);
+    }
+    if let Some(ref file_path) = args.arg_file {
+        shell_config = shell_config.name(file_path.as_str());
+    }
+    let mut shell = shell_config
+        .validate()
+        .and_then(create_shell)
+        .unwrap_or_else(|e| display_error_and_exit(&e));
+
+    if args.flag_restricted_path.is_some() {
+        shell.declare_readonly("PATH");
+    }
+    shell.set_positional_params(args.arg_arg.clone());
 
     let result = if let Some(ref command) = args.arg_command {
         shell.execute_command_string(command)
@@ -98,13 +194,48 @@ fn execute_from_command_string_or_file(args: &Args) -> ! {
     exit(result, &mut *shell);
 }
 
-fn execute_from_stdin() -> ! {
-    let shell_config = ShellConfig::interactive(COMMAND_HISTORY_CAPACITY);
-    let mut shell = create_shell(shell_config).unwrap_or_else(|e| display_error_and_exit(&e));
+fn execute_from_stdin(args: &Args) -> ! {
+    let mut shell_config = ShellConfig::interactive(COMMAND_HISTORY_CAPACITY)
+        .posix_mode(args.flag_posix)
+        .login(args.flag_login)
+        .line_editing(!args.flag_no_editing);
+    if let Some(seconds) = args.flag_background_job_timeout {
+        shell_config = shell_config.background_job_timeout(Duration::from_secs(seconds));
+    }
+    if let Some(ref path) = args.flag_command_log {
+        shell_config = shell_config.log_commands(true).command_log_path(path);
+    }
+    if let Some(ref path) = args.flag_profile {
+        shell_config = shell_config.profiling(true).profile_output(path);
+    }
+    if let Some(ref path) = args.flag_restricted_path {
+        shell_config = restrict_path(shell_config, path);
+    }
+    let mut shell = shell_config
+        .validate()
+        .and_then(create_shell)
+        .unwrap_or_else(|e| display_error_and_exit(&e));
+    if args.flag_restricted_path.is_some() {
+        shell.declare_readonly("PATH");
+    }
+    for command in &args.flag_init_command {
+        debug!("running init command: {}", command);
+        if let Err(e) = shell.execute_command_string(command) {
+            error!("init command failed: {}", e);
+            eprintln!("bsh: {}", e);
+        }
+    }
     shell.execute_from_stdin();
     shell.exit(None)
 }
 
+/// Sets `$PATH` to `path` and restricts external commands to its directories, e.g. for running
+/// scripts with limited privileges. `$PATH` should be made read-only once the shell is created.
+fn restrict_path(shell_config: ShellConfig, path: &str) -> ShellConfig {
+    env::set_var("PATH", path);
+    shell_config.restrict_path(env::split_paths(path).collect())
+}
+
 fn display_error_and_exit(error: &Error) -> ! {
     error!("failed to create shell: {}", error);
     eprintln!("bsh: {}", error);
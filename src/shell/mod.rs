@@ -1,26 +1,59 @@
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     env, fmt,
     fs::File,
+    io::{self, Read, Write},
+    mem,
+    os::unix::io::RawFd,
     path::{Path, PathBuf},
     process::{self, ExitStatus},
+    str::FromStr,
+    time::{Duration, Instant},
 };
 
 use atty::{self, Stream};
 use cfg_if::cfg_if;
-use failure::ResultExt;
+use failure::{Fail, ResultExt};
 use log::{error, info, warn};
+use nix::sys::signal::Signal;
 
 use crate::{
-    core::{intermediate_representation as ir, parser::Command, variable_expansion},
+    builtins,
+    core::{
+        alias_expansion, arithmetic, intermediate_representation as ir,
+        parser::{self, Command},
+        posix,
+        variable_expansion::{self, VariableLookup},
+    },
     editor::Editor,
     errors::{Error, ErrorKind, Result},
-    execute_command::{spawn_processes, Process, ProcessStatus},
-    util::BshExitStatusExt,
+    execute_command::{
+        copy_stdout, execute_command_string_capturing_output, handle_command_failure,
+        pipefail_status, spawn_processes, spawn_processes_with_piped_stdout, Process, ProcessGroup,
+        ProcessStatus, XTRACE_FD_VARIABLE,
+    },
+    util::{signals, BshExitStatusExt, CommandAuditLogger, LimitedWriter, LogWriter, Profiler},
 };
 
 const HISTORY_FILE_NAME: &str = ".bsh_history";
+const LOGOUT_FILE_NAME: &str = ".bsh_logout";
+const COMMAND_LOG_FILE_NAME: &str = ".bsh_command_log";
+const PROFILE_OUTPUT_FILE_NAME: &str = ".bsh_profile.tsv";
 const SYNTAX_ERROR_EXIT_STATUS: i32 = 2;
-const COMMAND_NOT_FOUND_EXIT_STATUS: i32 = 127;
+pub(crate) const PERMISSION_DENIED_EXIT_STATUS: i32 = 126;
+const DEFAULT_MAX_ARGUMENT_COUNT: usize = 65536;
+const DEFAULT_MAX_ARGUMENT_LENGTH: usize = 2 * 1024 * 1024;
+const DEFAULT_MAX_OPEN_FILES: usize = 256;
+const DEFAULT_SHELL_NAME: &str = "bsh";
+pub(crate) const COMMAND_NOT_FOUND_EXIT_STATUS: i32 = 127;
+/// The trap condition of the shell exiting, see [`Shell::set_trap`].
+pub(crate) const EXIT_TRAP: &str = "EXIT";
+/// The trap condition of a command failing.
+pub(crate) const ERR_TRAP: &str = "ERR";
+/// The trap condition of a command about to be executed.
+pub(crate) const DEBUG_TRAP: &str = "DEBUG";
+/// Variables that may not be set or unset in sandbox mode.
+const SANDBOX_PROTECTED_VARIABLES: &[&str] = &["PATH", "LD_PRELOAD", "LD_LIBRARY_PATH"];
 
 cfg_if! {
     if #[cfg(unix)] {
@@ -43,7 +76,26 @@ pub trait Job {
     fn id(&self) -> JobId;
     fn input(&self) -> String;
     fn display(&self) -> String;
+    /// Returns the job like [`Job::display`], with a line for each process including its ID.
+    fn display_long(&self) -> String;
     fn processes(&self) -> &Vec<Box<dyn Process>>;
+    /// Returns the process group ID of the job, or `None` if it has no external processes.
+    fn pgid(&self) -> Option<u32>;
+    fn is_stopped(&self) -> bool;
+    fn is_completed(&self) -> bool;
+    /// Returns `true` if the status of the job has changed since the user was last notified of
+    /// it, e.g. by `jobs`.
+    fn is_status_changed(&self) -> bool;
+}
+
+/// The value of a shell variable, which is a string or an array.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VarValue {
+    Scalar(String),
+    /// An indexed array, e.g. from `name=(a b)`, by index.
+    Indexed(BTreeMap<usize, String>),
+    /// An associative array, declared with `declare -A name`, by key.
+    Assoc(HashMap<String, String>),
 }
 
 /// A shell is a collection of jobs.
@@ -67,12 +119,272 @@ pub trait Shell {
     /// command executed.
     fn exit(&mut self, n: Option<ExitStatus>) -> !;
 
+    /// Prepares the shell to be replaced by another program, see `exec`: runs the EXIT trap and
+    /// saves the history, like [`Shell::exit`].
+    fn prepare_for_exec(&mut self);
+
     /// Returns `true` if the shell is in interactive mode
     fn is_interactive(&self) -> bool;
 
+    /// Returns `true` if the shell is a login shell, see [`ShellConfig::login`].
+    fn is_login(&self) -> bool;
+
     /// Returns `true` if job control features are enabled.
     fn is_job_control_enabled(&self) -> bool;
 
+    /// Returns the [`ShellConfig`] the shell was created with.
+    fn config(&self) -> &ShellConfig;
+
+    /// Returns mutable [`ShellConfig`] for the shell, e.g. to change options at runtime.
+    fn config_mut(&mut self) -> &mut ShellConfig;
+
+    /// Returns the writer for the shell's own diagnostics, e.g. "command not found".
+    ///
+    /// This is stderr unless [`ShellConfig::redirect_stderr_to_log`] is enabled.
+    fn shell_stderr(&mut self) -> &mut dyn Write;
+
+    /// Replaces the writer for the shell's own diagnostics, e.g. to capture them.
+    fn set_shell_stderr(&mut self, stderr: Box<dyn Write>);
+
+    /// Returns `true` if `option` is enabled.
+    fn get_option(&self, option: ShellOption) -> bool;
+
+    /// Enables or disables `option`.
+    fn set_option(&mut self, option: ShellOption, enabled: bool);
+
+    /// Returns the exit status of the last command executed.
+    fn last_exit_status(&self) -> ExitStatus;
+
+    /// Records `status` as the exit status of the last command executed, e.g. between the
+    /// commands of a list.
+    fn set_last_exit_status(&mut self, status: ExitStatus);
+
+    /// Returns the exit statuses of the processes of the last command executed, in order, e.g.
+    /// of each stage of a pipeline.
+    fn pipeline_status_vec(&self) -> &[ExitStatus];
+
+    /// Records `statuses` as the exit statuses of the processes of the last command executed,
+    /// e.g. once each pipeline of a list has completed.
+    fn set_pipeline_status(&mut self, statuses: Vec<ExitStatus>);
+
+    /// Returns the last argument of the last simple command executed, see `$_`.
+    fn last_argument(&self) -> Option<&str>;
+
+    /// Records `arg` as the last argument of the simple command being executed.
+    fn set_last_argument(&mut self, arg: &str);
+
+    /// Returns the number of loops enclosing the command being executed, e.g. for `break`.
+    fn loop_depth(&self) -> usize;
+
+    /// Sets the number of loops enclosing the command being executed.
+    fn set_loop_depth(&mut self, depth: usize);
+
+    /// Returns the number of function calls enclosing the command being executed, e.g. for
+    /// `return`.
+    fn function_depth(&self) -> usize;
+
+    /// Sets the number of function calls enclosing the command being executed.
+    fn set_function_depth(&mut self, depth: usize);
+
+    /// Returns the number of conditions enclosing the command being executed, e.g. of `if` or
+    /// `while`, in which failures do not run the ERR trap or exit the shell with `set -e`.
+    fn condition_depth(&self) -> usize;
+
+    /// Sets the number of conditions enclosing the command being executed.
+    fn set_condition_depth(&mut self, depth: usize);
+
+    /// Returns `true` if the failure of the last command does not run the ERR trap or exit the
+    /// shell with `set -e`, e.g. because it is the left side of `&&` or negated with `!`.
+    fn errexit_ignored(&self) -> bool;
+
+    /// Sets whether the failure of the last command is ignored, see [`Shell::errexit_ignored`].
+    fn set_errexit_ignored(&mut self, ignored: bool);
+
+    /// Returns the process ID of the last process of the most recent background job, see `$!`.
+    fn last_background_pid(&self) -> Option<u32>;
+
+    /// Returns the line number of the command being executed, see `$LINENO`.
+    fn line_number(&self) -> usize;
+
+    /// Returns the positional parameters, see `$1`, `$#` and `$@`.
+    fn positional_params(&self) -> &[String];
+
+    /// Replaces the positional parameters, e.g. with the arguments of a script.
+    fn set_positional_params(&mut self, params: Vec<String>);
+
+    /// Returns the body of the function `name`, defined with `name() { ...; }`.
+    fn get_function(&self, name: &str) -> Option<&ir::Command>;
+
+    /// Defines the function `name`, replacing any previous definition.
+    ///
+    /// Fails if the function would shadow a builtin in sandbox mode.
+    fn define_function(&mut self, name: &str, body: ir::Command) -> Result<()>;
+
+    /// Returns the names of the defined functions, sorted.
+    fn function_names(&self) -> Vec<String>;
+
+    /// Returns the aliases defined with `alias`, by name.
+    fn aliases(&self) -> &HashMap<String, String>;
+
+    /// Returns the aliases defined with `alias` for modification.
+    fn aliases_mut(&mut self) -> &mut HashMap<String, String>;
+
+    /// Returns the directories saved with `pushd`, most recent first. The current directory is
+    /// not included.
+    fn dir_stack(&self) -> &[PathBuf];
+
+    /// Returns the directories saved with `pushd` for modification.
+    fn dir_stack_mut(&mut self) -> &mut Vec<PathBuf>;
+
+    /// Returns the paths of the external commands found in `$PATH`, by name. The cache is
+    /// cleared when `$PATH` changes.
+    fn command_cache(&self) -> &HashMap<String, PathBuf>;
+
+    /// Returns the paths of the external commands found in `$PATH` for modification.
+    fn command_cache_mut(&mut self) -> &mut HashMap<String, PathBuf>;
+
+    /// Returns a noninteractive copy of the shell for running a subshell.
+    ///
+    /// The copy starts with the shell's configuration, variables, and last exit status, but
+    /// changes to it do not affect this shell. It does not share this shell's jobs. The current
+    /// directory is a property of the process, so the copy sees this shell's current directory.
+    fn clone_for_subshell(&self) -> Result<Box<dyn Shell>>;
+
+    /// Returns the value of the variable `name`, checking the shell's local variables before the
+    /// environment.
+    fn get_variable(&self, name: &str) -> Option<String>;
+
+    /// Sets the variable `name` to `value`.
+    ///
+    /// Updates the local variable if `name` is declared locally, otherwise sets the environment
+    /// variable.
+    fn set_variable(&mut self, name: &str, value: &str);
+
+    /// Removes the variable `name`, following the same lookup order as [`Shell::get_variable`],
+    /// and the array variable `name`.
+    fn unset_variable(&mut self, name: &str);
+
+    /// Marks the variable `name` as read-only, so it may no longer be assigned or unset.
+    fn declare_readonly(&mut self, name: &str);
+
+    /// Returns the names of the read-only variables, sorted.
+    fn readonly_variables(&self) -> Vec<String>;
+
+    /// Marks the variable `name` to be passed to the environment of external commands, or
+    /// removes the mark if `exported` is `false`.
+    fn set_exported(&mut self, name: &str, exported: bool);
+
+    /// Returns `true` if the variable `name` is passed to the environment of external commands.
+    ///
+    /// Variables inherited from the shell's environment are exported.
+    fn is_exported(&self, name: &str) -> bool;
+
+    /// Returns the names of the exported variables, sorted.
+    fn exported_variables(&self) -> Vec<String>;
+
+    /// Marks the variable `name` as an integer, whose assigned values are evaluated as
+    /// arithmetic expressions, or removes the mark if `integer` is `false`.
+    fn set_integer(&mut self, name: &str, integer: bool);
+
+    /// Returns `true` if the variable `name` is an integer, declared with `declare -i`.
+    fn is_integer(&self, name: &str) -> bool;
+
+    /// Verifies that the variable `name` may be modified, i.e. it is not read-only or protected
+    /// by sandbox mode.
+    fn check_variable_assignment(&self, name: &str) -> Result<()>;
+
+    /// Returns `true` if the builtin `name` has not been disabled, see `enable`.
+    fn is_builtin_enabled(&self, name: &str) -> bool;
+
+    /// Enables or disables the builtin `name`. Disabled builtins are run as external commands.
+    fn set_builtin_enabled(&mut self, name: &str, enabled: bool);
+
+    /// Returns the commands set with `trap`, by condition, e.g. `EXIT` or `SIGINT`.
+    ///
+    /// An empty command means the signal is ignored.
+    fn traps(&self) -> &HashMap<String, String>;
+
+    /// Sets the command run on `condition`, or removes it if `handler` is `None`.
+    ///
+    /// Conditions are signal names, e.g. `SIGINT`, whose disposition the caller changes, or
+    /// shell events: `EXIT` when the shell exits, `ERR` after a command fails, and `DEBUG`
+    /// before each command the shell executes.
+    fn set_trap(&mut self, condition: &str, handler: Option<String>);
+
+    /// Runs the trap for `condition`, e.g. `ERR` or `SIGINT`, if set, unless a trap is already
+    /// running.
+    fn run_trap(&mut self, condition: &str);
+
+    /// Runs the traps of the signals received since they last ran, see
+    /// [`signals::take_pending`].
+    fn run_signal_traps(&mut self);
+
+    /// Evaluates the arithmetic expression `expr`, e.g. `x += 2 * 3`, reading and assigning
+    /// variables in the shell.
+    fn eval_arithmetic(&mut self, expr: &str) -> Result<i64>;
+
+    /// Returns the elements of the array variable `name` by index.
+    ///
+    /// Like bash, arrays are sparse, e.g. `a[5]=x` sets a single element.
+    fn get_array(&self, name: &str) -> Option<&BTreeMap<usize, String>>;
+
+    /// Declares `name` as an empty array variable, unless it is already an array.
+    fn declare_array(&mut self, name: &str);
+
+    /// Replaces the elements of the array variable `name`, e.g. for `name=(a b)`.
+    fn set_array(&mut self, name: &str, elements: BTreeMap<usize, String>);
+
+    /// Sets element `index` of the array variable `name`, creating the array if necessary.
+    fn set_array_element(&mut self, name: &str, index: usize, value: &str);
+
+    /// Returns the associative array variable `name`.
+    fn get_assoc_array(&self, name: &str) -> Option<&HashMap<String, String>>;
+
+    /// Declares `name` as an empty associative array, unless it is already one.
+    fn declare_assoc_array(&mut self, name: &str);
+
+    /// Replaces the elements of the associative array `name`, e.g. for `name=([key]=value)`.
+    fn set_assoc_array(&mut self, name: &str, elements: HashMap<String, String>);
+
+    /// Sets `key` of the associative array `name` to `value`, creating the array if necessary.
+    fn set_assoc_element(&mut self, name: &str, key: &str, value: &str);
+
+    /// Removes `subscript` from the array `name`.
+    ///
+    /// The indices of the later elements of indexed arrays are unchanged.
+    fn unset_array_element(&mut self, name: &str, subscript: &str);
+
+    /// Returns the value of the variable `name`, which may be an array.
+    fn get_value(&self, name: &str) -> Option<VarValue> {
+        if let Some(assoc_array) = self.get_assoc_array(name) {
+            Some(VarValue::Assoc(assoc_array.clone()))
+        } else if let Some(array) = self.get_array(name) {
+            Some(VarValue::Indexed(array.clone()))
+        } else {
+            self.get_variable(name).map(VarValue::Scalar)
+        }
+    }
+
+    /// Sets the variable `name` to `value`. An array replaces a variable of another kind with
+    /// the same name.
+    fn set_value(&mut self, name: &str, value: VarValue) {
+        match value {
+            VarValue::Scalar(value) => self.set_variable(name, &value),
+            VarValue::Indexed(elements) => {
+                if self.get_array(name).is_none() {
+                    self.unset_variable(name);
+                }
+                self.set_array(name, elements);
+            }
+            VarValue::Assoc(elements) => {
+                if self.get_assoc_array(name).is_none() {
+                    self.unset_variable(name);
+                }
+                self.set_assoc_array(name, elements);
+            }
+        }
+    }
+
     /// Returns [`Editor`] for the shell.
     fn editor(&self) -> &Editor;
 
@@ -85,20 +397,224 @@ pub trait Shell {
     /// Returns `true` if the shell has background jobs.
     fn has_background_jobs(&self) -> bool;
 
+    /// Finds the job identified by `spec`, e.g. `%1`, `%%`, `%-`, `%name`, `%?name`, or `1`.
+    fn job_by_spec(&self, spec: &str) -> Result<JobId>;
+
+    /// Records that the user has been notified of the current status of the job, see
+    /// [`Job::is_status_changed`].
+    fn set_job_notified(&mut self, job_id: JobId);
+
+    /// Waits for the job to stop or complete and returns its exit status, removing it if it
+    /// completed.
+    fn wait_for_job(&mut self, job_id: JobId) -> Result<Option<ExitStatus>>;
+
+    /// Waits for the next background job to complete and returns its exit status, or `None` if
+    /// no background jobs are running.
+    fn wait_for_next_job(&mut self) -> Result<Option<ExitStatus>>;
+
+    /// Waits for the background process `pid` to complete and returns its exit status, or
+    /// `None` if no background job is running it.
+    fn wait_for_process(&mut self, pid: u32) -> Result<Option<ExitStatus>>;
+
     /// Starts the specified job or the current one.
     fn put_job_in_foreground(&mut self, job_id: Option<JobId>) -> Result<Option<ExitStatus>>;
 
     /// Puts the specified job in the background, or the current one.
     fn put_job_in_background(&mut self, job_id: Option<JobId>) -> Result<()>;
 
-    /// Kills a child with the corresponding job id.
+    /// Sends `signal` to the processes in the job with the corresponding job id.
     ///
-    /// Returns `true` if a corresponding job exists; `false`, otherwise.
-    fn kill_background_job(&mut self, job_id: u32) -> Result<Option<&dyn Job>>;
+    /// Returns the job if it exists; `None`, otherwise.
+    fn kill_background_job(&mut self, job_id: JobId, signal: Signal) -> Result<Option<&dyn Job>>;
+}
+
+/// An option that changes the behavior of a running shell, see [`Shell::set_option`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ShellOption {
+    /// Check the window size after each command. Accepted for compatibility with bash.
+    Checkwinsize,
+    /// Let glob patterns match files whose names begin with `.`.
+    Dotglob,
+    /// Exit the shell when a command fails, see `set -e`.
+    Errexit,
+    /// Expand aliases at the start of commands, see `alias`.
+    ExpandAliases,
+    /// Extended pattern matching operators. Accepted for compatibility with bash.
+    Extglob,
+    /// Treat a glob pattern that matches no paths as an error instead of leaving it unchanged.
+    Failglob,
+    /// `**` matches any number of directories. Accepted for compatibility with bash.
+    Globstar,
+    /// Append to the history file when the shell exits instead of overwriting it.
+    Histappend,
+    /// Edit the result of history expansion before running it. Accepted for compatibility with
+    /// bash.
+    Histverify,
+    /// Match glob patterns regardless of case.
+    Nocaseglob,
+    /// Refuse to overwrite existing files with `>` redirections, see `set -C`.
+    Noclobber,
+    /// Treat expanding an unset variable as an error, see `set -u`.
+    Nounset,
+    /// Remove a glob pattern that matches no paths instead of leaving it unchanged.
+    Nullglob,
+    /// Use the status of the last failed command of a pipeline as the pipeline's status.
+    Pipefail,
+    /// Print commands to stderr before they are executed.
+    Xtrace,
+    /// Reject bsh extensions to keep scripts portable, see [`ShellConfig::posix_mode`].
+    Posix,
+    /// Use vi-style line editing instead of emacs-style.
+    Vi,
+}
+
+impl ShellOption {
+    /// All options, in the order they are displayed.
+    pub const ALL: &'static [ShellOption] = &[
+        ShellOption::Checkwinsize,
+        ShellOption::Dotglob,
+        ShellOption::Errexit,
+        ShellOption::ExpandAliases,
+        ShellOption::Extglob,
+        ShellOption::Failglob,
+        ShellOption::Globstar,
+        ShellOption::Histappend,
+        ShellOption::Histverify,
+        ShellOption::Nocaseglob,
+        ShellOption::Noclobber,
+        ShellOption::Nounset,
+        ShellOption::Nullglob,
+        ShellOption::Pipefail,
+        ShellOption::Posix,
+        ShellOption::Vi,
+        ShellOption::Xtrace,
+    ];
+
+    /// Returns the option's name, e.g. for `debug set`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ShellOption::Checkwinsize => "checkwinsize",
+            ShellOption::Dotglob => "dotglob",
+            ShellOption::Errexit => "errexit",
+            ShellOption::ExpandAliases => "expand_aliases",
+            ShellOption::Extglob => "extglob",
+            ShellOption::Failglob => "failglob",
+            ShellOption::Globstar => "globstar",
+            ShellOption::Histappend => "histappend",
+            ShellOption::Histverify => "histverify",
+            ShellOption::Nocaseglob => "nocaseglob",
+            ShellOption::Noclobber => "noclobber",
+            ShellOption::Nounset => "nounset",
+            ShellOption::Nullglob => "nullglob",
+            ShellOption::Pipefail => "pipefail",
+            ShellOption::Xtrace => "xtrace",
+            ShellOption::Posix => "posix",
+            ShellOption::Vi => "vi",
+        }
+    }
+
+    /// Returns the option called `name`, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|option| option.name() == name)
+    }
+
+    /// Returns `true` if the option is enabled when the shell starts, like bash.
+    pub fn is_enabled_by_default(self) -> bool {
+        matches!(self, ShellOption::Checkwinsize | ShellOption::ExpandAliases)
+    }
+
+    /// Returns the single-letter flag that enables the option with `set`, e.g. `e` for `set -e`.
+    pub fn flag(self) -> Option<char> {
+        match self {
+            ShellOption::Errexit => Some('e'),
+            ShellOption::Noclobber => Some('C'),
+            ShellOption::Nounset => Some('u'),
+            ShellOption::Xtrace => Some('x'),
+            _ => None,
+        }
+    }
+
+    /// Returns the option enabled by the single-letter flag `flag`, if any.
+    pub fn from_flag(flag: char) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|option| option.flag() == Some(flag))
+    }
+}
+
+/// The character encoding of script files, see [`ShellConfig::input_encoding`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEncoding {
+    /// Scripts must be valid UTF-8.
+    Utf8,
+    /// Scripts are ISO-8859-1, where each byte is a character.
+    Latin1,
+    /// The encoding is detected from a byte order mark, i.e. UTF-8 or UTF-16LE. Scripts without
+    /// one are read as UTF-8 if they are valid UTF-8 and as Latin-1 otherwise.
+    Auto,
+}
+
+impl InputEncoding {
+    /// Decodes `bytes`, returning `None` if they are not valid in this encoding.
+    fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            InputEncoding::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            InputEncoding::Latin1 => Some(bytes.iter().copied().map(char::from).collect()),
+            InputEncoding::Auto => {
+                if let Some(bytes) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+                    InputEncoding::Utf8.decode(bytes)
+                } else if let Some(bytes) = bytes.strip_prefix(b"\xFF\xFE") {
+                    decode_utf16le(bytes)
+                } else {
+                    InputEncoding::Utf8
+                        .decode(bytes)
+                        .or_else(|| InputEncoding::Latin1.decode(bytes))
+                }
+            }
+        }
+    }
+}
+
+impl Default for InputEncoding {
+    fn default() -> Self {
+        InputEncoding::Utf8
+    }
+}
+
+impl FromStr for InputEncoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(InputEncoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(InputEncoding::Latin1),
+            "auto" => Ok(InputEncoding::Auto),
+            _ => Err(Error::configuration(format!(
+                "unknown input encoding: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Decodes UTF-16LE `bytes`, returning `None` if they are not valid UTF-16.
+fn decode_utf16le(bytes: &[u8]) -> Option<String> {
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16(&units).ok()
 }
 
 /// Policy object to control a Shell's behavior
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct ShellConfig {
     /// Determines if new command entries will be added to the shell's command history.
     ///
@@ -113,6 +629,66 @@ pub struct ShellConfig {
 
     /// Determines if some messages (e.g. "exit") should be displayed.
     display_messages: bool,
+
+    /// Determines if lines read from stdin can be edited, e.g. with the arrow keys.
+    pub(crate) enable_line_editing: bool,
+
+    /// Determines if the shell is a login shell.
+    login: bool,
+
+    /// If set, external commands may only be run from these directories.
+    pub(crate) restrict_path: Option<Vec<PathBuf>>,
+
+    /// Options that may be changed while the shell is running, e.g. with `debug set`.
+    options: HashSet<ShellOption>,
+
+    /// Maximum number of arguments, including the program name, passed to an external command.
+    pub(crate) max_argument_count: usize,
+
+    /// Maximum total length in bytes of the arguments passed to an external command.
+    pub(crate) max_argument_length: usize,
+
+    /// Maximum number of files the shell may have open when opening a file for a redirection.
+    pub(crate) max_open_files: usize,
+
+    /// Determines if untrusted scripts are restricted, see [`ShellConfig::sandbox_mode`].
+    pub(crate) sandbox_mode: bool,
+
+    /// Directories removed from `$PATH` in sandbox mode.
+    pub(crate) sandbox_blocked_paths: Vec<PathBuf>,
+
+    /// If set, the only directory files may be redirected to or from in sandbox mode.
+    pub(crate) sandbox_dir: Option<PathBuf>,
+
+    /// If set, the maximum number of bytes a noninteractive shell may write to stdout.
+    pub(crate) max_output_size: Option<u64>,
+
+    /// Determines if each command is appended to the command log before it is executed.
+    log_commands: bool,
+
+    /// The command log, defaults to `~/.bsh_command_log`.
+    command_log_path: Option<PathBuf>,
+
+    /// Determines if the time each command takes is written to the profile output.
+    enable_profiling: bool,
+
+    /// The profile output, defaults to `~/.bsh_profile.tsv`.
+    profile_output: Option<PathBuf>,
+
+    /// The character encoding of script files.
+    input_encoding: InputEncoding,
+
+    /// Determines if the shell's own diagnostics are written to the log instead of stderr.
+    redirect_shell_stderr: bool,
+
+    /// If set, background jobs running for longer than this are killed.
+    background_job_timeout: Option<Duration>,
+
+    /// The file descriptor xtrace output is written to, unless `$BASH_XTRACEFD` is set.
+    pub(crate) trace_fd: RawFd,
+
+    /// The name of the shell or the script it runs, see `$0`.
+    pub(crate) name: String,
 }
 
 impl ShellConfig {
@@ -128,6 +704,26 @@ impl ShellConfig {
             command_history_capacity,
             enable_job_control: true,
             display_messages: true,
+            enable_line_editing: true,
+            login: false,
+            restrict_path: None,
+            options: default_options(),
+            max_argument_count: DEFAULT_MAX_ARGUMENT_COUNT,
+            max_argument_length: DEFAULT_MAX_ARGUMENT_LENGTH,
+            max_open_files: DEFAULT_MAX_OPEN_FILES,
+            sandbox_mode: false,
+            sandbox_blocked_paths: Vec::new(),
+            sandbox_dir: None,
+            max_output_size: None,
+            log_commands: false,
+            command_log_path: None,
+            enable_profiling: false,
+            profile_output: None,
+            input_encoding: InputEncoding::Utf8,
+            redirect_shell_stderr: false,
+            background_job_timeout: None,
+            trace_fd: libc::STDERR_FILENO,
+            name: DEFAULT_SHELL_NAME.to_string(),
         }
     }
 
@@ -141,6 +737,308 @@ impl ShellConfig {
     pub fn noninteractive() -> Self {
         Default::default()
     }
+
+    /// Enables or disables line editing.
+    ///
+    /// Without line editing, lines are read from stdin as is, e.g. for piped input.
+    pub fn line_editing(mut self, enabled: bool) -> Self {
+        self.enable_line_editing = enabled;
+        self
+    }
+
+    /// Makes the shell a login shell.
+    ///
+    /// A login shell may be exited with `logout` and runs `~/.bsh_logout` when it exits.
+    pub fn login(mut self, enabled: bool) -> Self {
+        self.login = enabled;
+        self
+    }
+
+    /// Restricts external commands to those located in one of `dirs`.
+    ///
+    /// Commands are resolved using `$PATH` and rejected with exit status 126 if the resolved
+    /// path does not start with one of the whitelisted directories.
+    pub fn restrict_path(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.restrict_path = Some(dirs);
+        self
+    }
+
+    /// Enables or disables POSIX mode.
+    ///
+    /// In POSIX mode, commands using bsh extensions (e.g. `[[...]]` or `$(...)`) are not run and
+    /// fail with exit status 1.
+    pub fn posix_mode(mut self, enabled: bool) -> Self {
+        self.set_option(ShellOption::Posix, enabled);
+        self
+    }
+
+    /// Returns `true` if `option` is enabled.
+    pub fn get_option(&self, option: ShellOption) -> bool {
+        self.options.contains(&option)
+    }
+
+    /// Enables or disables `option`.
+    pub fn set_option(&mut self, option: ShellOption, enabled: bool) {
+        if enabled {
+            self.options.insert(option);
+        } else {
+            self.options.remove(&option);
+        }
+    }
+
+    /// Limits the number of arguments, including the program name, passed to external commands.
+    ///
+    /// Commands exceeding the limit are rejected with exit status 126 instead of being executed.
+    pub fn max_argument_count(mut self, count: usize) -> Self {
+        self.max_argument_count = count;
+        self
+    }
+
+    /// Limits the total length in bytes of the arguments passed to external commands.
+    pub fn max_argument_length(mut self, length: usize) -> Self {
+        self.max_argument_length = length;
+        self
+    }
+
+    /// Limits the number of files the shell may have open, e.g. so that a script opening many
+    /// redirections does not exhaust the process's file descriptors.
+    ///
+    /// Redirections that would exceed the limit fail with exit status 1.
+    pub fn max_open_files(mut self, count: usize) -> Self {
+        self.max_open_files = count;
+        self
+    }
+
+    /// Enables or disables sandbox mode for running untrusted scripts.
+    ///
+    /// In sandbox mode:
+    /// - External commands are resolved without the directories in
+    ///   [`ShellConfig::sandbox_blocked_paths`].
+    /// - `exec`, `source`, `eval`, `nice`, and `nohup` may not be run.
+    /// - Redirections must refer to files inside [`ShellConfig::sandbox_dir`]; if it is not set,
+    ///   redirecting to or from files is not allowed.
+    /// - `PATH`, `LD_PRELOAD`, and `LD_LIBRARY_PATH` may not be modified.
+    /// - Functions and aliases may not be named after builtins, e.g. `cd() { ...; }`.
+    ///
+    /// Blocked commands and redirections are rejected with exit status 126.
+    pub fn sandbox_mode(mut self, enabled: bool) -> Self {
+        self.sandbox_mode = enabled;
+        self
+    }
+
+    /// Removes `dirs`, e.g. directories containing network tools, from `$PATH` in sandbox mode.
+    pub fn sandbox_blocked_paths(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.sandbox_blocked_paths = dirs;
+        self
+    }
+
+    /// Restricts redirections in sandbox mode to files inside `dir`.
+    pub fn sandbox_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.sandbox_dir = Some(dir.into());
+        self
+    }
+
+    /// Limits the total output of a noninteractive shell to `bytes` bytes.
+    ///
+    /// Once the limit is exceeded, the shell exits with exit status 1. This prevents a runaway
+    /// script from filling the disk when its output is redirected to a file.
+    pub fn max_output_size(mut self, bytes: u64) -> Self {
+        self.max_output_size = Some(bytes);
+        self
+    }
+
+    /// Enables or disables logging each command before it is executed, for an audit trail.
+    ///
+    /// Commands are appended to [`ShellConfig::command_log_path`] as lines of the form
+    /// `timestamp pid user command`.
+    pub fn log_commands(mut self, enabled: bool) -> Self {
+        self.log_commands = enabled;
+        self
+    }
+
+    /// Sets the file commands are logged to, see [`ShellConfig::log_commands`].
+    pub fn command_log_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.command_log_path = Some(path.into());
+        self
+    }
+
+    /// Enables or disables recording how long each command takes, e.g. to find slow commands
+    /// in a script.
+    ///
+    /// Records are written to [`ShellConfig::profile_output`] as lines of the form
+    /// `time_us\tline\tcommand` when the shell exits. Commands not read from a script or stdin,
+    /// e.g. with `-c`, have line number 0.
+    pub fn profiling(mut self, enabled: bool) -> Self {
+        self.enable_profiling = enabled;
+        self
+    }
+
+    /// Sets the file profiling records are written to, see [`ShellConfig::profiling`].
+    pub fn profile_output<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.profile_output = Some(path.into());
+        self
+    }
+
+    /// Sets the character encoding of script files, e.g. files run with `source`.
+    ///
+    /// Lines read from stdin are not affected.
+    pub fn input_encoding(mut self, encoding: InputEncoding) -> Self {
+        self.input_encoding = encoding;
+        self
+    }
+
+    /// Enables or disables writing the shell's own diagnostics, e.g. "command not found", to the
+    /// log instead of stderr, e.g. for server deployments.
+    ///
+    /// The stderr of commands run by the shell is not affected.
+    pub fn redirect_stderr_to_log(mut self, enabled: bool) -> Self {
+        self.redirect_shell_stderr = enabled;
+        self
+    }
+
+    /// Kills background jobs that have been running for longer than `timeout`, e.g. so that
+    /// forgotten jobs do not run forever in an interactive session.
+    ///
+    /// Jobs are checked before each prompt, so a job may run for longer than `timeout` while the
+    /// shell waits for input. Stopped jobs are not killed.
+    pub fn background_job_timeout(mut self, timeout: Duration) -> Self {
+        self.background_job_timeout = Some(timeout);
+        self
+    }
+
+    /// Writes xtrace output, e.g. from `debug start`, to the file descriptor `fd` instead of
+    /// stderr. The file descriptor must stay open for as long as the shell runs.
+    ///
+    /// `$BASH_XTRACEFD` is set to `fd` when the shell is created; changing it while the shell
+    /// runs takes precedence over `fd`.
+    pub fn trace_fd(mut self, fd: RawFd) -> Self {
+        self.trace_fd = fd;
+        self
+    }
+
+    /// Sets the name of the shell, `$0`, e.g. to the path of the script it runs. Defaults to
+    /// `bsh`.
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Exports `$BASH_XTRACEFD` if xtrace output is not written to stderr.
+    fn export_trace_fd(&self) {
+        if self.trace_fd != libc::STDERR_FILENO {
+            env::set_var(XTRACE_FD_VARIABLE, self.trace_fd.to_string());
+        }
+    }
+
+    /// Creates the writer the shell's own diagnostics are written to.
+    fn shell_stderr(&self) -> Box<dyn Write> {
+        if self.redirect_shell_stderr {
+            Box::new(LogWriter::new())
+        } else {
+            Box::new(io::stderr())
+        }
+    }
+
+    /// Creates the profile output if profiling is enabled.
+    fn create_profiler(&self) -> Result<Option<Profiler>> {
+        if !self.enable_profiling {
+            return Ok(None);
+        }
+
+        let path = self
+            .profile_output
+            .clone()
+            .or_else(|| dirs::home_dir().map(|home| home.join(PROFILE_OUTPUT_FILE_NAME)))
+            .ok_or_else(|| Error::configuration("no profile output path or home directory"))?;
+        Profiler::create(path).map(Some)
+    }
+
+    /// Opens the command log if commands are logged.
+    fn open_command_log(&self) -> Result<Option<CommandAuditLogger>> {
+        if !self.log_commands {
+            return Ok(None);
+        }
+
+        let path = self
+            .command_log_path
+            .clone()
+            .or_else(|| dirs::home_dir().map(|home| home.join(COMMAND_LOG_FILE_NAME)))
+            .ok_or_else(|| Error::configuration("no command log path or home directory"))?;
+        CommandAuditLogger::open(path).map(Some)
+    }
+
+    /// Checks that the settings do not contradict each other, returning the config if so.
+    ///
+    /// Enabling job control for a shell whose stdin is not a terminal is allowed, as the shell
+    /// disables it at runtime, but a warning is logged.
+    pub fn validate(self) -> Result<Self> {
+        if self.enable_command_history && self.command_history_capacity == 0 {
+            return Err(Error::configuration(
+                "command history is enabled with a capacity of 0",
+            ));
+        }
+        if !self.sandbox_mode
+            && (self.sandbox_dir.is_some() || !self.sandbox_blocked_paths.is_empty())
+        {
+            return Err(Error::configuration(
+                "sandbox options are set but sandbox mode is disabled",
+            ));
+        }
+        if !self.log_commands && self.command_log_path.is_some() {
+            return Err(Error::configuration(
+                "a command log path is set but command logging is disabled",
+            ));
+        }
+        if !self.enable_profiling && self.profile_output.is_some() {
+            return Err(Error::configuration(
+                "a profile output path is set but profiling is disabled",
+            ));
+        }
+        if self.enable_job_control && !atty::is(Stream::Stdin) {
+            warn!("job control is enabled, but stdin is not a terminal");
+        }
+
+        Ok(self)
+    }
+
+    /// Returns `true` if new commands are added to the shell's command history.
+    pub(crate) fn is_command_history_enabled(&self) -> bool {
+        self.enable_command_history
+    }
+
+    /// Starts or stops adding new commands to the shell's command history, e.g. while `fc` runs
+    /// the editor.
+    pub(crate) fn set_command_history_enabled(&mut self, enabled: bool) {
+        self.enable_command_history = enabled;
+    }
+
+    /// Verifies that the variable `name` may be modified.
+    pub(crate) fn check_variable_assignment(&self, name: &str) -> Result<()> {
+        if self.sandbox_mode && SANDBOX_PROTECTED_VARIABLES.contains(&name) {
+            return Err(Error::permission_denied(name));
+        }
+
+        Ok(())
+    }
+
+    /// Verifies that a command defined by the user, i.e. a function or an alias, may be named
+    /// `name`.
+    pub(crate) fn check_command_name(&self, name: &str) -> Result<()> {
+        if self.sandbox_mode && builtins::all_builtins().contains(name) {
+            return Err(Error::permission_denied(name));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the options enabled when the shell starts, see [`ShellOption::is_enabled_by_default`].
+fn default_options() -> HashSet<ShellOption> {
+    ShellOption::ALL
+        .iter()
+        .copied()
+        .filter(|option| option.is_enabled_by_default())
+        .collect()
 }
 
 impl Default for ShellConfig {
@@ -150,6 +1048,26 @@ impl Default for ShellConfig {
             command_history_capacity: 0,
             enable_job_control: false,
             display_messages: false,
+            enable_line_editing: true,
+            login: false,
+            restrict_path: None,
+            options: default_options(),
+            max_argument_count: DEFAULT_MAX_ARGUMENT_COUNT,
+            max_argument_length: DEFAULT_MAX_ARGUMENT_LENGTH,
+            max_open_files: DEFAULT_MAX_OPEN_FILES,
+            sandbox_mode: false,
+            sandbox_blocked_paths: Vec::new(),
+            sandbox_dir: None,
+            max_output_size: None,
+            log_commands: false,
+            command_log_path: None,
+            enable_profiling: false,
+            profile_output: None,
+            input_encoding: InputEncoding::Utf8,
+            redirect_shell_stderr: false,
+            background_job_timeout: None,
+            trace_fd: libc::STDERR_FILENO,
+            name: DEFAULT_SHELL_NAME.to_string(),
         }
     }
 }
@@ -160,25 +1078,104 @@ impl fmt::Display for JobId {
     }
 }
 
+/// A cross-platform shell with job control and terminal handling features disabled.
 pub struct SimpleShell {
     editor: Editor,
     history_file: Option<PathBuf>,
     last_exit_status: ExitStatus,
+    pipeline_status: Vec<ExitStatus>,
+    last_arg: Option<String>,
+    last_background_pid: Option<u32>,
+    positional_params: Vec<String>,
+    functions: HashMap<String, ir::Command>,
+    aliases: HashMap<String, String>,
+    dir_stack: Vec<PathBuf>,
+    command_cache: HashMap<String, PathBuf>,
+    local_vars: HashMap<String, String>,
+    readonly_vars: HashSet<String>,
+    exported_vars: HashSet<String>,
+    integer_vars: HashSet<String>,
+    array_vars: HashMap<String, BTreeMap<usize, String>>,
+    assoc_vars: HashMap<String, HashMap<String, String>>,
+    enabled_builtins: HashSet<String>,
     config: ShellConfig,
     is_interactive: bool,
+    /// Stdout of noninteractive shells with a maximum output size.
+    output: Option<LimitedWriter<io::Stdout>>,
+    /// Writer for the shell's own diagnostics, see [`Shell::shell_stderr`].
+    shell_stderr: Box<dyn Write>,
+    /// Audit log of executed commands, see [`ShellConfig::log_commands`].
+    command_log: Option<CommandAuditLogger>,
+    /// Records the time each command takes, see [`ShellConfig::profiling`].
+    profiler: Option<Profiler>,
+    /// Line number of the command being executed, e.g. in a script.
+    line_number: usize,
+    /// Number of loops enclosing the command being executed, see [`Shell::loop_depth`].
+    loop_depth: usize,
+    /// Number of function calls enclosing the command being executed, see
+    /// [`Shell::function_depth`].
+    function_depth: usize,
+    /// Number of conditions enclosing the command being executed, see
+    /// [`Shell::condition_depth`].
+    condition_depth: usize,
+    /// See [`Shell::errexit_ignored`].
+    errexit_ignored: bool,
+    traps: HashMap<String, String>,
+    in_trap: bool,
+    /// Commands run with `&`, which are polled until they complete, see `wait`.
+    background_jobs: Vec<ProcessGroup>,
 }
 
 impl SimpleShell {
-    fn new(config: ShellConfig) -> Result<Self> {
+    /// Constructs a new SimpleShell.
+    pub fn new(config: ShellConfig) -> Result<Self> {
         let mut shell = SimpleShell {
             editor: Editor::with_capacity(config.command_history_capacity),
             history_file: None,
             last_exit_status: ExitStatus::from_success(),
+            pipeline_status: Vec::new(),
+            last_arg: None,
+            last_background_pid: None,
+            positional_params: Vec::new(),
+            functions: HashMap::new(),
+            aliases: HashMap::new(),
+            dir_stack: Vec::new(),
+            command_cache: HashMap::new(),
+            local_vars: HashMap::new(),
+            readonly_vars: HashSet::new(),
+            exported_vars: HashSet::new(),
+            integer_vars: HashSet::new(),
+            array_vars: HashMap::new(),
+            assoc_vars: HashMap::new(),
+            enabled_builtins: builtins::all_builtins(),
+            shell_stderr: config.shell_stderr(),
             config,
             is_interactive: atty::is(Stream::Stdin),
+            output: None,
+            command_log: None,
+            profiler: None,
+            line_number: 0,
+            loop_depth: 0,
+            function_depth: 0,
+            condition_depth: 0,
+            errexit_ignored: false,
+            traps: HashMap::new(),
+            in_trap: false,
+            background_jobs: Vec::new(),
         };
 
-        if config.enable_command_history {
+        if !shell.is_interactive {
+            shell.output = shell
+                .config
+                .max_output_size
+                .map(|limit| LimitedWriter::new(io::stdout(), limit));
+        }
+
+        shell.command_log = shell.config.open_command_log()?;
+        shell.profiler = shell.config.create_profiler()?;
+        shell.config.export_trace_fd();
+        shell.exported_vars = environment_variable_names();
+        if shell.config.enable_command_history {
             shell.load_history()?
         }
 
@@ -186,6 +1183,45 @@ impl SimpleShell {
         Ok(shell)
     }
 
+    /// Constructs a noninteractive shell with `config` for a subshell, whose state is then
+    /// copied from its parent.
+    fn new_subshell(config: ShellConfig) -> Self {
+        SimpleShell {
+            editor: Editor::with_capacity(config.command_history_capacity),
+            history_file: None,
+            last_exit_status: ExitStatus::from_success(),
+            pipeline_status: Vec::new(),
+            last_arg: None,
+            last_background_pid: None,
+            positional_params: Vec::new(),
+            functions: HashMap::new(),
+            aliases: HashMap::new(),
+            dir_stack: Vec::new(),
+            command_cache: HashMap::new(),
+            local_vars: HashMap::new(),
+            readonly_vars: HashSet::new(),
+            exported_vars: HashSet::new(),
+            integer_vars: HashSet::new(),
+            array_vars: HashMap::new(),
+            assoc_vars: HashMap::new(),
+            enabled_builtins: HashSet::new(),
+            shell_stderr: config.shell_stderr(),
+            config,
+            is_interactive: false,
+            output: None,
+            command_log: None,
+            profiler: None,
+            line_number: 0,
+            loop_depth: 0,
+            function_depth: 0,
+            condition_depth: 0,
+            errexit_ignored: false,
+            traps: HashMap::new(),
+            in_trap: false,
+            background_jobs: Vec::new(),
+        }
+    }
+
     fn load_history(&mut self) -> Result<()> {
         self.history_file = dirs::home_dir().map(|p| p.join(HISTORY_FILE_NAME));
         if let Some(ref history_file) = self.history_file {
@@ -212,24 +1248,142 @@ impl SimpleShell {
             self.last_exit_status.code().unwrap(),
             rel.display()
         );
-        let line = self.editor.readline(&prompt)?;
+        let line = if self.config.enable_line_editing {
+            self.editor.readline(&prompt)?
+        } else {
+            self.editor.readline_without_editing(&prompt)?
+        };
         Ok(line)
     }
 
+    /// Runs `$PROMPT_COMMAND`, if set.
+    fn run_prompt_command(&mut self) {
+        if let Some(command) = self.get_variable("PROMPT_COMMAND") {
+            self.run_hook(&command, "PROMPT_COMMAND");
+        }
+    }
+
+    /// Runs the `DEBUG` trap, if set, before `command` is executed.
+    ///
+    /// The trap can read the command from `$BASH_COMMAND`.
+    fn run_debug_trap(&mut self, command: &str) {
+        if !self.in_trap && self.traps.contains_key(DEBUG_TRAP) {
+            env::set_var("BASH_COMMAND", command);
+            self.run_trap(DEBUG_TRAP);
+        }
+    }
+
+    /// Saves the history to the history file, if enabled.
+    fn save_history(&mut self) {
+        if self.config.enable_command_history {
+            if let Some(ref history_file) = self.history_file {
+                // With `shopt -s histappend`, other shells' entries are kept.
+                let result = if self.config.get_option(ShellOption::Histappend) {
+                    self.editor.append_history(&history_file)
+                } else {
+                    self.editor.save_history(&history_file)
+                };
+                if let Err(e) = result {
+                    error!(
+                        "error: failed to save history to file during shutdown: {}",
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Runs `command` on behalf of the shell, without changing the last exit status or the
+    /// history.
+    fn run_hook(&mut self, command: &str, name: &str) {
+        let last_exit_status = self.last_exit_status;
+        let pipeline_status = mem::take(&mut self.pipeline_status);
+        let last_arg = self.last_arg.take();
+        let enable_command_history = mem::replace(&mut self.config.enable_command_history, false);
+        let temp_result = self.execute_command_string(command);
+        log_if_err!(temp_result, "{}", name);
+        self.config.enable_command_history = enable_command_history;
+        self.last_exit_status = last_exit_status;
+        self.pipeline_status = pipeline_status;
+        self.last_arg = last_arg;
+    }
+
+    /// Runs `commands` as the stages of a pipeline and returns the pipeline's stdout.
+    ///
+    /// This is a stepping stone towards command substitution, e.g. `X=$(cmd1 | cmd2)`.
+    pub fn pipe_to_string(&mut self, commands: &[&str]) -> Result<String> {
+        let (output, exit_status) =
+            execute_command_string_capturing_output(self, &commands.join(" | "))?;
+        self.last_exit_status = exit_status;
+        Ok(output)
+    }
+
     fn execute_command(&mut self, command_group: &mut ir::CommandGroup) -> Result<()> {
-        let mut process_group = match spawn_processes(self, command_group) {
+        let spawn_result = if self.output.is_some() {
+            spawn_processes_with_piped_stdout(self, command_group)
+        } else {
+            spawn_processes(self, command_group)
+        };
+        let mut process_group = match spawn_result {
             Ok(process_group) => Ok(process_group),
             Err(e) => {
                 if let ErrorKind::CommandNotFound(ref command) = *e.kind() {
-                    eprintln!("bsh: {}: command not found", command);
+                    print_error(
+                        &mut *self.shell_stderr,
+                        format_args!("{}: command not found", command),
+                    );
                     self.last_exit_status = ExitStatus::from_status(COMMAND_NOT_FOUND_EXIT_STATUS);
+                    self.pipeline_status = vec![self.last_exit_status];
+                    return Ok(());
+                } else if let ErrorKind::PermissionDenied(_) | ErrorKind::ArgumentListTooLong(_) =
+                    *e.kind()
+                {
+                    print_error(&mut *self.shell_stderr, &e);
+                    self.last_exit_status = ExitStatus::from_status(PERMISSION_DENIED_EXIT_STATUS);
+                    self.pipeline_status = vec![self.last_exit_status];
                     return Ok(());
+                } else if let ErrorKind::TooManyOpenFiles
+                | ErrorKind::Arithmetic(_)
+                | ErrorKind::NoGlobMatch(_)
+                | ErrorKind::NoClobber(_) = *e.kind()
+                {
+                    print_error(&mut *self.shell_stderr, &e);
+                    self.last_exit_status = ExitStatus::from_failure();
+                    self.pipeline_status = vec![self.last_exit_status];
+                    return Ok(());
+                } else if let ErrorKind::ParameterNullOrUnset { .. } = *e.kind() {
+                    // Like bash, only an interactive shell keeps going, a script exits.
+                    if self.is_interactive() {
+                        print_error(&mut *self.shell_stderr, &e);
+                        self.last_exit_status = ExitStatus::from_failure();
+                        self.pipeline_status = vec![self.last_exit_status];
+                        return Ok(());
+                    }
                 }
 
                 Err(e)
             }
         }?;
 
+        if command_group.background {
+            self.last_background_pid = process_group
+                .processes
+                .last()
+                .and_then(|process| process.id())
+                .map(u32::from);
+        }
+
+        if let Some(ref mut output) = self.output {
+            if !write_limited_output(&mut process_group, output, &mut *self.shell_stderr)? {
+                self.exit(Some(ExitStatus::from_failure()));
+            }
+        }
+
+        if !process_group.foreground {
+            self.background_jobs.push(process_group);
+            return Ok(());
+        }
+
         let num_processes = process_group.processes.len();
         let mut num_done = 0;
         while num_done < num_processes {
@@ -239,6 +1393,14 @@ impl SimpleShell {
                 }
             }
         }
+        self.pipeline_status = process_group
+            .processes
+            .iter()
+            .filter_map(|process| process.status_code())
+            .collect();
+        if let Some(status) = pipefail_status(self, &self.pipeline_status) {
+            self.last_exit_status = status;
+        }
 
         Ok(())
     }
@@ -257,11 +1419,23 @@ impl Shell for SimpleShell {
             self.editor.add_history_entry(input);
         }
 
-        let command = match Command::parse(input) {
+        if let Some(ref mut command_log) = self.command_log {
+            command_log.log(&command)?;
+        }
+
+        let expanded = if self.get_option(ShellOption::ExpandAliases) {
+            alias_expansion::expand_aliases(input, &self.aliases)
+        } else {
+            input.to_string()
+        };
+        let command = match Command::parse(&expanded) {
             Ok(command) => Ok(command),
             Err(e) => {
                 if let ErrorKind::Syntax(ref line) = *e.kind() {
-                    eprintln!("bsh: syntax error near: {}", line);
+                    print_error(
+                        &mut *self.shell_stderr,
+                        format_args!("syntax error near: {}", line),
+                    );
                     self.last_exit_status = ExitStatus::from_status(SYNTAX_ERROR_EXIT_STATUS);
                     return Ok(());
                 }
@@ -270,30 +1444,51 @@ impl Shell for SimpleShell {
             }
         }?;
 
-        let inner_command =
-            variable_expansion::expand_variables(&command.inner, dirs::home_dir(), env::vars());
-        let mut command_group = ir::Interpreter::parse(Command::new(&command.input, inner_command));
+        if self.get_option(ShellOption::Posix) {
+            if let Err(e) = posix::check_posix_compat(&command.inner) {
+                print_error(&mut *self.shell_stderr, &e);
+                self.last_exit_status = ExitStatus::from_failure();
+                return Ok(());
+            }
+        }
+
+        self.run_debug_trap(input);
+        let mut command_group = ir::Interpreter::parse(command);
+        let start = Instant::now();
         self.execute_command(&mut command_group)?;
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.record(input, start.elapsed(), self.line_number)?;
+        }
+        if !self.last_exit_status.success() {
+            handle_command_failure(self);
+        }
+        self.run_signal_traps();
 
         Ok(())
     }
 
     fn execute_commands_from_file(&mut self, path: &Path) -> Result<()> {
-        use std::io::Read;
-        let mut f = File::open(path).context(ErrorKind::Io)?;
-        let mut buffer = String::new();
-        f.read_to_string(&mut buffer)
-            .with_context(|_| ErrorKind::Io)?;
-
-        for line in buffer.split('\n') {
-            self.execute_command_string(line)?
-        }
-
-        Ok(())
+        let buffer = read_script(path, self.config.input_encoding)?;
+
+        // Restore the line number of the caller, e.g. a script running `source`.
+        let line_number = self.line_number;
+        // Here-document bodies are read with the line containing their operator.
+        let result =
+            parser::split_script(&buffer)
+                .into_iter()
+                .try_for_each(|(line_number, command)| {
+                    self.line_number = line_number;
+                    self.execute_command_string(&command)
+                        .map_err(|e| Error::with_location(e, path, line_number))
+                });
+        self.line_number = line_number;
+        result
     }
 
     fn execute_from_stdin(&mut self) {
         loop {
+            self.run_signal_traps();
+            self.run_prompt_command();
             let input = match self.prompt() {
                 Ok(Some(line)) => line.trim().to_owned(),
                 Ok(None) => break,
@@ -303,6 +1498,7 @@ impl Shell for SimpleShell {
                 }
             };
 
+            self.line_number += 1;
             let temp_result = self.execute_command_string(&input);
             log_if_err!(temp_result, "execute_command_string");
         }
@@ -317,35 +1513,402 @@ impl Shell for SimpleShell {
             Some(n) => n.code().unwrap(),
             None => self.last_exit_status.code().unwrap(),
         };
+        // The trap is removed first, so that `exit` in the trap exits immediately.
+        if let Some(handler) = self.traps.remove(EXIT_TRAP) {
+            self.last_exit_status = ExitStatus::from_status(code);
+            self.run_hook(&handler, "EXIT trap");
+        }
+        if self.config.login {
+            run_logout_file(self);
+        }
+        if let Some(ref mut profiler) = self.profiler {
+            log_if_err!(profiler.finish(), "failed to write profile");
+        }
         let code_like_u8 = if code < 0 {
             (256 + code) % 256
         } else {
             code % 256
         };
 
-        if self.config.enable_command_history {
-            if let Some(ref history_file) = self.history_file {
-                if let Err(e) = self.editor.save_history(&history_file) {
-                    error!(
-                        "error: failed to save history to file during shutdown: {}",
-                        e
-                    );
-                }
-            }
-        }
+        self.save_history();
 
         info!("bsh has shut down");
         process::exit(code_like_u8);
     }
 
+    fn prepare_for_exec(&mut self) {
+        if let Some(handler) = self.traps.remove(EXIT_TRAP) {
+            self.run_hook(&handler, "EXIT trap");
+        }
+        if let Some(ref mut profiler) = self.profiler {
+            log_if_err!(profiler.finish(), "failed to write profile");
+        }
+        self.save_history();
+        info!("bsh is replaced by exec");
+    }
+
     fn is_interactive(&self) -> bool {
         self.is_interactive
     }
 
+    fn is_login(&self) -> bool {
+        self.config.login
+    }
+
     fn is_job_control_enabled(&self) -> bool {
         false
     }
 
+    fn config(&self) -> &ShellConfig {
+        &self.config
+    }
+
+    fn config_mut(&mut self) -> &mut ShellConfig {
+        &mut self.config
+    }
+
+    fn shell_stderr(&mut self) -> &mut dyn Write {
+        &mut *self.shell_stderr
+    }
+
+    fn set_shell_stderr(&mut self, stderr: Box<dyn Write>) {
+        self.shell_stderr = stderr;
+    }
+
+    fn get_option(&self, option: ShellOption) -> bool {
+        self.config.get_option(option)
+    }
+
+    fn set_option(&mut self, option: ShellOption, enabled: bool) {
+        self.config.set_option(option, enabled);
+        if option == ShellOption::Vi {
+            self.editor.set_vi_mode(enabled);
+        }
+    }
+
+    fn last_exit_status(&self) -> ExitStatus {
+        self.last_exit_status
+    }
+
+    fn set_last_exit_status(&mut self, status: ExitStatus) {
+        self.last_exit_status = status;
+    }
+
+    fn pipeline_status_vec(&self) -> &[ExitStatus] {
+        &self.pipeline_status
+    }
+
+    fn set_pipeline_status(&mut self, statuses: Vec<ExitStatus>) {
+        self.pipeline_status = statuses;
+    }
+
+    fn last_argument(&self) -> Option<&str> {
+        self.last_arg.as_deref()
+    }
+
+    fn set_last_argument(&mut self, arg: &str) {
+        self.last_arg = Some(arg.to_string());
+    }
+
+    fn loop_depth(&self) -> usize {
+        self.loop_depth
+    }
+
+    fn set_loop_depth(&mut self, depth: usize) {
+        self.loop_depth = depth;
+    }
+
+    fn function_depth(&self) -> usize {
+        self.function_depth
+    }
+
+    fn set_function_depth(&mut self, depth: usize) {
+        self.function_depth = depth;
+    }
+
+    fn condition_depth(&self) -> usize {
+        self.condition_depth
+    }
+
+    fn set_condition_depth(&mut self, depth: usize) {
+        self.condition_depth = depth;
+    }
+
+    fn errexit_ignored(&self) -> bool {
+        self.errexit_ignored
+    }
+
+    fn set_errexit_ignored(&mut self, ignored: bool) {
+        self.errexit_ignored = ignored;
+    }
+
+    fn last_background_pid(&self) -> Option<u32> {
+        self.last_background_pid
+    }
+
+    fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    fn positional_params(&self) -> &[String] {
+        &self.positional_params
+    }
+
+    fn set_positional_params(&mut self, params: Vec<String>) {
+        self.positional_params = params;
+    }
+
+    fn get_function(&self, name: &str) -> Option<&ir::Command> {
+        self.functions.get(name)
+    }
+
+    fn define_function(&mut self, name: &str, body: ir::Command) -> Result<()> {
+        self.config.check_command_name(name)?;
+        self.functions.insert(name.to_string(), body);
+        Ok(())
+    }
+
+    fn function_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn aliases(&self) -> &HashMap<String, String> {
+        &self.aliases
+    }
+
+    fn aliases_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.aliases
+    }
+
+    fn dir_stack(&self) -> &[PathBuf] {
+        &self.dir_stack
+    }
+
+    fn dir_stack_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.dir_stack
+    }
+
+    fn command_cache(&self) -> &HashMap<String, PathBuf> {
+        &self.command_cache
+    }
+
+    fn command_cache_mut(&mut self) -> &mut HashMap<String, PathBuf> {
+        &mut self.command_cache
+    }
+
+    fn clone_for_subshell(&self) -> Result<Box<dyn Shell>> {
+        Ok(Box::new(SimpleShell {
+            last_exit_status: self.last_exit_status,
+            pipeline_status: self.pipeline_status.clone(),
+            last_arg: self.last_arg.clone(),
+            last_background_pid: self.last_background_pid,
+            positional_params: self.positional_params.clone(),
+            functions: self.functions.clone(),
+            aliases: self.aliases.clone(),
+            dir_stack: self.dir_stack.clone(),
+            command_cache: self.command_cache.clone(),
+            line_number: self.line_number,
+            loop_depth: self.loop_depth,
+            function_depth: self.function_depth,
+            condition_depth: self.condition_depth,
+            local_vars: self.local_vars.clone(),
+            readonly_vars: self.readonly_vars.clone(),
+            exported_vars: self.exported_vars.clone(),
+            integer_vars: self.integer_vars.clone(),
+            array_vars: self.array_vars.clone(),
+            assoc_vars: self.assoc_vars.clone(),
+            enabled_builtins: self.enabled_builtins.clone(),
+            ..SimpleShell::new_subshell(self.config.clone())
+        }))
+    }
+
+    fn get_variable(&self, name: &str) -> Option<String> {
+        self.local_vars
+            .get(name)
+            .cloned()
+            .or_else(|| env::var(name).ok())
+    }
+
+    fn set_variable(&mut self, name: &str, value: &str) {
+        let value = if self.integer_vars.contains(name) {
+            match self.eval_arithmetic(value) {
+                Ok(n) => n.to_string(),
+                Err(e) => {
+                    print_error(&mut *self.shell_stderr, &e);
+                    return;
+                }
+            }
+        } else {
+            value.to_string()
+        };
+        if name == "PATH" {
+            self.command_cache.clear();
+        }
+        match self.local_vars.get_mut(name) {
+            Some(local_value) => *local_value = value,
+            None => env::set_var(name, value),
+        }
+    }
+
+    fn unset_variable(&mut self, name: &str) {
+        if name == "PATH" {
+            self.command_cache.clear();
+        }
+        if self.local_vars.remove(name).is_none() {
+            env::remove_var(name);
+            self.exported_vars.remove(name);
+            self.integer_vars.remove(name);
+        }
+        self.array_vars.remove(name);
+        self.assoc_vars.remove(name);
+    }
+
+    fn declare_readonly(&mut self, name: &str) {
+        self.readonly_vars.insert(name.to_string());
+    }
+
+    fn readonly_variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.readonly_vars.iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn set_exported(&mut self, name: &str, exported: bool) {
+        if exported {
+            self.exported_vars.insert(name.to_string());
+        } else {
+            self.exported_vars.remove(name);
+        }
+    }
+
+    fn is_exported(&self, name: &str) -> bool {
+        self.exported_vars.contains(name)
+    }
+
+    fn exported_variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.exported_vars.iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn set_integer(&mut self, name: &str, integer: bool) {
+        if integer {
+            self.integer_vars.insert(name.to_string());
+        } else {
+            self.integer_vars.remove(name);
+        }
+    }
+
+    fn is_integer(&self, name: &str) -> bool {
+        self.integer_vars.contains(name)
+    }
+
+    fn check_variable_assignment(&self, name: &str) -> Result<()> {
+        self.config.check_variable_assignment(name)?;
+        if self.readonly_vars.contains(name) {
+            return Err(Error::readonly_variable(name));
+        }
+
+        Ok(())
+    }
+
+    fn is_builtin_enabled(&self, name: &str) -> bool {
+        self.enabled_builtins.contains(name)
+    }
+
+    fn set_builtin_enabled(&mut self, name: &str, enabled: bool) {
+        if enabled {
+            self.enabled_builtins.insert(name.to_string());
+        } else {
+            self.enabled_builtins.remove(name);
+        }
+    }
+
+    fn traps(&self) -> &HashMap<String, String> {
+        &self.traps
+    }
+
+    fn set_trap(&mut self, condition: &str, handler: Option<String>) {
+        match handler {
+            Some(handler) => self.traps.insert(condition.to_string(), handler),
+            None => self.traps.remove(condition),
+        };
+    }
+
+    fn run_trap(&mut self, condition: &str) {
+        if self.in_trap {
+            return;
+        }
+        if let Some(handler) = self.traps.get(condition).cloned() {
+            self.in_trap = true;
+            self.run_hook(&handler, &format!("{} trap", condition));
+            self.in_trap = false;
+        }
+    }
+
+    fn run_signal_traps(&mut self) {
+        if self.in_trap {
+            return;
+        }
+        for signal in signals::take_pending() {
+            self.run_trap(signal.as_str());
+        }
+    }
+
+    fn eval_arithmetic(&mut self, expr: &str) -> Result<i64> {
+        arithmetic::evaluate(expr, self)
+    }
+
+    fn get_array(&self, name: &str) -> Option<&BTreeMap<usize, String>> {
+        self.array_vars.get(name)
+    }
+
+    fn declare_array(&mut self, name: &str) {
+        self.array_vars.entry(name.to_string()).or_default();
+    }
+
+    fn set_array(&mut self, name: &str, elements: BTreeMap<usize, String>) {
+        self.array_vars.insert(name.to_string(), elements);
+    }
+
+    fn set_array_element(&mut self, name: &str, index: usize, value: &str) {
+        self.array_vars
+            .entry(name.to_string())
+            .or_default()
+            .insert(index, value.to_string());
+    }
+
+    fn get_assoc_array(&self, name: &str) -> Option<&HashMap<String, String>> {
+        self.assoc_vars.get(name)
+    }
+
+    fn declare_assoc_array(&mut self, name: &str) {
+        self.assoc_vars.entry(name.to_string()).or_default();
+    }
+
+    fn set_assoc_array(&mut self, name: &str, elements: HashMap<String, String>) {
+        self.assoc_vars.insert(name.to_string(), elements);
+    }
+
+    fn set_assoc_element(&mut self, name: &str, key: &str, value: &str) {
+        self.assoc_vars
+            .entry(name.to_string())
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    fn unset_array_element(&mut self, name: &str, subscript: &str) {
+        if let Some(assoc_array) = self.assoc_vars.get_mut(name) {
+            assoc_array.remove(subscript);
+        } else if let (Some(array), Ok(index)) =
+            (self.array_vars.get_mut(name), subscript.parse::<usize>())
+        {
+            array.remove(&index);
+        }
+    }
+
     fn editor(&self) -> &Editor {
         &self.editor
     }
@@ -362,6 +1925,51 @@ impl Shell for SimpleShell {
         false
     }
 
+    fn job_by_spec(&self, _spec: &str) -> Result<JobId> {
+        Err(Error::no_job_control())
+    }
+
+    fn set_job_notified(&mut self, _job_id: JobId) {}
+
+    fn wait_for_job(&mut self, _job_id: JobId) -> Result<Option<ExitStatus>> {
+        Err(Error::no_job_control())
+    }
+
+    fn wait_for_next_job(&mut self) -> Result<Option<ExitStatus>> {
+        while !self.background_jobs.is_empty() {
+            for (index, process_group) in self.background_jobs.iter_mut().enumerate() {
+                if try_wait_all(&mut process_group.processes)? {
+                    let process_group = self.background_jobs.remove(index);
+                    return Ok(process_group
+                        .processes
+                        .last()
+                        .and_then(|process| process.status_code()));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn wait_for_process(&mut self, pid: u32) -> Result<Option<ExitStatus>> {
+        let is_process = |process: &Box<dyn Process>| process.id().map(u32::from) == Some(pid);
+        let index = match self
+            .background_jobs
+            .iter()
+            .position(|process_group| process_group.processes.iter().any(is_process))
+        {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        let processes = &mut self.background_jobs[index].processes;
+        let process = processes.iter_mut().find(|process| is_process(process));
+        let exit_status = process.map(|process| process.wait()).transpose()?;
+        if try_wait_all(processes)? {
+            self.background_jobs.remove(index);
+        }
+        Ok(exit_status)
+    }
+
     fn put_job_in_foreground(&mut self, _job_id: Option<JobId>) -> Result<Option<ExitStatus>> {
         Err(Error::no_job_control())
     }
@@ -370,13 +1978,164 @@ impl Shell for SimpleShell {
         Err(Error::no_job_control())
     }
 
-    fn kill_background_job(&mut self, job_id: u32) -> Result<Option<&dyn Job>> {
+    fn kill_background_job(&mut self, job_id: JobId, _signal: Signal) -> Result<Option<&dyn Job>> {
         // For compatibility with bash, return "no such job" instead of "no job
         // control"
         Err(Error::no_such_job(job_id.to_string()))
     }
 }
 
+impl<'a> VariableLookup for dyn Shell + 'a {
+    fn lookup_variable(&self, name: &str) -> Option<String> {
+        variable_expansion::expand_pipeline_status(name, self.pipeline_status_vec())
+            .or_else(|| self.get_variable(name))
+    }
+
+    fn lookup_last_argument(&self) -> Option<String> {
+        self.last_argument().map(str::to_string)
+    }
+
+    fn lookup_last_exit_status(&self) -> ExitStatus {
+        self.last_exit_status()
+    }
+
+    fn lookup_last_background_pid(&self) -> Option<u32> {
+        self.last_background_pid()
+    }
+
+    fn lookup_shell_name(&self) -> String {
+        self.config().name.clone()
+    }
+
+    fn lookup_line_number(&self) -> usize {
+        self.line_number()
+    }
+
+    fn lookup_positional_params(&self) -> &[String] {
+        self.positional_params()
+    }
+
+    fn lookup_array(&self, name: &str) -> Option<&BTreeMap<usize, String>> {
+        self.get_array(name)
+    }
+
+    fn lookup_assoc_array(&self, name: &str) -> Option<&HashMap<String, String>> {
+        self.get_assoc_array(name)
+    }
+
+    fn treats_unset_as_error(&self) -> bool {
+        self.get_option(ShellOption::Nounset)
+    }
+}
+
+// `shell_stderr` is not `Debug`, so this cannot be derived.
+impl fmt::Debug for SimpleShell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimpleShell")
+            .field("editor", &self.editor)
+            .field("history_file", &self.history_file)
+            .field("last_exit_status", &self.last_exit_status)
+            .field("pipeline_status", &self.pipeline_status)
+            .field("last_arg", &self.last_arg)
+            .field("last_background_pid", &self.last_background_pid)
+            .field("positional_params", &self.positional_params)
+            .field("functions", &self.functions)
+            .field("aliases", &self.aliases)
+            .field("dir_stack", &self.dir_stack)
+            .field("command_cache", &self.command_cache)
+            .field("local_vars", &self.local_vars)
+            .field("readonly_vars", &self.readonly_vars)
+            .field("exported_vars", &self.exported_vars)
+            .field("integer_vars", &self.integer_vars)
+            .field("array_vars", &self.array_vars)
+            .field("assoc_vars", &self.assoc_vars)
+            .field("enabled_builtins", &self.enabled_builtins)
+            .field("config", &self.config)
+            .field("is_interactive", &self.is_interactive)
+            .field("output", &self.output)
+            .field("command_log", &self.command_log)
+            .field("profiler", &self.profiler)
+            .field("line_number", &self.line_number)
+            .field("loop_depth", &self.loop_depth)
+            .field("function_depth", &self.function_depth)
+            .field("condition_depth", &self.condition_depth)
+            .field("errexit_ignored", &self.errexit_ignored)
+            .field("traps", &self.traps)
+            .field("in_trap", &self.in_trap)
+            .field("background_jobs", &self.background_jobs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs `~/.bsh_logout`, if it exists, as a login shell exits.
+fn run_logout_file(shell: &mut dyn Shell) {
+    // The file may call `exit`, which must not run it again.
+    shell.config_mut().login = false;
+    if let Some(path) = dirs::home_dir().map(|home| home.join(LOGOUT_FILE_NAME)) {
+        if path.is_file() {
+            let result = shell.execute_commands_from_file(&path);
+            log_if_err!(result, "failed to run {}", path.display());
+        }
+    }
+}
+
+/// Returns the names of the variables in the shell's environment, which are exported.
+fn environment_variable_names() -> HashSet<String> {
+    env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .collect()
+}
+
+/// Writes the diagnostic `message` to `stderr`, e.g. [`Shell::shell_stderr`].
+pub(crate) fn print_error<T: fmt::Display>(stderr: &mut dyn Write, message: T) {
+    log_if_err!(
+        writeln!(stderr, "bsh: {}", message),
+        "failed to write diagnostic"
+    );
+}
+
+/// Reads the script at `path`, decoding it with `encoding`.
+fn read_script(path: &Path, encoding: InputEncoding) -> Result<String> {
+    let mut f = File::open(path).context(ErrorKind::Io)?;
+    let mut buffer = Vec::new();
+    f.read_to_end(&mut buffer).context(ErrorKind::Io)?;
+    encoding
+        .decode(&buffer)
+        .ok_or_else(|| Error::encoding(path))
+}
+
+/// Copies the stdout of `process_group` to `output`.
+///
+/// Returns `Ok(false)` if the output size limit was exceeded.
+fn write_limited_output(
+    process_group: &mut ProcessGroup,
+    output: &mut LimitedWriter<io::Stdout>,
+    stderr: &mut dyn Write,
+) -> Result<bool> {
+    let result = copy_stdout(process_group, output);
+    output.flush().context(ErrorKind::Io)?;
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) if output.is_limit_exceeded() => {
+            print_error(stderr, e);
+            Ok(false)
+        }
+        Err(e) => Err(e.context(ErrorKind::Io).into()),
+    }
+}
+
+/// Checks whether `processes` have completed, without blocking.
+fn try_wait_all(processes: &mut [Box<dyn Process>]) -> Result<bool> {
+    for process in processes.iter_mut() {
+        if process.status() != ProcessStatus::Completed {
+            process.try_wait()?;
+        }
+    }
+    Ok(processes
+        .iter()
+        .all(|process| process.status() == ProcessStatus::Completed))
+}
+
 /// Creates a new `SimpleShell` instance.
 ///
 /// `SimpleShell` is cross-platform and has job control and terminal handling
@@ -385,3 +2144,249 @@ pub fn create_simple_shell(config: ShellConfig) -> Result<Box<dyn Shell>> {
     let shell = SimpleShell::new(config)?;
     Ok(Box::new(shell))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pipe_to_string() {
+        let mut shell = SimpleShell::new(ShellConfig::noninteractive()).unwrap();
+        assert_eq!(shell.pipe_to_string(&["echo hello"]).unwrap(), "hello\n");
+        assert_eq!(
+            shell.pipe_to_string(&["echo hello", "tr a-z A-Z"]).unwrap(),
+            "HELLO\n"
+        );
+        assert!(shell.last_exit_status().success());
+    }
+
+    #[test]
+    fn test_get_variable_lookup_order() {
+        let mut shell = SimpleShell::new(ShellConfig::noninteractive()).unwrap();
+        let key = "BSH_TEST_GET_VARIABLE_LOOKUP_ORDER";
+        assert_eq!(shell.get_variable(key), None);
+
+        env::set_var(key, "env");
+        assert_eq!(shell.get_variable(key), Some("env".to_string()));
+
+        shell
+            .local_vars
+            .insert(key.to_string(), "local".to_string());
+        assert_eq!(shell.get_variable(key), Some("local".to_string()));
+        env::remove_var(key);
+    }
+
+    #[test]
+    fn test_set_variable_updates_local_if_declared() {
+        let mut shell = SimpleShell::new(ShellConfig::noninteractive()).unwrap();
+        let key = "BSH_TEST_SET_VARIABLE_LOCAL";
+        shell.local_vars.insert(key.to_string(), "old".to_string());
+
+        shell.set_variable(key, "new");
+        assert_eq!(shell.local_vars.get(key), Some(&"new".to_string()));
+        assert!(env::var(key).is_err());
+
+        shell.unset_variable(key);
+        assert_eq!(shell.get_variable(key), None);
+    }
+
+    /// A writer whose contents can be read after it is given to a shell.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn take(&self) -> String {
+            String::from_utf8(self.0.borrow_mut().split_off(0)).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_shell_stderr() {
+        let mut shell = SimpleShell::new(ShellConfig::noninteractive()).unwrap();
+        let stderr = SharedBuffer::default();
+        shell.set_shell_stderr(Box::new(stderr.clone()));
+
+        shell
+            .execute_command_string("bsh_test_missing_command")
+            .unwrap();
+        assert_eq!(
+            stderr.take(),
+            "bsh: bsh_test_missing_command: command not found\n"
+        );
+
+        shell.execute_command_string("echo foo |").unwrap();
+        assert!(stderr.take().starts_with("bsh: syntax error near: "));
+    }
+
+    #[test]
+    fn test_redirect_stderr_to_log() {
+        let config = ShellConfig::noninteractive().redirect_stderr_to_log(true);
+        let mut shell = SimpleShell::new(config).unwrap();
+        shell
+            .execute_command_string("bsh_test_missing_command")
+            .unwrap();
+        assert_eq!(shell.last_exit_status().code(), Some(127));
+    }
+
+    #[test]
+    fn test_get_set_option() {
+        let mut shell = SimpleShell::new(ShellConfig::noninteractive()).unwrap();
+        for option in ShellOption::ALL {
+            assert_eq!(shell.get_option(*option), option.is_enabled_by_default());
+            shell.set_option(*option, true);
+            assert!(shell.get_option(*option));
+            shell.set_option(*option, false);
+            assert!(!shell.get_option(*option));
+
+            assert_eq!(ShellOption::from_name(option.name()), Some(*option));
+        }
+        assert_eq!(ShellOption::from_name("bogus"), None);
+
+        let config = ShellConfig::noninteractive().posix_mode(true);
+        assert!(config.get_option(ShellOption::Posix));
+        assert!(!config.get_option(ShellOption::Xtrace));
+    }
+
+    #[test]
+    fn test_input_encoding_decode() {
+        let latin1 = b"caf\xE9";
+        assert_eq!(InputEncoding::Utf8.decode(latin1), None);
+        assert_eq!(
+            InputEncoding::Latin1.decode(latin1),
+            Some("café".to_string())
+        );
+        assert_eq!(InputEncoding::Auto.decode(latin1), Some("café".to_string()));
+
+        let utf8 = "café".as_bytes();
+        assert_eq!(InputEncoding::Utf8.decode(utf8), Some("café".to_string()));
+        assert_eq!(InputEncoding::Auto.decode(utf8), Some("café".to_string()));
+        assert_eq!(
+            InputEncoding::Auto.decode(b"\xEF\xBB\xBFcaf\xC3\xA9"),
+            Some("café".to_string())
+        );
+        assert_eq!(
+            InputEncoding::Auto.decode(b"\xFF\xFEc\0a\0f\0\xE9\0"),
+            Some("café".to_string())
+        );
+        assert_eq!(InputEncoding::Auto.decode(b"\xFF\xFEc"), None);
+    }
+
+    #[test]
+    fn test_input_encoding_from_str() {
+        assert_eq!(
+            "UTF-8".parse::<InputEncoding>().unwrap(),
+            InputEncoding::Utf8
+        );
+        assert_eq!(
+            "latin1".parse::<InputEncoding>().unwrap(),
+            InputEncoding::Latin1
+        );
+        assert_eq!(
+            "auto".parse::<InputEncoding>().unwrap(),
+            InputEncoding::Auto
+        );
+        assert!("ebcdic".parse::<InputEncoding>().is_err());
+    }
+
+    #[test]
+    fn test_validate_config() {
+        assert!(ShellConfig::noninteractive().validate().is_ok());
+        assert!(ShellConfig::interactive(10).validate().is_ok());
+        assert!(ShellConfig::noninteractive()
+            .sandbox_mode(true)
+            .sandbox_dir("/tmp")
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_config_errors() {
+        let is_configuration_error = |result: Result<ShellConfig>| match result {
+            Err(e) => matches!(e.kind(), ErrorKind::Configuration(_)),
+            Ok(_) => false,
+        };
+
+        assert!(is_configuration_error(
+            ShellConfig::interactive(0).validate()
+        ));
+        assert!(is_configuration_error(
+            ShellConfig::noninteractive().sandbox_dir("/tmp").validate()
+        ));
+        assert!(is_configuration_error(
+            ShellConfig::noninteractive()
+                .sandbox_blocked_paths(vec![PathBuf::from("/usr/bin")])
+                .validate()
+        ));
+        assert!(is_configuration_error(
+            ShellConfig::noninteractive()
+                .command_log_path("commands.log")
+                .validate()
+        ));
+    }
+
+    #[test]
+    fn test_clone_for_subshell() {
+        let mut shell = SimpleShell::new(ShellConfig::noninteractive().posix_mode(true)).unwrap();
+        shell.last_exit_status = ExitStatus::from_status(3);
+        shell
+            .local_vars
+            .insert("FOO".to_string(), "foo".to_string());
+        shell.set_array_element("ARRAY", 0, "a");
+
+        let mut subshell = shell.clone_for_subshell().unwrap();
+        assert!(!subshell.is_interactive());
+        assert!(subshell.get_option(ShellOption::Posix));
+        assert_eq!(subshell.last_exit_status().code(), Some(3));
+        assert_eq!(subshell.get_variable("FOO"), Some("foo".to_string()));
+        assert_eq!(subshell.get_array("ARRAY").unwrap().len(), 1);
+
+        subshell.set_variable("FOO", "bar");
+        subshell.set_array_element("ARRAY", 1, "b");
+        subshell.declare_assoc_array("ASSOC");
+        assert_eq!(shell.get_variable("FOO"), Some("foo".to_string()));
+        assert_eq!(shell.get_array("ARRAY").unwrap().len(), 1);
+        assert!(shell.get_assoc_array("ASSOC").is_none());
+    }
+
+    #[test]
+    fn test_eval_arithmetic() {
+        let mut shell = SimpleShell::new(ShellConfig::noninteractive()).unwrap();
+        let key = "BSH_TEST_EVAL_ARITHMETIC";
+
+        assert_eq!(shell.eval_arithmetic("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(
+            shell.eval_arithmetic(&format!("{} = 6 & 3", key)).unwrap(),
+            2
+        );
+        assert_eq!(shell.get_variable(key).unwrap(), "2");
+        assert_eq!(shell.eval_arithmetic(&format!("{} << 3", key)).unwrap(), 16);
+        assert!(shell.eval_arithmetic("1 / 0").is_err());
+        shell.unset_variable(key);
+    }
+
+    #[test]
+    fn test_set_variable_falls_back_to_env() {
+        let mut shell = SimpleShell::new(ShellConfig::noninteractive()).unwrap();
+        let key = "BSH_TEST_SET_VARIABLE_ENV";
+
+        shell.set_variable(key, "value");
+        assert!(shell.local_vars.is_empty());
+        assert_eq!(env::var(key).unwrap(), "value");
+
+        shell.unset_variable(key);
+        assert!(env::var(key).is_err());
+    }
+}
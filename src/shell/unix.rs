@@ -2,11 +2,14 @@
 //! in addition to the normal shell abilities such as managing the command
 //! history.
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fmt;
-use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
 use std::path::{Path, PathBuf};
 use std::process::{self, ExitStatus};
+use std::time::{Duration, Instant};
 
 use atty::{self, Stream};
 use dirs;
@@ -22,15 +25,25 @@ use nix::{
 };
 
 use super::{
-    Job, JobId, Shell, ShellConfig, COMMAND_NOT_FOUND_EXIT_STATUS, HISTORY_FILE_NAME,
+    environment_variable_names, print_error, read_script, run_logout_file, write_limited_output,
+    Job, JobId, Shell, ShellConfig, ShellOption, SimpleShell, COMMAND_NOT_FOUND_EXIT_STATUS,
+    DEBUG_TRAP, EXIT_TRAP, HISTORY_FILE_NAME, PERMISSION_DENIED_EXIT_STATUS,
     SYNTAX_ERROR_EXIT_STATUS,
 };
 use crate::{
-    core::{intermediate_representation as ir, parser::Command, variable_expansion},
+    builtins,
+    core::{
+        alias_expansion, arithmetic, intermediate_representation as ir,
+        parser::{self, Command},
+        posix,
+    },
     editor::Editor,
     errors::{Error, ErrorKind, Result},
-    execute_command::{spawn_processes, Process, ProcessGroup, ProcessStatus},
-    util::{self, BshExitStatusExt},
+    execute_command::{
+        handle_command_failure, pipefail_status, spawn_processes,
+        spawn_processes_with_piped_stdout, Process, ProcessGroup, ProcessStatus,
+    },
+    util::{self, signals, BshExitStatusExt, CommandAuditLogger, LimitedWriter, Profiler},
 };
 
 pub struct JobControlShell {
@@ -40,10 +53,64 @@ pub struct JobControlShell {
     job_manager: JobManager,
     /// Exit status of last command executed.
     last_exit_status: ExitStatus,
+    /// Exit statuses of the processes of the last command executed, see `$PIPESTATUS`.
+    pipeline_status: Vec<ExitStatus>,
+    /// Last argument of the last simple command executed, see `$_`.
+    last_arg: Option<String>,
+    /// Process ID of the last process of the most recent background job, see `$!`.
+    last_background_pid: Option<u32>,
+    /// Positional parameters, e.g. the arguments of a script, see `$1`.
+    positional_params: Vec<String>,
+    /// Functions defined with `name() { ...; }`, by name.
+    functions: HashMap<String, ir::Command>,
+    /// Aliases defined with `alias`, by name.
+    aliases: HashMap<String, String>,
+    /// Directories saved with `pushd`, most recent first, see [`Shell::dir_stack`].
+    dir_stack: Vec<PathBuf>,
+    /// Paths of external commands found in `$PATH`, by name, see `hash`.
+    command_cache: HashMap<String, PathBuf>,
+    /// Shell variables, which take precedence over environment variables.
+    local_vars: HashMap<String, String>,
+    /// Variables that may not be assigned or unset, declared with `readonly`.
+    readonly_vars: HashSet<String>,
+    /// Variables passed to the environment of external commands, see [`Shell::is_exported`].
+    exported_vars: HashSet<String>,
+    /// Variables whose assigned values are evaluated as arithmetic, declared with `declare -i`.
+    integer_vars: HashSet<String>,
+    /// Array variables, e.g. declared with `declare -a`.
+    array_vars: HashMap<String, BTreeMap<usize, String>>,
+    /// Associative array variables, declared with `declare -A`.
+    assoc_vars: HashMap<String, HashMap<String, String>>,
+    /// Builtins that have not been disabled with `enable -n`.
+    enabled_builtins: HashSet<String>,
     config: ShellConfig,
     /// Is `false` if the shell is running a script or if initializing job
     /// control fails.
     is_interactive: bool,
+    /// Stdout of noninteractive shells with a maximum output size.
+    output: Option<LimitedWriter<io::Stdout>>,
+    /// Writer for the shell's own diagnostics, see [`Shell::shell_stderr`].
+    shell_stderr: Box<dyn Write>,
+    /// Audit log of executed commands, see [`ShellConfig::log_commands`].
+    command_log: Option<CommandAuditLogger>,
+    /// Records the time each command takes, see [`ShellConfig::profiling`].
+    profiler: Option<Profiler>,
+    /// Line number of the command being executed, e.g. in a script.
+    line_number: usize,
+    /// Number of loops enclosing the command being executed, see [`Shell::loop_depth`].
+    loop_depth: usize,
+    /// Number of function calls enclosing the command being executed, see
+    /// [`Shell::function_depth`].
+    function_depth: usize,
+    /// Number of conditions enclosing the command being executed, see
+    /// [`Shell::condition_depth`].
+    condition_depth: usize,
+    /// See [`Shell::errexit_ignored`].
+    errexit_ignored: bool,
+    /// Commands run on signals and shell events, by condition, see [`Shell::set_trap`].
+    traps: HashMap<String, String>,
+    /// Is `true` while a trap runs, so that it does not trigger itself.
+    in_trap: bool,
 }
 
 impl JobControlShell {
@@ -54,8 +121,34 @@ impl JobControlShell {
             history_file: None,
             job_manager: Default::default(),
             last_exit_status: ExitStatus::from_success(),
+            pipeline_status: Vec::new(),
+            last_arg: None,
+            last_background_pid: None,
+            positional_params: Vec::new(),
+            functions: HashMap::new(),
+            aliases: HashMap::new(),
+            dir_stack: Vec::new(),
+            command_cache: HashMap::new(),
+            local_vars: HashMap::new(),
+            readonly_vars: HashSet::new(),
+            exported_vars: HashSet::new(),
+            integer_vars: HashSet::new(),
+            array_vars: HashMap::new(),
+            assoc_vars: HashMap::new(),
+            enabled_builtins: builtins::all_builtins(),
+            shell_stderr: config.shell_stderr(),
             config,
             is_interactive: atty::is(Stream::Stdin),
+            output: None,
+            command_log: None,
+            profiler: None,
+            line_number: 0,
+            loop_depth: 0,
+            function_depth: 0,
+            condition_depth: 0,
+            errexit_ignored: false,
+            traps: HashMap::new(),
+            in_trap: false,
         };
 
         if shell.is_interactive {
@@ -69,7 +162,18 @@ impl JobControlShell {
             }
         }
 
-        if config.enable_command_history {
+        if !shell.is_interactive {
+            shell.output = shell
+                .config
+                .max_output_size
+                .map(|limit| LimitedWriter::new(io::stdout(), limit));
+        }
+
+        shell.command_log = shell.config.open_command_log()?;
+        shell.profiler = shell.config.create_profiler()?;
+        shell.config.export_trace_fd();
+        shell.exported_vars = environment_variable_names();
+        if shell.config.enable_command_history {
             shell.load_history()?
         }
 
@@ -103,39 +207,154 @@ impl JobControlShell {
             self.last_exit_status.code().unwrap(),
             rel.display()
         );
-        let line = self.editor.readline(&prompt)?;
+        let line = if self.config.enable_line_editing {
+            self.editor.readline(&prompt)?
+        } else {
+            self.editor.readline_without_editing(&prompt)?
+        };
         Ok(line)
     }
 
+    /// Runs `$PROMPT_COMMAND`, if set.
+    fn run_prompt_command(&mut self) {
+        if let Some(command) = self.get_variable("PROMPT_COMMAND") {
+            self.run_hook(&command, "PROMPT_COMMAND");
+        }
+    }
+
+    /// Runs the `DEBUG` trap, if set, before `command` is executed.
+    ///
+    /// The trap can read the command from `$BASH_COMMAND`.
+    fn run_debug_trap(&mut self, command: &str) {
+        if !self.in_trap && self.traps.contains_key(DEBUG_TRAP) {
+            env::set_var("BASH_COMMAND", command);
+            self.run_trap(DEBUG_TRAP);
+        }
+    }
+
+    /// Saves the history to the history file, if enabled.
+    fn save_history(&mut self) {
+        if self.config.enable_command_history {
+            if let Some(ref history_file) = self.history_file {
+                // With `shopt -s histappend`, other shells' entries are kept.
+                let result = if self.config.get_option(ShellOption::Histappend) {
+                    self.editor.append_history(&history_file)
+                } else {
+                    self.editor.save_history(&history_file)
+                };
+                if let Err(e) = result {
+                    error!(
+                        "error: failed to save history to file during shutdown: {}",
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Runs `command` on behalf of the shell, without changing the last exit status or the
+    /// history.
+    fn run_hook(&mut self, command: &str, name: &str) {
+        let last_exit_status = self.last_exit_status;
+        let pipeline_status = mem::take(&mut self.pipeline_status);
+        let last_arg = self.last_arg.take();
+        let enable_command_history = mem::replace(&mut self.config.enable_command_history, false);
+        let temp_result = self.execute_command_string(command);
+        log_if_err!(temp_result, "{}", name);
+        self.config.enable_command_history = enable_command_history;
+        self.last_exit_status = last_exit_status;
+        self.pipeline_status = pipeline_status;
+        self.last_arg = last_arg;
+    }
+
     /// Runs a job.
     fn execute_command(&mut self, command_group: &mut ir::CommandGroup) -> Result<()> {
-        let process_group = match spawn_processes(self, command_group) {
+        let spawn_result = if self.output.is_some() {
+            spawn_processes_with_piped_stdout(self, command_group)
+        } else {
+            spawn_processes(self, command_group)
+        };
+        let mut process_group = match spawn_result {
             Ok(process_group) => Ok(process_group),
             Err(e) => {
                 if let ErrorKind::CommandNotFound(ref command) = *e.kind() {
-                    eprintln!("bsh: {}: command not found", command);
+                    print_error(
+                        &mut *self.shell_stderr,
+                        format_args!("{}: command not found", command),
+                    );
                     self.last_exit_status = ExitStatus::from_status(COMMAND_NOT_FOUND_EXIT_STATUS);
+                    self.pipeline_status = vec![self.last_exit_status];
                     return Ok(());
+                } else if let ErrorKind::PermissionDenied(_) | ErrorKind::ArgumentListTooLong(_) =
+                    *e.kind()
+                {
+                    print_error(&mut *self.shell_stderr, &e);
+                    self.last_exit_status = ExitStatus::from_status(PERMISSION_DENIED_EXIT_STATUS);
+                    self.pipeline_status = vec![self.last_exit_status];
+                    return Ok(());
+                } else if let ErrorKind::TooManyOpenFiles
+                | ErrorKind::Arithmetic(_)
+                | ErrorKind::NoGlobMatch(_)
+                | ErrorKind::NoClobber(_) = *e.kind()
+                {
+                    print_error(&mut *self.shell_stderr, &e);
+                    self.last_exit_status = ExitStatus::from_failure();
+                    self.pipeline_status = vec![self.last_exit_status];
+                    return Ok(());
+                } else if let ErrorKind::ParameterNullOrUnset { .. } = *e.kind() {
+                    // Like bash, only an interactive shell keeps going, a script exits.
+                    if self.is_interactive() {
+                        print_error(&mut *self.shell_stderr, &e);
+                        self.last_exit_status = ExitStatus::from_failure();
+                        self.pipeline_status = vec![self.last_exit_status];
+                        return Ok(());
+                    }
                 }
 
                 Err(e)
             }
         }?;
 
+        if command_group.background {
+            self.last_background_pid = process_group
+                .processes
+                .last()
+                .and_then(|process| process.id())
+                .map(u32::from);
+        }
+
+        if let Some(ref mut output) = self.output {
+            if !write_limited_output(&mut process_group, output, &mut *self.shell_stderr)? {
+                self.exit(Some(ExitStatus::from_failure()));
+            }
+        }
+
         let foreground = process_group.foreground;
         let job_id = self
             .job_manager
             .create_job(&command_group.input, process_group);
-        if !self.is_interactive() {
+        if !foreground {
+            // Background jobs run until `wait`, even without job control.
+            self.job_manager
+                .put_job_in_background(Some(job_id), false /* cont */)?;
+        } else if !self.is_interactive() {
             self.last_exit_status = self.job_manager.wait_for_job(job_id)?.unwrap();
-        } else if foreground {
+            self.pipeline_status = self.job_manager.status_codes(job_id);
+            if let Some(status) = pipefail_status(self, &self.pipeline_status) {
+                self.last_exit_status = status;
+            }
+            // Without job notifications the job is never reported, so it is removed now, e.g.
+            // so that `exit` does not see it.
+            self.job_manager.remove_job(job_id);
+        } else {
             self.last_exit_status = self
                 .job_manager
                 .put_job_in_foreground(Some(job_id), false /* cont */)?
                 .unwrap();
-        } else {
-            self.job_manager
-                .put_job_in_background(Some(job_id), false /* cont */)?;
+            self.pipeline_status = self.job_manager.status_codes(job_id);
+            if let Some(status) = pipefail_status(self, &self.pipeline_status) {
+                self.last_exit_status = status;
+            }
         }
         Ok(())
     }
@@ -154,11 +373,23 @@ impl Shell for JobControlShell {
             self.editor.add_history_entry(input);
         }
 
-        let command = match Command::parse(input) {
+        if let Some(ref mut command_log) = self.command_log {
+            command_log.log(&command)?;
+        }
+
+        let expanded = if self.get_option(ShellOption::ExpandAliases) {
+            alias_expansion::expand_aliases(input, &self.aliases)
+        } else {
+            input.to_string()
+        };
+        let command = match Command::parse(&expanded) {
             Ok(command) => Ok(command),
             Err(e) => {
                 if let ErrorKind::Syntax(ref line) = *e.kind() {
-                    eprintln!("bsh: syntax error near: {}", line);
+                    print_error(
+                        &mut *self.shell_stderr,
+                        format_args!("syntax error near: {}", line),
+                    );
                     self.last_exit_status = ExitStatus::from_status(SYNTAX_ERROR_EXIT_STATUS);
                     return Ok(());
                 }
@@ -167,35 +398,57 @@ impl Shell for JobControlShell {
             }
         }?;
 
-        let inner_command =
-            variable_expansion::expand_variables(&command.inner, dirs::home_dir(), env::vars());
-        let mut command_group = ir::Interpreter::parse(Command::new(&command.input, inner_command));
+        if self.get_option(ShellOption::Posix) {
+            if let Err(e) = posix::check_posix_compat(&command.inner) {
+                print_error(&mut *self.shell_stderr, &e);
+                self.last_exit_status = ExitStatus::from_failure();
+                return Ok(());
+            }
+        }
+
+        self.run_debug_trap(input);
+        let mut command_group = ir::Interpreter::parse(command);
+        let start = Instant::now();
         self.execute_command(&mut command_group)?;
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.record(input, start.elapsed(), self.line_number)?;
+        }
+        if !self.last_exit_status.success() {
+            handle_command_failure(self);
+        }
+        self.run_signal_traps();
 
         Ok(())
     }
 
     fn execute_commands_from_file(&mut self, path: &Path) -> Result<()> {
-        use std::io::Read;
-        let mut f = File::open(path).context(ErrorKind::Io)?;
-        let mut buffer = String::new();
-        f.read_to_string(&mut buffer)
-            .with_context(|_| ErrorKind::Io)?;
-
-        for line in buffer.split('\n') {
-            self.execute_command_string(line)?
-        }
-
-        Ok(())
+        let buffer = read_script(path, self.config.input_encoding)?;
+
+        // Restore the line number of the caller, e.g. a script running `source`.
+        let line_number = self.line_number;
+        // Here-document bodies are read with the line containing their operator.
+        let result =
+            parser::split_script(&buffer)
+                .into_iter()
+                .try_for_each(|(line_number, command)| {
+                    self.line_number = line_number;
+                    self.execute_command_string(&command)
+                        .map_err(|e| Error::with_location(e, path, line_number))
+                });
+        self.line_number = line_number;
+        result
     }
 
     fn execute_from_stdin(&mut self) {
         loop {
             if self.config.enable_job_control {
                 // Check the status of background jobs, removing exited ones.
-                self.job_manager.do_job_notification();
+                self.job_manager
+                    .do_job_notification(self.config.background_job_timeout);
             }
 
+            self.run_signal_traps();
+            self.run_prompt_command();
             let input = match self.prompt() {
                 Ok(Some(line)) => line.trim().to_owned(),
                 Ok(None) => break,
@@ -205,6 +458,7 @@ impl Shell for JobControlShell {
                 }
             };
 
+            self.line_number += 1;
             let temp_result = self.execute_command_string(&input);
             log_if_err!(temp_result, "execute_command_string");
         }
@@ -219,35 +473,402 @@ impl Shell for JobControlShell {
             Some(n) => n.code().unwrap(),
             None => self.last_exit_status.code().unwrap(),
         };
+        // The trap is removed first, so that `exit` in the trap exits immediately.
+        if let Some(handler) = self.traps.remove(EXIT_TRAP) {
+            self.last_exit_status = ExitStatus::from_status(code);
+            self.run_hook(&handler, "EXIT trap");
+        }
+        if self.config.login {
+            run_logout_file(self);
+        }
+        if let Some(ref mut profiler) = self.profiler {
+            log_if_err!(profiler.finish(), "failed to write profile");
+        }
         let code_like_u8 = if code < 0 {
             (256 + code) % 256
         } else {
             code % 256
         };
 
-        if self.config.enable_command_history {
-            if let Some(ref history_file) = self.history_file {
-                if let Err(e) = self.editor.save_history(&history_file) {
-                    error!(
-                        "error: failed to save history to file during shutdown: {}",
-                        e
-                    );
-                }
-            }
-        }
+        self.save_history();
 
         info!("bsh has shut down");
         process::exit(code_like_u8);
     }
 
+    fn prepare_for_exec(&mut self) {
+        if let Some(handler) = self.traps.remove(EXIT_TRAP) {
+            self.run_hook(&handler, "EXIT trap");
+        }
+        if let Some(ref mut profiler) = self.profiler {
+            log_if_err!(profiler.finish(), "failed to write profile");
+        }
+        self.save_history();
+        info!("bsh is replaced by exec");
+    }
+
     fn is_interactive(&self) -> bool {
         self.is_interactive
     }
 
+    fn is_login(&self) -> bool {
+        self.config.login
+    }
+
     fn is_job_control_enabled(&self) -> bool {
         self.is_interactive
     }
 
+    fn config(&self) -> &ShellConfig {
+        &self.config
+    }
+
+    fn config_mut(&mut self) -> &mut ShellConfig {
+        &mut self.config
+    }
+
+    fn shell_stderr(&mut self) -> &mut dyn Write {
+        &mut *self.shell_stderr
+    }
+
+    fn set_shell_stderr(&mut self, stderr: Box<dyn Write>) {
+        self.shell_stderr = stderr;
+    }
+
+    fn get_option(&self, option: ShellOption) -> bool {
+        self.config.get_option(option)
+    }
+
+    fn set_option(&mut self, option: ShellOption, enabled: bool) {
+        self.config.set_option(option, enabled);
+        if option == ShellOption::Vi {
+            self.editor.set_vi_mode(enabled);
+        }
+    }
+
+    fn last_exit_status(&self) -> ExitStatus {
+        self.last_exit_status
+    }
+
+    fn set_last_exit_status(&mut self, status: ExitStatus) {
+        self.last_exit_status = status;
+    }
+
+    fn pipeline_status_vec(&self) -> &[ExitStatus] {
+        &self.pipeline_status
+    }
+
+    fn set_pipeline_status(&mut self, statuses: Vec<ExitStatus>) {
+        self.pipeline_status = statuses;
+    }
+
+    fn last_argument(&self) -> Option<&str> {
+        self.last_arg.as_deref()
+    }
+
+    fn set_last_argument(&mut self, arg: &str) {
+        self.last_arg = Some(arg.to_string());
+    }
+
+    fn loop_depth(&self) -> usize {
+        self.loop_depth
+    }
+
+    fn set_loop_depth(&mut self, depth: usize) {
+        self.loop_depth = depth;
+    }
+
+    fn function_depth(&self) -> usize {
+        self.function_depth
+    }
+
+    fn set_function_depth(&mut self, depth: usize) {
+        self.function_depth = depth;
+    }
+
+    fn condition_depth(&self) -> usize {
+        self.condition_depth
+    }
+
+    fn set_condition_depth(&mut self, depth: usize) {
+        self.condition_depth = depth;
+    }
+
+    fn errexit_ignored(&self) -> bool {
+        self.errexit_ignored
+    }
+
+    fn set_errexit_ignored(&mut self, ignored: bool) {
+        self.errexit_ignored = ignored;
+    }
+
+    fn last_background_pid(&self) -> Option<u32> {
+        self.last_background_pid
+    }
+
+    fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    fn positional_params(&self) -> &[String] {
+        &self.positional_params
+    }
+
+    fn set_positional_params(&mut self, params: Vec<String>) {
+        self.positional_params = params;
+    }
+
+    fn get_function(&self, name: &str) -> Option<&ir::Command> {
+        self.functions.get(name)
+    }
+
+    fn define_function(&mut self, name: &str, body: ir::Command) -> Result<()> {
+        self.config.check_command_name(name)?;
+        self.functions.insert(name.to_string(), body);
+        Ok(())
+    }
+
+    fn function_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn aliases(&self) -> &HashMap<String, String> {
+        &self.aliases
+    }
+
+    fn aliases_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.aliases
+    }
+
+    fn dir_stack(&self) -> &[PathBuf] {
+        &self.dir_stack
+    }
+
+    fn dir_stack_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.dir_stack
+    }
+
+    fn command_cache(&self) -> &HashMap<String, PathBuf> {
+        &self.command_cache
+    }
+
+    fn command_cache_mut(&mut self) -> &mut HashMap<String, PathBuf> {
+        &mut self.command_cache
+    }
+
+    fn clone_for_subshell(&self) -> Result<Box<dyn Shell>> {
+        Ok(Box::new(SimpleShell {
+            last_exit_status: self.last_exit_status,
+            pipeline_status: self.pipeline_status.clone(),
+            last_arg: self.last_arg.clone(),
+            last_background_pid: self.last_background_pid,
+            positional_params: self.positional_params.clone(),
+            functions: self.functions.clone(),
+            aliases: self.aliases.clone(),
+            dir_stack: self.dir_stack.clone(),
+            command_cache: self.command_cache.clone(),
+            line_number: self.line_number,
+            loop_depth: self.loop_depth,
+            function_depth: self.function_depth,
+            condition_depth: self.condition_depth,
+            local_vars: self.local_vars.clone(),
+            readonly_vars: self.readonly_vars.clone(),
+            exported_vars: self.exported_vars.clone(),
+            integer_vars: self.integer_vars.clone(),
+            array_vars: self.array_vars.clone(),
+            assoc_vars: self.assoc_vars.clone(),
+            enabled_builtins: self.enabled_builtins.clone(),
+            ..SimpleShell::new_subshell(self.config.clone())
+        }))
+    }
+
+    fn get_variable(&self, name: &str) -> Option<String> {
+        self.local_vars
+            .get(name)
+            .cloned()
+            .or_else(|| env::var(name).ok())
+    }
+
+    fn set_variable(&mut self, name: &str, value: &str) {
+        let value = if self.integer_vars.contains(name) {
+            match self.eval_arithmetic(value) {
+                Ok(n) => n.to_string(),
+                Err(e) => {
+                    print_error(&mut *self.shell_stderr, &e);
+                    return;
+                }
+            }
+        } else {
+            value.to_string()
+        };
+        if name == "PATH" {
+            self.command_cache.clear();
+        }
+        match self.local_vars.get_mut(name) {
+            Some(local_value) => *local_value = value,
+            None => env::set_var(name, value),
+        }
+    }
+
+    fn unset_variable(&mut self, name: &str) {
+        if name == "PATH" {
+            self.command_cache.clear();
+        }
+        if self.local_vars.remove(name).is_none() {
+            env::remove_var(name);
+            self.exported_vars.remove(name);
+            self.integer_vars.remove(name);
+        }
+        self.array_vars.remove(name);
+        self.assoc_vars.remove(name);
+    }
+
+    fn declare_readonly(&mut self, name: &str) {
+        self.readonly_vars.insert(name.to_string());
+    }
+
+    fn readonly_variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.readonly_vars.iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn set_exported(&mut self, name: &str, exported: bool) {
+        if exported {
+            self.exported_vars.insert(name.to_string());
+        } else {
+            self.exported_vars.remove(name);
+        }
+    }
+
+    fn is_exported(&self, name: &str) -> bool {
+        self.exported_vars.contains(name)
+    }
+
+    fn exported_variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.exported_vars.iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn set_integer(&mut self, name: &str, integer: bool) {
+        if integer {
+            self.integer_vars.insert(name.to_string());
+        } else {
+            self.integer_vars.remove(name);
+        }
+    }
+
+    fn is_integer(&self, name: &str) -> bool {
+        self.integer_vars.contains(name)
+    }
+
+    fn check_variable_assignment(&self, name: &str) -> Result<()> {
+        self.config.check_variable_assignment(name)?;
+        if self.readonly_vars.contains(name) {
+            return Err(Error::readonly_variable(name));
+        }
+
+        Ok(())
+    }
+
+    fn is_builtin_enabled(&self, name: &str) -> bool {
+        self.enabled_builtins.contains(name)
+    }
+
+    fn set_builtin_enabled(&mut self, name: &str, enabled: bool) {
+        if enabled {
+            self.enabled_builtins.insert(name.to_string());
+        } else {
+            self.enabled_builtins.remove(name);
+        }
+    }
+
+    fn traps(&self) -> &HashMap<String, String> {
+        &self.traps
+    }
+
+    fn set_trap(&mut self, condition: &str, handler: Option<String>) {
+        match handler {
+            Some(handler) => self.traps.insert(condition.to_string(), handler),
+            None => self.traps.remove(condition),
+        };
+    }
+
+    fn run_trap(&mut self, condition: &str) {
+        if self.in_trap {
+            return;
+        }
+        if let Some(handler) = self.traps.get(condition).cloned() {
+            self.in_trap = true;
+            self.run_hook(&handler, &format!("{} trap", condition));
+            self.in_trap = false;
+        }
+    }
+
+    fn run_signal_traps(&mut self) {
+        if self.in_trap {
+            return;
+        }
+        for signal in signals::take_pending() {
+            self.run_trap(signal.as_str());
+        }
+    }
+
+    fn eval_arithmetic(&mut self, expr: &str) -> Result<i64> {
+        arithmetic::evaluate(expr, self)
+    }
+
+    fn get_array(&self, name: &str) -> Option<&BTreeMap<usize, String>> {
+        self.array_vars.get(name)
+    }
+
+    fn declare_array(&mut self, name: &str) {
+        self.array_vars.entry(name.to_string()).or_default();
+    }
+
+    fn set_array(&mut self, name: &str, elements: BTreeMap<usize, String>) {
+        self.array_vars.insert(name.to_string(), elements);
+    }
+
+    fn set_array_element(&mut self, name: &str, index: usize, value: &str) {
+        self.array_vars
+            .entry(name.to_string())
+            .or_default()
+            .insert(index, value.to_string());
+    }
+
+    fn get_assoc_array(&self, name: &str) -> Option<&HashMap<String, String>> {
+        self.assoc_vars.get(name)
+    }
+
+    fn declare_assoc_array(&mut self, name: &str) {
+        self.assoc_vars.entry(name.to_string()).or_default();
+    }
+
+    fn set_assoc_array(&mut self, name: &str, elements: HashMap<String, String>) {
+        self.assoc_vars.insert(name.to_string(), elements);
+    }
+
+    fn set_assoc_element(&mut self, name: &str, key: &str, value: &str) {
+        self.assoc_vars
+            .entry(name.to_string())
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    fn unset_array_element(&mut self, name: &str, subscript: &str) {
+        if let Some(assoc_array) = self.assoc_vars.get_mut(name) {
+            assoc_array.remove(subscript);
+        } else if let (Some(array), Ok(index)) =
+            (self.array_vars.get_mut(name), subscript.parse::<usize>())
+        {
+            array.remove(&index);
+        }
+    }
+
     fn editor(&self) -> &Editor {
         &self.editor
     }
@@ -264,6 +885,26 @@ impl Shell for JobControlShell {
         self.job_manager.has_jobs()
     }
 
+    fn job_by_spec(&self, spec: &str) -> Result<JobId> {
+        self.job_manager.job_by_spec(spec)
+    }
+
+    fn set_job_notified(&mut self, job_id: JobId) {
+        self.job_manager.set_job_notified(job_id);
+    }
+
+    fn wait_for_job(&mut self, job_id: JobId) -> Result<Option<ExitStatus>> {
+        self.job_manager.reap_job(job_id)
+    }
+
+    fn wait_for_next_job(&mut self) -> Result<Option<ExitStatus>> {
+        self.job_manager.wait_for_next_job()
+    }
+
+    fn wait_for_process(&mut self, pid: u32) -> Result<Option<ExitStatus>> {
+        self.job_manager.wait_for_process(pid)
+    }
+
     fn put_job_in_foreground(&mut self, job_id: Option<JobId>) -> Result<Option<ExitStatus>> {
         self.job_manager
             .put_job_in_foreground(job_id, true /* cont */)
@@ -274,8 +915,8 @@ impl Shell for JobControlShell {
             .put_job_in_background(job_id, true /* cont */)
     }
 
-    fn kill_background_job(&mut self, job_id: u32) -> Result<Option<&dyn Job>> {
-        self.job_manager.kill_job(JobId(job_id))
+    fn kill_background_job(&mut self, job_id: JobId, signal: Signal) -> Result<Option<&dyn Job>> {
+        self.job_manager.kill_job(job_id, signal)
     }
 }
 
@@ -340,7 +981,7 @@ impl<T: Job> AsJob for T {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum JobStatus {
     Running,
     Stopped,
@@ -357,6 +998,7 @@ pub struct JobManager {
     jobs: Vec<JobImpl>,
     job_count: u32,
     current_job: Option<JobId>,
+    previous_job: Option<JobId>,
 }
 
 impl JobManager {
@@ -375,10 +1017,36 @@ impl JobManager {
         !self.jobs.is_empty()
     }
 
+    /// Removes the job from the active job list.
+    pub fn remove_job(&mut self, job_id: JobId) {
+        self.jobs.retain(|job| job.id() != job_id);
+    }
+
     pub fn get_jobs(&self) -> Vec<&dyn Job> {
         self.jobs.iter().map(|j| j.as_job()).collect()
     }
 
+    /// Finds the job identified by `spec`, e.g. `%1`, `%%`, `%-`, `%name`, `%?name`, or `1`.
+    pub fn job_by_spec(&self, spec: &str) -> Result<JobId> {
+        let job_id = match spec.strip_prefix('%') {
+            Some("") | Some("%") | Some("+") => self.current_job(),
+            Some("-") => self.previous_job(),
+            Some(query) => match query.parse() {
+                Ok(n) => self.existing_job(JobId(n)),
+                Err(_) => self.find_job_by_command(spec, query)?,
+            },
+            None => spec.parse().ok().and_then(|n| self.existing_job(JobId(n))),
+        };
+
+        job_id.ok_or_else(|| Error::no_such_job(spec))
+    }
+
+    pub fn set_job_notified(&mut self, job_id: JobId) {
+        if let Some(job_index) = self.find_job(job_id) {
+            self.jobs[job_index].set_notified();
+        }
+    }
+
     /// Waits for job to stop or complete.
     ///
     /// This function also updates the statuses of other jobs if we receive
@@ -394,13 +1062,69 @@ impl JobManager {
         Ok(self.jobs[job_index].last_status_code())
     }
 
+    /// Waits for the job to stop or complete, removing it from the active job list if it
+    /// completed, and returns its exit status.
+    pub fn reap_job(&mut self, job_id: JobId) -> Result<Option<ExitStatus>> {
+        let exit_status = self.wait_for_job(job_id)?;
+        if let Some(job_index) = self.find_job(job_id) {
+            if self.jobs[job_index].is_completed() {
+                self.jobs.remove(job_index);
+            }
+        }
+        Ok(exit_status)
+    }
+
+    /// Waits for any running job to complete, removes it from the active job list, and returns
+    /// its exit status.
+    ///
+    /// Returns `None` if there are no running jobs, e.g. all jobs are stopped.
+    pub fn wait_for_next_job(&mut self) -> Result<Option<ExitStatus>> {
+        loop {
+            self.update_job_statues()?;
+            if let Some(job_index) = self.jobs.iter().position(|job| job.is_completed()) {
+                let job = self.jobs.remove(job_index);
+                return Ok(job.last_status_code());
+            }
+            if self.jobs.iter().all(|job| job.is_stopped()) {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Waits for the job running the process `pid` to stop or complete, and returns the exit
+    /// status of the process, or `None` if no job is running it.
+    pub fn wait_for_process(&mut self, pid: u32) -> Result<Option<ExitStatus>> {
+        let is_process = |process: &Box<dyn Process>| process.id().map(u32::from) == Some(pid);
+        let job_id = match self
+            .jobs
+            .iter()
+            .find(|job| job.processes().iter().any(is_process))
+        {
+            Some(job) => job.id(),
+            None => return Ok(None),
+        };
+
+        self.wait_for_job(job_id)?;
+        let job_index = self.find_job(job_id).expect("job not found");
+        let exit_status = self.jobs[job_index]
+            .processes()
+            .iter()
+            .find(|process| is_process(process))
+            .and_then(|process| process.status_code())
+            .unwrap_or_else(ExitStatus::from_failure);
+        if self.jobs[job_index].is_completed() {
+            self.jobs.remove(job_index);
+        }
+        Ok(Some(exit_status))
+    }
+
     pub fn put_job_in_foreground(
         &mut self,
         job_id: Option<JobId>,
         cont: bool,
     ) -> Result<Option<ExitStatus>> {
         let job_id = job_id
-            .or(self.current_job)
+            .or_else(|| self.current_job())
             .ok_or_else(|| Error::no_such_job("current"))?;
         debug!("putting job [{}] in foreground", job_id);
 
@@ -409,7 +1133,7 @@ impl JobManager {
                 .find_job(job_id)
                 .ok_or_else(|| Error::no_such_job(format!("{}", job_id)))?;
             self.jobs[job_index].set_last_running_in_foreground(true);
-            let job_pgid = self.jobs[job_index].pgid();
+            let job_pgid = self.jobs[job_index].pgid;
             let job_tmodes = self.jobs[job_index].tmodes().clone();
             let _terminal_state = job_pgid.map(|pgid| TerminalState::new(Pid::from_raw(pgid)));
 
@@ -433,12 +1157,21 @@ impl JobManager {
             }
             _terminal_state
         };
-        self.wait_for_job(job_id)
+        let exit_status = self.wait_for_job(job_id)?;
+
+        // A job stopped in the foreground becomes the current job
+        if let Some(job_index) = self.find_job(job_id) {
+            if self.jobs[job_index].is_stopped() {
+                self.set_current_job(job_id);
+            }
+        }
+
+        Ok(exit_status)
     }
 
     pub fn put_job_in_background(&mut self, job_id: Option<JobId>, cont: bool) -> Result<()> {
         let job_id = job_id
-            .or(self.current_job)
+            .or_else(|| self.current_job())
             .ok_or_else(|| Error::no_such_job("current"))?;
         debug!("putting job [{}] in background", job_id);
 
@@ -447,7 +1180,7 @@ impl JobManager {
                 .find_job(job_id)
                 .ok_or_else(|| Error::no_such_job(format!("{}", job_id)))?;
             self.jobs[job_index].set_last_running_in_foreground(false);
-            self.jobs[job_index].pgid()
+            self.jobs[job_index].pgid
         };
 
         if cont {
@@ -456,13 +1189,13 @@ impl JobManager {
             }
         }
 
-        self.current_job = Some(job_id);
+        self.set_current_job(job_id);
         Ok(())
     }
 
-    pub fn kill_job(&mut self, job_id: JobId) -> Result<Option<&dyn Job>> {
+    pub fn kill_job(&mut self, job_id: JobId, signal: Signal) -> Result<Option<&dyn Job>> {
         if let Some(job_index) = self.find_job(job_id) {
-            self.jobs[job_index].kill()?;
+            self.jobs[job_index].kill(signal)?;
             Ok(Some(&self.jobs[job_index]))
         } else {
             Ok(None)
@@ -481,18 +1214,24 @@ impl JobManager {
 
     /// Notify the user about stopped or terminated jobs and remove terminated
     /// jobs from the active job list.
-    pub fn do_job_notification(&mut self) {
+    ///
+    /// Background jobs running for longer than `background_job_timeout` are killed.
+    pub fn do_job_notification(&mut self, background_job_timeout: Option<Duration>) {
         let temp_result = self.update_job_statues();
         log_if_err!(temp_result, "do_job_notification");
 
+        if let Some(timeout) = background_job_timeout {
+            self.kill_timed_out_jobs(timeout);
+        }
+
         for job in &mut self.jobs.iter_mut() {
             if job.is_completed() && !job.last_running_in_foreground() {
                 // Unnecessary to notify if the job was last running in the
                 // foreground, because the user will have noticed it completed.
                 println!("{}", *job);
-            } else if job.is_stopped() && !job.notified_stopped_job() {
+            } else if job.is_stopped() && job.is_status_changed() {
                 println!("{}", *job);
-                job.set_notified_stopped_job(true);
+                job.set_notified();
             }
         }
 
@@ -500,6 +1239,35 @@ impl JobManager {
         self.jobs.retain(|j| !j.is_completed());
     }
 
+    /// Kills the running background jobs started more than `timeout` ago and removes them from
+    /// the active job list.
+    fn kill_timed_out_jobs(&mut self, timeout: Duration) {
+        let timed_out_jobs: Vec<JobId> = self
+            .jobs
+            .iter()
+            .filter(|job| !job.last_running_in_foreground())
+            .filter(|job| !job.is_stopped() && !job.is_completed())
+            .filter(|job| job.start_time.elapsed() > timeout)
+            .map(|job| job.id())
+            .collect();
+
+        for job_id in timed_out_jobs {
+            match self.kill_job(job_id, Signal::SIGKILL) {
+                Ok(Some(job)) => println!("[{}] Killed (timeout): {}", job.id(), job.input()),
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("failed to kill job [{}]: {}", job_id, e);
+                    continue;
+                }
+            }
+
+            // Reap the job so that it is not reported again as completed.
+            let temp_result = self.wait_for_job(job_id);
+            log_if_err!(temp_result, "kill_timed_out_jobs");
+            self.jobs.retain(|job| job.id() != job_id);
+        }
+    }
+
     fn get_next_job_id(&mut self) -> JobId {
         self.job_count += 1;
         JobId(self.job_count)
@@ -515,6 +1283,61 @@ impl JobManager {
     fn find_job(&self, job_id: JobId) -> Option<usize> {
         self.jobs.iter().position(|job| job.id() == job_id)
     }
+
+    /// Returns the exit statuses of the completed processes of `job_id`, in order.
+    fn status_codes(&self, job_id: JobId) -> Vec<ExitStatus> {
+        self.find_job(job_id).map_or_else(Vec::new, |job_index| {
+            self.jobs[job_index]
+                .processes()
+                .iter()
+                .filter_map(|process| process.status_code())
+                .collect()
+        })
+    }
+
+    fn existing_job(&self, job_id: JobId) -> Option<JobId> {
+        self.find_job(job_id).map(|_| job_id)
+    }
+
+    /// Returns the job that was most recently stopped or put in the background, falling back to
+    /// the newest job if that one has been removed.
+    fn current_job(&self) -> Option<JobId> {
+        self.current_job
+            .and_then(|job_id| self.existing_job(job_id))
+            .or_else(|| self.jobs.last().map(|job| job.id()))
+    }
+
+    fn previous_job(&self) -> Option<JobId> {
+        self.previous_job
+            .and_then(|job_id| self.existing_job(job_id))
+    }
+
+    fn set_current_job(&mut self, job_id: JobId) {
+        if self.current_job != Some(job_id) {
+            self.previous_job = self.current_job;
+            self.current_job = Some(job_id);
+        }
+    }
+
+    /// Finds the job whose command starts with `query`, or contains it if `query` starts with
+    /// `?`.
+    fn find_job_by_command(&self, spec: &str, query: &str) -> Result<Option<JobId>> {
+        let matches: Vec<JobId> = self
+            .jobs
+            .iter()
+            .filter(|job| match query.strip_prefix('?') {
+                Some(substring) => job.input.contains(substring),
+                None => job.input.starts_with(query),
+            })
+            .map(|job| job.id())
+            .collect();
+
+        match matches.as_slice() {
+            [] => Ok(None),
+            [job_id] => Ok(Some(*job_id)),
+            _ => Err(Error::ambiguous_job_spec(spec)),
+        }
+    }
 }
 
 impl fmt::Debug for JobManager {
@@ -545,8 +1368,11 @@ pub struct JobImpl {
     processes: Vec<Box<dyn Process>>,
     last_status_code: Option<ExitStatus>,
     last_running_in_foreground: bool,
-    notified_stopped_job: bool,
+    /// The status the user was last notified of, see [`Job::is_status_changed`].
+    notified_status: Option<JobStatus>,
     tmodes: Option<Termios>,
+    /// When the job was started, see [`ShellConfig::background_job_timeout`].
+    start_time: Instant,
 }
 
 impl JobImpl {
@@ -568,15 +1394,12 @@ impl JobImpl {
             processes,
             last_status_code,
             last_running_in_foreground: true,
-            notified_stopped_job: false,
+            notified_status: None,
             tmodes: termios::tcgetattr(util::unix::get_terminal()).ok(),
+            start_time: Instant::now(),
         }
     }
 
-    fn pgid(&self) -> Option<libc::pid_t> {
-        self.pgid
-    }
-
     fn last_status_code(&self) -> Option<ExitStatus> {
         self.last_status_code
     }
@@ -589,9 +1412,17 @@ impl JobImpl {
         self.last_running_in_foreground = last_running_in_foreground;
     }
 
-    fn kill(&mut self) -> Result<()> {
-        for process in &mut self.processes {
-            process.kill()?;
+    /// Sends `signal` to each process in the job that has not completed.
+    fn kill(&mut self, signal: Signal) -> Result<()> {
+        for process in &self.processes {
+            if process.status() == ProcessStatus::Completed {
+                continue;
+            }
+
+            if let Some(pid) = process.id() {
+                signal::kill(Pid::from_raw(u32::from(pid) as libc::pid_t), signal)
+                    .context(ErrorKind::Nix)?;
+            }
         }
 
         Ok(())
@@ -609,24 +1440,8 @@ impl JobImpl {
         Ok(self.last_status_code)
     }
 
-    fn notified_stopped_job(&self) -> bool {
-        self.notified_stopped_job
-    }
-
-    fn set_notified_stopped_job(&mut self, notified_stopped_job: bool) {
-        self.notified_stopped_job = notified_stopped_job;
-    }
-
-    fn is_stopped(&self) -> bool {
-        self.processes
-            .iter()
-            .all(|p| p.status() == ProcessStatus::Stopped)
-    }
-
-    fn is_completed(&self) -> bool {
-        self.processes
-            .iter()
-            .all(|p| p.status() == ProcessStatus::Completed)
+    fn set_notified(&mut self) {
+        self.notified_status = Some(self.status());
     }
 }
 
@@ -643,9 +1458,52 @@ impl Job for JobImpl {
         format!("[{}] {}\t{}", self.id, self.status(), self.input)
     }
 
+    fn display_long(&self) -> String {
+        let prefix = format!("[{}]", self.id);
+        let indent = " ".repeat(prefix.len());
+        self.processes
+            .iter()
+            .enumerate()
+            .map(|(i, process)| {
+                let pid = process
+                    .id()
+                    .map_or_else(String::new, |pid| u32::from(pid).to_string());
+                let prefix = if i == 0 { &prefix } else { &indent };
+                format!(
+                    "{} {}\t{}\t{}",
+                    prefix,
+                    pid,
+                    process.status(),
+                    process.argv()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     fn processes(&self) -> &Vec<Box<dyn Process>> {
         &self.processes
     }
+
+    fn pgid(&self) -> Option<u32> {
+        self.pgid.map(|pgid| pgid as u32)
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.processes
+            .iter()
+            .all(|p| p.status() == ProcessStatus::Stopped)
+    }
+
+    fn is_completed(&self) -> bool {
+        self.processes
+            .iter()
+            .all(|p| p.status() == ProcessStatus::Completed)
+    }
+
+    fn is_status_changed(&self) -> bool {
+        self.notified_status != Some(self.status())
+    }
 }
 
 impl JobExt for JobImpl {
@@ -709,3 +1567,106 @@ impl Drop for TerminalState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_job(job_manager: &mut JobManager, input: &str) -> JobId {
+        let process_group = ProcessGroup {
+            id: None,
+            processes: vec![],
+            foreground: false,
+        };
+        job_manager.create_job(input, process_group)
+    }
+
+    #[test]
+    fn test_clone_for_subshell() {
+        let mut shell = JobControlShell::new(ShellConfig::noninteractive()).unwrap();
+        create_job(&mut shell.job_manager, "sleep 10");
+        shell
+            .local_vars
+            .insert("FOO".to_string(), "foo".to_string());
+
+        let mut subshell = shell.clone_for_subshell().unwrap();
+        assert!(!subshell.is_interactive());
+        assert!(!subshell.is_job_control_enabled());
+        assert_eq!(subshell.get_variable("FOO"), Some("foo".to_string()));
+
+        subshell.unset_variable("FOO");
+        assert_eq!(shell.get_variable("FOO"), Some("foo".to_string()));
+        assert!(shell.job_manager.job_by_spec("%1").is_ok());
+    }
+
+    #[test]
+    fn test_job_by_spec() {
+        let mut job_manager = JobManager::default();
+        assert!(job_manager.job_by_spec("%%").is_err());
+
+        let sleep = create_job(&mut job_manager, "sleep 10");
+        let vim = create_job(&mut job_manager, "vim foo.txt");
+        let vi = create_job(&mut job_manager, "vi bar.txt");
+
+        assert_eq!(job_manager.job_by_spec("%1").unwrap(), sleep);
+        assert_eq!(job_manager.job_by_spec("2").unwrap(), vim);
+        assert_eq!(job_manager.job_by_spec("%sl").unwrap(), sleep);
+        assert_eq!(job_manager.job_by_spec("%vim").unwrap(), vim);
+        assert_eq!(job_manager.job_by_spec("%?bar").unwrap(), vi);
+        assert!(job_manager.job_by_spec("%4").is_err());
+        assert!(job_manager.job_by_spec("%nope").is_err());
+        assert!(job_manager.job_by_spec("sleep").is_err());
+        match job_manager.job_by_spec("%vi") {
+            Err(e) => assert_eq!(*e.kind(), ErrorKind::AmbiguousJobSpec("%vi".into())),
+            Ok(job_id) => panic!("%vi resolved to {}", job_id),
+        }
+    }
+
+    #[test]
+    fn test_current_and_previous_job() {
+        let mut job_manager = JobManager::default();
+        let first = create_job(&mut job_manager, "sleep 10");
+        let second = create_job(&mut job_manager, "sleep 20");
+
+        // Without a job put in the background, the newest job is current
+        assert_eq!(job_manager.job_by_spec("%%").unwrap(), second);
+        assert!(job_manager.job_by_spec("%-").is_err());
+
+        job_manager
+            .put_job_in_background(Some(first), false)
+            .unwrap();
+        job_manager
+            .put_job_in_background(Some(second), false)
+            .unwrap();
+        assert_eq!(job_manager.job_by_spec("%").unwrap(), second);
+        assert_eq!(job_manager.job_by_spec("%+").unwrap(), second);
+        assert_eq!(job_manager.job_by_spec("%-").unwrap(), first);
+    }
+
+    #[test]
+    fn test_background_job_timeout() {
+        let mut shell = JobControlShell::new(ShellConfig::noninteractive()).unwrap();
+        let mut spawn_job = |input: &str| {
+            let command_group = ir::Interpreter::parse(Command::parse(input).unwrap());
+            let process_group = spawn_processes(&mut shell, &command_group).unwrap();
+            shell.job_manager.create_job(input, process_group)
+        };
+        let background = spawn_job("sleep 10 &");
+        let foreground = spawn_job("sleep 20");
+        let job_manager = &mut shell.job_manager;
+        job_manager
+            .put_job_in_background(Some(background), false)
+            .unwrap();
+
+        job_manager.do_job_notification(Some(Duration::from_secs(60)));
+        assert!(job_manager.job_by_spec("%1").is_ok());
+
+        // Jobs last running in the foreground are not killed
+        job_manager.do_job_notification(Some(Duration::from_millis(1)));
+        assert!(job_manager.job_by_spec("%1").is_err());
+        assert!(job_manager.job_by_spec("%2").is_ok());
+
+        job_manager.kill_job(foreground, Signal::SIGKILL).unwrap();
+        job_manager.wait_for_job(foreground).unwrap();
+    }
+}
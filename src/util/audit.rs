@@ -0,0 +1,98 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use failure::ResultExt;
+use nix::unistd::{getuid, User};
+
+use crate::errors::{ErrorKind, Result};
+
+/// Appends each command run by the shell to a log file, for an audit trail.
+///
+/// Each line has the form `timestamp pid user command`, where `timestamp` is the number of
+/// seconds since the Unix epoch. The command is last so that it may contain spaces; backslashes
+/// and newlines in it are escaped so that each command is on a single line.
+#[derive(Debug)]
+pub struct CommandAuditLogger {
+    file: File,
+    user: String,
+}
+
+impl CommandAuditLogger {
+    /// Opens the log at `path` for appending, creating it if necessary.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(ErrorKind::Io)?;
+        Ok(Self {
+            file,
+            user: current_user_name(),
+        })
+    }
+
+    /// Appends `command` to the log.
+    pub fn log(&mut self, command: &str) -> Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let command = command.replace('\\', "\\\\").replace('\n', "\\n");
+        // Write the line at once so that lines from concurrent shells are not interleaved.
+        let line = format!(
+            "{} {} {} {}\n",
+            timestamp,
+            process::id(),
+            self.user,
+            command
+        );
+        self.file
+            .write_all(line.as_bytes())
+            .context(ErrorKind::Io)?;
+        Ok(())
+    }
+}
+
+/// Returns the name of the user running the shell, or their uid if it has no name.
+///
+/// `$USER` is not used, as any command may change it before the log is opened.
+fn current_user_name() -> String {
+    let uid = getuid();
+    match User::from_uid(uid) {
+        Ok(Some(user)) => user.name,
+        _ => uid.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn test_command_audit_logger() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("commands.log");
+        fs::write(&path, "existing\n").unwrap();
+
+        let mut logger = CommandAuditLogger::open(&path).unwrap();
+        logger.log("echo foo bar").unwrap();
+        logger.log("echo a\\b\nc").unwrap();
+
+        let log = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "existing");
+
+        let fields: Vec<&str> = lines[1].splitn(4, ' ').collect();
+        assert!(fields[0].parse::<u64>().is_ok());
+        assert_eq!(fields[1], process::id().to_string());
+        assert_eq!(fields[2], current_user_name());
+        assert_eq!(fields[3], "echo foo bar");
+        assert!(lines[2].ends_with(" echo a\\\\b\\nc"));
+    }
+}
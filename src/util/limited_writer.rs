@@ -0,0 +1,76 @@
+use std::io::{self, Write};
+
+/// A writer that fails once more than `limit` bytes have been written to it in total.
+#[derive(Debug)]
+pub struct LimitedWriter<W> {
+    inner: W,
+    limit: u64,
+    written: u64,
+}
+
+impl<W: Write> LimitedWriter<W> {
+    /// Wraps `inner`, allowing at most `limit` bytes to be written.
+    pub fn new(inner: W, limit: u64) -> Self {
+        Self {
+            inner,
+            limit,
+            written: 0,
+        }
+    }
+
+    /// Returns `true` if a write failed because the limit was reached.
+    pub fn is_limit_exceeded(&self) -> bool {
+        self.written > self.limit
+    }
+}
+
+impl<W: Write> Write for LimitedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let remaining = self.limit.saturating_sub(self.written);
+        if remaining == 0 {
+            // Remember that output was truncated, see `is_limit_exceeded`.
+            self.written = self.limit + 1;
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "output size limit exceeded",
+            ));
+        }
+
+        let len = buf.len().min(remaining as usize);
+        let n = self.inner.write(&buf[..len])?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limited_writer() {
+        let mut writer = LimitedWriter::new(Vec::new(), 4);
+        writer.write_all(b"abc").unwrap();
+        assert!(!writer.is_limit_exceeded());
+        assert!(writer.write_all(b"de").is_err());
+        assert!(writer.is_limit_exceeded());
+        assert_eq!(writer.inner, b"abcd");
+    }
+
+    #[test]
+    fn test_limited_writer_exact_limit() {
+        let mut writer = LimitedWriter::new(Vec::new(), 3);
+        writer.write_all(b"abc").unwrap();
+        assert!(!writer.is_limit_exceeded());
+        writer.write_all(b"").unwrap();
+        assert!(!writer.is_limit_exceeded());
+    }
+}
@@ -0,0 +1,67 @@
+use std::io::{self, Write};
+
+use log::error;
+
+/// A writer that logs each line written to it as an error, e.g. to send the shell's diagnostics
+/// to the log file instead of stderr.
+#[derive(Debug, Default)]
+pub struct LogWriter {
+    /// The incomplete last line written so far.
+    buffer: Vec<u8>,
+}
+
+impl LogWriter {
+    /// Creates a writer with nothing buffered.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Logs `line`, without its trailing newline.
+    fn log_line(line: &[u8]) {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        error!("{}", String::from_utf8_lossy(line));
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            Self::log_line(&line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            Self::log_line(&self.buffer);
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_writer_buffers_incomplete_lines() {
+        let mut writer = LogWriter::new();
+        write!(writer, "bsh: foo").unwrap();
+        assert_eq!(writer.buffer, b"bsh: foo");
+        writeln!(writer, ": command not found\nbsh: bar").unwrap();
+        assert!(writer.buffer.is_empty());
+
+        write!(writer, "partial").unwrap();
+        writer.flush().unwrap();
+        assert!(writer.buffer.is_empty());
+    }
+}
@@ -1,8 +1,34 @@
 use std::process::ExitStatus;
 
+mod audit;
+mod limited_writer;
+mod log_writer;
+pub mod path;
+mod profiler;
+#[allow(unsafe_code)]
+pub mod signals;
 #[cfg(unix)]
 pub mod unix;
 
+pub use self::audit::CommandAuditLogger;
+pub use self::limited_writer::LimitedWriter;
+pub use self::log_writer::LogWriter;
+pub use self::profiler::Profiler;
+
+/// Quotes `s` so that the shell reads it back as a single word with the same value, e.g. to
+/// print a variable's value as a command.
+///
+/// Strings without special characters are left as is; other strings are single-quoted, with
+/// single quotes written as `'\''`.
+pub fn quote_for_shell(s: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !s.is_empty() && s.chars().all(is_safe) {
+        return s.to_string();
+    }
+
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
 pub trait VecExt<T> {
     /// Replace element at `index` with the result of the closure.
     fn update<F>(&mut self, index: usize, f: F)
@@ -98,6 +124,29 @@ impl BshExitStatusExt for ExitStatus {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_quote_for_shell() {
+        assert_eq!(quote_for_shell("hello"), "hello");
+        assert_eq!(quote_for_shell("/usr/bin/env"), "/usr/bin/env");
+        assert_eq!(
+            quote_for_shell("a=b,c:d@e%f+g-h_i.j"),
+            "a=b,c:d@e%f+g-h_i.j"
+        );
+        assert_eq!(quote_for_shell(""), "''");
+        assert_eq!(quote_for_shell("hello world"), "'hello world'");
+        assert_eq!(quote_for_shell("$HOME"), "'$HOME'");
+        assert_eq!(quote_for_shell("a;b|c&d"), "'a;b|c&d'");
+        assert_eq!(quote_for_shell("*.rs"), "'*.rs'");
+        assert_eq!(quote_for_shell("~"), "'~'");
+        assert_eq!(quote_for_shell("tab\there"), "'tab\there'");
+        assert_eq!(quote_for_shell("line\nbreak"), "'line\nbreak'");
+        assert_eq!(quote_for_shell("it's"), "'it'\\''s'");
+        assert_eq!(quote_for_shell("'"), "''\\'''");
+        assert_eq!(quote_for_shell("\"a\""), "'\"a\"'");
+        assert_eq!(quote_for_shell("back\\slash"), "'back\\slash'");
+        assert_eq!(quote_for_shell("café"), "'café'");
+    }
+
     #[test]
     fn test_vec_update() {
         let mut primes = vec![1, 2, 3];
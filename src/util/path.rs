@@ -0,0 +1,119 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// The value of `$PATH` that finds the standard utilities, like `getconf PATH`.
+pub const DEFAULT_PATH: &str = "/usr/bin:/bin:/usr/sbin:/sbin";
+
+/// Searches the directories in `path_var` (formatted like `$PATH`) for an executable named
+/// `name`.
+///
+/// If `name` contains a path separator, then it is returned as is if it refers to an executable
+/// file, following bash's behavior.
+pub fn search_in_path<S: AsRef<OsStr>>(name: S, path_var: &OsStr) -> Option<PathBuf> {
+    let name = Path::new(name.as_ref());
+    if name.components().count() > 1 {
+        return if is_executable(name) {
+            Some(name.to_path_buf())
+        } else {
+            None
+        };
+    }
+
+    std::env::split_paths(path_var)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Returns all the executables named `name` in the directories in `path_var`, in order.
+pub fn search_all_in_path<S: AsRef<OsStr>>(name: S, path_var: &OsStr) -> Vec<PathBuf> {
+    let name = Path::new(name.as_ref());
+    if name.components().count() > 1 {
+        return search_in_path(name, path_var).into_iter().collect();
+    }
+
+    std::env::split_paths(path_var)
+        .map(|dir| dir.join(name))
+        .filter(|candidate| is_executable(candidate))
+        .collect()
+}
+
+/// Returns the names of the executables starting with `prefix` in the directories in `path_var`
+/// (formatted like `$PATH`), sorted and without duplicates.
+pub fn executables_in_path(prefix: &str, path_var: &OsStr) -> Vec<String> {
+    let mut names: Vec<String> = std::env::split_paths(path_var)
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Returns `true` if `path` is a file that the user may execute.
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.is_file() && (metadata.permissions().mode() & 0o111 != 0))
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+pub fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_search_in_path() {
+        let path_var = env::join_paths(&["/nonexistent", "/bin"]).unwrap();
+        assert_eq!(
+            search_in_path("sh", &path_var),
+            Some(PathBuf::from("/bin/sh"))
+        );
+        assert_eq!(search_in_path("bsh_nonexistent_command", &path_var), None);
+        assert_eq!(
+            search_in_path("/bin/sh", OsStr::new("")),
+            Some(PathBuf::from("/bin/sh"))
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_executables_in_path() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (first, second) = (temp_dir.path().join("a"), temp_dir.path().join("b"));
+        for (dir, names) in &[
+            (&first, &["foo", "foobar", "bar"]),
+            (&second, &["foo", "food", "fog"]),
+        ] {
+            fs::create_dir(dir).unwrap();
+            for name in names.iter() {
+                let path = dir.join(name);
+                fs::write(&path, "").unwrap();
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+        fs::set_permissions(second.join("fog"), fs::Permissions::from_mode(0o644)).unwrap();
+
+        let path_var = env::join_paths(&[&first, &second]).unwrap();
+        assert_eq!(
+            executables_in_path("foo", &path_var),
+            ["foo", "foobar", "food"]
+        );
+        assert!(executables_in_path("baz", &path_var).is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use failure::ResultExt;
+
+use crate::errors::{ErrorKind, Result};
+
+/// Records how long each command run by the shell took, for profiling scripts.
+///
+/// Each record is a line of the form `time_us\tline\tcommand`, where `time_us` is the duration
+/// of the command in microseconds and `line` is its line number, e.g. in a script. Records are
+/// buffered until [`Profiler::finish`] is called.
+#[derive(Debug)]
+pub struct Profiler {
+    output: BufWriter<File>,
+}
+
+impl Profiler {
+    /// Creates the profile at `path`, replacing any existing file.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::create(path).context(ErrorKind::Io)?;
+        Ok(Self {
+            output: BufWriter::new(file),
+        })
+    }
+
+    /// Records that `command` on line `line_number` took `duration` to run.
+    pub fn record(&mut self, command: &str, duration: Duration, line_number: usize) -> Result<()> {
+        // Keep each record on a single line with exactly three fields.
+        let command = command
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n");
+        writeln!(
+            self.output,
+            "{}\t{}\t{}",
+            duration.as_micros(),
+            line_number,
+            command
+        )
+        .context(ErrorKind::Io)?;
+        Ok(())
+    }
+
+    /// Writes any buffered records to the profile.
+    pub fn finish(&mut self) -> Result<()> {
+        self.output.flush().context(ErrorKind::Io)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn test_profiler() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("profile.tsv");
+        fs::write(&path, "existing\n").unwrap();
+
+        let mut profiler = Profiler::create(&path).unwrap();
+        profiler
+            .record("echo foo bar", Duration::from_millis(2), 1)
+            .unwrap();
+        profiler
+            .record("echo a\tb\nc", Duration::from_micros(7), 3)
+            .unwrap();
+        profiler.finish().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2000\t1\techo foo bar\n7\t3\techo a\\tb\\nc\n"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Signal dispositions for `trap`
+//!
+//! Caught signals are recorded by an async-signal-safe handler and the shell runs their traps
+//! later, between commands, see [`take_pending`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use failure::ResultExt;
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+use crate::errors::{ErrorKind, Result};
+
+/// Bit `n` is set if signal `n` has been caught since the last call to [`take_pending`].
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+/// Bit `n` is set once the disposition signal `n` had when the shell started has been saved.
+static SAVED: AtomicU64 = AtomicU64::new(0);
+
+/// Bit `n` is set if signal `n` was ignored when the shell started, see [`reset`].
+static IGNORED_ON_ENTRY: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn record_signal(signal: libc::c_int) {
+    PENDING.fetch_or(1 << signal, Ordering::SeqCst);
+}
+
+/// Records `signal` when it is received, to be returned by [`take_pending`].
+pub fn catch(signal: Signal) -> Result<()> {
+    set_handler(signal, SigHandler::Handler(record_signal))
+}
+
+/// Ignores `signal`, e.g. `trap '' INT`.
+pub fn ignore(signal: Signal) -> Result<()> {
+    set_handler(signal, SigHandler::SigIgn)
+}
+
+/// Restores the disposition `signal` had when the shell started, e.g. `trap - INT`.
+///
+/// Signals ignored on entry stay ignored, e.g. `SIGINT` in an interactive shell.
+pub fn reset(signal: Signal) -> Result<()> {
+    let handler = if IGNORED_ON_ENTRY.load(Ordering::SeqCst) & bit(signal) != 0 {
+        SigHandler::SigIgn
+    } else {
+        SigHandler::SigDfl
+    };
+    set_handler(signal, handler)
+}
+
+/// Returns the signals caught since the last call, in order of their numbers.
+pub fn take_pending() -> Vec<Signal> {
+    let pending = PENDING.swap(0, Ordering::SeqCst);
+    if pending == 0 {
+        return Vec::new();
+    }
+    Signal::iterator()
+        .filter(|&signal| pending & bit(signal) != 0)
+        .collect()
+}
+
+fn set_handler(signal: Signal, handler: SigHandler) -> Result<()> {
+    let action = SigAction::new(handler, SaFlags::SA_RESTART, SigSet::empty());
+    // SAFETY: `record_signal` only updates an atomic, which is async-signal-safe.
+    let previous = unsafe { signal::sigaction(signal, &action) }.context(ErrorKind::Nix)?;
+    if SAVED.fetch_or(bit(signal), Ordering::SeqCst) & bit(signal) == 0
+        && previous.handler() == SigHandler::SigIgn
+    {
+        IGNORED_ON_ENTRY.fetch_or(bit(signal), Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+fn bit(signal: Signal) -> u64 {
+    1 << signal as i32
+}
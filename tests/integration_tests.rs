@@ -1,11 +1,13 @@
 //! Integration Tests
 
 use std::ffi::OsStr;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Read};
 use std::path::PathBuf;
+use std::process::Command;
 
 use assert_cmd::prelude::*;
+use bsh::{create_shell, ShellConfig};
 use chrono::{DateTime, Local};
 use lazy_static::lazy_static;
 use predicates::prelude::*;
@@ -139,48 +141,2799 @@ fn test_stderr_redirect() {
     file.read_to_string(&mut contents)
         .expect("failed to read errfile");
     assert_eq!(contents, "needle\n");
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "echo needle >&2"])
+        .assert()
+        .success()
+        .stdout(predicates::str::is_empty())
+        .stderr(predicates::str::diff("needle\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_append_redirects() {
+    let temp_dir = generate_temp_directory().unwrap();
+    let command = "echo first >>outfile; echo second >> outfile; \
+                   sh -c 'echo err >&2' 2>>outfile; \
+                   sh -c 'echo both; echo both >&2' &>>outfile";
+    for _ in 0..2 {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .current_dir(temp_dir.path())
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::is_empty().from_utf8())
+            .stderr(predicates::str::is_empty().from_utf8());
+    }
+
+    let contents = fs::read_to_string(temp_dir.path().join("outfile")).unwrap();
+    assert_eq!(contents, "first\nsecond\nerr\nboth\nboth\n".repeat(2));
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_here_documents() {
+    let command = "cat <<EOF | tr a-z A-Z; cat <<'EOF'; cat <<-END\n\
+                   hello $BSH_TEST_NAME\nEOF\n\
+                   $BSH_TEST_NAME\nEOF\n\
+                   \t\tindented\n\tEND";
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .env("BSH_TEST_NAME", "world")
+        .args(&["-c", command])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("HELLO WORLD\n$BSH_TEST_NAME\nindented\n").from_utf8());
+
+    let temp_dir = generate_temp_directory().unwrap();
+    let script = temp_dir.path().join("script.sh");
+    fs::write(&script, "cat <<EOF\nfirst\nEOF\ncat <<EOF\nsecond\nEOF\n").unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("first\nsecond\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_here_strings() {
+    let command = "cat <<<word; cat <<< \"hello $BSH_TEST_NAME\" | tr a-z A-Z; \
+                   tr -d x <<<~/x";
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .env("BSH_TEST_NAME", "world")
+        .env("HOME", "/home/bsh")
+        .args(&["-c", command])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("word\nHELLO WORLD\n/home/bsh/\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_command_substitution() {
+    let temp_dir = generate_temp_directory().unwrap();
+    let command = "echo $(echo hello   world)! `echo backticks`; \
+                   echo $(echo $(echo nested)); \
+                   printf '%s|' $(printf 'a\\nb c\\n\\n') a$(true)b; \
+                   echo redirected > $(echo outfile)";
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .current_dir(temp_dir.path())
+        .args(&["-c", command])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("hello world! backticks\nnested\na|b|c|ab|").from_utf8());
+
+    let contents = fs::read_to_string(temp_dir.path().join("outfile")).unwrap();
+    assert_eq!(contents, "redirected\n");
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_arithmetic_expansion() {
+    let command = "let x=4; echo $(( x + 1 )) $(( $x * (2 + 3) )) n$((x ** 2)) $(( x += 2 )); \
+                   echo $x $((9223372036854775807 + 1))";
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", command])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("5 20 n16 6\n6 -9223372036854775808\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "-c",
+            "echo $((1+$((2)))) $(( (1 + 2) * $((3)) )) $(( $(echo 4) + 1 ))",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("3 9 5\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "echo $(( 1 / 0 ))"])
+        .assert()
+        .failure()
+        .stdout(predicates::str::is_empty().from_utf8())
+        .stderr(predicates::str::diff("bsh: division by 0\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_special_variables() {
+    let temp_dir = generate_temp_directory().unwrap();
+    let script = temp_dir.path().join("script.sh");
+    fs::write(
+        &script,
+        "false\necho $? $0 $LINENO\nsh -c 'exit 3'; echo $?\nsleep 0 &\necho $! $$ $RANDOM\n",
+    )
+    .unwrap();
+    let expected_stdout = format!(
+        r"^1 {} 2\n3\n\d+ \d+ \d+\n$",
+        regex::escape(&script.display().to_string())
+    );
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::is_match(expected_stdout)
+                .unwrap()
+                .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_positional_parameters() {
+    let temp_dir = generate_temp_directory().unwrap();
+    let script = temp_dir.path().join("script.sh");
+    fs::write(
+        &script,
+        "echo $1 $2\necho $#\nprintf '[%s]' $@\necho\necho $*\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .args(&["arg1", "arg 2", "arg3"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff("arg1 arg 2\n3\n[arg1][arg 2][arg3]\narg1 arg 2 arg3\n")
+                .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_default_value_expansion() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[
+            OsStr::new("--log"),
+            LOG_FILE_NAME.as_os_str(),
+            OsStr::new("-c"),
+            OsStr::new("echo ${BSH_UNSET:-a default} ${HOME:+alt}; echo ${BSH_UNSET:=assigned}; echo $BSH_UNSET"),
+        ])
+        .env_remove("BSH_UNSET")
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("a default alt\nassigned\nassigned\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_required_value_expansion_exits_script() {
+    let temp_dir = generate_temp_directory().unwrap();
+    let script = temp_dir.path().join("script.sh");
+    fs::write(
+        &script,
+        "echo before\necho ${BSH_UNSET:?must be set}\necho after\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .env_remove("BSH_UNSET")
+        .assert()
+        .failure()
+        .stdout(predicates::str::diff("before\n").from_utf8())
+        .stderr(predicates::str::contains("BSH_UNSET: must be set").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_user_home_dir_expansion() {
+    let root = nix::unistd::User::from_name("root").unwrap().unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "echo ~root ~bsh-no-such-user"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(format!("{} ~bsh-no-such-user\n", root.dir.display()))
+                .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_glob_expansion() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "-c",
+            "echo *.toml; echo '*.toml' \"*.toml\"; echo *.no-such-extension",
+        ])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff("Cargo.toml\n*.toml *.toml\n*.no-such-extension\n").from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_failglob() {
+    let temp_dir = generate_temp_directory().unwrap();
+    let script = temp_dir.path().join("script.sh");
+    fs::write(
+        &script,
+        "debug set failglob on\necho *.no-such-extension\necho $?\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("1\n").from_utf8())
+        .stderr(predicates::str::contains("no match: *.no-such-extension").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_brace_expansion() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "-c",
+            "echo {a,b,c} file{,.bak} {1..5} {{a,b},{c,d}}; echo '{a,b}' \"{1..3}\" Cargo.{toml,no-such*}",
+        ])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(
+                "a b c file file.bak 1 2 3 4 5 a b c d\n{a,b} {1..3} Cargo.toml Cargo.no-such*\n",
+            )
+            .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_negated_command() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "! echo foo; echo $?"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("foo\n1\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "! false && echo yes; ! (exit 3) || echo no"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("yes\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_group_command() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "{ echo foo; echo bar; } | wc -l"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("2").trim().from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "{ declare X=1; cd /; }; echo $X; pwd"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("1\n/\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "echo foo | { echo start; cat; echo end; } | cat"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("start\nfoo\nend\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_if_command() {
+    for (command, expected) in &[
+        ("if true; then echo yes; fi", "yes"),
+        ("if false; then echo no; else echo yes; fi", "yes"),
+        (
+            "if false; then echo 1; elif false; then echo 2; elif true; then echo 3; else echo 4; fi",
+            "3",
+        ),
+        ("if false; then echo no; fi; echo $?", "0"),
+        ("if true; then (exit 3); fi; echo $?", "3"),
+        ("if true && false; then echo no; elif ! false; then echo yes; fi", "yes"),
+        ("if true; then echo a; echo b; fi | wc -l", "2"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).trim().from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_for_command() {
+    for (command, expected) in &[
+        ("for x in 1 2 3; do echo $x; done", "1\n2\n3\n"),
+        ("for x in a 'b c'; do echo \"$x\"; done", "a\nb c\n"),
+        ("for x in {1..3}; do false; done; echo $?", "1\n"),
+        ("for x in; do echo no; done; echo $?", "0\n"),
+        ("for x in a b; do echo $x; done; echo \"$x\"", "a\nb\n\n"),
+        (
+            "for x in 1 2 3 4; do if (( x == 2 )); then continue; fi; if (( x == 4 )); then break; fi; echo $x; done",
+            "1\n3\n",
+        ),
+        (
+            "for x in 1 2; do for y in a b; do echo $y; continue 2; done; echo no; done",
+            "a\na\n",
+        ),
+        (
+            "for x in 1 2; do for y in a b; do echo $y; break 5; done; done; echo end",
+            "a\nend\n",
+        ),
+        (
+            "for (( i = 0; i < 3; i++ )); do echo $i; done",
+            "0\n1\n2\n",
+        ),
+        (
+            "let n=0; for (( ; ; )); do let n+=1; if (( n > 2 )); then break; fi; done; echo $n",
+            "3\n",
+        ),
+        ("for x in a b; do echo $x; done | wc -l", "2\n"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "for f in src/*.rs; do echo $f; done"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("src/lib.rs\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "break; echo $?"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("0\n").from_utf8())
+        .stderr(
+            predicates::str::contains(
+                "break: only meaningful in a `for', `while', or `until' loop",
+            )
+            .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_while_command() {
+    for (command, expected) in &[
+        (
+            "let x=0; while [ $x -lt 3 ]; do echo $x; let x+=1; done",
+            "0\n1\n2\n",
+        ),
+        (
+            "let x=0; until (( x == 3 )); do echo $x; let x+=1; done",
+            "0\n1\n2\n",
+        ),
+        ("until false; do break; done; echo $?", "0\n"),
+        ("while false; do echo no; done; echo $?", "0\n"),
+        (
+            "let x=0; while (( x < 3 )); do let x+=1; (exit $x); done; echo $?",
+            "3\n",
+        ),
+        (
+            "let x=0; while true; do let x+=1; if (( x < 3 )); then continue; fi; break; done; echo $x",
+            "3\n",
+        ),
+        (
+            "let x=0; while true; do for y in a b; do let x+=1; if (( x > 2 )); then break 2; fi; done; done; echo $x",
+            "3\n",
+        ),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_loop_control_levels() {
+    for (command, expected) in &[
+        (
+            "for x in 1 2; do for y in a b; do echo $x $y; break 2; done; echo no; done; echo end",
+            "1 a\nend",
+        ),
+        (
+            "for x in 1 2; do for y in a b; do echo $x $y; continue 2; echo no; done; echo no; done",
+            "1 a\n2 a",
+        ),
+        (
+            "let x=0; while (( x < 2 )); do let x+=1; until false; do echo $x; continue 2; done; done",
+            "1\n2",
+        ),
+        (
+            "for x in 1 2; do for y in a; do for z in b; do break 2; done; done; echo $x; done",
+            "1\n2",
+        ),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).trim().from_utf8());
+    }
+
+    for command in &["break 0", "continue x"] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&[
+                "-c",
+                &format!("for x in 1 2; do {}; done; echo $?", command),
+            ])
+            .assert()
+            .success()
+            .stdout("1\n")
+            .stderr(predicates::str::contains("loop count out of range").from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_select_command() {
+    let mut command = BIN_UNDER_TEST.command();
+    command
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "select x in a b c; do echo $x $REPLY; done; echo $?"]);
+    assert_cmd::Command::from_std(command)
+        .write_stdin("2\n\n9\n")
+        .assert()
+        .success()
+        .stdout("b 2\n9\n0\n")
+        .stderr("1) a\n2) b\n3) c\n#? #? 1) a\n2) b\n3) c\n#? #? ");
+
+    let mut command = BIN_UNDER_TEST.command();
+    command
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "-c",
+            "declare PS3=pick:; select x in a b c; do break; done; echo $x",
+        ]);
+    assert_cmd::Command::from_std(command)
+        .write_stdin("3\n1\n")
+        .assert()
+        .success()
+        .stdout("c\n")
+        .stderr(predicates::str::ends_with("pick:").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "echo 1 | select x in a b; do echo $x; done"])
+        .assert()
+        .success()
+        .stdout("a\n");
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_case_command() {
+    for (command, expected) in &[
+        ("case foo in foo) echo yes;; esac", "yes"),
+        ("case foo in bar) echo bar;; *) echo other;; esac", "other"),
+        (
+            "case main.rs in *.txt) echo text;; *.rs) echo rust;; *) echo other;; esac",
+            "rust",
+        ),
+        ("case b in a | b | c) echo abc;; esac", "abc"),
+        (
+            "case x1 in ?[0-9]) echo digit; echo two;; esac",
+            "digit\ntwo",
+        ),
+        (
+            "case foo in f*) echo first;; foo) echo second;; esac",
+            "first",
+        ),
+        ("declare x=foo; case $x in $x) echo var; esac", "var"),
+        (
+            "case foo in '*') echo star;; *) echo literal;; esac",
+            "literal",
+        ),
+        ("case foo in bar) echo bar;; esac; echo $?", "0"),
+        ("case foo in foo) (exit 3);; esac; echo $?", "3"),
+        ("case foo in foo) echo foo;; esac | wc -l", "1"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).trim().from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_function_definition() {
+    for (command, expected) in &[
+        ("greet() { echo hello $1; }; greet world", "hello world"),
+        ("function greet { echo hello; }; greet; greet", "hello\nhello"),
+        ("args() { echo $# $2; }; args a b c; echo $#", "3 b\n0"),
+        (
+            "f() { echo $1; }; g() { f b; echo $1; }; g a",
+            "b\na",
+        ),
+        (
+            "countdown() { echo $1; if [ $1 -gt 0 ]; then countdown $(( $1 - 1 )); fi; }; countdown 2",
+            "2\n1\n0",
+        ),
+        (
+            "fib() { if [ $1 -eq 0 ]; then echo $2; else fib $(( $1 - 1 )) $3 $(( $2 + $3 )); fi; }; fib 10 0 1",
+            "55",
+        ),
+        ("cd() { echo no cd $1; }; cd /", "no cd /"),
+        ("f() { echo one; }; f() { echo two; }; f", "two"),
+        ("f() (exit 3); f; echo $?", "3"),
+        ("f() { cat; echo done; }; echo piped | f | wc -l", "2"),
+        ("f() { for x in a b; do echo $x; done; }; f", "a\nb"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).trim().from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_return() {
+    for (command, expected) in &[
+        ("f() { return 42; }; f; echo $?", "42"),
+        ("f() { return; }; true; f; echo $?", "0"),
+        ("f() { false; return; }; f; echo $?", "1"),
+        ("f() { echo a; return 3; echo b; }; f; echo $?", "a\n3"),
+        (
+            "f() { for x in a b; do echo $x; return 2; done; echo no; }; f; echo $?",
+            "a\n2",
+        ),
+        (
+            "f() { return $1; }; g() { f 4; echo $?; return 5; }; g; echo $?",
+            "4\n5",
+        ),
+        ("f() { return 1; }; f || echo failed", "failed"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).trim().from_utf8());
+    }
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "return 5; echo no"])
+        .assert()
+        .code(5)
+        .stdout("");
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_pwd() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = fs::canonicalize(temp_dir.path()).unwrap();
+    fs::create_dir(dir.join("real")).unwrap();
+    std::os::unix::fs::symlink(dir.join("real"), dir.join("link")).unwrap();
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "pwd"])
+        .current_dir(&dir)
+        .assert()
+        .success()
+        .stdout(format!("{}\n", dir.display()));
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "cd /tmp; pwd; cd /; echo $OLDPWD $PWD"])
+        .assert()
+        .success()
+        .stdout("/tmp\n/tmp /\n");
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "cd link; pwd; pwd -L; pwd -P; cd ..; pwd"])
+        .current_dir(&dir)
+        .assert()
+        .success()
+        .stdout(format!(
+            "{dir}/link\n{dir}/link\n{dir}/real\n{dir}\n",
+            dir = dir.display()
+        ));
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "pwd -x"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicates::str::contains("pwd: -x: invalid option").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_echo() {
+    for (command, expected) in &[
+        ("echo", "\n"),
+        ("echo foo   bar", "foo bar\n"),
+        ("echo -n foo; echo -n bar", "foobar"),
+        (r#"echo -e "a\tb\nc""#, "a\tb\nc\n"),
+        (r#"echo "a\tb""#, "a\\tb\n"),
+        (r#"echo -n -e "\n""#, "\n"),
+        (r#"echo -ne "x\0101\x42" y"#, "xAB y"),
+        (r#"echo -e -E "\n""#, "\\n\n"),
+        ("echo -x -n", "-x -n\n"),
+        ("echo foo | cat", "foo\n"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_printf() {
+    for (command, expected) in &[
+        (r#"printf "%d\n" 42"#, "42\n"),
+        (r#"printf "%05d" 7"#, "00007"),
+        (r#"printf -v x "%.2f" 3.14159; echo $x"#, "3.14\n"),
+        (r#"printf "%s=%x\n" a 255 b 16"#, "a=ff\nb=10\n"),
+        (r#"printf "[%-4s|%4s]" ab cd"#, "[ab  |  cd]"),
+        (r#"printf "%b %q" "a\tb" "c d""#, "a\tb 'c d'"),
+        (r#"printf "%s" foo | cat"#, "foo"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_read() {
+    for (script, input, expected) in &[
+        ("read A B; echo $A; echo $B", "foo bar\n", "foo\nbar\n"),
+        (
+            "read A B; echo $B; echo $REPLY",
+            "a b  c\n",
+            "b  c\na b  c\n",
+        ),
+        ("read a; read b; echo $b $a", "1\n2\n", "2 1\n"),
+        (r"read a b; echo $a; echo $b", "x\\ y\\\nz\n", "x yz\n\n"),
+        (r"read -r a; echo $a", "x\\ y\n", "x\\ y\n"),
+        ("read -n 3 a; read b; echo $a $b", "abcdef\n", "abc def\n"),
+        ("read -d , a; echo $a", "x y,z", "x y\n"),
+        ("read a; echo $? $a", "foo", "1 foo\n"),
+        ("echo foo | read a; echo $a", "", "foo\n"),
+    ] {
+        let mut command = BIN_UNDER_TEST.command();
+        command
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", script]);
+        assert_cmd::Command::from_std(command)
+            .write_stdin(*input)
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "sleep 1 | read -t 0.1 a; echo $?"])
+        .assert()
+        .success()
+        .stdout("1\n");
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_source() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    fs::write(temp_dir.path().join("init.bsh"), "declare X=0\n").unwrap();
+    let lib_dir = temp_dir.path().join("lib");
+    fs::create_dir(&lib_dir).unwrap();
+    fs::write(lib_dir.join("outer.bsh"), "echo $1 $#\nsource inner.bsh\n").unwrap();
+    fs::write(lib_dir.join("inner.bsh"), "declare Y=inner\nfalse\n").unwrap();
+
+    for (command, expected) in &[
+        ("source init.bsh; echo $X", "0\n"),
+        (". ./init.bsh; echo $X", "0\n"),
+        (
+            "source lib/outer.bsh a b; echo $? $Y $#",
+            "a 2\n1 inner 0\n",
+        ),
+        ("source nonexistent.bsh; echo $?", "1\n"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .current_dir(temp_dir.path())
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_export() {
+    for (command, expected) in &[
+        ("declare BSH_X=foo; env | grep BSH_X; echo $BSH_X", "foo\n"),
+        (
+            "declare BSH_X=foo; export BSH_X; env | grep BSH_X",
+            "BSH_X=foo\n",
+        ),
+        ("export BSH_X=foo; printenv BSH_X", "foo\n"),
+        (
+            "export BSH_X=foo; export -n BSH_X; env | grep BSH_X; echo $BSH_X",
+            "foo\n",
+        ),
+        (
+            r#"export "BSH_X=a b"; export -p | grep BSH_X"#,
+            "declare -x BSH_X='a b'\n",
+        ),
+        ("export BSH_X=foo; (printenv BSH_X)", "foo\n"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_alias() {
+    // Aliases are expanded when a line is read, so they are defined on separate lines of a script
+    let temp_dir = tempfile::tempdir().unwrap();
+    fs::write(temp_dir.path().join("file"), "").unwrap();
+    fs::write(
+        temp_dir.path().join("alias.bsh"),
+        "alias 'l=ls -a'\nl\nalias\nunalias l\nl\necho $?\n",
+    )
+    .unwrap();
+
+    BIN_UNDER_TEST
+        .command()
+        .current_dir(temp_dir.path())
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg("alias.bsh")
+        .assert()
+        .success()
+        .stdout(predicates::str::diff(".\n..\nalias.bsh\nfile\nalias l='ls -a'\n127\n").from_utf8())
+        .stderr(predicates::str::diff("bsh: l: command not found\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_type() {
+    for (command, expected) in &[
+        ("type echo", "echo is a shell builtin\n"),
+        ("type -t ls", "file\n"),
+        ("alias l=ls; type l", "l is an alias for `ls'\n"),
+        ("f() { true; }; type -t f if", "function\nkeyword\n"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "type ls"])
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with("ls is /").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "type nonexistent_command_xyz"])
+        .assert()
+        .code(predicate::eq(1))
+        .stderr(
+            predicates::str::diff("bsh: type: nonexistent_command_xyz: not found\n").from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_command() {
+    for (command, expected) in &[
+        (
+            "ls() { echo hijacked; }; ls /dev/null; command ls /dev/null",
+            "hijacked\n/dev/null\n",
+        ),
+        ("command -v pwd", "pwd\n"),
+        ("echo() { true; }; command echo foo", "foo\n"),
+        ("command -V echo", "echo is a shell builtin\n"),
+        ("command -v bsh_nonexistent_command; echo $?", "1\n"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_test() {
+    for (command, expected) in &[
+        ("test -f Cargo.toml && echo yes", "yes\n"),
+        ("[ 3 -gt 2 ] && echo yes", "yes\n"),
+        (r#"[ "" = "" ] && echo eq"#, "eq\n"),
+        (r#"[ -z "" ] && echo empty"#, "empty\n"),
+        ("[ -d src -a ! -f src ]; echo $?", "0\n"),
+        ("test a = b -o 1 -ne 1; echo $?", "1\n"),
+        ("[ a = a; echo $?", "2\n"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_true_false() {
+    for (command, expected) in &[
+        ("true && echo yes", "yes\n"),
+        ("false || echo fallback", "fallback\n"),
+        ("while false; do echo loop; done", ""),
+        ("true foo; false foo; echo $?", "1\n"),
+        (": foo > /dev/null && echo colon", "colon\n"),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_trap() {
+    for (command, expected) in &[
+        ("trap 'echo bye' EXIT; echo hi", "hi\nbye\n"),
+        (
+            "trap 'echo caught' INT; kill -INT $$; echo after",
+            "caught\nafter\n",
+        ),
+        ("trap 'echo failed' ERR; false; echo next", "failed\nnext\n"),
+        ("trap '' TERM; kill -TERM $$; echo ignored", "ignored\n"),
+        (
+            "trap 'echo x' USR1; trap -p USR1",
+            "trap -- 'echo x' SIGUSR1\n",
+        ),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "trap 'echo cleanup' EXIT; exit 3"])
+        .assert()
+        .code(3)
+        .stdout(predicates::str::diff("cleanup\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_wait() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("wait.bsh");
+    let contents = "\
+sleep 1 &
+wait
+echo $?
+sh -c 'exit 3' &
+wait $!
+echo $?
+sh -c 'exit 4' &
+wait %%
+echo $?
+sh -c 'exit 5' &
+wait -n
+echo $?
+wait -n
+echo $?
+";
+    fs::write(&script, contents).unwrap();
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .unwrap()
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("0\n3\n4\n5\n127\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_jobs() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("jobs.bsh");
+    let contents = "\
+sleep 5 &
+echo $!
+jobs -l
+jobs -p
+jobs -s
+jobs -r
+jobs -n
+kill %1
+";
+    fs::write(&script, contents).unwrap();
+
+    let output = BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let pid = stdout.lines().next().unwrap();
+    // `jobs -n` lists nothing, because the previous commands reported the job's status.
+    assert_eq!(
+        stdout,
+        format!(
+            "{pid}\n[1] {pid}\tRunning\tsleep 5\n{pid}\n[1] Running\tsleep 5 &\n",
+            pid = pid
+        )
+    );
+
+    // `jobs -s` lists only the stopped job, and `jobs -r` only the running one.
+    let contents = "\
+sleep 5 &
+sleep 5 &
+kill -STOP %2
+sleep 0.2
+jobs -s
+jobs -r
+kill -KILL %2
+kill %1
+";
+    fs::write(&script, contents).unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff("[2] Stopped\tsleep 5 &\n[1] Running\tsleep 5 &\n").from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_pushd_popd_dirs() {
+    for (command, expected) in &[
+        (
+            "pushd /tmp; echo $PWD; popd; echo $PWD",
+            "~ /\n/tmp\n/\n/\n",
+        ),
+        (
+            "pushd /tmp > /dev/null; pushd /var > /dev/null; dirs; dirs -v; dirs -l +1",
+            "/var ~ /\n 0  /var\n 1  ~\n 2  /\n/tmp\n",
+        ),
+        (
+            "pushd /tmp > /dev/null; pushd /var > /dev/null; pushd +2; popd -0; dirs -c; dirs",
+            "/ /var ~\n/ /var\n/\n",
+        ),
+    ] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .current_dir("/")
+            .env("HOME", "/tmp")
+            .args(&["-c", command])
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_hash() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .env("PATH", "/bin")
+        .args(&[
+            "-c",
+            "hash; sh -c true; sh -c true; hash; hash -t sh; hash -r; hash",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(
+                "hash: hash table empty\nhash -p /bin/sh sh\n/bin/sh\nhash: hash table empty\n",
+            )
+            .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_exec() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "exec echo replaced"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("replaced\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "trap 'echo bye' EXIT; exec echo replaced"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("bye\nreplaced\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "exec bsh_nonexistent_command"])
+        .assert()
+        .code(127)
+        .stderr(
+            predicates::str::diff("bsh: exec: bsh_nonexistent_command: not found\n").from_utf8(),
+        );
+
+    // The command must be in one of the directories of `--restricted-path`
+    let temp_dir = tempfile::tempdir().unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(format!("--restricted-path={}", temp_dir.path().display()))
+        .args(&["-c", "exec /bin/sh -c 'echo escaped'"])
+        .assert()
+        .code(126)
+        .stdout(predicates::str::is_empty())
+        .stderr(predicates::str::diff("bsh: exec: /bin/sh: Permission denied\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_fc() {
+    let home_dir = tempfile::tempdir().unwrap();
+    let mut command = BIN_UNDER_TEST.command();
+    command
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg("--no-editing")
+        .env("HOME", home_dir.path())
+        .env("EDITOR", "sed -i s/hello/bye/");
+    assert_cmd::Command::from_std(command)
+        .write_stdin("echo hello\necho world\nfc -s world=there\nfc 1\nfc -l\n")
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(
+                "hello\nworld\necho there\nthere\necho bye\nbye\n\
+                 1\t echo hello\n2\t echo world\n3\t fc -s world=there\n4\t echo there\n\
+                 5\t fc 1\n6\t echo bye\nexit\n",
+            )
+            .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_umask() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "umask 022; umask; umask -S"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("0022\nu=rwx,g=rx,o=rx\n").from_utf8());
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .current_dir(temp_dir.path())
+        .args(&["-c", "umask 0; touch tmpfile; stat -c %a tmpfile"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("666\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_set_errexit() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("errexit.bsh");
+    fs::write(
+        &script,
+        "set -e\n\
+         false && true\n\
+         if false; then :; fi\n\
+         ! true\n\
+         echo ignored\n\
+         false\n\
+         echo no\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .code(1)
+        .stdout(predicates::str::diff("ignored\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "set -e; set +e; false; echo yes"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("yes\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_set_nounset() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "-c",
+            "set -u; echo ${bsh_unset-default}; echo $bsh_unset; echo no",
+        ])
+        .assert()
+        .code(1)
+        .stdout(predicates::str::diff("default\n").from_utf8())
+        .stderr(predicates::str::contains("bsh_unset: unbound variable"));
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "set -u; set +u; echo $bsh_unset yes"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("yes\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_set_xtrace() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "-c",
+            "set -x; echo one; export PS4=++; echo two; set +x; echo three",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("one\ntwo\nthree\n").from_utf8())
+        .stderr(
+            predicates::str::diff("+ echo one\n+ export PS4=++\n++echo two\n++set +x\n")
+                .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_set_pipefail() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("pipefail.bsh");
+    fs::write(
+        &script,
+        "false | true\n\
+         echo $?\n\
+         set -o pipefail\n\
+         (exit 3) | (exit 4) | true\n\
+         echo $?\n\
+         true | true\n\
+         echo $?\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("0\n4\n0\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_set_noclobber() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("noclobber.bsh");
+    fs::write(
+        &script,
+        "echo a > file\n\
+         set -C\n\
+         echo b > file\n\
+         echo c >> file\n\
+         set +C\n\
+         cat file\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .current_dir(temp_dir.path())
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("a\nc\n").from_utf8())
+        .stderr(predicates::str::contains(
+            "file: cannot overwrite existing file",
+        ));
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_set_positional_params() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "set -- a -b c; echo $# $2; set --; echo $#"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("3 -b\n0\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_shopt_nullglob() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .current_dir(temp_dir.path())
+        .args(&["-c", "shopt -s nullglob; echo *.zyx"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .current_dir(temp_dir.path())
+        .args(&["-c", "shopt -u nullglob; echo *.zyx"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("*.zyx\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_shopt_histappend() {
+    let home_dir = tempfile::tempdir().unwrap();
+    let history_file = home_dir.path().join(".bsh_history");
+    let mut command = BIN_UNDER_TEST.command();
+    command
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg("--no-editing")
+        .env("HOME", home_dir.path());
+    // The history file is changed by another command while the shell runs, e.g. another shell.
+    assert_cmd::Command::from_std(command)
+        .write_stdin(format!(
+            "shopt -s histappend\necho other >> {}\n",
+            history_file.display()
+        ))
+        .assert()
+        .success();
+    let history = fs::read_to_string(&history_file).unwrap();
+    assert!(history.contains("other\n"));
+    assert!(history.contains("shopt -s histappend\n"));
+}
+
+#[test]
+fn test_readonly() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("readonly.bsh");
+    fs::write(
+        &script,
+        "readonly bsh_x=5\n\
+         declare bsh_x=6\n\
+         echo $bsh_x\n\
+         readonly bsh_y\n\
+         unset bsh_y\n\
+         echo $?\n\
+         declare -a bsh_a\n\
+         bsh_a[0]=x\n\
+         readonly bsh_a\n\
+         unset bsh_a[0]\n\
+         echo $? ${bsh_a[0]}\n\
+         readonly -p\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(
+                "5\n1\n1 x\ndeclare -r bsh_a\ndeclare -r bsh_x=5\ndeclare -r bsh_y\n",
+            )
+            .from_utf8(),
+        )
+        .stderr(predicates::str::contains(
+            "declare: bsh_x: readonly variable",
+        ))
+        .stderr(predicates::str::contains("unset: bsh_y: readonly variable"))
+        .stderr(predicates::str::contains("unset: bsh_a: readonly variable"));
+}
+
+#[test]
+fn test_declare_attributes() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("declare.bsh");
+    fs::write(
+        &script,
+        "declare -i n=3+4\n\
+         echo $n\n\
+         declare -r CONST=42\n\
+         declare CONST=99\n\
+         echo $CONST\n\
+         declare -p n CONST\n\
+         f() { echo f; }\n\
+         declare -F\n\
+         declare -f f\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(
+                "7\n42\ndeclare -i n=7\ndeclare -r CONST=42\ndeclare -f f\nf () { echo f; }\n",
+            )
+            .from_utf8(),
+        )
+        .stderr(predicates::str::contains(
+            "declare: CONST: readonly variable",
+        ));
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_env() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "export bsh_x=foo; declare bsh_y=bar; env | grep bsh_"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("bsh_x=foo\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "env -i bsh_x=1 bsh_y=2 env"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("bsh_x=1\nbsh_y=2\n").from_utf8());
+}
+
+#[test]
+fn test_printenv() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "printenv PATH"])
+        .env("PATH", "/bsh/test/path")
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("/bsh/test/path\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "printenv NONEXISTENT_XYZ"])
+        .assert()
+        .code(1)
+        .stdout(predicates::str::is_empty().from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_command_not_found() {
+    let args = ["-c", "foo"];
+    let expected_stderr = "bsh: foo: command not found\n";
+    let err = BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&args)
+        .unwrap_err();
+    let output = err.as_output().unwrap();
+    output
+        .clone()
+        .assert()
+        .stderr(predicates::str::diff(expected_stderr).from_utf8())
+        .code(predicate::eq(127));
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_syntax_error() {
+    let args = ["-c", ";"];
+    let expected_stderr = "bsh: syntax error near: ;\n";
+    let err = BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&args)
+        .unwrap_err();
+
+    let output = err.as_output().unwrap();
+    output
+        .clone()
+        .assert()
+        .stderr(predicates::str::diff(expected_stderr).from_utf8())
+        .code(predicate::eq(2));
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_restrict_path() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("needle");
+    fs::write(&script, "#!/bin/sh\necho needle\n").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = ShellConfig::noninteractive().restrict_path(vec![PathBuf::from("/usr/bin")]);
+    let mut shell = create_shell(config).unwrap();
+
+    shell
+        .execute_command_string(script.to_str().unwrap())
+        .unwrap();
+    assert_eq!(shell.last_exit_status().code(), Some(126));
+
+    // `..` is resolved before checking the directory
+    let escaped = format!("/usr/bin/../..{}", script.display());
+    shell.execute_command_string(&escaped).unwrap();
+    assert_eq!(shell.last_exit_status().code(), Some(126));
+
+    // The `env` builtin is restricted like the shell, and may not change `$PATH`
+    for command in &[
+        format!("env {}", script.display()),
+        format!("env PATH={} needle", temp_dir.path().display()),
+    ] {
+        shell.execute_command_string(command).unwrap();
+        assert_eq!(shell.last_exit_status().code(), Some(126));
+    }
+
+    shell.execute_command_string("/usr/bin/env true").unwrap();
+    assert!(shell.last_exit_status().success());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_restricted_path_flag() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let bin_dir = temp_dir.path().join("bin");
+    fs::create_dir(&bin_dir).unwrap();
+    let needle = bin_dir.join("needle");
+    fs::write(&needle, "#!/bin/sh\necho needle\n").unwrap();
+    fs::set_permissions(&needle, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let script = temp_dir.path().join("script.bsh");
+    fs::write(
+        &script,
+        "needle\ncat /dev/null\n/bin/cat /dev/null\ndeclare PATH=/bin\nunset PATH\nneedle\n",
+    )
+    .unwrap();
+
+    // PATH is read-only, so needle is still found after trying to change it
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(format!("--restricted-path={}", bin_dir.display()))
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("needle\nneedle\n").from_utf8())
+        .stderr(
+            predicates::str::diff(
+                "bsh: cat: command not found\nbsh: /bin/cat: Permission denied\n\
+                 bsh: declare: PATH: readonly variable\nbsh: unset: PATH: readonly variable\n",
+            )
+            .from_utf8(),
+        );
+
+    // Builtins that run commands are restricted too
+    fs::write(
+        &script,
+        "nice /bin/cat /dev/null\nnohup /bin/cat /dev/null\nenv /bin/cat /dev/null\n\
+         env needle\nexec /bin/cat /dev/null\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(format!("--restricted-path={}", bin_dir.display()))
+        .arg(&script)
+        .assert()
+        .code(126)
+        .stdout(predicates::str::diff("needle\n").from_utf8())
+        .stderr(
+            predicates::str::diff(
+                "bsh: nice: /bin/cat: Permission denied\nbsh: nohup: /bin/cat: Permission denied\n\
+                 bsh: env: /bin/cat: Permission denied\nbsh: exec: /bin/cat: Permission denied\n",
+            )
+            .from_utf8(),
+        );
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_sandbox_mode_blocked_paths() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("bsh_sandbox_needle");
+    fs::write(&script, "#!/bin/sh\necho needle\n").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = ShellConfig::noninteractive()
+        .sandbox_mode(true)
+        .sandbox_blocked_paths(vec![temp_dir.path().to_path_buf()]);
+    let mut shell = create_shell(config).unwrap();
+
+    for command in &[
+        script.display().to_string(),
+        format!("env {}", script.display()),
+    ] {
+        shell.execute_command_string(command).unwrap();
+        assert_eq!(shell.last_exit_status().code(), Some(126));
+    }
+
+    shell.execute_command_string("true").unwrap();
+    assert!(shell.last_exit_status().success());
+}
+
+#[test]
+fn test_sandbox_mode_disabled_commands() {
+    let config = ShellConfig::noninteractive().sandbox_mode(true);
+    let mut shell = create_shell(config).unwrap();
+    for command in &["exec true", "source script", "eval true", "nice true"] {
+        shell.execute_command_string(command).unwrap();
+        assert_eq!(shell.last_exit_status().code(), Some(126));
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_sandbox_mode_redirects() {
+    let sandbox_dir = tempfile::tempdir().unwrap();
+    let outside_dir = tempfile::tempdir().unwrap();
+    let inside = sandbox_dir.path().join("inside");
+    let outside = outside_dir.path().join("outside");
+    let escaped = sandbox_dir.path().join("..").join("escaped");
+
+    let config = ShellConfig::noninteractive()
+        .sandbox_mode(true)
+        .sandbox_dir(sandbox_dir.path());
+    let mut shell = create_shell(config).unwrap();
+
+    shell
+        .execute_command_string(&format!("echo hi > {}", inside.display()))
+        .unwrap();
+    assert!(shell.last_exit_status().success());
+    assert_eq!(fs::read_to_string(&inside).unwrap(), "hi\n");
+
+    for redirect in &[
+        format!("echo hi > {}", outside.display()),
+        format!("echo hi 2> {}", escaped.display()),
+        format!("cat < {}", outside.display()),
+    ] {
+        shell.execute_command_string(redirect).unwrap();
+        assert_eq!(shell.last_exit_status().code(), Some(126));
+    }
+    assert!(!outside.exists());
+    assert!(!escaped.exists());
+
+    let config = ShellConfig::noninteractive().sandbox_mode(true);
+    let mut shell = create_shell(config).unwrap();
+    shell
+        .execute_command_string(&format!("echo hi > {}", inside.display()))
+        .unwrap();
+    assert_eq!(shell.last_exit_status().code(), Some(126));
+}
+
+#[test]
+fn test_sandbox_mode_protected_variables() {
+    let config = ShellConfig::noninteractive().sandbox_mode(true);
+    let mut shell = create_shell(config).unwrap();
+    for command in &[
+        "declare PATH=/tmp",
+        "declare LD_PRELOAD=/tmp/evil.so",
+        "unset LD_LIBRARY_PATH",
+        "let PATH=1",
+        "env LD_PRELOAD=/tmp/evil.so true",
+        "env -u LD_LIBRARY_PATH true",
+    ] {
+        shell.execute_command_string(command).unwrap();
+        assert!(!shell.last_exit_status().success());
+    }
+    assert_ne!(shell.get_variable("PATH").as_deref(), Some("/tmp"));
+
+    shell
+        .execute_command_string("declare BSH_SANDBOX_TEST=1")
+        .unwrap();
+    assert!(shell.last_exit_status().success());
+}
+
+#[test]
+fn test_sandbox_mode_shadowed_builtins() {
+    let config = ShellConfig::noninteractive().sandbox_mode(true);
+    let mut shell = create_shell(config).unwrap();
+    shell
+        .execute_command_string("cd() { echo pwned; }")
+        .unwrap();
+    assert!(!shell.last_exit_status().success());
+    assert!(shell.get_function("cd").is_none());
+
+    shell
+        .execute_command_string("alias cd='echo pwned'")
+        .unwrap();
+    assert!(!shell.last_exit_status().success());
+    assert!(shell.aliases().get("cd").is_none());
+
+    shell
+        .execute_command_string("bsh_sandbox_test() { true; }")
+        .unwrap();
+    assert!(shell.last_exit_status().success());
+}
+
+fn generate_temp_directory() -> io::Result<TempDir> {
+    // Because of limitation in `assert_cli`, temporary directory must be
+    // subdirectory of directory containing Cargo.toml
+    let root: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests"].iter().collect();
+    tempfile::tempdir_in(root)
+}
+
+#[test]
+fn test_version() {
+    for flag in &["--version", "--version-info"] {
+        BIN_UNDER_TEST
+            .command()
+            .arg(flag)
+            .unwrap()
+            .assert()
+            .success()
+            .stdout(predicate::str::is_empty().not())
+            .stdout(predicate::str::contains(env!("CARGO_PKG_VERSION")));
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_posix_mode() {
+    let restrictions = [
+        ("alias ll=ls", "aliases"),
+        ("[[ -n foo ]]", "[[...]]"),
+        ("((1+2))", "((...))"),
+        ("function foo { true; }", "function keyword"),
+        ("echo $(date)", "$(...)"),
+        ("echo {a,b}", "{...} brace expansion"),
+        ("declare -x FOO", "declare"),
+        ("export -n FOO", "export -n"),
+    ];
+    for (command, feature) in &restrictions {
+        let err = BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["--posix", "-c", *command])
+            .unwrap_err();
+        let expected_stderr = format!("bsh: not available in POSIX mode: {}\n", feature);
+        err.as_output()
+            .unwrap()
+            .clone()
+            .assert()
+            .stderr(predicates::str::diff(expected_stderr).from_utf8())
+            .code(predicate::eq(1));
+    }
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["--posix", "-c", "echo foo"])
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("foo\n").from_utf8());
+
+    // Unlike command substitutions, arithmetic expansions are POSIX
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["--posix", "-c", "echo $((1+2)) \"$((1+$((2))))\""])
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("3 3\n").from_utf8());
+
+    // POSIX `export -p` is allowed, as are quoted literals that look like extensions
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "--posix",
+            "-c",
+            "export -p > /dev/null && echo '$(date)' \"{a,b}\"",
+        ])
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("$(date) {a,b}\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_script_error_location() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("script.bsh");
+    fs::write(&script, "echo foo\ncat < /bsh_nonexistent_file\necho bar\n").unwrap();
+
+    let err = BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .unwrap_err();
+    let expected_stderr = format!("bsh: {}:2: I/O error occurred\n", script.display());
+    err.as_output()
+        .unwrap()
+        .clone()
+        .assert()
+        .stdout(predicates::str::diff("foo\n").from_utf8())
+        .stderr(predicates::str::diff(expected_stderr).from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_debug_xtrace() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "debug start; echo hi; debug stop; echo hidden"])
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("hi\nhidden\n").from_utf8())
+        .stderr(predicates::str::diff("+ echo hi\n+ debug stop\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_compgen() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "compgen -A function -A builtin comp"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("compgen\ncomplete\ncompopt\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "compgen -A function"])
+        .assert()
+        .code(1)
+        .stdout(predicates::str::is_empty().from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_xtrace_fd() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let trace = temp_dir.path().join("trace.txt");
+
+    // Open fd 3 for the shell with sh, as bsh cannot redirect its own file descriptors.
+    Command::new("sh")
+        .arg("-c")
+        .arg(r#""$0" --log "$1" -c "debug start; echo hi" 3>"$2""#)
+        .arg(BIN_UNDER_TEST.path())
+        .arg(LOG_FILE_NAME.as_os_str())
+        .arg(&trace)
+        .env("BASH_XTRACEFD", "3")
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("hi\n").from_utf8())
+        .stderr(predicates::str::is_empty().from_utf8());
+    assert_eq!(fs::read_to_string(&trace).unwrap(), "+ echo hi\n");
+
+    // xtrace output falls back to stderr if the file descriptor is not open
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "debug start; echo hi"])
+        .env("BASH_XTRACEFD", "9")
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("hi\n").from_utf8())
+        .stderr(predicates::str::diff("+ echo hi\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_argument_list_too_long() {
+    let config = ShellConfig::noninteractive().max_argument_count(3);
+    let mut shell = create_shell(config).unwrap();
+    shell.execute_command_string("/bin/echo a b c").unwrap();
+    assert_eq!(shell.last_exit_status().code(), Some(126));
+    shell.execute_command_string("/bin/echo a b").unwrap();
+    assert!(shell.last_exit_status().success());
+
+    // Glob expansion can produce more arguments than the command line shows
+    let temp_dir = tempfile::tempdir().unwrap();
+    for i in 0..5 {
+        fs::write(temp_dir.path().join(format!("file{}", i)), "").unwrap();
+    }
+    let glob = temp_dir.path().join("*");
+    shell
+        .execute_command_string(&format!("/bin/echo {}", glob.display()))
+        .unwrap();
+    assert_eq!(shell.last_exit_status().code(), Some(126));
+    let config = ShellConfig::noninteractive().max_argument_count(6);
+    let mut shell = create_shell(config).unwrap();
+    shell
+        .execute_command_string(&format!("/bin/echo {} > /dev/null", glob.display()))
+        .unwrap();
+    assert!(shell.last_exit_status().success());
+
+    let config = ShellConfig::noninteractive().max_argument_length(16);
+    let mut shell = create_shell(config).unwrap();
+    shell
+        .execute_command_string("/bin/echo aaaaaaaaaaaaaaaa")
+        .unwrap();
+    assert_eq!(shell.last_exit_status().code(), Some(126));
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_arrays() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("arrays.bsh");
+    let contents = "\
+declare -a arr
+arr[0]=foo
+arr[1]=baz
+echo ${arr[0]}
+echo ${arr[@]}
+echo ${#arr[@]}
+unset arr[0]
+echo ${#arr[@]} ${!arr[@]}
+sparse[5]=q
+sparse[99999999999]=r
+echo ${#sparse[@]} ${!sparse[@]} ${sparse[@]}
+";
+    fs::write(&script, contents).unwrap();
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("foo\nfoo baz\n2\n1 1\n2 5 99999999999 q r\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_assoc_arrays() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("assoc_arrays.bsh");
+    let contents = "\
+declare -A counts
+counts[apple]=3
+counts[banana]=1
+counts[cherry]=2
+echo ${counts[apple]}
+echo ${!counts[@]}
+echo ${counts[@]}
+unset counts[banana]
+echo ${#counts[@]}
+";
+    fs::write(&script, contents).unwrap();
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("3\napple banana cherry\n3 1 2\n2\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_mapfile() {
+    for (script, input, expected) in &[
+        (
+            "mapfile; echo ${#MAPFILE[@]} ${MAPFILE[1]}",
+            "a\nb\n",
+            "2 b\n\n",
+        ),
+        (
+            "mapfile -C 'echo cb' -c 2 arr; echo ${#arr[@]}",
+            "a\nb\nc\nd\ne\n",
+            "cb 1 b\n\ncb 3 d\n\n5\n",
+        ),
+        (
+            r#"printf "a\nb\nc\n" | mapfile -t lines; echo ${lines[1]}"#,
+            "",
+            "b\n",
+        ),
+        (
+            "mapfile -n 2 -t; echo ${MAPFILE[1]} ${MAPFILE[2]}",
+            "1\n2\n3\n",
+            "2\n",
+        ),
+        ("readarray -t -d , parts; echo ${parts[2]}", "a,b,c", "c\n"),
+        (
+            "cb() { echo cb $1 $2; }; mapfile -t -C cb -c 2 arr; echo ${arr[@]}",
+            "a\nb\nc\nd\ne\n",
+            "cb 1 b\ncb 3 d\na b c d e\n",
+        ),
+    ] {
+        let mut command = BIN_UNDER_TEST.command();
+        command
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", script]);
+        assert_cmd::Command::from_std(command)
+            .write_stdin(*input)
+            .assert()
+            .success()
+            .stdout(predicates::str::diff(*expected).from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_array_assignment() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "arr=(a b c); echo ${arr[1]}"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("b\n").from_utf8());
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("array_assignment.bsh");
+    let contents = "\
+arr=(a 'b c')
+arr+=(d)
+echo ${#arr[@]} $arr
+for x in \"${arr[@]}\"; do echo $x; done
+declare -A dict
+dict=([x]=1 [y]=2)
+echo ${!dict[@]} ${dict[y]}
+declare -p arr
+sparse=(a [3]=\"b c\" d)
+declare -p sparse
+declare -A map=([k]=v)
+readonly list=(x y)
+declare -p map list
+undeclared=([k]=v)
+echo ${undeclared[0]}
+";
+    fs::write(&script, contents).unwrap();
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(
+                "3 a\na\nb c\nd\nx y 2\ndeclare -a arr=([0]=a [1]='b c' [2]=d)\n\
+                 declare -a sparse=([0]=a [3]='b c' [4]=d)\n\
+                 declare -A map=([k]=v)\ndeclare -ar list=([0]=x [1]=y)\nv\n",
+            )
+            .from_utf8(),
+        );
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "echo a=(b)"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("syntax error"));
+}
+
+#[test]
+fn test_let() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "let a=2+3; echo $a; let \"a *= 2\" && echo $a"])
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("5\n10\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "let \"1/0\""])
+        .assert()
+        .failure()
+        .stderr(predicates::str::diff("bsh: let: 1/0: division by 0\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_arithmetic_command() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "-c",
+            "(( 5 > 3 )) && echo yes; ((x = 2 * 3)); (( x < 6 )) || echo $x",
+        ])
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("yes\n6\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_double_bracket_command() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "-c",
+            r#"[[ "hello" =~ ^hel ]] && echo yes; declare x=world; [[ $x == w*d && -n $x ]] && echo $x"#,
+        ])
+        .unwrap()
+        .assert()
+        .stdout(predicates::str::diff("yes\nworld\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_job_specs_without_job_control() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("job_specs.bsh");
+    let contents = "\
+true &
+fg %1
+bg %tr
+fg
+echo done
+";
+    fs::write(&script, contents).unwrap();
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .unwrap()
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("done\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_kill() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Command;
+
+    for (sigspec, expected_signal) in &[
+        (None, libc::SIGTERM),
+        (Some("-TERM"), libc::SIGTERM),
+        (Some("-SIGKILL"), libc::SIGKILL),
+        (Some("-9"), libc::SIGKILL),
+        (Some("-int"), libc::SIGINT),
+    ] {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let command = match sigspec {
+            Some(sigspec) => format!("kill {} {}", sigspec, child.id()),
+            None => format!("kill {}", child.id()),
+        };
+
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", &command])
+            .unwrap()
+            .assert()
+            .success();
+        assert_eq!(child.wait().unwrap().signal(), Some(*expected_signal));
+    }
 }
 
 #[test]
 #[cfg(unix)] // TODO (#22): Support Windows
-fn test_command_not_found() {
-    let args = ["-c", "foo"];
-    let expected_stderr = "bsh: foo: command not found\n";
-    let err = BIN_UNDER_TEST
+fn test_kill_errors() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Command;
+
+    BIN_UNDER_TEST
         .command()
         .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
-        .args(&args)
-        .unwrap_err();
-    let output = err.as_output().unwrap();
-    output
-        .clone()
+        .args(&["-c", "kill -l"])
+        .unwrap()
         .assert()
-        .stderr(predicates::str::diff(expected_stderr).from_utf8())
-        .code(predicate::eq(127));
+        .success()
+        .stdout(predicates::str::contains("15) SIGTERM\n"));
+
+    for command in &["kill -FOO 1", "kill %1", "kill -9"] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .args(&["-c", command])
+            .assert()
+            .failure();
+    }
+
+    // Every target is signaled even if an earlier one is invalid
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", &format!("kill %nope foo {}", child.id())])
+        .assert()
+        .failure();
+    assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGTERM));
 }
 
 #[test]
 #[cfg(unix)] // TODO (#22): Support Windows
-fn test_syntax_error() {
-    let args = ["-c", ";"];
-    let expected_stderr = "bsh: syntax error near: ;\n";
-    let err = BIN_UNDER_TEST
+fn test_nohup() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("hangup.sh");
+    fs::write(&script, "kill -HUP $$\necho survived\n").unwrap();
+
+    BIN_UNDER_TEST
         .command()
         .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
-        .args(&args)
-        .unwrap_err();
+        .arg("-c")
+        .arg(format!("nohup sh {} | cat", script.display()))
+        .unwrap()
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("survived\n").from_utf8());
 
-    let output = err.as_output().unwrap();
-    output
-        .clone()
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "nohup false"])
         .assert()
-        .stderr(predicates::str::diff(expected_stderr).from_utf8())
-        .code(predicate::eq(2));
+        .code(1);
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "nohup bsh_nonexistent_command"])
+        .assert()
+        .code(127);
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg("-c")
+        .arg(format!("nohup {}", temp_dir.path().display()))
+        .assert()
+        .code(126);
+
+    // The command must be in one of the directories of `restrict_path`
+    let config = ShellConfig::noninteractive().restrict_path(vec![temp_dir.path().to_path_buf()]);
+    let mut shell = create_shell(config).unwrap();
+    shell
+        .execute_command_string("nohup /bin/sh -c 'echo escaped'")
+        .unwrap();
+    assert_eq!(shell.last_exit_status().code(), Some(126));
 }
 
-fn generate_temp_directory() -> io::Result<TempDir> {
-    // Because of limitation in `assert_cli`, temporary directory must be
-    // subdirectory of directory containing Cargo.toml
-    let root: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests"].iter().collect();
-    tempfile::tempdir_in(root)
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_nice() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "nice echo hello"])
+        .unwrap()
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("hello\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "nice -n 5 nice"])
+        .unwrap()
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"^-?\d+\n$").unwrap());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "nice -n foo echo hello"])
+        .assert()
+        .code(125);
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "nice bsh_nonexistent_command"])
+        .assert()
+        .code(127);
+
+    // The command must be in one of the directories of `restrict_path`
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config = ShellConfig::noninteractive().restrict_path(vec![temp_dir.path().to_path_buf()]);
+    let mut shell = create_shell(config).unwrap();
+    shell
+        .execute_command_string("nice /bin/sh -c 'echo escaped'")
+        .unwrap();
+    assert_eq!(shell.last_exit_status().code(), Some(126));
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_watch() {
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::Duration;
+
+    use nix::sys::signal::{self, Signal};
+    use nix::unistd::Pid;
+
+    let child = BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "watch -n 0.1 -d echo hello"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    thread::sleep(Duration::from_secs(1));
+    signal::kill(Pid::from_raw(child.id() as i32), Signal::SIGINT).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Every 0.1s: echo hello"));
+    assert!(stdout.contains("hello\n"));
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "watch -n"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_max_output_size() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["--max-output-size=8", "-c", "echo 0123456789"])
+        .assert()
+        .code(1)
+        .stdout(predicates::str::diff("01234567").from_utf8())
+        .stderr(predicates::str::contains("output size limit exceeded"));
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("output.bsh");
+    fs::write(&script, "echo foo\necho bar\necho baz\necho unreachable\n").unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg("--max-output-size=10")
+        .arg(&script)
+        .assert()
+        .code(1)
+        .stdout(predicates::str::diff("foo\nbar\nba").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&[
+            "--max-output-size=16",
+            "-c",
+            "echo foo && help help > /dev/null",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("foo\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_subshell() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let cwd = temp_dir.path().canonicalize().unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "(cd /; pwd); pwd"])
+        .current_dir(&cwd)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff(format!("/\n{}\n", cwd.display())).from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "(declare FOO=bar; echo $FOO); echo $FOO"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("bar\n\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "(cd /; declare PWD=/); echo $PWD"])
+        .current_dir(&cwd)
+        .env("PWD", &cwd)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff(format!("{}\n", cwd.display())).from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "(debug set xtrace on); debug set; (exit 4); echo $?"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(
+                "checkwinsize\ton\ndotglob\toff\nerrexit\toff\nexpand_aliases\ton\n\
+                 extglob\toff\nfailglob\toff\nglobstar\toff\nhistappend\toff\n\
+                 histverify\toff\nnocaseglob\toff\nnoclobber\toff\nnounset\toff\n\
+                 nullglob\toff\npipefail\toff\nposix\toff\nvi\toff\nxtrace\toff\n4\n",
+            )
+            .from_utf8(),
+        );
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "(echo foo; echo bar) | cat"])
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("foo\nbar\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "(exit 3)"])
+        .assert()
+        .code(3);
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_pipestatus() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("pipestatus.bsh");
+    fs::write(
+        &script,
+        "true | false | true\n\
+         echo $PIPESTATUS_0 $PIPESTATUS_1 $PIPESTATUS_2\n\
+         false | true\n\
+         echo $PIPESTATUS\n\
+         true | false; echo $PIPESTATUS\n\
+         echo $PIPESTATUS $PIPESTATUS_0\n\
+         true | false\n\
+         echo $PIPESTATUS $PIPESTATUS_0 $PIPESTATUS_1\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("0 1 0\n1 0\n0 1\n0 0\n0 1 0 1\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_last_argument() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("last_argument.bsh");
+    fs::write(
+        &script,
+        "echo foo /tmp > /dev/null\n\
+         echo $_\n\
+         true | echo a b\n\
+         echo $_\n\
+         echo $_\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("/tmp\na b\nb\nb\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_prompt_command() {
+    let mut command = BIN_UNDER_TEST.command();
+    command
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .env("PROMPT_COMMAND", "echo hook");
+    assert_cmd::Command::from_std(command)
+        .write_stdin("echo foo\necho bar\n")
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("hook\nfoo\nhook\nbar\nhook\nexit\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_init_command() {
+    let mut command = BIN_UNDER_TEST.command();
+    command
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["--init-command", "declare FOO=foo", "-i", "echo init"]);
+    assert_cmd::Command::from_std(command)
+        .write_stdin("echo $FOO\n")
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("init\nfoo\nexit\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_no_editing() {
+    let mut command = BIN_UNDER_TEST.command();
+    command
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg("--no-editing");
+    assert_cmd::Command::from_std(command)
+        .write_stdin("echo foo\necho bar")
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("foo\nbar\nexit\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_logout() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "logout"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::diff("bsh: not login shell: use 'exit'\n").from_utf8());
+
+    let home_dir = tempfile::tempdir().unwrap();
+    fs::write(home_dir.path().join(".bsh_logout"), "echo goodbye\n").unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["--login", "-c", "echo foo && logout && echo unreachable"])
+        .env("HOME", home_dir.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("foo\ngoodbye\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_enable() {
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "enable -n help && help"])
+        .assert()
+        .code(127)
+        .stderr(predicates::str::diff("bsh: help: command not found\n").from_utf8());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .args(&["-c", "enable -n help && enable help && help enable"])
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with("enable: enable").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_command_log() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let command_log = temp_dir.path().join("commands.log");
+    let script = temp_dir.path().join("script.bsh");
+    fs::write(&script, "echo foo\necho bar | cat\n").unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(format!("--command-log={}", command_log.display()))
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("foo\nbar\n").from_utf8());
+
+    let log = fs::read_to_string(&command_log).unwrap();
+    let commands: Vec<&str> = log
+        .lines()
+        .map(|line| line.splitn(4, ' ').last().unwrap())
+        .collect();
+    assert_eq!(commands, ["echo foo", "echo bar | cat"]);
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_profile() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let profile = temp_dir.path().join("profile.tsv");
+    let script = temp_dir.path().join("script.bsh");
+    fs::write(&script, "echo foo\n\necho bar | cat\n").unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(format!("--profile={}", profile.display()))
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("foo\nbar\n").from_utf8());
+
+    let profile = fs::read_to_string(&profile).unwrap();
+    let records: Vec<Vec<&str>> = profile
+        .lines()
+        .map(|line| line.splitn(3, '\t').collect())
+        .collect();
+    assert_eq!(records.len(), 2);
+    for record in &records {
+        assert!(record[0].parse::<u64>().is_ok());
+    }
+    assert_eq!(&records[0][1..], ["1", "echo foo"]);
+    assert_eq!(&records[1][1..], ["3", "echo bar | cat"]);
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_input_encoding() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("latin1.bsh");
+    fs::write(&script, b"echo caf\xE9 cr\xE8me\n").unwrap();
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicates::str::contains("invalid character encoding"));
+
+    for encoding in &["latin1", "auto"] {
+        BIN_UNDER_TEST
+            .command()
+            .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+            .arg(format!("--input-encoding={}", encoding))
+            .arg(&script)
+            .assert()
+            .success()
+            .stdout(predicates::str::diff("café crème\n").from_utf8());
+    }
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_max_open_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output = temp_dir.path().join("output.txt");
+    let script = temp_dir.path().join("script.bsh");
+    fs::write(
+        &script,
+        format!("echo foo > {0}\ncat < {0}\necho done\n", output.display()),
+    )
+    .unwrap();
+
+    // stdin, stdout, and stderr are always open, so no file may be opened for a redirection.
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg("--max-open-files=3")
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("done\n").from_utf8())
+        .stderr(predicates::str::diff(
+            "bsh: too many open files\nbsh: too many open files\n",
+        ));
+    assert!(!output.exists());
+
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("foo\ndone\n").from_utf8());
+}
+
+#[test]
+#[cfg(unix)] // TODO (#22): Support Windows
+fn test_debug_trap() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script = temp_dir.path().join("script.bsh");
+    fs::write(
+        &script,
+        "debug trap 'echo + $BASH_COMMAND'\n\
+         echo foo\n\
+         false\n\
+         echo bar | cat\n\
+         debug trap\n\
+         echo baz\n",
+    )
+    .unwrap();
+    BIN_UNDER_TEST
+        .command()
+        .args(&[OsStr::new("--log"), LOG_FILE_NAME.as_os_str()])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::diff(
+                "+ echo foo\nfoo\n+ false\n+ echo bar | cat\nbar\n+ debug trap\nbaz\n",
+            )
+            .from_utf8(),
+        );
 }